@@ -0,0 +1,645 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const DEFAULT_EDITOR: &str = "vim";
+const DEFAULT_ID_CHARS: &str = "dfghklsa";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The editor, when `$EDITOR` isn't set; git's `core.editor` otherwise
+    pub editor: Option<String>,
+    pub id_chars: String,
+    pub notifications: bool,
+    /// Which file `f d`, `f a` and the like take when given no ID
+    pub default_target: DefaultTarget,
+    pub watch: WatchConfig,
+    pub commit: CommitConfig,
+    pub push: PushConfig,
+    pub branch: BranchConfig,
+    pub tools: Vec<ToolConfig>,
+    pub owners: OwnersConfig,
+    pub images: ImagesConfig,
+    pub diff: DiffConfig,
+    pub list: ListConfig,
+    /// Globs of generated or vendored files to roll up into one row per
+    /// pattern in listings, e.g. `target/**` or `*.lock`
+    pub collapse: Vec<String>,
+    pub maintenance: MaintenanceConfig,
+    pub theme: ThemeConfig,
+    /// The language of f's messages, e.g. `es`, over `LC_ALL` and `LANG`
+    pub locale: Option<String>,
+    /// Seconds any command f runs may take before it's killed, for CI
+    pub timeout: Option<u64>,
+    /// Where debug logs go, for sessions where stderr is the screen. `~/`
+    /// is the home directory.
+    pub log_file: Option<String>,
+}
+
+/// Which of the unstaged and untracked files an action without an ID
+/// takes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DefaultTarget {
+    /// The first in the list, which is the least recently modified
+    #[default]
+    FirstUnstaged,
+    /// The one an f command used most recently
+    LastUsed,
+    /// The one with the most lines added and removed
+    LargestDiff,
+    /// Ask in the picker
+    Prompt,
+}
+
+/// What `f watch` does when the working tree becomes clean.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnClean {
+    Nothing,
+    Notify,
+    Bell,
+}
+
+/// The `[watch]` table: automation applied on each watch refresh.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct WatchConfig {
+    pub on_clean: Option<OnClean>,
+    /// Globs of files to stage automatically as they appear or change
+    pub auto_stage: Vec<String>,
+}
+
+/// The `[commit]` table: signing, and checks applied to `f c` messages
+/// before git runs.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CommitConfig {
+    pub max_subject_length: Option<usize>,
+    /// Reject subjects starting with e.g. "Fixed" or "Adds"
+    pub imperative: bool,
+    /// Require Conventional Commits subjects (`feat(scope): ...`)
+    pub conventional: bool,
+    /// Regex the subject must match
+    pub subject_pattern: Option<String>,
+    /// Regex that must match somewhere in the message, e.g. `[A-Z]+-\d+`
+    pub ticket: Option<String>,
+    /// Sign every commit (`git commit -S`)
+    pub sign: bool,
+}
+
+/// The `[push]` table.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PushConfig {
+    /// Ask before pushing, after showing what's outgoing. Off by default so
+    /// scripts that push keep working.
+    pub confirm: bool,
+}
+
+/// `theme = "light"` names a preset; a `[theme]` table can name one with
+/// `preset` and give its own color for any role, e.g. `staged = "bold blue"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(from = "ThemeSetting")]
+pub struct ThemeConfig {
+    pub preset: Option<String>,
+    /// Role name to color, overriding the preset's
+    pub colors: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ThemeSetting {
+    Preset(String),
+    Table {
+        #[serde(default)]
+        preset: Option<String>,
+        #[serde(flatten)]
+        colors: BTreeMap<String, String>,
+    },
+}
+
+impl From<ThemeSetting> for ThemeConfig {
+    fn from(setting: ThemeSetting) -> Self {
+        match setting {
+            ThemeSetting::Preset(preset) => Self {
+                preset: Some(preset),
+                colors: BTreeMap::new(),
+            },
+            ThemeSetting::Table { preset, colors } => Self { preset, colors },
+        }
+    }
+}
+
+/// The `[maintenance]` table.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    /// Say above the file list when gc is running or loose objects have
+    /// piled up
+    pub hint: bool,
+}
+
+/// The `[branch]` table, for the warnings above the file list.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BranchConfig {
+    /// Warn when the branch hasn't taken in the default branch for this
+    /// many days; 0 turns the warning off
+    pub stale_days: u64,
+}
+
+impl Default for BranchConfig {
+    fn default() -> Self {
+        Self { stale_days: 30 }
+    }
+}
+
+/// A `[[tools]]` entry: the commands `f fmt`, `f test` and `f check` run
+/// for files matching `glob`. The first entry with the command wins.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ToolConfig {
+    pub glob: String,
+    /// e.g. `rustfmt {file}`
+    pub fmt: Option<String>,
+    /// e.g. `shellcheck {file}`, run only by `f check`
+    pub lint: Option<String>,
+    /// e.g. `cargo test -p {package}`
+    pub test: Option<String>,
+}
+
+/// The `[owners]` table, for CODEOWNERS.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct OwnersConfig {
+    /// The names `--mine` counts as you, e.g. `["@alice", "@acme/cli"]`,
+    /// on top of your git email
+    pub me: Vec<String>,
+}
+
+/// The `[images]` table, for `f d` on changed images.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ImagesConfig {
+    /// Run in place of the summary, e.g. `compare {old} {new} png:- | display`;
+    /// `{old}` and `{new}` are files holding each version
+    pub diff_tool: Option<String>,
+}
+
+/// The `[diff]` table, for `f d`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DiffConfig {
+    /// Summarize a file's diff, as `f d --summary` does, when it's longer
+    /// than this many lines
+    pub summary_lines: Option<usize>,
+}
+
+/// The `[list]` table, for `f` and `f list`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ListConfig {
+    /// Files shown per section before "… and N more"; all of them unset
+    pub limit: Option<usize>,
+    /// Page a listing taller than the terminal
+    pub pager: bool,
+}
+
+impl Default for ListConfig {
+    fn default() -> Self {
+        Self {
+            limit: None,
+            pager: true,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            editor: None,
+            id_chars: DEFAULT_ID_CHARS.to_string(),
+            notifications: false,
+            default_target: DefaultTarget::default(),
+            watch: WatchConfig::default(),
+            commit: CommitConfig::default(),
+            push: PushConfig::default(),
+            branch: BranchConfig::default(),
+            tools: Vec::new(),
+            owners: OwnersConfig::default(),
+            images: ImagesConfig::default(),
+            diff: DiffConfig::default(),
+            list: ListConfig::default(),
+            collapse: Vec::new(),
+            maintenance: MaintenanceConfig::default(),
+            theme: ThemeConfig::default(),
+            locale: None,
+            timeout: None,
+            log_file: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Self {
+        let config_path = Self::config_path();
+        match config_path {
+            Some(path) if path.exists() => Self::load_from_file(&path),
+            _ => Self::default(),
+        }
+    }
+
+    pub fn config_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("f.toml"))
+    }
+
+    fn load_from_file(path: &PathBuf) -> Self {
+        match fs::read_to_string(path) {
+            Ok(content) => match toml::from_str(&content) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!(
+                        "Warning: Failed to parse config file {}: {}",
+                        path.display(),
+                        e
+                    );
+                    Self::default()
+                }
+            },
+            Err(e) => {
+                eprintln!(
+                    "Warning: Failed to read config file {}: {}",
+                    path.display(),
+                    e
+                );
+                Self::default()
+            }
+        }
+    }
+
+    pub fn editor(&self) -> String {
+        self.editor_source().0
+    }
+
+    /// The editor and where it came from: `$EDITOR`, then `editor` in
+    /// f.toml, then git's `core.editor`, so f opens what `git commit`
+    /// would, then vim.
+    pub fn editor_source(&self) -> (String, &'static str) {
+        if let Ok(editor) = std::env::var("EDITOR") {
+            return (editor, "$EDITOR");
+        }
+        if let Some(editor) = &self.editor {
+            return (editor.clone(), "f.toml");
+        }
+        match crate::git_status::git_output(&["config", "core.editor"]) {
+            Some(editor) => (editor, "git core.editor"),
+            None => (DEFAULT_EDITOR.to_string(), "default"),
+        }
+    }
+
+    /// The editor opening `paths`, in tabs for editors that need a flag for
+    /// that. Run through the shell to support an editor with arguments
+    /// (e.g. "vim -u NONE"). `line` is the first path's line for an editor
+    /// template with `{line}` in it, otherwise ignored.
+    pub fn editor_command(&self, paths: &[&str], line: Option<u32>) -> Command {
+        let mut editor = self.editor();
+        if let Some(script) = template_script(&editor, line.unwrap_or(1)) {
+            return shell(&script, paths);
+        }
+        if paths.len() > 1
+            && let Some(flag) = multi_file_flag(&editor)
+        {
+            editor = format!("{} {}", editor, flag);
+        }
+        shell(&format!("{} \"$@\"", editor), paths)
+    }
+
+    /// The editor opening `path` at `line`, from the editor template or for
+    /// the editors that take a line on the command line, otherwise at the
+    /// top.
+    pub fn editor_command_at(&self, path: &str, line: u32) -> Command {
+        let editor = self.editor();
+        if let Some(script) = template_script(&editor, line) {
+            return shell(&script, &[path]);
+        }
+        shell(
+            &format!("{} \"$@\"", editor),
+            &line_args(&editor, path, line)
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// The effective `on_clean` action. Without an explicit setting, watch
+    /// notifies when `notifications` is enabled.
+    pub fn watch_on_clean(&self) -> OnClean {
+        match self.watch.on_clean {
+            Some(action) => action,
+            None if self.notifications => OnClean::Notify,
+            None => OnClean::Nothing,
+        }
+    }
+
+    /// `log_file` with a leading `~/` expanded.
+    pub fn log_path(&self) -> Option<PathBuf> {
+        let path = self.log_file.as_deref().filter(|p| !p.is_empty())?;
+        match path.strip_prefix("~/") {
+            Some(rest) => Some(dirs::home_dir()?.join(rest)),
+            None => Some(PathBuf::from(path)),
+        }
+    }
+
+    pub fn id_chars(&self) -> Vec<char> {
+        let chars: Vec<char> = self.id_chars.chars().collect();
+        if chars.len() >= 2 {
+            chars
+        } else {
+            DEFAULT_ID_CHARS.chars().collect()
+        }
+    }
+}
+
+/// The flag that makes `editor` open several files side by side rather
+/// than as a buffer list only `:next` reaches: one tab page each for the
+/// vi family. Editors like `code` open every file they're given as is.
+pub fn multi_file_flag(editor: &str) -> Option<&'static str> {
+    let program = editor.split_whitespace().next()?;
+    let name = program.rsplit('/').next().unwrap_or(program);
+    matches!(name, "vi" | "vim" | "nvim" | "gvim" | "mvim").then_some("-p")
+}
+
+fn shell(script: &str, args: &[&str]) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(script)
+        .arg("sh") // $0
+        .args(args); // $1...
+    cmd
+}
+
+/// An editor template, one with `{file}` or `{line}` in it (e.g.
+/// "code -g {file}:{line}"), as a script taking the paths as arguments:
+/// `{file}` is the first, opened at `line`, and the rest follow. `None` for
+/// a plain editor command.
+pub fn template_script(editor: &str, line: u32) -> Option<String> {
+    if !editor.contains("{file}") && !editor.contains("{line}") {
+        return None;
+    }
+    let editor = editor.replace("{line}", &line.to_string());
+    Some(if editor.contains("{file}") {
+        format!(
+            "f=\"$1\"; shift; {} \"$@\"",
+            editor.replace("{file}", "\"$f\"")
+        )
+    } else {
+        format!("{} \"$@\"", editor)
+    })
+}
+
+/// The arguments that make `editor` open `path` at `line`: `+N path` for
+/// the terminal editors, `-g path:N` for VS Code and its forks and
+/// `path:N` for those that read it from the path.
+pub fn line_args(editor: &str, path: &str, line: u32) -> Vec<String> {
+    let program = editor.split_whitespace().next().unwrap_or_default();
+    let name = program.rsplit('/').next().unwrap_or(program);
+    match name {
+        "vi" | "vim" | "nvim" | "gvim" | "mvim" | "emacs" | "emacsclient" | "nano" | "micro"
+        | "kak" => vec![format!("+{}", line), path.to_string()],
+        "code" | "codium" | "cursor" => vec!["-g".to_string(), format!("{}:{}", path, line)],
+        "subl" | "hx" | "zed" => vec![format!("{}:{}", path, line)],
+        _ => vec![path.to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = Config::default();
+        assert_eq!(config.editor, None);
+        assert_eq!(config.id_chars, "dfghklsa");
+        assert!(!config.notifications);
+        assert_eq!(config.log_path(), None);
+    }
+
+    #[test]
+    fn test_log_path() {
+        let config: Config = toml::from_str("log_file = \"/tmp/f.log\"").unwrap();
+        assert_eq!(config.log_path(), Some(PathBuf::from("/tmp/f.log")));
+        let config: Config = toml::from_str("log_file = \"~/logs/f.log\"").unwrap();
+        assert_eq!(
+            config.log_path(),
+            dirs::home_dir().map(|home| home.join("logs/f.log"))
+        );
+    }
+
+    #[test]
+    fn test_parse_watch_table() {
+        let config: Config =
+            toml::from_str("[watch]\non_clean = \"bell\"\nauto_stage = [\"generated/**\"]")
+                .unwrap();
+        assert_eq!(config.watch.on_clean, Some(OnClean::Bell));
+        assert_eq!(config.watch.auto_stage, vec!["generated/**"]);
+    }
+
+    #[test]
+    fn test_watch_on_clean_follows_notifications() {
+        let mut config = Config::default();
+        assert_eq!(config.watch_on_clean(), OnClean::Nothing);
+        config.notifications = true;
+        assert_eq!(config.watch_on_clean(), OnClean::Notify);
+        config.watch.on_clean = Some(OnClean::Nothing);
+        assert_eq!(config.watch_on_clean(), OnClean::Nothing);
+    }
+
+    #[test]
+    fn test_parse_commit_table() {
+        let config: Config =
+            toml::from_str("[commit]\nmax_subject_length = 50\nticket = \"[A-Z]+-\\\\d+\"")
+                .unwrap();
+        assert_eq!(config.commit.max_subject_length, Some(50));
+        assert_eq!(config.commit.ticket.as_deref(), Some("[A-Z]+-\\d+"));
+        assert!(!config.commit.imperative);
+    }
+
+    #[test]
+    fn test_parse_push_table() {
+        assert!(!Config::default().push.confirm);
+        let config: Config = toml::from_str("[push]\nconfirm = true").unwrap();
+        assert!(config.push.confirm);
+    }
+
+    #[test]
+    fn test_parse_tools() {
+        let config: Config = toml::from_str(
+            "[[tools]]\nglob = \"*.rs\"\nfmt = \"rustfmt {file}\"\n\n[[tools]]\nglob = \"web/**\"\ntest = \"npm test -w {package}\"",
+        )
+        .unwrap();
+        assert_eq!(config.tools.len(), 2);
+        assert_eq!(config.tools[0].fmt.as_deref(), Some("rustfmt {file}"));
+        assert_eq!(config.tools[0].test, None);
+        assert_eq!(config.tools[1].glob, "web/**");
+    }
+
+    #[test]
+    fn test_parse_owners_table() {
+        assert!(Config::default().owners.me.is_empty());
+        let config: Config = toml::from_str("[owners]\nme = [\"@alice\", \"@acme/cli\"]").unwrap();
+        assert_eq!(config.owners.me, ["@alice", "@acme/cli"]);
+    }
+
+    #[test]
+    fn test_parse_images_table() {
+        assert_eq!(Config::default().images.diff_tool, None);
+        let config: Config = toml::from_str("[images]\ndiff_tool = \"kitten icat {new}\"").unwrap();
+        assert_eq!(
+            config.images.diff_tool.as_deref(),
+            Some("kitten icat {new}")
+        );
+    }
+
+    #[test]
+    fn test_parse_diff_table() {
+        assert_eq!(Config::default().diff.summary_lines, None);
+        let config: Config = toml::from_str("[diff]\nsummary_lines = 400").unwrap();
+        assert_eq!(config.diff.summary_lines, Some(400));
+    }
+
+    #[test]
+    fn test_parse_list_table() {
+        let config = Config::default();
+        assert_eq!(config.list.limit, None);
+        assert!(config.list.pager);
+        let config: Config = toml::from_str("[list]\nlimit = 20\npager = false").unwrap();
+        assert_eq!(config.list.limit, Some(20));
+        assert!(!config.list.pager);
+    }
+
+    #[test]
+    fn test_parse_collapse() {
+        assert!(Config::default().collapse.is_empty());
+        let config: Config = toml::from_str("collapse = [\"target/**\", \"*.lock\"]").unwrap();
+        assert_eq!(config.collapse, ["target/**", "*.lock"]);
+    }
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(Config::default().locale, None);
+        let config: Config = toml::from_str("locale = \"es\"").unwrap();
+        assert_eq!(config.locale.as_deref(), Some("es"));
+    }
+
+    #[test]
+    fn test_parse_default_target() {
+        assert_eq!(
+            Config::default().default_target,
+            DefaultTarget::FirstUnstaged
+        );
+        let config: Config = toml::from_str("default_target = \"largest-diff\"").unwrap();
+        assert_eq!(config.default_target, DefaultTarget::LargestDiff);
+        assert!(toml::from_str::<Config>("default_target = \"newest\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        assert_eq!(Config::default().timeout, None);
+        let config: Config = toml::from_str("timeout = 600").unwrap();
+        assert_eq!(config.timeout, Some(600));
+    }
+
+    #[test]
+    fn test_parse_branch_table() {
+        assert_eq!(Config::default().branch.stale_days, 30);
+        let config: Config = toml::from_str("[branch]\nstale_days = 0").unwrap();
+        assert_eq!(config.branch.stale_days, 0);
+    }
+
+    #[test]
+    fn test_parse_maintenance_table() {
+        assert!(!Config::default().maintenance.hint);
+        let config: Config = toml::from_str("[maintenance]\nhint = true").unwrap();
+        assert!(config.maintenance.hint);
+    }
+
+    #[test]
+    fn test_parse_theme() {
+        assert_eq!(Config::default().theme, ThemeConfig::default());
+        let config: Config = toml::from_str("theme = \"light\"").unwrap();
+        assert_eq!(config.theme.preset.as_deref(), Some("light"));
+        assert!(config.theme.colors.is_empty());
+        let config: Config =
+            toml::from_str("[theme]\npreset = \"solarized\"\nstaged = \"bold blue\"").unwrap();
+        assert_eq!(config.theme.preset.as_deref(), Some("solarized"));
+        assert_eq!(
+            config.theme.colors.get("staged").map(String::as_str),
+            Some("bold blue")
+        );
+    }
+
+    #[test]
+    fn test_multi_file_flag() {
+        assert_eq!(multi_file_flag("vim"), Some("-p"));
+        assert_eq!(multi_file_flag("/usr/bin/nvim -u NONE"), Some("-p"));
+        assert_eq!(multi_file_flag("code --wait"), None);
+        assert_eq!(multi_file_flag("vimdiff"), None);
+        assert_eq!(multi_file_flag(""), None);
+    }
+
+    #[test]
+    fn test_template_script() {
+        assert_eq!(template_script("vim -p", 3), None);
+        assert_eq!(
+            template_script("code -g {file}:{line}", 143).as_deref(),
+            Some("f=\"$1\"; shift; code -g \"$f\":143 \"$@\"")
+        );
+        assert_eq!(
+            template_script("vim +{line}", 7).as_deref(),
+            Some("vim +7 \"$@\"")
+        );
+    }
+
+    #[test]
+    fn test_line_args() {
+        assert_eq!(line_args("vim", "a.rs", 12), ["+12", "a.rs"]);
+        assert_eq!(line_args("code --wait", "a.rs", 12), ["-g", "a.rs:12"]);
+        assert_eq!(line_args("/usr/local/bin/hx", "a.rs", 3), ["a.rs:3"]);
+        assert_eq!(line_args("ed", "a.rs", 12), ["a.rs"]);
+    }
+
+    #[test]
+    fn test_parse_notifications() {
+        let config: Config = toml::from_str("notifications = true").unwrap();
+        assert!(config.notifications);
+        assert_eq!(config.editor, None);
+    }
+
+    #[test]
+    fn test_id_chars_valid() {
+        let config = Config {
+            editor: None,
+            id_chars: "abc".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(config.id_chars(), vec!['a', 'b', 'c']);
+    }
+
+    #[test]
+    fn test_id_chars_too_short_uses_default() {
+        let config = Config {
+            editor: None,
+            id_chars: "a".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.id_chars(),
+            DEFAULT_ID_CHARS.chars().collect::<Vec<_>>()
+        );
+    }
+}
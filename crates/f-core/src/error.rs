@@ -0,0 +1,143 @@
+//! The failures scripts and editor plugins may want to tell apart, each with
+//! its own exit code. Anything else exits with 1; clap's own usage errors
+//! exit with 2. A child's failure passes its code through instead, which
+//! may be any of these. The table is repeated in the README, keep the two
+//! in sync.
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Arguments that don't make sense, caught after clap has parsed them
+    Usage(String),
+    NotARepo,
+    /// An ID, path, commit or trash entry that matches nothing
+    NotFound(String),
+    /// An ID prefix shared by several files or commits
+    AmbiguousId {
+        id: String,
+        matches: usize,
+        what: &'static str,
+    },
+    /// A `[commit]` or other config value f can't use
+    Config(String),
+    /// A child process that exited unsuccessfully. f exits with the child's
+    /// own code, so `f a x || ...` sees what git reported, even where that
+    /// is one of the codes above.
+    GitFailed {
+        /// The program and its first argument, e.g. `git status`
+        command: String,
+        code: i32,
+        stderr: String,
+    },
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) => 2,
+            Error::NotARepo => 3,
+            Error::NotFound(_) => 4,
+            Error::AmbiguousId { .. } => 5,
+            Error::Config(_) => 6,
+            Error::GitFailed { code, .. } => *code,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Usage(message) | Error::NotFound(message) | Error::Config(message) => {
+                write!(f, "{}", message)
+            }
+            Error::NotARepo => write!(f, "Not in a git repository"),
+            Error::AmbiguousId { id, matches, what } => {
+                write!(
+                    f,
+                    "ID '{}' matches {} {} - be more specific",
+                    id, matches, what
+                )
+            }
+            Error::GitFailed {
+                command,
+                code,
+                stderr,
+            } => {
+                if stderr.is_empty() {
+                    write!(f, "{} failed (exit {})", command, code)
+                } else {
+                    write!(f, "{} failed: {}", command, stderr)
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The exit code for `err`: that of the first `Error` in its chain, so
+/// context added on the way up doesn't hide it, or 1 for anything else.
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    err.chain()
+        .find_map(|e| e.downcast_ref::<Error>())
+        .map_or(1, Error::exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            Error::Usage(String::new()),
+            Error::NotARepo,
+            Error::NotFound(String::new()),
+            Error::AmbiguousId {
+                id: "a".into(),
+                matches: 2,
+                what: "files",
+            },
+            Error::Config(String::new()),
+        ];
+        let mut codes: Vec<i32> = errors.iter().map(Error::exit_code).collect();
+        codes.push(1);
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len() + 1);
+    }
+
+    #[test]
+    fn test_exit_code_through_context() {
+        let err = anyhow::Error::from(Error::NotARepo).context("Couldn't list files");
+        assert_eq!(exit_code(&err), 3);
+        let err: anyhow::Error = Err::<(), _>(Error::GitFailed {
+            command: "git status".into(),
+            code: 128,
+            stderr: "fatal: bad index".into(),
+        })
+        .context("Listing failed")
+        .unwrap_err();
+        assert_eq!(exit_code(&err), 128);
+        assert_eq!(
+            format!("{:#}", err),
+            "Listing failed: git status failed: fatal: bad index"
+        );
+        assert_eq!(exit_code(&anyhow::anyhow!("other")), 1);
+    }
+
+    #[test]
+    fn test_ambiguous_message() {
+        let err = Error::AmbiguousId {
+            id: "d".into(),
+            matches: 3,
+            what: "commits",
+        };
+        assert_eq!(
+            err.to_string(),
+            "ID 'd' matches 3 commits - be more specific"
+        );
+    }
+}
@@ -0,0 +1,469 @@
+//! Every child process f starts goes through here, so `--print` and exit
+//! codes behave the same everywhere: a failing git's status becomes f's
+//! own, and its stderr is kept for the error message.
+
+use crate::error::Error;
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::{self, Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Set by `--print`: commands are shown instead of run.
+static PRINT_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--non-interactive`, or when stdout isn't a terminal: nothing
+/// asks a question or takes over the terminal.
+static NON_INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Seconds a child may run before it's killed, 0 for no limit.
+static TIMEOUT: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_print_only(on: bool) {
+    PRINT_ONLY.store(on, Ordering::Relaxed);
+}
+
+pub fn print_only() -> bool {
+    PRINT_ONLY.load(Ordering::Relaxed)
+}
+
+pub fn set_non_interactive(on: bool) {
+    NON_INTERACTIVE.store(on, Ordering::Relaxed);
+}
+
+/// Whether f may prompt or take over the terminal: stdin is one, and f
+/// isn't running non-interactively.
+pub fn interactive() -> bool {
+    !NON_INTERACTIVE.load(Ordering::Relaxed) && io::stdin().is_terminal()
+}
+
+pub fn set_timeout(timeout: Option<Duration>) {
+    TIMEOUT.store(timeout.map_or(0, |t| t.as_secs().max(1)), Ordering::Relaxed);
+}
+
+pub fn timeout() -> Option<Duration> {
+    match TIMEOUT.load(Ordering::Relaxed) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// A child, and whether it leads a process group of its own.
+struct Running {
+    child: Child,
+    group: bool,
+}
+
+/// Spawns `cmd`, with a timeout as the leader of a new process group, so
+/// stopping it stops whatever it started too, like the commands of an
+/// `sh -c`. Only for children that leave the terminal alone: outside the
+/// terminal's foreground group, reading from it stops them.
+fn spawn_grouped(cmd: &mut Command) -> io::Result<Running> {
+    #[cfg(unix)]
+    if timeout().is_some() {
+        std::os::unix::process::CommandExt::process_group(cmd, 0);
+        return Ok(Running {
+            child: cmd.spawn()?,
+            group: true,
+        });
+    }
+    Ok(Running {
+        child: cmd.spawn()?,
+        group: false,
+    })
+}
+
+/// Waits for `running`, killing it once it has run past the timeout.
+fn wait(running: &mut Running, name: &str) -> io::Result<ExitStatus> {
+    match timeout() {
+        Some(timeout) => wait_within(running, name, timeout),
+        None => running.child.wait(),
+    }
+}
+
+fn wait_within(running: &mut Running, name: &str, timeout: Duration) -> io::Result<ExitStatus> {
+    let started = Instant::now();
+    let child = &mut running.child;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if started.elapsed() >= timeout {
+            #[cfg(unix)]
+            if running.group
+                && let Ok(pid) = i32::try_from(child.id())
+            {
+                // SAFETY: kill(2) touches no memory; it signals the group
+                // this child was made to lead
+                unsafe { libc::kill(-pid, libc::SIGKILL) };
+            }
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("{} timed out after {}s", name, timeout.as_secs()),
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// `running`'s exit status and output, read as it runs so a full pipe
+/// can't stall it, within the timeout.
+fn wait_with_output(mut running: Running, name: &str) -> io::Result<Output> {
+    let stdout = drain(running.child.stdout.take());
+    let stderr = drain(running.child.stderr.take());
+    let status = wait(&mut running, name)?;
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+/// Runs `cmd` for its status and output, like `Command::output` but
+/// within the timeout.
+pub fn run_output(cmd: &mut Command) -> io::Result<Output> {
+    let running = spawn_grouped(
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )?;
+    wait_with_output(running, &describe(cmd))
+}
+
+/// Replaces f with `cmd`, or prints it under `--print`. With a timeout,
+/// `cmd` is run as a child instead, so it can be stopped.
+pub fn exec(cmd: &mut Command) -> ! {
+    if print_only() {
+        println!("{}", render(cmd));
+        process::exit(0);
+    }
+    if timeout().is_some() {
+        match status(cmd) {
+            Ok(status) => exit(status),
+            Err(e) => {
+                eprintln!("Failed to run {}: {}", describe(cmd), e);
+                process::exit(1);
+            }
+        }
+    }
+    log::debug!("exec: {}", render(cmd));
+    #[cfg(unix)]
+    let err = std::os::unix::process::CommandExt::exec(cmd);
+    // Without exec, run the command and pass its status on as ours
+    #[cfg(not(unix))]
+    let err = match cmd.status() {
+        Ok(status) => exit(status),
+        Err(e) => e,
+    };
+    eprintln!(
+        "Failed to exec {}: {}",
+        cmd.get_program().to_string_lossy(),
+        err
+    );
+    process::exit(1);
+}
+
+/// Runs `cmd` to completion. Under `--print` it is printed instead and
+/// reported as having succeeded, so the commands after it are shown too.
+pub fn status(cmd: &mut Command) -> io::Result<ExitStatus> {
+    if print_only() {
+        println!("{}", render(cmd));
+        return Ok(success());
+    }
+    log::debug!("run: {}", render(cmd));
+    let started = Instant::now();
+    // A child that may use the terminal stays in f's process group
+    let running = if interactive() {
+        cmd.spawn().map(|child| Running {
+            child,
+            group: false,
+        })
+    } else {
+        spawn_grouped(cmd)
+    };
+    let status = running.and_then(|mut running| wait(&mut running, &describe(cmd)));
+    if let Ok(status) = &status {
+        log::debug!(
+            "{} exited {} after {:?}",
+            describe(cmd),
+            code(*status),
+            started.elapsed()
+        );
+    }
+    status
+}
+
+#[cfg(unix)]
+fn success() -> ExitStatus {
+    std::os::unix::process::ExitStatusExt::from_raw(0)
+}
+
+#[cfg(windows)]
+fn success() -> ExitStatus {
+    std::os::windows::process::ExitStatusExt::from_raw(0)
+}
+
+/// The exit code a shell would report for `status`: the child's own code,
+/// or 128 plus the signal that killed it.
+pub fn code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return 128 + signal;
+    }
+    status.code().unwrap_or(1)
+}
+
+/// Exits f with the same code as a finished child.
+pub fn exit(status: ExitStatus) -> ! {
+    process::exit(code(status))
+}
+
+/// Runs `cmd` for its stdout. A non-zero exit becomes `Error::GitFailed`
+/// carrying git's stderr. Unlike `status`, this runs under `--print` too:
+/// it's meant for queries, not for the command being shown.
+pub fn output(cmd: &mut Command) -> anyhow::Result<Vec<u8>> {
+    output_with_input(cmd, None)
+}
+
+/// `output`, writing `input` to the command's stdin.
+pub fn output_with_input(cmd: &mut Command, input: Option<&[u8]>) -> anyhow::Result<Vec<u8>> {
+    let name = describe(cmd);
+    log::debug!("run: {}", render(cmd));
+    let started = Instant::now();
+    let mut running = spawn_grouped(
+        cmd.stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped()),
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", name, e))?;
+    let stdin = running.child.stdin.take();
+    let (written, output) = std::thread::scope(|scope| {
+        // Written alongside reading the output: a child that answers as it
+        // reads, like `git check-attr --stdin`, stops reading once its
+        // stdout fills
+        let writer = input
+            .zip(stdin)
+            .map(|(input, mut stdin)| scope.spawn(move || stdin.write_all(input)));
+        let output = wait_with_output(running, &name);
+        (writer.map(|w| w.join()), output)
+    });
+    let output = output?;
+    match written {
+        Some(Ok(Err(e))) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e.into()),
+        Some(Err(_)) => anyhow::bail!("Writing to {} panicked", name),
+        _ => {}
+    }
+    log::debug!(
+        "{} exited {} after {:?} with {} bytes of output",
+        name,
+        code(output.status),
+        started.elapsed(),
+        output.stdout.len()
+    );
+    if !output.status.success() {
+        return Err(Error::GitFailed {
+            command: name,
+            code: code(output.status),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+        .into());
+    }
+    Ok(output.stdout)
+}
+
+fn describe(cmd: &Command) -> String {
+    let program = cmd.get_program().to_string_lossy();
+    match cmd.get_args().next() {
+        Some(arg) => format!("{} {}", program, arg.to_string_lossy()),
+        None => program.into_owned(),
+    }
+}
+
+/// `template` with every `{}` replaced by `value`, or `value` appended as
+/// the last argument when there are none, like `xargs -I{}` and `find -exec`.
+pub fn substitute(template: &[String], value: &str) -> Vec<String> {
+    if !template.iter().any(|arg| arg.contains("{}")) {
+        return template
+            .iter()
+            .cloned()
+            .chain([value.to_string()])
+            .collect();
+    }
+    template
+        .iter()
+        .map(|arg| arg.replace("{}", value))
+        .collect()
+}
+
+/// `cmd` as a line that can be pasted into a shell.
+pub fn render(cmd: &Command) -> String {
+    let mut words = Vec::new();
+    if let Some(dir) = cmd.get_current_dir() {
+        words.push(format!("cd {} &&", quote(&dir.to_string_lossy())));
+    }
+    for (key, value) in cmd.get_envs() {
+        if let Some(value) = value {
+            words.push(format!(
+                "{}={}",
+                key.to_string_lossy(),
+                quote(&value.to_string_lossy())
+            ));
+        }
+    }
+    words.push(quote(&cmd.get_program().to_string_lossy()));
+    words.extend(cmd.get_args().map(|a| quote(&a.to_string_lossy())));
+    words.join(" ")
+}
+
+/// Single-quotes `arg` unless it is made only of shell-safe characters.
+/// Braces and commas aren't, since bash and zsh expand `x{a,b}` to two words.
+pub fn quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:+@%^".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("src/main.rs"), "src/main.rs");
+        assert_eq!(quote("HEAD..@{u}"), "'HEAD..@{u}'");
+        assert_eq!(quote("x{a,b}"), "'x{a,b}'");
+        assert_eq!(quote("a,b"), "'a,b'");
+        assert_eq!(quote("fix typo"), "'fix typo'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+        assert_eq!(quote(""), "''");
+    }
+
+    #[test]
+    fn test_output_keeps_code_and_stderr() {
+        let err = output(Command::new("sh").args(["-c", "echo oops >&2; exit 7"])).unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 7);
+        assert_eq!(err.to_string(), "sh -c failed: oops");
+    }
+
+    #[test]
+    fn test_wait_within_kills_at_the_timeout() {
+        let mut running = Running {
+            child: Command::new("sleep").arg("5").spawn().unwrap(),
+            group: false,
+        };
+        let started = Instant::now();
+        let err = wait_within(&mut running, "sleep", Duration::from_secs(1)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(err.to_string(), "sleep timed out after 1s");
+        assert!(started.elapsed() < Duration::from_secs(4));
+
+        let mut running = Running {
+            child: Command::new("true").spawn().unwrap(),
+            group: false,
+        };
+        assert!(
+            wait_within(&mut running, "true", Duration::from_secs(1))
+                .unwrap()
+                .success()
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_timeout_kills_what_the_child_started() {
+        use std::io::BufRead;
+        use std::os::unix::process::CommandExt;
+        let mut child = Command::new("sh")
+            .args(["-c", "sleep 30 & echo $!; wait"])
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let mut pid = String::new();
+        io::BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut pid)
+            .unwrap();
+        let mut running = Running { child, group: true };
+        wait_within(&mut running, "sh -c", Duration::from_secs(1)).unwrap_err();
+
+        // Gone, or dead and waiting for whoever adopted it to reap it
+        let stat = format!("/proc/{}/stat", pid.trim());
+        let alive = || {
+            std::fs::read_to_string(&stat).is_ok_and(|s| {
+                let state = s.rsplit(')').next().unwrap_or("").trim_start();
+                !state.starts_with('Z')
+            })
+        };
+        let started = Instant::now();
+        while alive() && started.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!alive(), "sleep {} outlived the timeout", pid.trim());
+    }
+
+    #[test]
+    fn test_output_with_input() {
+        let out = output_with_input(&mut Command::new("cat"), Some(b"piped")).unwrap();
+        assert_eq!(out, b"piped");
+        // Far more than a pipe holds, so cat blocks on its stdout unless
+        // that's read while the input is still going in
+        let input: Vec<u8> = (0..512 * 1024).map(|i| (i % 251) as u8).collect();
+        let out = output_with_input(&mut Command::new("cat"), Some(&input)).unwrap();
+        assert_eq!(out, input);
+    }
+
+    #[test]
+    fn test_code_of_signal() {
+        let status = Command::new("sh")
+            .args(["-c", "kill -TERM $$"])
+            .status()
+            .unwrap();
+        assert_eq!(code(status), 128 + libc::SIGTERM);
+    }
+
+    #[test]
+    fn test_substitute() {
+        let template = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            substitute(&template(&["bat", "{}"]), "/r/a b.rs"),
+            ["bat", "/r/a b.rs"]
+        );
+        assert_eq!(
+            substitute(&template(&["wc", "-l"]), "/r/a.rs"),
+            ["wc", "-l", "/r/a.rs"]
+        );
+        assert_eq!(
+            substitute(&template(&["cp", "{}", "--target={}.bak"]), "x"),
+            ["cp", "x", "--target=x.bak"]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let mut cmd = Command::new("git");
+        cmd.args(["commit", "-m", "fix typo"])
+            .env("GIT_SEQUENCE_EDITOR", "true");
+        assert_eq!(
+            render(&cmd),
+            "GIT_SEQUENCE_EDITOR=true git commit -m 'fix typo'"
+        );
+    }
+}
@@ -0,0 +1,1343 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+use crate::error::Error;
+use crate::lfs;
+use crate::porcelain;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileType {
+    Unstaged,
+    Untracked,
+    Staged,
+}
+
+impl FileType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileType::Unstaged => "unstaged",
+            FileType::Untracked => "untracked",
+            FileType::Staged => "staged",
+        }
+    }
+
+    /// Inverse of `as_str`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "unstaged" => Some(FileType::Unstaged),
+            "untracked" => Some(FileType::Untracked),
+            "staged" => Some(FileType::Staged),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StableId {
+    pub display: String,
+    pub full_hash: String,
+}
+
+impl std::fmt::Display for StableId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.display)
+    }
+}
+
+impl StableId {
+    pub fn matches(&self, input: &str) -> bool {
+        self.full_hash.starts_with(input)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffStats {
+    pub added: u32,
+    pub removed: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct GitFile {
+    pub mtime: u64,
+    pub rel_path: String,
+    pub abs_path: PathBuf,
+    pub file_type: FileType,
+    pub stable_id: StableId,
+    pub diff_stats: Option<DiffStats>,
+    /// The object's size when git stores the file in LFS
+    pub lfs_size: Option<u64>,
+    /// Old and new mode when the change includes one, e.g. `0o100644` to
+    /// `0o100755` for a file made executable
+    pub mode_change: Option<(u32, u32)>,
+    /// Git's status letter for the change on this side: `M`, `A`, `D`,
+    /// `R`, `C`, `T` or `U` for a conflict, and `?` for untracked files
+    pub status: char,
+}
+
+impl GitFile {
+    /// Where the file points when it's a symlink in the work tree.
+    pub fn symlink_target(&self) -> Option<PathBuf> {
+        std::fs::read_link(&self.abs_path).ok()
+    }
+}
+
+pub fn get_git_root() -> Result<PathBuf> {
+    let output =
+        crate::exec::run_output(Command::new("git").args(["rev-parse", "--show-toplevel"]))
+            .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+
+    if !output.status.success() {
+        if git_output(&["rev-parse", "--is-bare-repository"]).as_deref() == Some("true") {
+            return Err(Error::Usage(
+                "This is a bare repository, pass --work-tree <dir> to use it".into(),
+            )
+            .into());
+        }
+        return Err(Error::NotARepo.into());
+    }
+
+    let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(PathBuf::from(root))
+}
+
+/// Expresses `path` relative to `base`, walking up with `..` where needed.
+/// Both paths are expected to be absolute.
+pub fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let path_parts: Vec<Component> = path.components().collect();
+    let base_parts: Vec<Component> = base.components().collect();
+    let common = path_parts
+        .iter()
+        .zip(base_parts.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut result = PathBuf::new();
+    for _ in common..base_parts.len() {
+        result.push("..");
+    }
+    for part in &path_parts[common..] {
+        result.push(part);
+    }
+    if result.as_os_str().is_empty() {
+        result.push(".");
+    }
+    result
+}
+
+pub fn get_git_dir() -> Result<PathBuf> {
+    match git_output(&["rev-parse", "--absolute-git-dir"]) {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => Err(Error::NotARepo.into()),
+    }
+}
+
+/// Runs a git query and returns its trimmed stdout, or `None` on failure.
+pub fn git_output(args: &[&str]) -> Option<String> {
+    let output = crate::exec::run_output(Command::new("git").args(args)).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+const DEFAULT_ID_CHARS: &[char] = &['d', 'f', 'g', 'h', 'l', 'k', 's', 'a'];
+
+fn fnv1a_hash(s: &str) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+    let mut hash = FNV_OFFSET;
+    for byte in s.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+fn hash_to_id_chars(s: &str, id_chars: &[char]) -> Vec<char> {
+    let mut hash = fnv1a_hash(s);
+    let base = id_chars.len() as u64;
+    let mut chars = Vec::new();
+    for _ in 0..12 {
+        chars.push(id_chars[(hash % base) as usize]);
+        hash /= base;
+    }
+    chars
+}
+
+pub fn generate_ids(paths: &[String], id_chars: &[char]) -> Vec<(String, String)> {
+    if paths.is_empty() {
+        return vec![];
+    }
+
+    let hashes: Vec<Vec<char>> = paths
+        .iter()
+        .map(|p| hash_to_id_chars(p, id_chars))
+        .collect();
+    let mut result = Vec::with_capacity(hashes.len());
+
+    for (i, hash) in hashes.iter().enumerate() {
+        let full_hash: String = hash.iter().collect();
+        let mut len = 1;
+        'outer: while len <= hash.len() {
+            let prefix: String = hash[..len].iter().collect();
+            for (j, other) in hashes.iter().enumerate() {
+                if i != j && paths[i] != paths[j] && other.len() >= len {
+                    let other_prefix: String = other[..len].iter().collect();
+                    if prefix == other_prefix {
+                        len += 1;
+                        continue 'outer;
+                    }
+                }
+            }
+            break;
+        }
+        let final_len = len.min(hash.len());
+        let display: String = hash[..final_len].iter().collect();
+        result.push((display, full_hash));
+    }
+
+    result
+}
+
+fn get_mtime(path: &PathBuf) -> u64 {
+    std::fs::metadata(path)
+        .ok()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn get_diff_stats(staged: bool) -> HashMap<String, DiffStats> {
+    let mut args = vec!["diff", "--numstat", "-z"];
+    let base;
+    if staged {
+        args.push("--cached");
+        base = staged_base();
+        args.extend(base.as_deref());
+    }
+
+    let output = crate::exec::run_output(Command::new("git").args(&args));
+
+    let mut stats = HashMap::new();
+    if let Ok(output) = output {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        stats = parse_numstat_z(&stdout);
+    }
+    stats
+}
+
+/// Parses `git diff --numstat -z` output. Renamed entries have an empty path
+/// field followed by the old and new paths as separate NUL-terminated fields.
+fn parse_numstat_z(output: &str) -> HashMap<String, DiffStats> {
+    let mut stats = HashMap::new();
+    let mut fields = output.split('\0');
+    while let Some(field) = fields.next() {
+        let parts: Vec<&str> = field.splitn(3, '\t').collect();
+        if parts.len() < 3 {
+            continue;
+        }
+        let added = parts[0].parse().unwrap_or(0);
+        let removed = parts[1].parse().unwrap_or(0);
+        let filepath = if parts[2].is_empty() {
+            fields.next();
+            fields.next().unwrap_or_default()
+        } else {
+            parts[2]
+        };
+        stats.insert(filepath.to_string(), DiffStats { added, removed });
+    }
+    stats
+}
+
+/// The first changed line of the new version of each of `files`, from the
+/// first hunk header of one zero-context diff per side of just those
+/// files; 1 for untracked files. It's asked for when a file is about to be
+/// opened rather than with the rest of the status, since it means diffing
+/// every file.
+pub fn first_changed_lines(files: &[GitFile]) -> Vec<Option<u32>> {
+    let side = |file_type| {
+        let paths: Vec<&Path> = files
+            .iter()
+            .filter(|f| f.file_type == file_type)
+            .map(|f| f.abs_path.as_path())
+            .collect();
+        get_first_lines(file_type == FileType::Staged, &paths)
+    };
+    let (unstaged, staged) = (side(FileType::Unstaged), side(FileType::Staged));
+    files
+        .iter()
+        .map(|file| match file.file_type {
+            FileType::Untracked => Some(1),
+            FileType::Unstaged => unstaged.get(&file.rel_path).copied(),
+            FileType::Staged => staged.get(&file.rel_path).copied(),
+        })
+        .collect()
+}
+
+/// `first_changed_lines` for a single file.
+pub fn first_changed_line(file: &GitFile) -> Option<u32> {
+    first_changed_lines(std::slice::from_ref(file))[0]
+}
+
+fn get_first_lines(staged: bool, paths: &[&Path]) -> HashMap<String, u32> {
+    if paths.is_empty() {
+        return HashMap::new();
+    }
+    let mut args = vec![
+        "-c",
+        "core.quotePath=false",
+        "diff",
+        "--no-color",
+        "--no-ext-diff",
+        "--src-prefix=a/",
+        "--dst-prefix=b/",
+        "-U0",
+    ];
+    let base;
+    if staged {
+        args.push("--cached");
+        base = staged_base();
+        args.extend(base.as_deref());
+    }
+    match crate::exec::run_output(Command::new("git").args(&args).arg("--").args(paths)) {
+        Ok(output) => parse_first_lines(&String::from_utf8_lossy(&output.stdout)),
+        Err(_) => HashMap::new(),
+    }
+}
+
+/// Pairs each file's `+++ b/<path>` (`--- a/<path>` when deleted) with its
+/// first hunk header. Paths git still quotes, ones with control characters
+/// or quotes, are left out.
+fn parse_first_lines(diff: &str) -> HashMap<String, u32> {
+    let mut lines = HashMap::new();
+    let mut path = None;
+    let mut in_header = false;
+    for line in diff.lines() {
+        if line.starts_with("diff ") {
+            path = None;
+            in_header = true;
+        } else if !in_header {
+            continue;
+        } else if let Some(old) = line.strip_prefix("--- a/") {
+            path = Some(old);
+        } else if let Some(new) = line.strip_prefix("+++ b/") {
+            path = Some(new);
+        } else if line.starts_with("@@") {
+            in_header = false;
+            // git ends a path with spaces in it with a tab
+            if let Some(path) = path.take().map(|p: &str| p.trim_end_matches('\t'))
+                && let Some(first) = parse_first_hunk_line(line)
+            {
+                lines.insert(path.to_string(), first);
+            }
+        }
+    }
+    lines
+}
+
+/// Git stores a symlink as its target, one line, whatever it points at.
+fn count_lines(path: &PathBuf) -> Option<u32> {
+    if std::fs::read_link(path).is_ok() {
+        return Some(1);
+    }
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|content| content.lines().count() as u32)
+}
+
+/// `-uall` lists files inside untracked directories individually, unless the
+/// repo hides untracked files altogether, as dotfile setups with `$HOME` as
+/// the work tree usually do.
+fn untracked_mode() -> &'static str {
+    match git_output(&["config", "--get", "status.showUntrackedFiles"]).as_deref() {
+        Some("no" | "false") => "-uno",
+        _ => "-uall",
+    }
+}
+
+pub fn get_all_files(id_chars: &[char]) -> Result<Vec<GitFile>> {
+    let started = std::time::Instant::now();
+    let git_root = get_git_root()?;
+
+    let output = crate::exec::output(Command::new("git").args([
+        "status",
+        "--porcelain=v2",
+        "-z",
+        untracked_mode(),
+    ]))?;
+
+    let unstaged_stats = get_diff_stats(false);
+    let staged_stats = get_diff_stats(true);
+
+    let stdout = String::from_utf8_lossy(&output);
+
+    let mut unstaged = Vec::new();
+    let mut untracked = Vec::new();
+    let mut staged = Vec::new();
+
+    let entries = porcelain::parse(&stdout).entries;
+    let lfs_paths = lfs::tracked(&entries.iter().map(|e| e.path.as_str()).collect::<Vec<_>>());
+
+    for entry in entries {
+        if entry.is_submodule_untracked_only() {
+            continue;
+        }
+        let filepath = entry.path.as_str();
+        let abs_path = git_root.join(filepath);
+        let mtime = get_mtime(&abs_path);
+
+        let lfs_size = if lfs_paths.contains(filepath) {
+            lfs::size(&abs_path)
+        } else {
+            None
+        };
+        // IDs are generated once every path is known
+        let file = |file_type, status, diff_stats, mode_change| GitFile {
+            mtime,
+            rel_path: filepath.to_string(),
+            abs_path: abs_path.clone(),
+            file_type,
+            stable_id: StableId {
+                display: String::new(),
+                full_hash: String::new(),
+            },
+            diff_stats,
+            lfs_size,
+            mode_change,
+            status,
+        };
+
+        if entry.kind == porcelain::Kind::Untracked {
+            // Counting the lines of a big LFS object means reading all of it
+            let lines = if lfs_paths.contains(filepath) {
+                None
+            } else {
+                count_lines(&abs_path)
+            };
+            let stats = lines.map(|lines| DiffStats {
+                added: lines,
+                removed: 0,
+            });
+            untracked.push(file(FileType::Untracked, '?', stats, None));
+            continue;
+        }
+
+        if entry.is_staged() {
+            staged.push(file(
+                FileType::Staged,
+                entry.index,
+                staged_stats.get(filepath).cloned(),
+                entry.staged_mode_change(),
+            ));
+        }
+
+        if entry.is_unstaged() {
+            unstaged.push(file(
+                FileType::Unstaged,
+                entry.worktree,
+                unstaged_stats.get(filepath).cloned(),
+                entry.unstaged_mode_change(),
+            ));
+        }
+    }
+
+    let mut result: Vec<GitFile> = unstaged
+        .into_iter()
+        .chain(untracked)
+        .chain(staged)
+        .collect();
+    let all_paths: Vec<String> = result.iter().map(|f| f.rel_path.clone()).collect();
+    for (file, (display, full_hash)) in result.iter_mut().zip(generate_ids(&all_paths, id_chars)) {
+        file.stable_id = StableId { display, full_hash };
+    }
+
+    let mut unstaged_files: Vec<_> = result
+        .iter()
+        .filter(|f| f.file_type == FileType::Unstaged)
+        .cloned()
+        .collect();
+    let mut untracked_files: Vec<_> = result
+        .iter()
+        .filter(|f| f.file_type == FileType::Untracked)
+        .cloned()
+        .collect();
+    let mut staged_files: Vec<_> = result
+        .iter()
+        .filter(|f| f.file_type == FileType::Staged)
+        .cloned()
+        .collect();
+
+    unstaged_files.sort_by_key(|f| f.mtime);
+    untracked_files.sort_by_key(|f| f.mtime);
+    staged_files.sort_by_key(|f| f.mtime);
+
+    log::debug!(
+        "status: {} unstaged, {} untracked, {} staged in {:?}",
+        unstaged_files.len(),
+        untracked_files.len(),
+        staged_files.len(),
+        started.elapsed()
+    );
+    let mut final_result = Vec::new();
+    final_result.extend(unstaged_files);
+    final_result.extend(untracked_files);
+    final_result.extend(staged_files);
+    for file in &final_result {
+        log::trace!(
+            "{} {} {}",
+            file.stable_id.full_hash,
+            file.file_type.as_str(),
+            file.rel_path
+        );
+    }
+
+    Ok(final_result)
+}
+
+/// Branch and per-section counts, cheap enough to compute on every prompt.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatusSummary {
+    pub branch: Option<String>,
+    /// HEAD's commit, `None` before the first one
+    pub commit: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    pub staged: u32,
+    pub unstaged: u32,
+    pub untracked: u32,
+    pub conflicted: u32,
+}
+
+pub fn get_status_summary() -> Result<StatusSummary> {
+    status_summary(Command::new("git"))
+}
+
+/// Like `get_status_summary`, but for the repository at `dir` regardless of
+/// the current directory or any inherited `GIT_DIR`.
+pub fn get_status_summary_in(dir: &Path) -> Result<StatusSummary> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(dir)
+        .env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE");
+    status_summary(cmd)
+}
+
+fn status_summary(mut cmd: Command) -> Result<StatusSummary> {
+    let output = crate::exec::run_output(cmd.args(["status", "--porcelain=v2", "--branch", "-z"]))
+        .map_err(|e| anyhow::anyhow!("Failed to run git status: {}", e))?;
+
+    if !output.status.success() {
+        return Err(Error::NotARepo.into());
+    }
+
+    Ok(parse_status_summary(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+fn parse_status_summary(output: &str) -> StatusSummary {
+    let status = porcelain::parse(output);
+    let mut summary = StatusSummary {
+        branch: status
+            .header("branch.head")
+            .filter(|b| *b != "(detached)")
+            .map(str::to_string),
+        commit: status
+            .header("branch.oid")
+            .filter(|oid| *oid != "(initial)")
+            .map(str::to_string),
+        upstream: status.header("branch.upstream").map(str::to_string),
+        ..Default::default()
+    };
+    for part in status.header("branch.ab").unwrap_or("").split(' ') {
+        if let Some(n) = part.strip_prefix('+') {
+            summary.ahead = n.parse().unwrap_or(0);
+        } else if let Some(n) = part.strip_prefix('-') {
+            summary.behind = n.parse().unwrap_or(0);
+        }
+    }
+
+    for entry in &status.entries {
+        match entry.kind {
+            porcelain::Kind::Unmerged => summary.conflicted += 1,
+            porcelain::Kind::Untracked => summary.untracked += 1,
+            porcelain::Kind::Ignored => {}
+            _ if entry.is_submodule_untracked_only() => {}
+            _ => {
+                summary.staged += entry.is_staged() as u32;
+                summary.unstaged += entry.is_unstaged() as u32;
+            }
+        }
+    }
+    summary
+}
+
+/// Paths (repo-relative) with unresolved merge conflicts.
+pub fn conflicted_paths() -> Vec<String> {
+    git_output(&["diff", "--name-only", "--diff-filter=U", "-z"])
+        .map(|out| {
+            out.split('\0')
+                .filter(|p| !p.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The current branch when it has no upstream configured, or `None` when
+/// it does (or HEAD is detached, where there is nothing to track).
+pub fn branch_without_upstream() -> Option<String> {
+    let branch = git_output(&["symbolic-ref", "--short", "-q", "HEAD"])?;
+    match git_output(&["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"]) {
+        Some(_) => None,
+        None => Some(branch),
+    }
+}
+
+/// How HEAD differs from its upstream: what a push would send and what it
+/// would have to overwrite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outgoing {
+    pub upstream: String,
+    pub ahead: u32,
+    pub behind: u32,
+    /// Lines added and removed by the commits ahead
+    pub added: u32,
+    pub removed: u32,
+}
+
+impl Outgoing {
+    /// e.g. "2 commits ahead of origin/main (+120/−14)"
+    pub fn describe(&self) -> String {
+        let commits = if self.ahead == 1 { "commit" } else { "commits" };
+        let mut text = format!(
+            "{} {} ahead of {} (+{}/−{})",
+            self.ahead, commits, self.upstream, self.added, self.removed
+        );
+        if self.behind > 0 {
+            text.push_str(&format!(", {} behind", self.behind));
+        }
+        text
+    }
+}
+
+/// `None` when HEAD has no upstream.
+pub fn outgoing() -> Option<Outgoing> {
+    let upstream = git_output(&["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"])?;
+    let counts = git_output(&["rev-list", "--left-right", "--count", "@{u}...HEAD"])?;
+    let (behind, ahead) = parse_left_right(&counts)?;
+    let (added, removed) = git_output(&["diff", "--numstat", "-z", "@{u}...HEAD"])
+        .map(|out| {
+            parse_numstat_z(&out)
+                .values()
+                .fold((0, 0), |(a, r), s| (a + s.added, r + s.removed))
+        })
+        .unwrap_or_default();
+    Some(Outgoing {
+        upstream,
+        ahead,
+        behind,
+        added,
+        removed,
+    })
+}
+
+/// Parses `git rev-list --left-right --count`: the left count, then the
+/// right.
+fn parse_left_right(output: &str) -> Option<(u32, u32)> {
+    let mut counts = output.split_whitespace().map(str::parse);
+    match (counts.next()?, counts.next()?) {
+        (Ok(left), Ok(right)) => Some((left, right)),
+        _ => None,
+    }
+}
+
+/// Where `git push` would send `branch` without an upstream.
+pub fn push_remote(branch: &str) -> Option<String> {
+    let remotes: Vec<String> = git_output(&["remote"])?
+        .lines()
+        .map(str::to_string)
+        .collect();
+    choose_push_remote(
+        git_output(&["config", &format!("branch.{}.pushRemote", branch)]),
+        git_output(&["config", "remote.pushDefault"]),
+        &remotes,
+    )
+}
+
+/// Git's precedence: branch.<name>.pushRemote, then remote.pushDefault,
+/// then `origin`; a repo with a single remote uses that.
+fn choose_push_remote(
+    branch_remote: Option<String>,
+    push_default: Option<String>,
+    remotes: &[String],
+) -> Option<String> {
+    branch_remote
+        .or(push_default)
+        .or_else(|| remotes.iter().find(|r| *r == "origin").cloned())
+        .or_else(|| match remotes {
+            [only] => Some(only.clone()),
+            _ => None,
+        })
+}
+
+/// Where HEAD points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Head {
+    Branch(String),
+    /// Not on a branch; holds the abbreviated commit
+    Detached(String),
+    /// On a branch with no commits yet, as in a fresh repository
+    Unborn(String),
+}
+
+pub fn head() -> Option<Head> {
+    let branch = git_output(&["symbolic-ref", "--short", "-q", "HEAD"]);
+    let commit = git_output(&["rev-parse", "--verify", "-q", "--short", "HEAD"]);
+    match (branch, commit) {
+        (Some(branch), Some(_)) => Some(Head::Branch(branch)),
+        (Some(branch), None) => Some(Head::Unborn(branch)),
+        (None, Some(commit)) => Some(Head::Detached(commit)),
+        (None, None) => None,
+    }
+}
+
+/// What staged changes are compared against: HEAD, or the empty tree before
+/// the first commit. `None` means HEAD, which `git diff --cached` defaults to.
+fn staged_base() -> Option<String> {
+    if git_output(&["rev-parse", "--verify", "-q", "HEAD"]).is_some() {
+        return None;
+    }
+    // Hashed rather than hardcoded, so SHA-256 repositories work too
+    let tree = crate::exec::output_with_input(
+        Command::new("git").args(["hash-object", "-t", "tree", "--stdin"]),
+        Some(b""),
+    )
+    .ok()?;
+    Some(String::from_utf8_lossy(&tree).trim().to_string())
+}
+
+/// Captures the uncolored diff for `file`: the index diff when `staged`,
+/// otherwise the working tree diff (against `/dev/null` for untracked files).
+pub fn file_diff(file: &GitFile, staged: bool) -> Result<String> {
+    if !staged
+        && file.file_type == FileType::Untracked
+        && let Some(target) = file.symlink_target()
+    {
+        return Ok(new_symlink_diff(&file.rel_path, &target));
+    }
+    let path = file.abs_path.to_string_lossy();
+    let base = if staged { staged_base() } else { None };
+    let args: Vec<&str> = if staged {
+        let mut args = vec!["diff", "--no-color", "--staged"];
+        args.extend(base.as_deref());
+        args.extend(["--", &path]);
+        args
+    } else if file.file_type == FileType::Untracked {
+        vec!["diff", "--no-color", "--no-index", "/dev/null", &path]
+    } else {
+        vec!["diff", "--no-color", "--", &path]
+    };
+    let output = crate::exec::run_output(Command::new("git").args(&args))
+        .map_err(|e| anyhow::anyhow!("Failed to run git diff: {}", e))?;
+
+    // `git diff --no-index` exits 1 when the files differ, which is the point
+    if !matches!(output.status.code(), Some(0 | 1)) {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The diff git would show for adding a symlink. `git diff --no-index`
+/// follows links, so it can't show a new one itself and fails outright
+/// when the link points at a directory.
+pub fn new_symlink_diff(rel_path: &str, target: &Path) -> String {
+    format!(
+        "diff --git a/{path} b/{path}\n\
+         new file mode 120000\n\
+         --- /dev/null\n\
+         +++ b/{path}\n\
+         @@ -0,0 +1 @@\n\
+         +{target}\n\
+         \\ No newline at end of file\n",
+        path = rel_path,
+        target = target.display()
+    )
+}
+
+/// Extracts the new-file start line from the first `@@ -a,b +c,d @@` header.
+/// Pure deletions report the line before the removed block, clamped to 1.
+fn parse_first_hunk_line(diff: &str) -> Option<u32> {
+    let header = diff.lines().find(|l| l.starts_with("@@ "))?;
+    let new_range = header.split(' ').find(|p| p.starts_with('+'))?;
+    let start = new_range[1..].split(',').next()?;
+    start.parse::<u32>().ok().map(|n| n.max(1))
+}
+
+pub enum IdMatch {
+    Unique(GitFile),
+    Ambiguous(usize),
+    NotFound,
+}
+
+pub fn find_file_by_id(files: &[GitFile], id: &str) -> IdMatch {
+    let matches: Vec<_> = files.iter().filter(|f| f.stable_id.matches(id)).collect();
+    if matches.is_empty() {
+        log::debug!("ID {} matches none of {} files", id, files.len());
+        return IdMatch::NotFound;
+    }
+    let unique_paths: std::collections::HashSet<_> = matches.iter().map(|f| &f.rel_path).collect();
+    if unique_paths.len() == 1 {
+        log::debug!("ID {} is {}", id, matches[0].rel_path);
+        IdMatch::Unique(matches[0].clone())
+    } else {
+        log::debug!("ID {} matches {:?}", id, unique_paths);
+        IdMatch::Ambiguous(unique_paths.len())
+    }
+}
+
+/// Files whose IDs are a typo away from `id`: one character changed,
+/// added, dropped or swapped with the next, against the full hash cut to
+/// the length of `id` or one either side, so a stray or missing last
+/// character counts too. A stray character leaving a single one to match
+/// would find too many, so that needs two, and a single character only
+/// suggests the IDs it starts. One per path, in list order, at most three.
+pub fn near_ids<'a>(files: &'a [GitFile], id: &str) -> Vec<&'a GitFile> {
+    let input: Vec<char> = id.chars().collect();
+    let mut near: Vec<&GitFile> = Vec::new();
+    for file in files {
+        if near.len() == 3 || near.iter().any(|f| f.rel_path == file.rel_path) {
+            continue;
+        }
+        let hash: Vec<char> = file.stable_id.full_hash.chars().collect();
+        if input.len() < 2 && !hash.starts_with(&input) {
+            continue;
+        }
+        let typo = (input.len().saturating_sub(1)..=input.len() + 1)
+            .filter(|&len| len <= hash.len() && (len >= input.len() || len >= 2))
+            .any(|len| edit_distance(&input, &hash[..len]) <= 1);
+        if typo {
+            near.push(file);
+        }
+    }
+    near
+}
+
+/// Edits, counting a swap of neighbours as one, to turn `a` into `b`.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (rows[i - 1][j] + 1)
+                .min(row[j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+/// The unstaged and untracked files, which an action without an ID
+/// chooses from.
+pub fn actionable_files(files: &[GitFile]) -> Vec<&GitFile> {
+    files
+        .iter()
+        .filter(|f| f.file_type == FileType::Unstaged || f.file_type == FileType::Untracked)
+        .collect()
+}
+
+/// How an action without an ID chooses from the actionable files, short
+/// of asking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pick<'a> {
+    First,
+    /// The one earliest in these paths, most recent first
+    LastUsed(&'a [String]),
+    LargestDiff,
+}
+
+/// The file of `actionable` that `pick` chooses, else the first.
+pub fn get_first_actionable_file(actionable: &[&GitFile], pick: Pick) -> Option<GitFile> {
+    let chosen = match pick {
+        Pick::First => None,
+        Pick::LastUsed(recent) => recent
+            .iter()
+            .find_map(|path| actionable.iter().find(|f| f.rel_path == *path)),
+        // The first of any that tie
+        Pick::LargestDiff => actionable.iter().rev().max_by_key(|f| {
+            f.diff_stats
+                .as_ref()
+                .map_or(0, |s| u64::from(s.added) + u64::from(s.removed))
+        }),
+    };
+    chosen.or(actionable.first()).map(|f| (*f).clone())
+}
+
+pub fn get_first_staged_file(files: &[GitFile]) -> Option<GitFile> {
+    files
+        .iter()
+        .find(|f| f.file_type == FileType::Staged)
+        .cloned()
+}
+
+/// Finds a file by a path relative to `cwd`, as f displays them, or failing
+/// that by repo-relative path, as git prints them.
+pub fn find_file_by_path(files: &[GitFile], input: &str, cwd: &Path) -> Option<GitFile> {
+    let abs = normalize_path(&cwd.join(input));
+    files
+        .iter()
+        .find(|f| f.abs_path == abs)
+        .or_else(|| files.iter().find(|f| f.rel_path == input))
+        .cloned()
+}
+
+/// `input`, resolved from `cwd`, as a path relative to the work tree
+/// `root`, or `None` when it points outside it. The path needn't exist.
+pub fn repo_path(input: &str, cwd: &Path, root: &Path) -> Option<String> {
+    let abs = normalize_path(&cwd.join(input));
+    let rel = abs.strip_prefix(root).ok()?;
+    Some(rel.to_string_lossy().into_owned())
+}
+
+/// Lexically resolves `.` and `..` components without touching the filesystem.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            other => result.push(other),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_push_remote() {
+        let remotes = vec!["fork".to_string(), "origin".to_string()];
+        assert_eq!(
+            choose_push_remote(None, None, &remotes).as_deref(),
+            Some("origin")
+        );
+        assert_eq!(
+            choose_push_remote(None, Some("fork".into()), &remotes).as_deref(),
+            Some("fork")
+        );
+        assert_eq!(
+            choose_push_remote(Some("up".into()), Some("fork".into()), &remotes).as_deref(),
+            Some("up")
+        );
+        assert_eq!(
+            choose_push_remote(None, None, &["upstream".to_string()]).as_deref(),
+            Some("upstream")
+        );
+        assert_eq!(
+            choose_push_remote(None, None, &["a".to_string(), "b".to_string()]),
+            None
+        );
+        assert_eq!(choose_push_remote(None, None, &[]), None);
+    }
+
+    fn make_file(rel_path: &str, display: &str, full_hash: &str) -> GitFile {
+        GitFile {
+            mtime: 0,
+            rel_path: rel_path.to_string(),
+            abs_path: PathBuf::from(rel_path),
+            file_type: FileType::Unstaged,
+            stable_id: StableId {
+                display: display.to_string(),
+                full_hash: full_hash.to_string(),
+            },
+            diff_stats: None,
+            lfs_size: None,
+            mode_change: None,
+            status: 'M',
+        }
+    }
+
+    #[test]
+    fn stable_id_matches_exact() {
+        let id = StableId {
+            display: "fk".to_string(),
+            full_hash: "fkkabcdefghi".to_string(),
+        };
+        assert!(id.matches("fkkabcdefghi"));
+    }
+
+    #[test]
+    fn stable_id_matches_prefix() {
+        let id = StableId {
+            display: "fk".to_string(),
+            full_hash: "fkkabcdefghi".to_string(),
+        };
+        assert!(id.matches("fk"));
+        assert!(id.matches("fkk"));
+        assert!(id.matches("fkka"));
+    }
+
+    #[test]
+    fn stable_id_no_match_wrong_prefix() {
+        let id = StableId {
+            display: "fk".to_string(),
+            full_hash: "fkkabcdefghi".to_string(),
+        };
+        assert!(!id.matches("fka"));
+        assert!(!id.matches("gk"));
+        assert!(!id.matches("fkkz"));
+    }
+
+    #[test]
+    fn find_file_unique_with_short_input() {
+        let files = vec![make_file("src/main.rs", "fk", "fkkabcdefghi")];
+        match find_file_by_id(&files, "fk") {
+            IdMatch::Unique(f) => assert_eq!(f.rel_path, "src/main.rs"),
+            _ => panic!("expected unique match"),
+        }
+    }
+
+    #[test]
+    fn find_file_unique_with_longer_input() {
+        let files = vec![make_file("src/main.rs", "fk", "fkkabcdefghi")];
+        match find_file_by_id(&files, "fkk") {
+            IdMatch::Unique(f) => assert_eq!(f.rel_path, "src/main.rs"),
+            _ => panic!("expected unique match"),
+        }
+    }
+
+    #[test]
+    fn find_file_ambiguous_short_prefix() {
+        let files = vec![
+            make_file("src/main.rs", "fkk", "fkkabcdefghi"),
+            make_file("src/lib.rs", "fka", "fkaabcdefghi"),
+        ];
+        match find_file_by_id(&files, "fk") {
+            IdMatch::Ambiguous(n) => assert_eq!(n, 2),
+            _ => panic!("expected ambiguous match"),
+        }
+    }
+
+    #[test]
+    fn find_file_disambiguate_with_longer_prefix() {
+        let files = vec![
+            make_file("src/main.rs", "fkk", "fkkabcdefghi"),
+            make_file("src/lib.rs", "fka", "fkaabcdefghi"),
+        ];
+        match find_file_by_id(&files, "fkk") {
+            IdMatch::Unique(f) => assert_eq!(f.rel_path, "src/main.rs"),
+            _ => panic!("expected unique match for fkk"),
+        }
+        match find_file_by_id(&files, "fka") {
+            IdMatch::Unique(f) => assert_eq!(f.rel_path, "src/lib.rs"),
+            _ => panic!("expected unique match for fka"),
+        }
+    }
+
+    #[test]
+    fn find_file_not_found() {
+        let files = vec![make_file("src/main.rs", "fk", "fkkabcdefghi")];
+        match find_file_by_id(&files, "gk") {
+            IdMatch::NotFound => {}
+            _ => panic!("expected not found"),
+        }
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        let d = |a: &str, b: &str| {
+            edit_distance(
+                &a.chars().collect::<Vec<_>>(),
+                &b.chars().collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(d("gk", "gk"), 0);
+        assert_eq!(d("gk", "kg"), 1);
+        assert_eq!(d("gk", "gl"), 1);
+        assert_eq!(d("gk", "g"), 1);
+        assert_eq!(d("gk", "gkd"), 1);
+        assert_eq!(d("gk", "df"), 2);
+        assert_eq!(d("", "ab"), 2);
+    }
+
+    #[test]
+    fn near_ids_finds_typos_and_stray_characters() {
+        let files = vec![
+            make_file("src/main.rs", "fk", "fkkabcdefghi"),
+            make_file("src/lib.rs", "gd", "gdsabcdefghi"),
+            make_file("src/lib.rs", "gd", "gdsabcdefghi"),
+            make_file("README.md", "hl", "hlaabcdefghi"),
+        ];
+        let paths = |id: &str| -> Vec<String> {
+            near_ids(&files, id)
+                .iter()
+                .map(|f| f.rel_path.clone())
+                .collect()
+        };
+        assert_eq!(paths("kf"), ["src/main.rs"]);
+        assert_eq!(paths("gf"), ["src/lib.rs"]);
+        assert_eq!(paths("gdx"), ["src/lib.rs"]);
+        assert_eq!(paths("hlx"), ["README.md"]);
+        assert!(paths("sa").is_empty());
+        // Any ID is a change away from one character
+        assert_eq!(paths("f"), ["src/main.rs"]);
+        assert!(paths("d").is_empty());
+    }
+
+    #[test]
+    fn default_target_strategies() {
+        let mut files = vec![
+            make_file("Cargo.toml", "fk", "fkkabcdefghi"),
+            make_file("src/main.rs", "gd", "gdsabcdefghi"),
+            make_file("src/lib.rs", "hl", "hlaabcdefghi"),
+            make_file("notes.md", "sa", "saaabcdefghi"),
+        ];
+        files[0].file_type = FileType::Staged;
+        files[1].diff_stats = Some(DiffStats {
+            added: 2,
+            removed: 1,
+        });
+        files[2].diff_stats = Some(DiffStats {
+            added: 10,
+            removed: 0,
+        });
+        files[3].file_type = FileType::Untracked;
+        let recent = ["Cargo.toml".to_string(), "notes.md".to_string()];
+        let actionable = actionable_files(&files);
+        let pick = |pick: Pick| get_first_actionable_file(&actionable, pick).map(|f| f.rel_path);
+
+        assert_eq!(pick(Pick::First).as_deref(), Some("src/main.rs"));
+        // Staged files aren't the default, however recent
+        assert_eq!(pick(Pick::LastUsed(&recent)).as_deref(), Some("notes.md"));
+        assert_eq!(pick(Pick::LastUsed(&[])).as_deref(), Some("src/main.rs"));
+        assert_eq!(pick(Pick::LargestDiff).as_deref(), Some("src/lib.rs"));
+        assert_eq!(
+            get_first_actionable_file(&actionable_files(&files[..1]), Pick::LargestDiff)
+                .map(|f| f.rel_path),
+            None
+        );
+    }
+
+    #[test]
+    fn find_file_old_id_still_works_after_collision() {
+        // Scenario: User memorized "fk" for file A (full_hash fkkabcdefghi)
+        // Later, file B is added with full_hash fkaabcdefghi
+        // Display IDs are now "fkk" and "fka", but "fkk" should still find file A
+        let files = vec![
+            make_file("src/main.rs", "fkk", "fkkabcdefghi"),
+            make_file("src/lib.rs", "fka", "fkaabcdefghi"),
+        ];
+        // Old memorized ID "fkk" still works
+        match find_file_by_id(&files, "fkk") {
+            IdMatch::Unique(f) => assert_eq!(f.rel_path, "src/main.rs"),
+            _ => panic!("old ID should still work"),
+        }
+    }
+
+    #[test]
+    fn find_file_same_path_staged_and_unstaged_is_unique() {
+        let mut staged = make_file("src/main.rs", "fk", "fkkabcdefghi");
+        staged.file_type = FileType::Staged;
+        let mut unstaged = make_file("src/main.rs", "fk", "fkkabcdefghi");
+        unstaged.file_type = FileType::Unstaged;
+        let files = vec![staged, unstaged];
+        match find_file_by_id(&files, "fk") {
+            IdMatch::Unique(f) => assert_eq!(f.rel_path, "src/main.rs"),
+            IdMatch::Ambiguous(n) => panic!("expected unique, got ambiguous({})", n),
+            IdMatch::NotFound => panic!("expected unique, got not found"),
+        }
+    }
+
+    #[test]
+    fn parse_numstat_z_plain_and_renamed() {
+        let output = "2\t1\tsrc/main.rs\0\
+                      5\t0\t\0old name.rs\0new\nname.rs\0\
+                      -\t-\tlogo.png\0";
+        let stats = parse_numstat_z(output);
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats["src/main.rs"].added, 2);
+        assert_eq!(stats["src/main.rs"].removed, 1);
+        assert_eq!(stats["new\nname.rs"].added, 5);
+        assert_eq!(stats["logo.png"].added, 0);
+    }
+
+    #[test]
+    fn relative_to_descendant_and_sibling() {
+        let root = Path::new("/repo");
+        let file = Path::new("/repo/src/main.rs");
+        assert_eq!(relative_to(file, root), PathBuf::from("src/main.rs"));
+        assert_eq!(
+            relative_to(file, Path::new("/repo/docs/guide")),
+            PathBuf::from("../../src/main.rs")
+        );
+        assert_eq!(relative_to(root, root), PathBuf::from("."));
+    }
+
+    #[test]
+    fn parse_status_summary_counts_sections() {
+        let output = "# branch.oid abc123\0# branch.head main\0\
+                      # branch.upstream origin/main\0# branch.ab +2 -1\0\
+                      1 M. N... 100644 100644 100644 a b src/lib.rs\0\
+                      1 .M N... 100644 100644 100644 a b src/main.rs\0\
+                      1 MM N... 100644 100644 100644 a b both.rs\0\
+                      2 R. N... 100644 100644 100644 a b R100 new.rs\0old.rs\0\
+                      u UU N... 100644 100644 100644 100644 a b c conflict.rs\0\
+                      ? notes.txt\0";
+        let summary = parse_status_summary(output);
+        assert_eq!(summary.branch.as_deref(), Some("main"));
+        assert_eq!(summary.upstream.as_deref(), Some("origin/main"));
+        assert_eq!((summary.ahead, summary.behind), (2, 1));
+        assert_eq!(summary.staged, 3);
+        assert_eq!(summary.unstaged, 2);
+        assert_eq!(summary.untracked, 1);
+        assert_eq!(summary.conflicted, 1);
+    }
+
+    #[test]
+    fn parse_status_summary_detached() {
+        let summary = parse_status_summary("# branch.oid abc\0# branch.head (detached)\0");
+        assert_eq!(summary.branch, None);
+        assert_eq!(summary.commit.as_deref(), Some("abc"));
+        let unborn = parse_status_summary("# branch.oid (initial)\0# branch.head main\0");
+        assert_eq!(unborn.commit, None);
+        assert_eq!(summary.staged, 0);
+    }
+
+    #[test]
+    fn parse_first_hunk_line_variants() {
+        let diff = "diff --git a/x b/x\n--- a/x\n+++ b/x\n\
+                    @@ -10,2 +12,3 @@ fn main() {\n+a\n@@ -40 +44 @@\n";
+        assert_eq!(parse_first_hunk_line(diff), Some(12));
+        assert_eq!(parse_first_hunk_line("@@ -3 +5 @@\n"), Some(5));
+        assert_eq!(parse_first_hunk_line("@@ -1,2 +0,0 @@\n"), Some(1));
+        assert_eq!(parse_first_hunk_line("Binary files differ\n"), None);
+    }
+
+    #[test]
+    fn test_parse_first_lines() {
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs\n\
+                    --- a/src/lib.rs\n\
+                    +++ b/src/lib.rs\n\
+                    @@ -140,0 +143,2 @@ impl Repo {\n\
+                    +--- a/looks like a header\n\
+                    @@ -200 +203 @@\n\
+                    diff --git a/old.txt b/old.txt\n\
+                    deleted file mode 100644\n\
+                    --- a/old.txt\n\
+                    +++ /dev/null\n\
+                    @@ -1,3 +0,0 @@\n\
+                    diff --git a/my notes.md b/my notes.md\n\
+                    --- a/my notes.md\t\n\
+                    +++ b/my notes.md\t\n\
+                    @@ -7 +7 @@\n\
+                    diff --git a/logo.png b/logo.png\n\
+                    Binary files a/logo.png and b/logo.png differ\n";
+        let lines = parse_first_lines(diff);
+        assert_eq!(lines.get("src/lib.rs"), Some(&143));
+        assert_eq!(lines.get("old.txt"), Some(&1));
+        assert_eq!(lines.get("my notes.md"), Some(&7));
+        assert_eq!(lines.len(), 3);
+    }
+
+    #[test]
+    fn find_file_by_path_repo_and_cwd_relative() {
+        let mut file = make_file("src/main.rs", "fk", "fkkabcdefghi");
+        file.abs_path = PathBuf::from("/repo/src/main.rs");
+        let files = vec![file];
+        let root = Path::new("/repo");
+        assert!(find_file_by_path(&files, "src/main.rs", Path::new("/repo/docs")).is_some());
+        assert!(find_file_by_path(&files, "main.rs", Path::new("/repo/src")).is_some());
+        assert!(find_file_by_path(&files, "../src/./main.rs", Path::new("/repo/docs")).is_some());
+        assert!(find_file_by_path(&files, "/repo/src/main.rs", root).is_some());
+        assert!(find_file_by_path(&files, "main.rs", root).is_none());
+    }
+
+    #[test]
+    fn find_file_by_path_prefers_cwd_relative() {
+        let mut nested = make_file("src/lib.rs", "fk", "fkkabcdefghi");
+        nested.abs_path = PathBuf::from("/repo/src/lib.rs");
+        let mut top = make_file("lib.rs", "sa", "saaabcdefghi");
+        top.abs_path = PathBuf::from("/repo/lib.rs");
+        let files = vec![top, nested];
+        let found = find_file_by_path(&files, "lib.rs", Path::new("/repo/src")).unwrap();
+        assert_eq!(found.rel_path, "src/lib.rs");
+        let found = find_file_by_path(&files, "lib.rs", Path::new("/repo/docs")).unwrap();
+        assert_eq!(found.rel_path, "lib.rs");
+    }
+
+    #[test]
+    fn generate_ids_no_collision() {
+        let paths = vec!["src/main.rs".to_string()];
+        let ids = generate_ids(&paths, DEFAULT_ID_CHARS);
+        assert_eq!(ids.len(), 1);
+        assert_eq!(ids[0].0.len(), 1); // minimal display length
+        assert_eq!(ids[0].1.len(), 12); // full hash length
+    }
+
+    #[test]
+    fn generate_ids_with_collision_extends() {
+        // Find two paths that collide on first char
+        // We'll just verify that when there's a collision, displays get longer
+        let paths = vec!["a".to_string(), "b".to_string()];
+        let ids = generate_ids(&paths, DEFAULT_ID_CHARS);
+        // Both should have display IDs, and if they collide, they extend
+        assert!(!ids[0].0.is_empty());
+        assert!(!ids[1].0.is_empty());
+        // Full hashes should be 12 chars
+        assert_eq!(ids[0].1.len(), 12);
+        assert_eq!(ids[1].1.len(), 12);
+    }
+
+    #[test]
+    fn new_symlink_diff_adds_the_target() {
+        let diff = new_symlink_diff("docs/current", Path::new("../v2"));
+        assert!(
+            diff.starts_with("diff --git a/docs/current b/docs/current\nnew file mode 120000\n")
+        );
+        assert!(diff.contains("+++ b/docs/current\n@@ -0,0 +1 @@\n+../v2\n"));
+    }
+
+    #[test]
+    fn test_describe_outgoing() {
+        let mut outgoing = Outgoing {
+            upstream: "origin/main".into(),
+            ahead: 2,
+            behind: 0,
+            added: 120,
+            removed: 14,
+        };
+        assert_eq!(
+            outgoing.describe(),
+            "2 commits ahead of origin/main (+120/−14)"
+        );
+        outgoing.ahead = 1;
+        outgoing.behind = 3;
+        assert_eq!(
+            outgoing.describe(),
+            "1 commit ahead of origin/main (+120/−14), 3 behind"
+        );
+    }
+
+    #[test]
+    fn test_parse_left_right() {
+        assert_eq!(parse_left_right("3\t2"), Some((3, 2)));
+        assert_eq!(parse_left_right("0\t0\n"), Some((0, 0)));
+        assert_eq!(parse_left_right("7"), None);
+        assert_eq!(parse_left_right("a\tb"), None);
+    }
+}
@@ -0,0 +1,164 @@
+//! Git LFS files. Git itself only sees their pointers, so without help f
+//! would count a 120 MB video as 3 lines and diff the pointer text.
+
+use crate::git_status::GitFile;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Pointer files are small; anything bigger is real content.
+const MAX_POINTER_SIZE: u64 = 1024;
+
+/// How much of a file is checked for NUL bytes to tell text from binary,
+/// the same amount git looks at.
+const BINARY_CHECK: usize = 8000;
+
+/// Makes `git diff` show what LFS pointers point to. git runs this through
+/// the shell with the file appended, which is what `git lfs smudge` reads.
+pub const TEXTCONV: &str = "diff.lfs.textconv=git lfs smudge <";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pointer {
+    /// `sha256:<hex>`
+    pub oid: String,
+    pub size: u64,
+}
+
+/// Parses an LFS pointer file, as stored in git in place of the content.
+pub fn parse_pointer(content: &[u8]) -> Option<Pointer> {
+    let text = std::str::from_utf8(content).ok()?;
+    let mut lines = text.lines();
+    if !lines.next()?.starts_with("version https://git-lfs.") {
+        return None;
+    }
+    let (mut oid, mut size) = (None, None);
+    for line in lines {
+        match line.split_once(' ') {
+            Some(("oid", value)) => oid = Some(value.to_string()),
+            Some(("size", value)) => size = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Some(Pointer {
+        oid: oid?,
+        size: size?,
+    })
+}
+
+/// The repo-relative `paths` git sends through the LFS filter, from one
+/// `git check-attr` for all of them.
+pub fn tracked(paths: &[&str]) -> HashSet<String> {
+    if paths.is_empty() {
+        return HashSet::new();
+    }
+    let input: Vec<u8> = paths
+        .iter()
+        .flat_map(|p| [p.as_bytes(), b"\0"].concat())
+        .collect();
+    let Ok(output) = crate::exec::output_with_input(
+        Command::new("git").args(["check-attr", "--stdin", "-z", "filter"]),
+        Some(&input),
+    ) else {
+        return HashSet::new();
+    };
+    // Records are `<path> NUL filter NUL <value> NUL`
+    let output = String::from_utf8_lossy(&output);
+    let fields: Vec<&str> = output.split('\0').collect();
+    fields
+        .chunks(3)
+        .filter(|record| record.len() == 3 && record[2] == "lfs")
+        .map(|record| record[0].to_string())
+        .collect()
+}
+
+/// The size of the LFS object at `path`: the file's own size once
+/// smudged, or the size its pointer records when it hasn't been fetched.
+pub fn size(path: &Path) -> Option<u64> {
+    let len = fs::metadata(path).ok()?.len();
+    if len <= MAX_POINTER_SIZE
+        && let Some(pointer) = fs::read(path).ok().as_deref().and_then(parse_pointer)
+    {
+        return Some(pointer.size);
+    }
+    Some(len)
+}
+
+/// `bytes` in decimal units, e.g. `120 MB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 || value >= 10.0 {
+        format!("{:.0} {}", value, UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Whether the `git lfs` command is installed.
+pub fn available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        crate::exec::run_output(Command::new("git").args(["lfs", "version"]))
+            .is_ok_and(|o| o.status.success())
+    })
+}
+
+/// Whether `file` should be diffed by content rather than by pointer: it's
+/// an LFS file, git-lfs can fetch the other side, and the content is text.
+/// For binaries the pointer diff, which shows the size change, says more.
+pub fn diff_smudged(file: &GitFile) -> bool {
+    file.lfs_size.is_some() && available() && is_text(&file.abs_path)
+}
+
+fn is_text(path: &Path) -> bool {
+    let Ok(mut f) = fs::File::open(path) else {
+        return false;
+    };
+    let mut buf = vec![0; BINARY_CHECK];
+    let Ok(n) = f.read(&mut buf) else {
+        return false;
+    };
+    !buf[..n].contains(&0) && parse_pointer(&buf[..n]).is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pointer() {
+        let pointer = b"version https://git-lfs.github.com/spec/v1\n\
+            oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+            size 12345\n";
+        assert_eq!(
+            parse_pointer(pointer),
+            Some(Pointer {
+                oid: "sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393"
+                    .into(),
+                size: 12345,
+            })
+        );
+        assert_eq!(parse_pointer(b"size 3\n"), None);
+        assert_eq!(
+            parse_pointer(b"version https://git-lfs.github.com/spec/v1\nsize 3\n"),
+            None
+        );
+        assert_eq!(parse_pointer(&[0xff, 0x00]), None);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1_500), "1.5 kB");
+        assert_eq!(format_size(120_000_000), "120 MB");
+        assert_eq!(format_size(3_210_000_000), "3.2 GB");
+    }
+}
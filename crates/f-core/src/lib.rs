@@ -0,0 +1,39 @@
+//! The repository model behind the `f` CLI, for editor plugins and other
+//! tools that want f's file IDs without shelling out to it.
+//!
+//! - [`git_status`] lists changed files ([`git_status::get_all_files`]),
+//!   gives each a stable ID ([`git_status::generate_ids`]) and resolves IDs
+//!   and paths back to files ([`git_status::find_file_by_id`],
+//!   [`git_status::find_file_by_path`]).
+//! - [`porcelain`] parses `git status --porcelain=v2 -z`.
+//! - [`repo_state`] detects a paused rebase, merge, cherry-pick, revert or
+//!   bisect, and a branch whose upstream is gone or that's fallen behind.
+//! - [`config`] reads `f.toml`.
+//! - [`error`] has the failures worth telling apart, and [`exec`] the
+//!   process helpers everything above runs git through.
+//! - [`lfs`] covers Git LFS files, whose sizes the file list reports.
+//!
+//! IDs are computed from paths alone, so the same path gets the same ID
+//! from this crate as from the `f` binary of the same version.
+//!
+//! ```no_run
+//! use f_core::git_status::{IdMatch, find_file_by_id, get_all_files};
+//!
+//! let config = f_core::config::Config::load();
+//! let files = get_all_files(&config.id_chars())?;
+//! if let IdMatch::Unique(file) = find_file_by_id(&files, "df") {
+//!     println!("{}", file.abs_path.display());
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The crate follows semver and is versioned with the binary. While it is
+//! at 0.x, a breaking change to anything public bumps the minor version.
+
+pub mod config;
+pub mod error;
+pub mod exec;
+pub mod git_status;
+pub mod lfs;
+pub mod porcelain;
+pub mod repo_state;
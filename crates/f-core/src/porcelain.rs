@@ -0,0 +1,295 @@
+//! Parser for `git status --porcelain=v2 -z`. With `-z`, paths are emitted
+//! verbatim (no quoting or octal escapes), so anything after the fixed
+//! fields of a record, spaces and ` -> ` included, is the path.
+
+/// One changed path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub kind: Kind,
+    /// Index status (`X`), with `.` meaning unchanged
+    pub index: char,
+    /// Work tree status (`Y`), with `.` meaning unchanged
+    pub worktree: char,
+    pub path: String,
+    /// The source path of a rename or copy
+    pub orig_path: Option<String>,
+    pub submodule: Option<Submodule>,
+    /// File modes in HEAD, the index and the work tree, `0` where the file
+    /// doesn't exist. Not reported for unmerged entries.
+    pub modes: Option<Modes>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Modes {
+    pub head: u32,
+    pub index: u32,
+    pub worktree: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Ordinary,
+    Renamed,
+    Copied,
+    Unmerged,
+    Untracked,
+    Ignored,
+}
+
+/// The `S<c><m><u>` field of a submodule record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Submodule {
+    pub commit_changed: bool,
+    pub modified: bool,
+    pub untracked: bool,
+}
+
+impl Entry {
+    pub fn is_staged(&self) -> bool {
+        !matches!(self.index, '.' | '?' | '!')
+    }
+
+    pub fn is_unstaged(&self) -> bool {
+        !matches!(self.worktree, '.' | '?' | '!')
+    }
+
+    /// The HEAD and index modes when staging changed the mode.
+    pub fn staged_mode_change(&self) -> Option<(u32, u32)> {
+        let modes = self.modes?;
+        mode_change(modes.head, modes.index)
+    }
+
+    /// The index and work tree modes when the work tree changed the mode.
+    pub fn unstaged_mode_change(&self) -> Option<(u32, u32)> {
+        let modes = self.modes?;
+        mode_change(modes.index, modes.worktree)
+    }
+
+    /// A submodule whose only change is untracked files inside it, which
+    /// nothing in the parent repository can stage.
+    pub fn is_submodule_untracked_only(&self) -> bool {
+        self.submodule
+            .is_some_and(|s| !s.commit_changed && !s.modified)
+            && !self.is_staged()
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Status {
+    /// `# <key> <value>` headers, e.g. `branch.head`, when `--branch` is given
+    pub headers: Vec<(String, String)>,
+    pub entries: Vec<Entry>,
+}
+
+impl Status {
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Additions and deletions change the mode from or to 0, which isn't a
+/// mode change worth reporting.
+fn mode_change(old: u32, new: u32) -> Option<(u32, u32)> {
+    (old != new && old != 0 && new != 0).then_some((old, new))
+}
+
+pub fn parse(output: &str) -> Status {
+    let mut status = Status::default();
+    let mut records = output.split('\0');
+    while let Some(record) = records.next() {
+        if let Some(header) = record.strip_prefix("# ") {
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+            status.headers.push((key.to_string(), value.to_string()));
+            continue;
+        }
+        let entry = match record.split_once(' ') {
+            Some(("1", rest)) => changed(rest, 7, Kind::Ordinary),
+            Some(("2", rest)) => {
+                // The original path follows as its own NUL-terminated field
+                let orig_path = records.next().map(str::to_string);
+                changed(rest, 8, Kind::Renamed).map(|mut entry| {
+                    if rest.split(' ').nth(7).is_some_and(|s| s.starts_with('C')) {
+                        entry.kind = Kind::Copied;
+                    }
+                    entry.orig_path = orig_path;
+                    entry
+                })
+            }
+            Some(("u", rest)) => changed(rest, 9, Kind::Unmerged),
+            Some(("?", path)) => Some(plain(path, Kind::Untracked, '?')),
+            Some(("!", path)) => Some(plain(path, Kind::Ignored, '!')),
+            _ => None,
+        };
+        status.entries.extend(entry);
+    }
+    status
+}
+
+/// A `1`, `2` or `u` record: `<XY> <sub>`, then `fields - 2` more fixed
+/// fields, then the path.
+fn changed(rest: &str, fields: usize, kind: Kind) -> Option<Entry> {
+    let parts: Vec<&str> = rest.splitn(fields + 1, ' ').collect();
+    if parts.len() != fields + 1 {
+        return None;
+    }
+    let mut xy = parts[0].chars();
+    Some(Entry {
+        kind,
+        index: xy.next()?,
+        worktree: xy.next()?,
+        path: parts[fields].to_string(),
+        orig_path: None,
+        submodule: parse_submodule(parts[1]),
+        modes: match kind {
+            Kind::Unmerged => None,
+            _ => parse_modes(&parts[2..5]),
+        },
+    })
+}
+
+fn parse_modes(fields: &[&str]) -> Option<Modes> {
+    let mode = |i: usize| u32::from_str_radix(fields.get(i)?, 8).ok();
+    Some(Modes {
+        head: mode(0)?,
+        index: mode(1)?,
+        worktree: mode(2)?,
+    })
+}
+
+fn plain(path: &str, kind: Kind, status: char) -> Entry {
+    Entry {
+        kind,
+        index: status,
+        worktree: status,
+        path: path.to_string(),
+        orig_path: None,
+        submodule: None,
+        modes: None,
+    }
+}
+
+fn parse_submodule(field: &str) -> Option<Submodule> {
+    let flags: Vec<char> = field.strip_prefix('S')?.chars().collect();
+    match flags.as_slice() {
+        [c, m, u] => Some(Submodule {
+            commit_changed: *c == 'C',
+            modified: *m == 'M',
+            untracked: *u == 'U',
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASHES: &str = "100644 100644 100644 1111111111111111111111111111111111111111 2222222222222222222222222222222222222222";
+
+    fn ordinary(xy: &str, path: &str) -> String {
+        format!("1 {} N... {} {}", xy, HASHES, path)
+    }
+
+    #[test]
+    fn test_paths_with_spaces_and_arrows() {
+        let output =
+            ordinary(".M", "docs/a -> b.md") + "\0" + &ordinary("M.", " leading space") + "\0";
+        let status = parse(&output);
+        assert_eq!(status.entries.len(), 2);
+        assert_eq!(status.entries[0].path, "docs/a -> b.md");
+        assert!(status.entries[0].is_unstaged() && !status.entries[0].is_staged());
+        assert_eq!(status.entries[1].path, " leading space");
+        assert!(status.entries[1].is_staged());
+    }
+
+    #[test]
+    fn test_non_ascii_and_quotes_are_verbatim() {
+        let status = parse("? caf\u{e9} \"quoted\".txt\0? tab\there\0");
+        assert_eq!(status.entries[0].path, "café \"quoted\".txt");
+        assert_eq!(status.entries[0].kind, Kind::Untracked);
+        assert_eq!(status.entries[1].path, "tab\there");
+        assert!(!status.entries[1].is_staged() && !status.entries[1].is_unstaged());
+    }
+
+    #[test]
+    fn test_rename_and_copy_records() {
+        let output = format!(
+            "2 R. N... {} R100 new name.rs\0old name.rs\02 C. N... {} C75 copy.rs\0orig.rs\0? after\0",
+            HASHES, HASHES
+        );
+        let status = parse(&output);
+        assert_eq!(status.entries.len(), 3);
+        let rename = &status.entries[0];
+        assert_eq!(rename.kind, Kind::Renamed);
+        assert_eq!(rename.path, "new name.rs");
+        assert_eq!(rename.orig_path.as_deref(), Some("old name.rs"));
+        assert_eq!(status.entries[1].kind, Kind::Copied);
+        assert_eq!(status.entries[1].orig_path.as_deref(), Some("orig.rs"));
+        assert_eq!(status.entries[2].path, "after");
+    }
+
+    #[test]
+    fn test_mode_changes() {
+        let record = |modes: &str| {
+            format!(
+                "1 MM N... {} {} {} exec.sh\0",
+                modes,
+                "1".repeat(40),
+                "2".repeat(40)
+            )
+        };
+        let entry = &parse(&record("100644 100755 100644")).entries[0];
+        assert_eq!(entry.staged_mode_change(), Some((0o100644, 0o100755)));
+        assert_eq!(entry.unstaged_mode_change(), Some((0o100755, 0o100644)));
+        let entry = &parse(&record("100644 100644 100644")).entries[0];
+        assert_eq!(entry.staged_mode_change(), None);
+        assert_eq!(entry.unstaged_mode_change(), None);
+        // Added and deleted files go from or to mode 0
+        let entry = &parse(&record("000000 100755 000000")).entries[0];
+        assert_eq!(entry.staged_mode_change(), None);
+        assert_eq!(entry.unstaged_mode_change(), None);
+    }
+
+    #[test]
+    fn test_unmerged_record() {
+        let sha = "3".repeat(40);
+        let output = format!(
+            "u UU N... 100644 100644 100644 100644 {} {} {} both.rs\0",
+            sha, sha, sha
+        );
+        let entry = &parse(&output).entries[0];
+        assert_eq!(entry.kind, Kind::Unmerged);
+        assert_eq!(entry.path, "both.rs");
+        assert!(entry.is_staged() && entry.is_unstaged());
+    }
+
+    #[test]
+    fn test_submodule_record() {
+        let output = format!("1 .M S.MU {} vendor/lib\0", HASHES);
+        let entry = &parse(&output).entries[0];
+        assert_eq!(
+            entry.submodule,
+            Some(Submodule {
+                commit_changed: false,
+                modified: true,
+                untracked: true,
+            })
+        );
+        assert_eq!(entry.path, "vendor/lib");
+        assert!(!entry.is_submodule_untracked_only());
+        let untracked_only = format!("1 .M S..U {} vendor/lib\0", HASHES);
+        assert!(parse(&untracked_only).entries[0].is_submodule_untracked_only());
+        assert_eq!(parse(&ordinary(".M", "x")).entries[0].submodule, None);
+    }
+
+    #[test]
+    fn test_headers() {
+        let status = parse("# branch.oid abc\0# branch.head main\0");
+        assert_eq!(status.header("branch.head"), Some("main"));
+        assert_eq!(status.header("branch.upstream"), None);
+        assert!(status.entries.is_empty());
+    }
+}
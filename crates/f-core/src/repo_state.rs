@@ -0,0 +1,327 @@
+use crate::git_status::git_output;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// An operation git has paused mid-way, detected from the marker files it
+/// leaves in the git directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoState {
+    Rebase {
+        /// (current, total) commits, when git records them
+        progress: Option<(u32, u32)>,
+        branch: Option<String>,
+    },
+    Merge,
+    CherryPick,
+    Revert,
+    Bisect,
+}
+
+impl RepoState {
+    pub fn describe(&self) -> String {
+        match self {
+            RepoState::Rebase { progress, branch } => {
+                let mut text = "Rebase in progress".to_string();
+                if let Some(branch) = branch {
+                    text.push_str(&format!(" on {}", branch));
+                }
+                if let Some((current, total)) = progress {
+                    text.push_str(&format!(" ({}/{})", current, total));
+                }
+                text
+            }
+            RepoState::Merge => "Merge in progress".to_string(),
+            RepoState::CherryPick => "Cherry-pick in progress".to_string(),
+            RepoState::Revert => "Revert in progress".to_string(),
+            RepoState::Bisect => "Bisect in progress".to_string(),
+        }
+    }
+
+    /// The operation as git names its command, e.g. `cherry-pick`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RepoState::Rebase { .. } => "rebase",
+            RepoState::Merge => "merge",
+            RepoState::CherryPick => "cherry-pick",
+            RepoState::Revert => "revert",
+            RepoState::Bisect => "bisect",
+        }
+    }
+
+    /// The commands that move the operation along, f's where it has them.
+    pub fn hint(&self) -> Option<&'static str> {
+        Some(match self {
+            RepoState::Rebase { .. } => "f rebase --continue | --skip | --abort",
+            RepoState::Merge => "f c <msg> to conclude, or git merge --abort",
+            RepoState::CherryPick => "git cherry-pick --continue | --skip | --abort",
+            RepoState::Revert => "git revert --continue | --skip | --abort",
+            RepoState::Bisect => "git bisect good | bad | reset",
+        })
+    }
+
+    /// The git command that commits the resolved changes and carries on,
+    /// for operations where that's what a plain commit would be for.
+    pub fn continue_command(&self) -> Option<[&'static str; 2]> {
+        match self {
+            RepoState::Rebase { .. } => Some(["rebase", "--continue"]),
+            RepoState::CherryPick => Some(["cherry-pick", "--continue"]),
+            RepoState::Revert => Some(["revert", "--continue"]),
+            RepoState::Merge | RepoState::Bisect => None,
+        }
+    }
+
+    /// Whether HEAD is detached while the operation runs, so there's no
+    /// branch to push.
+    pub fn detaches_head(&self) -> bool {
+        matches!(self, RepoState::Rebase { .. } | RepoState::Bisect)
+    }
+}
+
+pub fn detect(git_dir: &Path) -> Option<RepoState> {
+    // rebase-merge is used by the default (merge) backend, rebase-apply by
+    // the apply backend and `git am`
+    for (dir, current, total) in [
+        ("rebase-merge", "msgnum", "end"),
+        ("rebase-apply", "next", "last"),
+    ] {
+        let dir = git_dir.join(dir);
+        if dir.is_dir() && !dir.join("applying").exists() {
+            let read = |name: &str| {
+                fs::read_to_string(dir.join(name))
+                    .ok()
+                    .map(|s| s.trim().to_string())
+            };
+            let number = |name: &str| read(name).and_then(|s| s.parse().ok());
+            let progress = number(current).zip(number(total));
+            let branch = read("head-name").map(|h| {
+                h.strip_prefix("refs/heads/")
+                    .map(str::to_string)
+                    .unwrap_or(h)
+            });
+            return Some(RepoState::Rebase { progress, branch });
+        }
+    }
+    let markers = [
+        ("MERGE_HEAD", RepoState::Merge),
+        ("CHERRY_PICK_HEAD", RepoState::CherryPick),
+        ("REVERT_HEAD", RepoState::Revert),
+        ("BISECT_LOG", RepoState::Bisect),
+    ];
+    markers
+        .into_iter()
+        .find(|(file, _)| git_dir.join(file).exists())
+        .map(|(_, state)| state)
+}
+
+/// Trouble with the current branch's upstream or base, worth a line above
+/// the file list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BranchWarning {
+    /// The upstream was deleted from the remote, usually once it merged.
+    /// `base` is the remote's default branch, when it's known.
+    UpstreamGone {
+        branch: String,
+        upstream: String,
+        base: Option<String>,
+    },
+    /// `base` has moved on by `behind` commits since the branch last took
+    /// it in, `days` ago.
+    Stale {
+        branch: String,
+        base: String,
+        days: u64,
+        behind: u32,
+    },
+}
+
+impl BranchWarning {
+    pub fn describe(&self) -> String {
+        match self {
+            BranchWarning::UpstreamGone { upstream, .. } => {
+                format!("Upstream {} is gone", upstream)
+            }
+            BranchWarning::Stale {
+                branch,
+                base,
+                days,
+                behind,
+            } => format!(
+                "{} hasn't taken in {} for {} days ({} {} behind)",
+                branch,
+                base,
+                days,
+                behind,
+                if *behind == 1 { "commit" } else { "commits" }
+            ),
+        }
+    }
+
+    /// The commands that deal with it.
+    pub fn hint(&self) -> String {
+        match self {
+            BranchWarning::UpstreamGone { branch, base, .. } => {
+                let local = base
+                    .as_deref()
+                    .and_then(|b| b.split_once('/'))
+                    .map_or("main", |(_, name)| name);
+                format!("git switch {} && git branch -d {}", local, branch)
+            }
+            BranchWarning::Stale { base, .. } => {
+                let remote = base.split_once('/').map_or("origin", |(remote, _)| remote);
+                format!("git fetch {} && git rebase {}", remote, base)
+            }
+        }
+    }
+}
+
+/// The upstream in `%(upstream:short)%00%(upstream:track)` output, and
+/// whether git marks it `[gone]`. `None` for a branch without one.
+fn parse_tracking(line: &str) -> Option<(String, bool)> {
+    let (upstream, track) = line.split_once('\0').unwrap_or((line, ""));
+    (!upstream.is_empty()).then(|| (upstream.to_string(), track.trim() == "[gone]"))
+}
+
+/// The days since the fork point, committed at Unix time `forked`, when
+/// that's at least `stale_days` and the base has commits since.
+fn stale_for(forked: u64, now: u64, behind: u32, stale_days: u64) -> Option<u64> {
+    let days = now.saturating_sub(forked) / 86_400;
+    (behind > 0 && days >= stale_days).then_some(days)
+}
+
+/// The warning for the current branch: its upstream gone, or it falling
+/// behind the remote's default branch (`origin/HEAD`) for `stale_days` or
+/// more. `None` on a detached HEAD, and without `stale_days` only a gone
+/// upstream is checked.
+pub fn branch_warning(stale_days: Option<u64>) -> Option<BranchWarning> {
+    let branch = git_output(&["symbolic-ref", "--short", "-q", "HEAD"])?;
+    let base = git_output(&["symbolic-ref", "--short", "-q", "refs/remotes/origin/HEAD"]);
+    let tracking = git_output(&[
+        "for-each-ref",
+        "--format=%(upstream:short)%00%(upstream:track)",
+        &format!("refs/heads/{}", branch),
+    ])
+    .unwrap_or_default();
+    if let Some((upstream, true)) = parse_tracking(&tracking) {
+        return Some(BranchWarning::UpstreamGone {
+            branch,
+            upstream,
+            base,
+        });
+    }
+    let (base, stale_days) = (base?, stale_days?);
+    let fork = git_output(&["merge-base", "HEAD", &base])?;
+    let behind = git_output(&["rev-list", "--count", &format!("HEAD..{}", base)])?
+        .parse()
+        .ok()?;
+    let forked = git_output(&["log", "-1", "--format=%ct", &fork])?
+        .parse()
+        .ok()?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let days = stale_for(forked, now, behind, stale_days)?;
+    Some(BranchWarning::Stale {
+        branch,
+        base,
+        days,
+        behind,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("f-repo-state-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn clean_repo_has_no_state() {
+        let dir = scratch("clean");
+        assert_eq!(detect(&dir), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn detects_rebase_progress_and_branch() {
+        let dir = scratch("rebase");
+        let rebase = dir.join("rebase-merge");
+        fs::create_dir_all(&rebase).unwrap();
+        fs::write(rebase.join("msgnum"), "2\n").unwrap();
+        fs::write(rebase.join("end"), "5\n").unwrap();
+        fs::write(rebase.join("head-name"), "refs/heads/feature\n").unwrap();
+        let state = detect(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(state.describe(), "Rebase in progress on feature (2/5)");
+        assert_eq!(state.name(), "rebase");
+        assert!(state.hint().is_some());
+    }
+
+    #[test]
+    fn detects_merge_and_cherry_pick() {
+        let dir = scratch("merge");
+        fs::write(dir.join("CHERRY_PICK_HEAD"), "abc\n").unwrap();
+        assert_eq!(detect(&dir), Some(RepoState::CherryPick));
+        assert_eq!(
+            RepoState::CherryPick.continue_command(),
+            Some(["cherry-pick", "--continue"])
+        );
+        fs::write(dir.join("MERGE_HEAD"), "abc\n").unwrap();
+        assert_eq!(detect(&dir), Some(RepoState::Merge));
+        assert_eq!(RepoState::Merge.continue_command(), None);
+        assert!(!RepoState::Merge.detaches_head());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parses_upstream_tracking() {
+        assert_eq!(parse_tracking(""), None);
+        assert_eq!(
+            parse_tracking("origin/feature\0[ahead 2]"),
+            Some(("origin/feature".to_string(), false))
+        );
+        assert_eq!(
+            parse_tracking("origin/feature\0[gone]"),
+            Some(("origin/feature".to_string(), true))
+        );
+    }
+
+    #[test]
+    fn stale_needs_age_and_new_commits_on_the_base() {
+        let day = 86_400;
+        assert_eq!(stale_for(0, 45 * day, 12, 30), Some(45));
+        assert_eq!(stale_for(0, 45 * day, 0, 30), None);
+        assert_eq!(stale_for(20 * day, 45 * day, 12, 30), None);
+    }
+
+    #[test]
+    fn branch_warnings_suggest_a_fix() {
+        let gone = BranchWarning::UpstreamGone {
+            branch: "feature".into(),
+            upstream: "origin/feature".into(),
+            base: Some("origin/trunk".into()),
+        };
+        assert_eq!(gone.describe(), "Upstream origin/feature is gone");
+        assert_eq!(gone.hint(), "git switch trunk && git branch -d feature");
+        let stale = BranchWarning::Stale {
+            branch: "feature".into(),
+            base: "upstream/main".into(),
+            days: 45,
+            behind: 1,
+        };
+        assert_eq!(
+            stale.describe(),
+            "feature hasn't taken in upstream/main for 45 days (1 commit behind)"
+        );
+        assert_eq!(
+            stale.hint(),
+            "git fetch upstream && git rebase upstream/main"
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use crate::quoting;
+use anyhow::{Context, Result, bail};
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Command;
+
+/// One file's section of a `git diff -U0` patch.
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub path: String,
+    /// Everything before the first hunk (`diff --git`, `index`, `---`, `+++`)
+    header: String,
+    /// Why the whole file can't be absorbed (new, deleted, renamed or binary)
+    unsupported: Option<&'static str>,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    pub old_start: u32,
+    pub old_count: u32,
+    text: String,
+}
+
+/// Fixup commits to create, plus the hunks that stay staged and why.
+#[derive(Debug, Default)]
+pub struct Plan {
+    files: Vec<FileDiff>,
+    pub fixups: Vec<Fixup>,
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Fixup {
+    pub commit: String,
+    pub subject: String,
+    /// (file, hunk) indices into the plan's files
+    hunks: Vec<(usize, usize)>,
+}
+
+impl Fixup {
+    /// (path, hunk count) for each file contributing to this fixup.
+    pub fn files<'a>(&self, plan: &'a Plan) -> Vec<(&'a str, usize)> {
+        let mut files: Vec<(&str, usize)> = Vec::new();
+        for &(file, _) in &self.hunks {
+            let path = plan.files[file].path.as_str();
+            match files.iter_mut().find(|(p, _)| *p == path) {
+                Some((_, count)) => *count += 1,
+                None => files.push((path, 1)),
+            }
+        }
+        files
+    }
+}
+
+pub fn parse_diff(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    for line in diff.split_inclusive('\n') {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            files.push(FileDiff {
+                path: header_path(rest.trim_end()).unwrap_or_default(),
+                header: line.to_string(),
+                unsupported: None,
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if line.starts_with("@@") {
+            if let Some((old_start, old_count)) = parse_hunk_header(line) {
+                file.hunks.push(Hunk {
+                    old_start,
+                    old_count,
+                    text: line.to_string(),
+                });
+            }
+        } else if let Some(hunk) = file.hunks.last_mut() {
+            hunk.text.push_str(line);
+        } else {
+            if line.starts_with("new file mode") {
+                file.unsupported = Some("new file");
+            } else if line.starts_with("deleted file mode") {
+                file.unsupported = Some("deleted file");
+            } else if line.starts_with("rename from") || line.starts_with("copy from") {
+                file.unsupported = Some("renamed file");
+            } else if line.starts_with("Binary files") || line.starts_with("GIT binary patch") {
+                file.unsupported = Some("binary file");
+            }
+            // Unlike the `diff --git` line, this holds a single path
+            if let Some(path) = line.strip_prefix("+++ ")
+                && let Some(path) = quoting::maybe_unquote(path.trim_end()).strip_prefix("b/")
+            {
+                file.path = path.to_string();
+            }
+            file.header.push_str(line);
+        }
+    }
+    files
+}
+
+/// The b side of `a/<path> b/<path>`, i.e. the path in the index. Either
+/// side is C-quoted when the path has special characters.
+fn header_path(rest: &str) -> Option<String> {
+    let b_side = if rest.ends_with('"') {
+        quoting::unquote_path(&rest[rest.rfind(" \"b/")? + 1..])?
+    } else {
+        rest.rsplit_once(" b/").map(|(_, p)| format!("b/{}", p))?
+    };
+    b_side.strip_prefix("b/").map(str::to_string)
+}
+
+/// The old-side range of a `@@ -start[,count] +start[,count] @@` line.
+fn parse_hunk_header(line: &str) -> Option<(u32, u32)> {
+    let old = line.strip_prefix("@@ -")?.split(' ').next()?;
+    match old.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((old.parse().ok()?, 1)),
+    }
+}
+
+/// Commits named in `git blame --porcelain` output.
+fn blamed_commits(porcelain: &str) -> HashSet<String> {
+    porcelain
+        .lines()
+        .filter_map(|line| line.split(' ').next())
+        .filter(|sha| sha.len() == 40 && sha.bytes().all(|b| b.is_ascii_hexdigit()))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Commits that are safe to rewrite: those not yet on the upstream, or on
+/// any remote when the branch has no upstream. Newest first.
+pub fn candidates() -> Vec<String> {
+    let range: &[&str] = if git(&["rev-parse", "--verify", "-q", "@{u}"], None, None).is_ok() {
+        &["@{u}..HEAD"]
+    } else {
+        &["HEAD", "--not", "--remotes"]
+    };
+    let mut args = vec!["rev-list", "--no-merges"];
+    args.extend_from_slice(range);
+    git(&args, None, None)
+        .map(|out| out.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Matches each staged hunk to the one candidate commit that last touched
+/// the lines it changes.
+pub fn plan() -> Result<Plan> {
+    let diff = git(
+        &["diff", "--cached", "-U0", "--no-color", "--no-ext-diff"],
+        None,
+        None,
+    )?;
+    if diff.trim().is_empty() {
+        bail!("Nothing staged to absorb");
+    }
+    let candidates = candidates();
+    if candidates.is_empty() {
+        bail!("No unpushed commits to absorb into");
+    }
+
+    let mut plan = Plan {
+        files: parse_diff(&diff),
+        ..Default::default()
+    };
+    for (fi, file) in plan.files.iter().enumerate() {
+        if let Some(reason) = file.unsupported {
+            plan.skipped.push(format!("{} ({})", file.path, reason));
+            continue;
+        }
+        for (hi, hunk) in file.hunks.iter().enumerate() {
+            let location = format!("{}:{}", file.path, hunk.old_start);
+            if hunk.old_count == 0 {
+                plan.skipped.push(format!("{} (only adds lines)", location));
+                continue;
+            }
+            let range = format!("{},+{}", hunk.old_start, hunk.old_count);
+            let blame = git(
+                &[
+                    "blame",
+                    "--porcelain",
+                    "-L",
+                    &range,
+                    "HEAD",
+                    "--",
+                    &file.path,
+                ],
+                None,
+                None,
+            )?;
+            let commits = blamed_commits(&blame);
+            let target = match commits.iter().next() {
+                Some(commit) if commits.len() == 1 => commit,
+                _ => {
+                    plan.skipped
+                        .push(format!("{} (lines from several commits)", location));
+                    continue;
+                }
+            };
+            if !candidates.contains(target) {
+                plan.skipped
+                    .push(format!("{} (last changed in a pushed commit)", location));
+                continue;
+            }
+            match plan.fixups.iter_mut().find(|f| &f.commit == target) {
+                Some(fixup) => fixup.hunks.push((fi, hi)),
+                None => plan.fixups.push(Fixup {
+                    commit: target.clone(),
+                    subject: git_value(&["log", "-1", "--format=%s", target], None)?,
+                    hunks: vec![(fi, hi)],
+                }),
+            }
+        }
+    }
+    // Oldest target first, so the fixups read in history order
+    plan.fixups
+        .sort_by_key(|f| std::cmp::Reverse(candidates.iter().position(|c| *c == f.commit)));
+    Ok(plan)
+}
+
+/// A patch with the selected hunks, in file order as in the original diff.
+fn build_patch(files: &[FileDiff], selected: &HashSet<(usize, usize)>) -> String {
+    let mut patch = String::new();
+    for (fi, file) in files.iter().enumerate() {
+        let hunks: Vec<&Hunk> = (0..file.hunks.len())
+            .filter(|hi| selected.contains(&(fi, *hi)))
+            .map(|hi| &file.hunks[hi])
+            .collect();
+        if hunks.is_empty() {
+            continue;
+        }
+        patch.push_str(&file.header);
+        for hunk in hunks {
+            patch.push_str(&hunk.text);
+        }
+    }
+    patch
+}
+
+/// Creates the fixup commits on top of HEAD and returns the oldest commit
+/// they target. The index is left alone, so whatever wasn't absorbed is
+/// still staged afterwards.
+pub fn apply(plan: &Plan, git_dir: &Path, sign: bool) -> Result<String> {
+    let base = git_value(&["rev-parse", "HEAD"], None)?;
+    let index = git_dir.join("f-absorb-index");
+    let result = commit_fixups(plan, &base, &index, sign);
+    let _ = std::fs::remove_file(&index);
+    let head = result?;
+    git(
+        &["update-ref", "-m", "f absorb", "HEAD", &head, &base],
+        None,
+        None,
+    )?;
+    Ok(plan.fixups[0].commit.clone())
+}
+
+fn commit_fixups(plan: &Plan, base: &str, index: &Path, sign: bool) -> Result<String> {
+    let mut parent = base.to_string();
+    let mut selected = HashSet::new();
+    for fixup in &plan.fixups {
+        // Every hunk's line numbers refer to the original HEAD, so each
+        // tree is built from it with all hunks absorbed so far
+        selected.extend(fixup.hunks.iter().copied());
+        let patch = build_patch(&plan.files, &selected);
+        git(&["read-tree", base], Some(index), None)?;
+        git(
+            &["apply", "--cached", "--unidiff-zero", "-"],
+            Some(index),
+            Some(&patch),
+        )
+        .with_context(|| format!("Failed to apply hunks for {}", short(&fixup.commit)))?;
+        let tree = git_value(&["write-tree"], Some(index))?;
+        let message = format!("fixup! {}", fixup.subject);
+        let mut args = vec!["commit-tree", &tree, "-p", &parent, "-m", &message];
+        if sign {
+            args.push("-S");
+        }
+        parent = git_value(&args, None)?;
+    }
+    Ok(parent)
+}
+
+pub fn short(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+fn git(args: &[&str], index: Option<&Path>, input: Option<&str>) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.args(args);
+    if let Some(index) = index {
+        cmd.env("GIT_INDEX_FILE", index);
+    }
+    let output = crate::exec::output_with_input(&mut cmd, input.map(str::as_bytes))?;
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+/// `git` for commands that print a single value, such as a sha.
+fn git_value(args: &[&str], index: Option<&Path>) -> Result<String> {
+    Ok(git(args, index, None)?.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/a.rs b/src/a.rs
+index 1111111..2222222 100644
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -3 +3 @@ fn main() {
+-    old();
++    new();
+@@ -10,0 +11,2 @@ fn other() {
++    added();
++    more();
+diff --git a/new.txt b/new.txt
+new file mode 100644
+index 0000000..3333333
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1 @@
++hello
+";
+
+    #[test]
+    fn test_parse_diff() {
+        let files = parse_diff(DIFF);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "src/a.rs");
+        assert_eq!(files[0].unsupported, None);
+        assert_eq!(files[0].hunks.len(), 2);
+        assert_eq!(
+            (files[0].hunks[0].old_start, files[0].hunks[0].old_count),
+            (3, 1)
+        );
+        assert_eq!(
+            (files[0].hunks[1].old_start, files[0].hunks[1].old_count),
+            (10, 0)
+        );
+        assert_eq!(files[1].unsupported, Some("new file"));
+    }
+
+    #[test]
+    fn test_build_patch_selects_hunks() {
+        let files = parse_diff(DIFF);
+        let selected = HashSet::from([(0, 0)]);
+        let patch = build_patch(&files, &selected);
+        assert!(patch.starts_with("diff --git a/src/a.rs b/src/a.rs\n"));
+        assert!(patch.contains("+    new();\n"));
+        assert!(!patch.contains("added"));
+        assert!(!patch.contains("new.txt"));
+    }
+
+    #[test]
+    fn test_quoted_paths() {
+        assert_eq!(
+            header_path(r#""a/caf\303\251.txt" "b/caf\303\251.txt""#).as_deref(),
+            Some("café.txt")
+        );
+        assert_eq!(
+            header_path("a/with space b/with space").as_deref(),
+            Some("with space")
+        );
+        let diff = "diff --git \"a/tab\\there\" \"b/tab\\there\"\n--- \"a/tab\\there\"\n+++ \"b/tab\\there\"\n@@ -1 +1 @@\n-a\n+b\n";
+        assert_eq!(parse_diff(diff)[0].path, "tab\there");
+    }
+
+    #[test]
+    fn test_parse_hunk_header() {
+        assert_eq!(parse_hunk_header("@@ -5,3 +5,4 @@ ctx"), Some((5, 3)));
+        assert_eq!(parse_hunk_header("@@ -7 +7 @@"), Some((7, 1)));
+        assert_eq!(parse_hunk_header("not a hunk"), None);
+    }
+
+    #[test]
+    fn test_blamed_commits() {
+        let sha = "a".repeat(40);
+        let porcelain = format!(
+            "{} 3 3 1\nauthor Someone\n\tline\n{} 4 4\n\tline\n",
+            sha, sha
+        );
+        assert_eq!(blamed_commits(&porcelain), HashSet::from([sha]));
+    }
+}
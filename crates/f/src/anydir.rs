@@ -0,0 +1,117 @@
+//! `--any-dir`: outside a git repository, the files under the current
+//! directory are listed as if they were all untracked, so picking and
+//! editing them by ID still works there.
+
+use crate::git_status::{FileType, GitFile, StableId, generate_ids};
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Only the most recently modified files are listed beyond this many.
+const MAX_FILES: usize = 200;
+
+/// Directory entries looked at before giving up, so starting in `$HOME`
+/// or `/` doesn't walk the whole disk.
+const MAX_SCANNED: usize = 20_000;
+
+pub fn set_enabled(on: bool) {
+    ENABLED.store(on, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// The files under the current directory, least recently modified first
+/// like the git listing. Hidden files and directories are skipped, and
+/// symlinks aren't followed.
+pub fn get_files(id_chars: &[char]) -> Result<Vec<GitFile>> {
+    let root = std::env::current_dir()?;
+    let mut found = Vec::new();
+    let mut scanned = 0;
+    walk(&root, &root, &mut found, &mut scanned);
+
+    found.sort_by_key(|(mtime, _)| *mtime);
+    let start = found.len().saturating_sub(MAX_FILES);
+    let found = &found[start..];
+
+    let paths: Vec<String> = found.iter().map(|(_, p)| p.clone()).collect();
+    let ids = generate_ids(&paths, id_chars);
+    Ok(found
+        .iter()
+        .zip(ids)
+        .map(|((mtime, rel_path), (display, full_hash))| GitFile {
+            mtime: *mtime,
+            rel_path: rel_path.clone(),
+            abs_path: root.join(rel_path),
+            file_type: FileType::Untracked,
+            stable_id: StableId { display, full_hash },
+            diff_stats: None,
+            lfs_size: None,
+            mode_change: None,
+            status: '?',
+        })
+        .collect())
+}
+
+fn walk(root: &Path, dir: &Path, found: &mut Vec<(u64, String)>, scanned: &mut usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        *scanned += 1;
+        if *scanned > MAX_SCANNED {
+            return;
+        }
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        let path = entry.path();
+        if meta.is_dir() {
+            walk(root, &path, found, scanned);
+        } else if meta.is_file() {
+            let mtime = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs());
+            if let Ok(rel) = path.strip_prefix(root) {
+                found.push((mtime, rel.to_string_lossy().into_owned()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_walk_skips_hidden() {
+        let dir = std::env::temp_dir().join(format!("f-anydir-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("notes/.cache")).unwrap();
+        fs::create_dir_all(dir.join(".git-not")).unwrap();
+        fs::write(dir.join("todo.txt"), "").unwrap();
+        fs::write(dir.join("notes/a.md"), "").unwrap();
+        fs::write(dir.join("notes/.cache/x"), "").unwrap();
+        fs::write(dir.join(".git-not/y"), "").unwrap();
+        fs::write(dir.join(".hidden"), "").unwrap();
+
+        let mut found = Vec::new();
+        walk(&dir, &dir, &mut found, &mut 0);
+        let mut paths: Vec<String> = found.into_iter().map(|(_, p)| p).collect();
+        paths.sort();
+        assert_eq!(paths, ["notes/a.md", "todo.txt"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
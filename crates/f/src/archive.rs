@@ -0,0 +1,93 @@
+//! `f archive`: the changed files packed with `git archive`, from a tree
+//! of their work tree versions, or of the index for `--staged`.
+
+use crate::error::Error;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// The `git archive --format` for `output`, from its extension.
+pub fn format_for(output: &Path) -> Result<&'static str> {
+    let name = output.to_string_lossy();
+    [
+        (".tar.gz", "tar.gz"),
+        (".tgz", "tgz"),
+        (".tar", "tar"),
+        (".zip", "zip"),
+    ]
+    .into_iter()
+    .find(|(ext, _)| name.ends_with(ext))
+    .map(|(_, format)| format)
+    .ok_or_else(|| {
+        Error::Usage(format!(
+            "Can't tell the format of {}, name it .tar.gz, .tgz, .tar or .zip",
+            name
+        ))
+        .into()
+    })
+}
+
+/// A tree of `paths` (repo-relative) and the rest of the index: the index
+/// itself when `staged`, otherwise a copy with the work tree versions of
+/// `paths` added, leaving the real index alone.
+pub fn tree(paths: &[&str], staged: bool, root: &Path, git_dir: &Path) -> Result<String> {
+    if staged {
+        return write_tree(root, None);
+    }
+    let index = git_dir.join("f").join("archive-index");
+    fs::create_dir_all(git_dir.join("f"))?;
+    if git_dir.join("index").exists() {
+        fs::copy(git_dir.join("index"), &index).context("Failed to copy the index")?;
+    }
+    let added = crate::exec::output(
+        Command::new("git")
+            .args(["add", "-A", "--"])
+            .args(paths)
+            .current_dir(root)
+            .env("GIT_INDEX_FILE", &index),
+    );
+    let tree = added.and_then(|_| write_tree(root, Some(&index)));
+    let _ = fs::remove_file(&index);
+    tree
+}
+
+fn write_tree(root: &Path, index: Option<&Path>) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.arg("write-tree").current_dir(root);
+    if let Some(index) = index {
+        cmd.env("GIT_INDEX_FILE", index);
+    }
+    let output = crate::exec::output(&mut cmd)?;
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+/// Writes `paths` of `tree` to `output` in `format`.
+pub fn write(tree: &str, paths: &[&str], format: &str, output: &Path, root: &Path) -> Result<()> {
+    crate::exec::output(
+        Command::new("git")
+            .arg("archive")
+            .arg(format!("--format={}", format))
+            .arg("-o")
+            .arg(output)
+            .arg(tree)
+            .arg("--")
+            .args(paths)
+            .current_dir(root),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_for() {
+        assert_eq!(format_for(Path::new("wip.tar.gz")).unwrap(), "tar.gz");
+        assert_eq!(format_for(Path::new("/tmp/wip.tgz")).unwrap(), "tgz");
+        assert_eq!(format_for(Path::new("wip.tar")).unwrap(), "tar");
+        assert_eq!(format_for(Path::new("wip.zip")).unwrap(), "zip");
+        assert!(format_for(Path::new("wip.rar")).is_err());
+    }
+}
@@ -0,0 +1,382 @@
+//! `f batch`: a script of f commands run as one unit. Every line is parsed
+//! before anything runs, and if a line fails the index and HEAD go back to
+//! how they were, so a script either applies completely or not at all.
+//!
+//! ```text
+//! # stage two files and commit them
+//! add df gk
+//! unstage src/generated.rs
+//! commit "fix tests"
+//! ```
+//!
+//! `edit` opens files in the editor and waits for it. Edits are in the
+//! working tree, so rolling back leaves them alone.
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::exec;
+use crate::git_status::{FileType, GitFile, IdMatch, find_file_by_id, find_file_by_path};
+use crate::{daemon, lint, undo};
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use std::process::Command;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Step {
+    Edit(Vec<String>),
+    Add(Vec<String>),
+    Unstage(Vec<String>),
+    Commit(String),
+}
+
+#[derive(Debug)]
+pub struct Line {
+    pub number: usize,
+    pub text: String,
+    pub step: Step,
+}
+
+/// Parses a script: one command per line, with words split like a shell
+/// would and `#` starting a comment line. Fails on the first bad line.
+pub fn parse(script: &str) -> Result<Vec<Line>> {
+    let mut lines = Vec::new();
+    for (i, text) in script.lines().enumerate() {
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let number = i + 1;
+        let step = split_words(text)
+            .and_then(|words| step(&words))
+            .map_err(|e| Error::Usage(format!("Line {}: {}", number, e)))?;
+        lines.push(Line {
+            number,
+            text: text.to_string(),
+            step,
+        });
+    }
+    Ok(lines)
+}
+
+fn step(words: &[String]) -> Result<Step, String> {
+    let (command, args) = words.split_first().ok_or("empty line")?;
+    let make: fn(Vec<String>) -> Step = match command.as_str() {
+        "edit" | "e" => Step::Edit,
+        "add" | "a" => Step::Add,
+        "unstage" | "u" => Step::Unstage,
+        "commit" | "c" => |args| Step::Commit(args.join(" ")),
+        other => {
+            return Err(format!(
+                "unknown command '{}' (expected edit, add, unstage or commit)",
+                other
+            ));
+        }
+    };
+    if args.is_empty() {
+        return Err(format!("{} needs an argument", command));
+    }
+    Ok(make(args.to_vec()))
+}
+
+/// Splits `line` into words, honoring single and double quotes and
+/// backslash escapes outside single quotes.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unclosed '".into()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => word.extend(chars.next()),
+                        Some(c) => word.push(c),
+                        None => return Err("unclosed \"".into()),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).extend(chars.next()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Runs `lines` in order, printing a result for each. On the first failure
+/// everything done so far is rolled back and the error returned.
+pub fn run(lines: &[Line], config: &Config) -> Result<()> {
+    let snapshot = if exec::print_only() {
+        None
+    } else {
+        Some(Snapshot::take()?)
+    };
+    for line in lines {
+        match run_step(&line.step, config) {
+            Ok(summary) => println!(
+                "{} {:>3}  {}  {}",
+                "✓".green(),
+                line.number,
+                line.text,
+                summary.dimmed()
+            ),
+            Err(e) => {
+                println!("{} {:>3}  {}", "✗".red(), line.number, line.text);
+                if let Some(snapshot) = &snapshot {
+                    snapshot
+                        .restore()
+                        .context("Rolling back failed, check git status")?;
+                    let edited = lines
+                        .iter()
+                        .take_while(|l| l.number < line.number)
+                        .any(|l| matches!(l.step, Step::Edit(_)));
+                    if edited {
+                        println!(
+                            "{}",
+                            "Rolled back the index and HEAD, edits in the working tree were kept"
+                                .yellow()
+                        );
+                    } else {
+                        println!("{}", "Rolled back, nothing was changed".yellow());
+                    }
+                }
+                return Err(e.context(format!("Line {}", line.number)));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_step(step: &Step, config: &Config) -> Result<String> {
+    match step {
+        Step::Edit(targets) => {
+            let paths = edit_paths(targets, config)?;
+            let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+            let status = exec::status(&mut config.editor_command(&paths, None))
+                .context("Failed to start the editor")?;
+            if !status.success() {
+                bail!("The editor exited with code {}", exec::code(status));
+            }
+            Ok(String::new())
+        }
+        Step::Add(targets) => {
+            let files = resolve(targets, config, |_| true)?;
+            record_index("add", &files);
+            git(&paths_args(&["add"], &files))?;
+            Ok(describe(&files))
+        }
+        Step::Unstage(targets) => {
+            let files = resolve(targets, config, |f| f.file_type == FileType::Staged)?;
+            record_index("unstage", &files);
+            git(&paths_args(&["restore", "--staged"], &files))?;
+            Ok(describe(&files))
+        }
+        Step::Commit(message) => {
+            let violations = lint::check(message, &config.commit)?;
+            if let Some(v) = violations.first() {
+                bail!("Commit message rejected: {}: {}", v.rule, v.message);
+            }
+            if !exec::print_only()
+                && let Err(e) = undo::Journal::open().and_then(|j| j.record_commit())
+            {
+                eprintln!("Warning: f undo won't cover this: {:#}", e);
+            }
+            let mut args = vec!["commit", "-q"];
+            if config.commit.sign {
+                args.push("-S");
+            }
+            args.extend(["-m", message]);
+            git(&args)?;
+            let head = crate::git_status::git_output(&["rev-parse", "--short", "HEAD"]);
+            Ok(head.unwrap_or_default())
+        }
+    }
+}
+
+/// Resolves each target as a path or ID among the files `include` accepts,
+/// listed afresh so earlier lines are taken into account.
+fn resolve(
+    targets: &[String],
+    config: &Config,
+    include: impl Fn(&GitFile) -> bool,
+) -> Result<Vec<GitFile>> {
+    let files: Vec<GitFile> = daemon::get_files(&config.id_chars())?
+        .into_iter()
+        .filter(include)
+        .collect();
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let mut selected: Vec<GitFile> = Vec::new();
+    for target in targets {
+        let file = match find_file_by_path(&files, target, &cwd) {
+            Some(file) => file,
+            None => match find_file_by_id(&files, target) {
+                IdMatch::Unique(file) => file,
+                IdMatch::Ambiguous(matches) => {
+                    return Err(Error::AmbiguousId {
+                        id: target.clone(),
+                        matches,
+                        what: "files",
+                    }
+                    .into());
+                }
+                IdMatch::NotFound => return Err(crate::no_file_matches(&files, target).into()),
+            },
+        };
+        if !selected.iter().any(|f| f.rel_path == file.rel_path) {
+            selected.push(file);
+        }
+    }
+    Ok(selected)
+}
+
+/// Absolute paths for `edit`: a repo-relative path to any file, changed or
+/// not, since the point is usually to change it, or else an ID or path
+/// among the changed files.
+fn edit_paths(targets: &[String], config: &Config) -> Result<Vec<String>> {
+    let root = crate::git_status::get_git_root()?;
+    let mut paths = Vec::new();
+    let mut rest = Vec::new();
+    for target in targets {
+        let path = root.join(target);
+        if path.is_file() {
+            paths.push(path.to_string_lossy().into_owned());
+        } else {
+            rest.push(target.clone());
+        }
+    }
+    if !rest.is_empty() {
+        let files = resolve(&rest, config, |_| true)?;
+        paths.extend(
+            files
+                .iter()
+                .map(|f| f.abs_path.to_string_lossy().into_owned()),
+        );
+    }
+    Ok(paths)
+}
+
+fn record_index(command: &str, files: &[GitFile]) {
+    if exec::print_only() {
+        return;
+    }
+    let paths: Vec<&str> = files.iter().map(|f| f.rel_path.as_str()).collect();
+    if let Err(e) = undo::Journal::open().and_then(|j| j.record_index(command, &paths)) {
+        eprintln!("Warning: f undo won't cover this: {:#}", e);
+    }
+}
+
+fn paths_args<'a>(args: &[&'a str], files: &'a [GitFile]) -> Vec<&'a str> {
+    let mut args = args.to_vec();
+    args.push("--");
+    args.extend(files.iter().map(|f| f.rel_path.as_str()));
+    args
+}
+
+fn describe(files: &[GitFile]) -> String {
+    files
+        .iter()
+        .map(crate::display::display_path)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Runs git from the work tree root, where `rel_path`s are relative to,
+/// or prints the command under `--print`.
+fn git(args: &[&str]) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.args(args)
+        .current_dir(crate::git_status::get_git_root()?);
+    if exec::print_only() {
+        println!("{}", exec::render(&cmd));
+        return Ok(());
+    }
+    exec::output(&mut cmd)?;
+    Ok(())
+}
+
+/// HEAD and the index as a tree, to return to if a line fails.
+struct Snapshot {
+    head: Option<String>,
+    tree: String,
+}
+
+impl Snapshot {
+    fn take() -> Result<Snapshot> {
+        let tree = exec::output(Command::new("git").arg("write-tree"))
+            .context("Can't save the index, resolve any conflicts first")?;
+        Ok(Snapshot {
+            head: crate::git_status::git_output(&["rev-parse", "--verify", "-q", "HEAD"]),
+            tree: String::from_utf8_lossy(&tree).trim().to_string(),
+        })
+    }
+
+    fn restore(&self) -> Result<()> {
+        let head = crate::git_status::git_output(&["rev-parse", "--verify", "-q", "HEAD"]);
+        if head != self.head {
+            match &self.head {
+                Some(head) => {
+                    exec::output(Command::new("git").args(["reset", "-q", "--soft", head]))?
+                }
+                None => exec::output(Command::new("git").args(["update-ref", "-d", "HEAD"]))?,
+            };
+        }
+        exec::output(Command::new("git").args(["read-tree", &self.tree]))?;
+        // read-tree drops the cached stat data, which would make every
+        // file look modified until the next refresh
+        let _ = exec::output(Command::new("git").args(["update-index", "-q", "--refresh"]));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words(r#"commit "fix \"tests\"" its\ ok 'a b'"#).unwrap(),
+            ["commit", "fix \"tests\"", "its ok", "a b"]
+        );
+        assert_eq!(split_words("  add   df  ").unwrap(), ["add", "df"]);
+        assert_eq!(split_words(r#"a"b"c"#).unwrap(), ["abc"]);
+        assert_eq!(split_words("c ''").unwrap(), ["c", ""]);
+        assert!(split_words("commit \"open").is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let script = "# setup\nadd df gk\n\nunstage src/a.rs\ncommit fix the tests\n";
+        let lines = parse(script).unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].number, 2);
+        assert_eq!(lines[0].step, Step::Add(vec!["df".into(), "gk".into()]));
+        assert_eq!(lines[1].step, Step::Unstage(vec!["src/a.rs".into()]));
+        assert_eq!(lines[2].step, Step::Commit("fix the tests".into()));
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_line() {
+        let err = parse("add df\npush\n").unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 2);
+        assert!(
+            err.to_string()
+                .starts_with("Line 2: unknown command 'push'")
+        );
+        let err = parse("commit\n").unwrap_err();
+        assert_eq!(err.to_string(), "Line 1: commit needs an argument");
+    }
+}
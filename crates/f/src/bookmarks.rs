@@ -0,0 +1,130 @@
+//! Named bookmarks for files, kept in `.git/f/bookmarks` so `f @name e`
+//! reaches a file whether or not it has changes.
+
+use crate::error::Error;
+use crate::json::{self, Value};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+pub struct Bookmarks {
+    path: PathBuf,
+    /// Name and repo-relative path, sorted by name
+    entries: Vec<(String, String)>,
+}
+
+impl Bookmarks {
+    pub fn open() -> Result<Bookmarks> {
+        let path = crate::git_status::get_git_dir()?
+            .join("f")
+            .join("bookmarks");
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| json::parse(&content).ok())
+            .map(|value| from_json(&value))
+            .unwrap_or_default();
+        Ok(Bookmarks { path, entries })
+    }
+
+    pub fn entries(&self) -> &[(String, String)] {
+        &self.entries
+    }
+
+    /// The path bookmarked as `name`.
+    pub fn get(&self, name: &str) -> Result<&str> {
+        self.entries
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, path)| path.as_str())
+            .ok_or_else(|| Error::NotFound(format!("No bookmark named {}", name)).into())
+    }
+
+    /// Bookmarks `path` as `name`, moving the bookmark if it exists.
+    pub fn set(&mut self, name: &str, path: &str) -> Result<()> {
+        check_name(name)?;
+        self.entries.retain(|(n, _)| n != name);
+        self.entries.push((name.to_string(), path.to_string()));
+        self.entries.sort();
+        self.save()
+    }
+
+    /// Deletes the bookmark `name`, returning the path it pointed to.
+    pub fn remove(&mut self, name: &str) -> Result<String> {
+        let path = self.get(name)?.to_string();
+        self.entries.retain(|(n, _)| n != name);
+        self.save()?;
+        Ok(path)
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, format!("{}\n", to_json(&self.entries)))
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// Names are used as `@name`, so they stay to characters a shell leaves
+/// alone.
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_.".contains(c));
+    if !valid {
+        return Err(Error::Usage(format!(
+            "Invalid bookmark name: {} (use letters, digits, - _ and .)",
+            name
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+fn to_json(entries: &[(String, String)]) -> Value {
+    Value::object(
+        entries
+            .iter()
+            .map(|(name, path)| (name.clone(), Value::from(path.as_str()))),
+    )
+}
+
+fn from_json(value: &Value) -> Vec<(String, String)> {
+    let Value::Object(fields) = value else {
+        return Vec::new();
+    };
+    let mut entries: Vec<(String, String)> = fields
+        .iter()
+        .filter_map(|(name, path)| Some((name.clone(), path.as_str()?.to_string())))
+        .collect();
+    entries.sort();
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_round_trip() {
+        let entries = vec![
+            ("deploy-config".to_string(), "ops/deploy.yml".to_string()),
+            ("readme".to_string(), "docs/read me.md".to_string()),
+        ];
+        let text = to_json(&entries).to_string();
+        assert_eq!(from_json(&json::parse(&text).unwrap()), entries);
+        assert!(from_json(&json::parse("[1]").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("deploy-config").is_ok());
+        assert!(check_name("v1.2_notes").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("-d").is_err());
+        assert!(check_name("a b").is_err());
+        assert!(check_name("@x").is_err());
+    }
+}
@@ -0,0 +1,44 @@
+use crate::git_status::{GitFile, get_all_files};
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+/// Keeps the most recent file list in memory for long-running modes, so
+/// bursts of queries share one `git status` run.
+///
+/// Entries expire after `ttl`; callers that mutate the repo should call
+/// `invalidate` so the next query sees their change.
+pub struct StatusCache {
+    id_chars: Vec<char>,
+    ttl: Duration,
+    entry: Option<(Instant, Vec<GitFile>)>,
+}
+
+impl StatusCache {
+    pub fn new(id_chars: Vec<char>, ttl: Duration) -> Self {
+        Self {
+            id_chars,
+            ttl,
+            entry: None,
+        }
+    }
+
+    pub fn files(&mut self) -> Result<&[GitFile]> {
+        let fresh = self
+            .entry
+            .as_ref()
+            .is_some_and(|(at, _)| at.elapsed() < self.ttl);
+        if !fresh {
+            let files = get_all_files(&self.id_chars)?;
+            self.entry = Some((Instant::now(), files));
+        }
+        Ok(self
+            .entry
+            .as_ref()
+            .map(|(_, f)| f.as_slice())
+            .unwrap_or(&[]))
+    }
+
+    pub fn invalidate(&mut self) {
+        self.entry = None;
+    }
+}
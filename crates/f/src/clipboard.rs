@@ -0,0 +1,148 @@
+use anyhow::{Context, Result, bail};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// How text reached the clipboard, reported back to the user.
+pub enum Method {
+    Osc52,
+    Tool(&'static str),
+}
+
+/// Native clipboard tools, tried in order. Each reads the text on stdin.
+const TOOLS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+/// Tools that print the clipboard, tried in order.
+const PASTE_TOOLS: &[(&str, &[&str])] = &[
+    ("pbpaste", &[]),
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-o"]),
+    ("xsel", &["--clipboard", "--output"]),
+];
+
+/// Copies `text` to the system clipboard.
+///
+/// Over SSH the local machine's clipboard is only reachable through the
+/// terminal, so OSC 52 is used there; otherwise the first working native
+/// tool wins, with OSC 52 as a last resort.
+pub fn copy(text: &str) -> Result<Method> {
+    let over_ssh =
+        std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some();
+    if !over_ssh {
+        for (tool, args) in TOOLS {
+            if copy_with_tool(tool, args, text).is_ok() {
+                return Ok(Method::Tool(tool));
+            }
+        }
+    }
+    copy_osc52(text)?;
+    Ok(Method::Osc52)
+}
+
+/// The clipboard's text, from the first native tool that works. OSC 52
+/// can't be read back, so over SSH there may be none.
+pub fn paste() -> Result<String> {
+    for (tool, args) in PASTE_TOOLS {
+        let output = crate::exec::run_output(Command::new(tool).args(*args));
+        if let Ok(output) = output
+            && output.status.success()
+        {
+            return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+    }
+    bail!("No clipboard tool to paste from (pbpaste, wl-paste, xclip or xsel)")
+}
+
+fn copy_with_tool(tool: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .context("No stdin for clipboard tool")?
+        .write_all(text.as_bytes())?;
+    if !child.wait()?.success() {
+        bail!("{} failed", tool);
+    }
+    Ok(())
+}
+
+/// Writes the OSC 52 sequence to the controlling terminal rather than stdout,
+/// so it still works when f's output is piped.
+fn copy_osc52(text: &str) -> Result<()> {
+    let inside_tmux = std::env::var_os("TMUX").is_some();
+    let mut tty = std::fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/tty")
+        .context("No terminal available for OSC 52")?;
+    tty.write_all(osc52_sequence(text, inside_tmux).as_bytes())?;
+    tty.flush()?;
+    Ok(())
+}
+
+fn osc52_sequence(text: &str, inside_tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", base64_encode(text.as_bytes()));
+    if inside_tmux {
+        // tmux only forwards escape sequences wrapped in a DCS passthrough
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+pub fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        out.push(ALPHABET[(n >> 18) as usize & 63] as char);
+        out.push(ALPHABET[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 {
+            ALPHABET[(n >> 6) as usize & 63] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            ALPHABET[n as usize & 63] as char
+        } else {
+            '='
+        });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_encode_padding() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"src/main.rs"), "c3JjL21haW4ucnM=");
+    }
+
+    #[test]
+    fn osc52_sequence_plain_and_tmux() {
+        assert_eq!(osc52_sequence("foo", false), "\x1b]52;c;Zm9v\x07");
+        assert_eq!(
+            osc52_sequence("foo", true),
+            "\x1bPtmux;\x1b\x1b]52;c;Zm9v\x07\x1b\\"
+        );
+    }
+}
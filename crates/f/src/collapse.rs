@@ -0,0 +1,93 @@
+//! `collapse` in the config: changes to generated or vendored files,
+//! rolled up into a row per pattern instead of listed one by one.
+
+use crate::git_status::GitFile;
+use crate::glob::Glob;
+use crate::messages::{Msg, tf};
+
+/// The files one pattern collapsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rollup {
+    pub pattern: String,
+    pub count: usize,
+}
+
+impl Rollup {
+    /// The row standing in for the files, e.g. `target/** · 412 files`.
+    pub fn row(&self) -> String {
+        let files = if self.count == 1 {
+            Msg::OneFile
+        } else {
+            Msg::Files
+        };
+        format!(
+            "{} · {}",
+            self.pattern,
+            tf(files, &[&self.count.to_string()])
+        )
+    }
+}
+
+/// `files` split into those still listed and a rollup for each pattern
+/// that matched any, in the order of `globs`. A file matching several
+/// patterns counts under the first.
+pub fn split(files: &[GitFile], globs: &[Glob]) -> (Vec<GitFile>, Vec<Rollup>) {
+    let mut counts = vec![0; globs.len()];
+    let mut listed = Vec::new();
+    for file in files {
+        match globs.iter().position(|g| g.matches(&file.rel_path)) {
+            Some(i) => counts[i] += 1,
+            None => listed.push(file.clone()),
+        }
+    }
+    let rollups = globs
+        .iter()
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .map(|(glob, count)| Rollup {
+            pattern: glob.pattern().to_string(),
+            count,
+        })
+        .collect();
+    (listed, rollups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_status::{FileType, StableId};
+    use std::path::PathBuf;
+
+    fn file(rel_path: &str) -> GitFile {
+        GitFile {
+            mtime: 0,
+            rel_path: rel_path.to_string(),
+            abs_path: PathBuf::from("/repo").join(rel_path),
+            file_type: FileType::Untracked,
+            stable_id: StableId {
+                display: "a".into(),
+                full_hash: "a".into(),
+            },
+            diff_stats: None,
+            lfs_size: None,
+            mode_change: None,
+            status: '?',
+        }
+    }
+
+    #[test]
+    fn test_split() {
+        let globs = crate::glob::compile_all(&["target/**".into(), "*.lock".into()]);
+        let files = [
+            file("target/debug/f"),
+            file("src/main.rs"),
+            file("Cargo.lock"),
+            file("target/debug/f.d"),
+        ];
+        let (listed, rollups) = split(&files, &globs);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].rel_path, "src/main.rs");
+        let rows: Vec<String> = rollups.iter().map(Rollup::row).collect();
+        assert_eq!(rows, ["target/** · 2 files", "*.lock · 1 file"]);
+    }
+}
@@ -0,0 +1,283 @@
+use crate::git_status::{GitFile, get_git_dir, git_output};
+use crate::owners::Owners;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Written next to COMMIT_EDITMSG and overwritten by each `f c` that opens
+/// the editor.
+const TEMPLATE_NAME: &str = "F_COMMIT_TEMPLATE";
+
+/// Builds the initial editor buffer: the inline message (or the user's
+/// `commit.template`), followed by a commented list of the staged files.
+/// Git appends its own status comments below this and strips all comment
+/// lines once the editor closes.
+pub fn template(staged: &[GitFile], comment: char, head: Option<&str>) -> String {
+    let mut out = String::new();
+    if let Some(head) = head {
+        out.push_str(head.trim_end());
+        out.push('\n');
+    }
+    if staged.is_empty() {
+        return out;
+    }
+    out.push('\n');
+    out.push_str(&format!("{} Staged files:\n", comment));
+    for file in staged {
+        let stats = file
+            .diff_stats
+            .as_ref()
+            .map(|s| format!(" +{}/-{}", s.added, s.removed))
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "{}   {:<5} {}{}\n",
+            comment, file.stable_id.display, file.rel_path, stats
+        ));
+    }
+    out
+}
+
+/// Git's comment character: `core.commentChar` when it is a single
+/// character, `#` otherwise (including `auto`).
+pub fn comment_char() -> char {
+    git_output(&["config", "core.commentChar"])
+        .and_then(|value| {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => None,
+            }
+        })
+        .unwrap_or('#')
+}
+
+/// The contents of the user's `commit.template`, if one is configured.
+/// Passing our own template with `-t` overrides it, so it is folded in.
+pub fn user_template() -> Option<String> {
+    let path = git_output(&["config", "--path", "commit.template"])?;
+    std::fs::read_to_string(path).ok()
+}
+
+pub fn write_template(contents: &str) -> Result<PathBuf> {
+    let path = get_git_dir()?.join(TEMPLATE_NAME);
+    std::fs::write(&path, contents).with_context(|| format!("Cannot write {}", path.display()))?;
+    Ok(path)
+}
+
+/// f's configured editor, for git to use when git itself has none
+/// configured. `$EDITOR` is already honored by both, so this only matters
+/// for the `editor` config key.
+pub fn editor_override(editor: &str) -> Option<String> {
+    let configured = ["GIT_EDITOR", "VISUAL"]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some())
+        || git_output(&["config", "core.editor"]).is_some();
+    (!configured).then(|| editor.to_string())
+}
+
+/// How `f c --by-dir` and `--by-owner` split the staged files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Dir,
+    Owner,
+}
+
+/// The default message of each commit, `{group}` being the directory or
+/// owners.
+pub const GROUP_TEMPLATE: &str = "Update {group}";
+
+/// `paths` split into commits, each a label and its paths, by label.
+/// Files at the top level form one group, as do files nobody owns.
+pub fn group(paths: &[String], by: GroupBy, owners: Option<&Owners>) -> Vec<(String, Vec<String>)> {
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    for path in paths {
+        let label = match by {
+            GroupBy::Dir => match path.split_once('/') {
+                Some((dir, _)) => format!("{}/", dir),
+                None => "top-level files".to_string(),
+            },
+            GroupBy::Owner => match owners.map(|o| o.of(path)).unwrap_or_default() {
+                [] => "unowned files".to_string(),
+                names => names.join(" "),
+            },
+        };
+        match groups.iter_mut().find(|(l, _)| *l == label) {
+            Some((_, group)) => group.push(path.clone()),
+            None => groups.push((label, vec![path.clone()])),
+        }
+    }
+    groups.sort_by(|a, b| a.0.cmp(&b.0));
+    groups
+}
+
+/// Every path with a staged change. Renames count as both paths, so each
+/// side can land in its own group.
+pub fn staged_paths() -> Result<Vec<String>> {
+    let output = crate::exec::output(Command::new("git").args([
+        "diff",
+        "--cached",
+        "--name-only",
+        "--no-renames",
+        "-z",
+    ]))?;
+    let mut paths: Vec<String> = String::from_utf8_lossy(&output)
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// A path's entry in the index, as `git ls-files -s` prints it, or none
+/// when the path is staged for deletion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub path: String,
+    pub record: Option<String>,
+}
+
+pub fn index_entries(paths: &[String]) -> Result<Vec<IndexEntry>> {
+    let output = crate::exec::output(
+        Command::new("git")
+            .args(["ls-files", "-s", "-z", "--"])
+            .args(paths),
+    )?;
+    Ok(parse_index_entries(
+        &String::from_utf8_lossy(&output),
+        paths,
+    ))
+}
+
+fn parse_index_entries(output: &str, paths: &[String]) -> Vec<IndexEntry> {
+    let records: Vec<&str> = output.split('\0').filter(|r| !r.is_empty()).collect();
+    paths
+        .iter()
+        .map(|path| IndexEntry {
+            path: path.clone(),
+            record: records
+                .iter()
+                .find(|r| r.split_once('\t').is_some_and(|(_, p)| p == path))
+                .map(|r| r.to_string()),
+        })
+        .collect()
+}
+
+/// Puts `entries` back into the index as they were saved.
+pub fn restore_index(entries: &[IndexEntry]) -> Result<()> {
+    let records: String = entries
+        .iter()
+        .filter_map(|e| Some(format!("{}\0", e.record.as_ref()?)))
+        .collect();
+    if !records.is_empty() {
+        crate::exec::output_with_input(
+            Command::new("git").args(["update-index", "-z", "--index-info"]),
+            Some(records.as_bytes()),
+        )?;
+    }
+    let removed: Vec<&str> = entries
+        .iter()
+        .filter(|e| e.record.is_none())
+        .map(|e| e.path.as_str())
+        .collect();
+    if !removed.is_empty() {
+        crate::exec::output(
+            Command::new("git")
+                .args(["update-index", "--force-remove", "--"])
+                .args(removed),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_status::{DiffStats, FileType, StableId};
+
+    fn staged(path: &str, id: &str) -> GitFile {
+        GitFile {
+            mtime: 0,
+            rel_path: path.to_string(),
+            abs_path: PathBuf::from(path),
+            file_type: FileType::Staged,
+            stable_id: StableId {
+                display: id.to_string(),
+                full_hash: id.repeat(4),
+            },
+            diff_stats: Some(DiffStats {
+                added: 8,
+                removed: 2,
+            }),
+            lfs_size: None,
+            mode_change: None,
+            status: 'M',
+        }
+    }
+
+    #[test]
+    fn template_lists_staged_files_as_comments() {
+        let text = template(&[staged("src/lib.rs", "ak")], '#', None);
+        assert_eq!(text, "\n# Staged files:\n#   ak    src/lib.rs +8/-2\n");
+    }
+
+    #[test]
+    fn template_keeps_message_first() {
+        let text = template(&[staged("a.rs", "d")], ';', Some("fix typo\n\n"));
+        assert!(text.starts_with("fix typo\n\n; Staged files:\n"));
+    }
+
+    #[test]
+    fn template_without_staged_files_is_just_the_head() {
+        assert_eq!(template(&[], '#', Some("msg")), "msg\n");
+        assert_eq!(template(&[], '#', None), "");
+    }
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn group_by_top_level_directory() {
+        let staged = paths(&["Cargo.toml", "crates/f/a.rs", "crates/g/b.rs", "docs/x.md"]);
+        assert_eq!(
+            group(&staged, GroupBy::Dir, None),
+            [
+                (
+                    "crates/".to_string(),
+                    paths(&["crates/f/a.rs", "crates/g/b.rs"])
+                ),
+                ("docs/".to_string(), paths(&["docs/x.md"])),
+                ("top-level files".to_string(), paths(&["Cargo.toml"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn group_by_owner() {
+        let owners = Owners::parse("/web/ @acme/web\n/api/ @acme/api @bob\n");
+        let staged = paths(&["README.md", "api/main.go", "web/app.ts", "web/index.html"]);
+        let groups = group(&staged, GroupBy::Owner, Some(&owners));
+        let labels: Vec<&str> = groups.iter().map(|(l, _)| l.as_str()).collect();
+        assert_eq!(labels, ["@acme/api @bob", "@acme/web", "unowned files"]);
+        assert_eq!(groups[1].1, paths(&["web/app.ts", "web/index.html"]));
+    }
+
+    #[test]
+    fn test_parse_index_entries() {
+        let output = "100644 e69de29bb2d1d6434b8b29ae775ad8c2e48c5391 0\ta b.txt\0";
+        assert_eq!(
+            parse_index_entries(output, &paths(&["a b.txt", "gone.txt"])),
+            [
+                IndexEntry {
+                    path: "a b.txt".to_string(),
+                    record: Some(output.trim_end_matches('\0').to_string()),
+                },
+                IndexEntry {
+                    path: "gone.txt".to_string(),
+                    record: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use crate::error::Error;
+use crate::git_status::{StableId, generate_ids};
+use anyhow::{Result, bail};
+use std::process::Command;
+
+/// A commit listed for picking, with an ID from the same scheme as files,
+/// hashed from its sha so it stays the same between runs.
+#[derive(Debug, Clone)]
+pub struct Commit {
+    pub sha: String,
+    pub short: String,
+    pub subject: String,
+    pub author: String,
+    pub date: String,
+    pub id: StableId,
+}
+
+const FORMAT: &str = "--format=%H%x1f%h%x1f%s%x1f%an%x1f%ar";
+
+/// `git log` over `range`, newest first.
+pub fn log(range: &[&str], limit: usize, id_chars: &[char]) -> Result<Vec<Commit>> {
+    let limit = format!("--max-count={}", limit);
+    let output = crate::exec::run_output(
+        Command::new("git")
+            .args(["log", "--no-color", FORMAT, &limit])
+            .args(range)
+            .arg("--"),
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        bail!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(parse_log(
+        &String::from_utf8_lossy(&output.stdout),
+        id_chars,
+    ))
+}
+
+fn parse_log(output: &str, id_chars: &[char]) -> Vec<Commit> {
+    let rows: Vec<Vec<&str>> = output
+        .lines()
+        .map(|line| line.split('\x1f').collect::<Vec<_>>())
+        .filter(|fields| fields.len() == 5)
+        .collect();
+    let shas: Vec<String> = rows.iter().map(|r| r[0].to_string()).collect();
+    rows.iter()
+        .zip(generate_ids(&shas, id_chars))
+        .map(|(r, (display, full_hash))| Commit {
+            sha: r[0].to_string(),
+            short: r[1].to_string(),
+            subject: r[2].to_string(),
+            author: r[3].to_string(),
+            date: r[4].to_string(),
+            id: StableId { display, full_hash },
+        })
+        .collect()
+}
+
+pub fn find_by_id<'a>(commits: &'a [Commit], id: &str) -> Result<&'a Commit> {
+    let matches: Vec<_> = commits.iter().filter(|c| c.id.matches(id)).collect();
+    match matches.as_slice() {
+        [commit] => Ok(commit),
+        [] => Err(Error::NotFound(format!("No commit with ID '{}' in the list", id)).into()),
+        _ => Err(Error::AmbiguousId {
+            id: id.to_string(),
+            matches: matches.len(),
+            what: "commits",
+        }
+        .into()),
+    }
+}
+
+/// The revisions `git diff` takes for `range`: `A..B` compares the two,
+/// `A...B` compares B with where it forked from A, and leaving B out
+/// compares with the work tree instead. An empty side of `..` is HEAD, as
+/// in git.
+pub fn diff_revisions(range: &str) -> Result<Vec<String>> {
+    let (from, to, symmetric) = match range.split_once("...") {
+        Some((from, to)) => (from, to, true),
+        None => match range.split_once("..") {
+            Some((from, to)) => (from, to, false),
+            None => (range, "", false),
+        },
+    };
+    if from.is_empty() && to.is_empty() {
+        return Err(Error::Usage(format!("No commits in range '{}'", range)).into());
+    }
+    let from = if from.is_empty() { "HEAD" } else { from };
+    Ok(match (symmetric, to) {
+        (false, "") => vec![from.to_string()],
+        (false, to) => vec![from.to_string(), to.to_string()],
+        (true, "") => vec!["--merge-base".to_string(), from.to_string()],
+        (true, to) => vec![format!("{}...{}", from, to)],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID_CHARS: &[char] = &['d', 'f', 'g', 'h', 'k', 'l', 's', 'a'];
+
+    fn log_line(sha: &str, subject: &str) -> String {
+        format!(
+            "{}\x1f{}\x1f{}\x1fSomeone\x1f2 days ago\n",
+            sha,
+            &sha[..7],
+            subject
+        )
+    }
+
+    #[test]
+    fn test_parse_log() {
+        let output = log_line(&"a".repeat(40), "first") + &log_line(&"b".repeat(40), "second");
+        let commits = parse_log(&output, ID_CHARS);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].subject, "first");
+        assert_eq!(commits[1].short, "bbbbbbb");
+        assert_eq!(commits[1].date, "2 days ago");
+        assert_ne!(commits[0].id.display, commits[1].id.display);
+    }
+
+    #[test]
+    fn test_ids_are_stable_across_lists() {
+        let sha = "c".repeat(40);
+        let alone = parse_log(&log_line(&sha, "x"), ID_CHARS);
+        let with_other = parse_log(
+            &(log_line(&sha, "x") + &log_line(&"d".repeat(40), "y")),
+            ID_CHARS,
+        );
+        assert_eq!(alone[0].id.full_hash, with_other[0].id.full_hash);
+    }
+
+    #[test]
+    fn test_find_by_id() {
+        let commits = parse_log(&log_line(&"e".repeat(40), "only"), ID_CHARS);
+        let id = commits[0].id.display.clone();
+        assert_eq!(find_by_id(&commits, &id).unwrap().subject, "only");
+        assert!(find_by_id(&commits, "zz").is_err());
+    }
+
+    #[test]
+    fn test_diff_revisions() {
+        assert_eq!(
+            diff_revisions("main..feature").unwrap(),
+            ["main", "feature"]
+        );
+        assert_eq!(diff_revisions("v1.0..").unwrap(), ["v1.0"]);
+        assert_eq!(diff_revisions("HEAD~3").unwrap(), ["HEAD~3"]);
+        assert_eq!(diff_revisions("..feature").unwrap(), ["HEAD", "feature"]);
+        assert_eq!(
+            diff_revisions("main...feature").unwrap(),
+            ["main...feature"]
+        );
+        assert_eq!(diff_revisions("main...").unwrap(), ["--merge-base", "main"]);
+        assert!(diff_revisions("..").is_err());
+        assert!(diff_revisions("").is_err());
+    }
+}
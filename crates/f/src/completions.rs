@@ -0,0 +1,197 @@
+use clap::{Command, ValueEnum};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+struct SubcommandInfo {
+    names: Vec<String>,
+    about: String,
+    takes_id: bool,
+}
+
+fn subcommands(cmd: &Command) -> Vec<SubcommandInfo> {
+    cmd.get_subcommands()
+        .filter(|c| !c.is_hide_set())
+        .map(|c| {
+            let mut names = vec![c.get_name().to_string()];
+            names.extend(c.get_visible_aliases().map(str::to_string));
+            SubcommandInfo {
+                names,
+                about: c.get_about().map(|a| a.to_string()).unwrap_or_default(),
+                takes_id: c.get_arguments().any(|a| a.get_id() == "id"),
+            }
+        })
+        .collect()
+}
+
+/// Builds a completion script for `shell`. Subcommands come from the clap
+/// definition; file IDs are looked up at completion time via
+/// `f list --porcelain`, so they always reflect the current repo state.
+pub fn generate(shell: Shell, cmd: &Command) -> String {
+    let subs = subcommands(cmd);
+    let bin = cmd.get_name();
+    match shell {
+        Shell::Bash => bash(bin, &subs),
+        Shell::Zsh => zsh(bin, &subs),
+        Shell::Fish => fish(bin, &subs),
+    }
+}
+
+/// Where packages install the script for `shell`, relative to the
+/// shell's completion directory.
+pub fn file_name(shell: Shell, bin: &str) -> String {
+    match shell {
+        Shell::Bash => bin.to_string(),
+        Shell::Zsh => format!("_{}", bin),
+        Shell::Fish => format!("{}.fish", bin),
+    }
+}
+
+fn id_commands(subs: &[SubcommandInfo]) -> Vec<&str> {
+    subs.iter()
+        .filter(|s| s.takes_id)
+        .flat_map(|s| s.names.iter().map(String::as_str))
+        .collect()
+}
+
+fn all_names(subs: &[SubcommandInfo]) -> Vec<&str> {
+    subs.iter()
+        .flat_map(|s| s.names.iter().map(String::as_str))
+        .collect()
+}
+
+fn bash(bin: &str, subs: &[SubcommandInfo]) -> String {
+    format!(
+        r#"_{bin}_ids() {{
+    command {bin} list --porcelain 2>/dev/null | cut -f1 | sort -u
+}}
+
+_{bin}() {{
+    local cur prev
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+
+    if [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W "{names} $(_{bin}_ids)" -- "$cur"))
+        return
+    fi
+
+    case "$prev" in
+        {id_cmds})
+            COMPREPLY=($(compgen -W "$(_{bin}_ids)" -- "$cur"))
+            ;;
+    esac
+}}
+
+complete -F _{bin} {bin}
+"#,
+        names = all_names(subs).join(" "),
+        id_cmds = id_commands(subs).join("|"),
+    )
+}
+
+fn zsh(bin: &str, subs: &[SubcommandInfo]) -> String {
+    let described: Vec<String> = subs
+        .iter()
+        .flat_map(|s| {
+            s.names
+                .iter()
+                .map(move |n| format!("        '{}:{}'", n, s.about.replace('\'', "'\\''")))
+        })
+        .collect();
+    format!(
+        r#"#compdef {bin}
+
+_{bin}_ids() {{
+    local -a ids
+    ids=(${{(f)"$(command {bin} list --porcelain 2>/dev/null | awk -F'\t' '!seen[$1]++ {{ print $1 ":" $3 }}')"}})
+    _describe -t ids 'file id' ids
+}}
+
+_{bin}() {{
+    local -a commands
+    commands=(
+{described}
+    )
+
+    if (( CURRENT == 2 )); then
+        _describe -t commands 'command' commands
+        _{bin}_ids
+        return
+    fi
+
+    case "$words[2]" in
+        {id_cmds})
+            (( CURRENT == 3 )) && _{bin}_ids
+            ;;
+    esac
+}}
+
+_{bin} "$@"
+"#,
+        described = described.join("\n"),
+        id_cmds = id_commands(subs).join("|"),
+    )
+}
+
+fn fish(bin: &str, subs: &[SubcommandInfo]) -> String {
+    let mut script = format!(
+        r#"function __{bin}_ids
+    command {bin} list --porcelain 2>/dev/null | awk -F'\t' '!seen[$1]++ {{ print $1 "\t" $3 }}'
+end
+
+complete -c {bin} -f
+complete -c {bin} -n '__fish_use_subcommand' -a '(__{bin}_ids)'
+"#
+    );
+    for sub in subs {
+        for name in &sub.names {
+            script.push_str(&format!(
+                "complete -c {bin} -n '__fish_use_subcommand' -a '{}' -d '{}'\n",
+                name,
+                sub.about.replace('\'', "\\'")
+            ));
+        }
+    }
+    script.push_str(&format!(
+        "complete -c {bin} -n '__fish_seen_subcommand_from {}' -a '(__{bin}_ids)'\n",
+        id_commands(subs).join(" ")
+    ));
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+
+    fn test_command() -> Command {
+        Command::new("f")
+            .subcommand(Command::new("list").visible_alias("l").about("List"))
+            .subcommand(
+                Command::new("diff")
+                    .visible_alias("d")
+                    .about("Diff")
+                    .arg(Arg::new("id")),
+            )
+    }
+
+    #[test]
+    fn bash_completes_ids_after_id_commands() {
+        let script = generate(Shell::Bash, &test_command());
+        assert!(script.contains("diff|d)"));
+        assert!(script.contains("list l diff d"));
+        assert!(script.contains("f list --porcelain"));
+    }
+
+    #[test]
+    fn fish_lists_subcommands_with_descriptions() {
+        let script = generate(Shell::Fish, &test_command());
+        assert!(script.contains("-a 'list' -d 'List'"));
+        assert!(script.contains("__fish_seen_subcommand_from diff d"));
+    }
+}
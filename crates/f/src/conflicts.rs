@@ -0,0 +1,302 @@
+//! Conflicted files: taking one side with `f <id> ours|theirs`, editing
+//! from the first conflict marker with `f <id> both`, and `f conflict`'s
+//! view of the base, ours and theirs versions.
+
+use crate::error::Error;
+use crate::width::{pad, truncate_end};
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// The line (1-based) of the first `<<<<<<<` marker in `text`.
+pub fn first_marker(text: &str) -> Option<u32> {
+    text.lines()
+        .position(|line| line.starts_with("<<<<<<<"))
+        .map(|index| index as u32 + 1)
+}
+
+/// A stretch of the merged file: lines both sides agree on, or a conflict
+/// with each side's lines and the base they started from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Region {
+    Common(Vec<String>),
+    Conflict {
+        ours: Vec<String>,
+        base: Vec<String>,
+        theirs: Vec<String>,
+    },
+}
+
+/// Which of the three versions a side of the conflict is missing from,
+/// e.g. a file deleted on one side and changed on the other.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Missing {
+    pub base: bool,
+    pub ours: bool,
+    pub theirs: bool,
+}
+
+/// `path` (repo-relative) merged again from the index's conflict stages
+/// with `git merge-file --diff3`, rather than read from the work tree,
+/// which may already be partly resolved.
+pub fn merge(path: &str, root: &Path, git_dir: &Path) -> Result<(Vec<Region>, Missing)> {
+    let output = crate::exec::output(
+        Command::new("git")
+            .args(["ls-files", "-u", "-z", "--"])
+            .arg(path)
+            .current_dir(root),
+    )?;
+    let mut blobs: [Option<String>; 3] = Default::default();
+    for entry in String::from_utf8_lossy(&output).split('\0') {
+        // `<mode> <sha> <stage>\t<path>`
+        let fields: Vec<&str> = entry.split(['\t', ' ']).collect();
+        if let [_, sha, stage, ..] = fields.as_slice()
+            && let Ok(stage @ 1..=3) = stage.parse::<usize>()
+        {
+            blobs[stage - 1] = Some(sha.to_string());
+        }
+    }
+    if blobs.iter().all(Option::is_none) {
+        return Err(Error::Usage(format!("{} has no conflict to show", path)).into());
+    }
+    let missing = Missing {
+        base: blobs[0].is_none(),
+        ours: blobs[1].is_none(),
+        theirs: blobs[2].is_none(),
+    };
+
+    let dir = git_dir.join("f").join("conflict");
+    fs::create_dir_all(&dir)?;
+    let names = ["base", "ours", "theirs"];
+    for (name, blob) in names.iter().zip(&blobs) {
+        let content = match blob {
+            Some(sha) => crate::exec::output(
+                Command::new("git")
+                    .args(["cat-file", "blob", sha])
+                    .current_dir(root),
+            )?,
+            None => Vec::new(),
+        };
+        fs::write(dir.join(name), content)
+            .with_context(|| format!("Failed to write {}", dir.join(name).display()))?;
+    }
+    let merged = crate::exec::run_output(
+        Command::new("git")
+            .args(["merge-file", "-p", "--diff3"])
+            .args(["-L", "ours", "-L", "base", "-L", "theirs"])
+            .args(["ours", "base", "theirs"])
+            .current_dir(&dir),
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    let _ = fs::remove_dir_all(&dir);
+    // The exit code is the number of conflicts, up to 127; above that
+    // merge-file failed
+    if !matches!(merged.status.code(), Some(0..=127)) {
+        bail!(
+            "git merge-file failed: {}",
+            String::from_utf8_lossy(&merged.stderr).trim()
+        );
+    }
+    Ok((
+        parse_diff3(&String::from_utf8_lossy(&merged.stdout)),
+        missing,
+    ))
+}
+
+/// The regions of a file merged with `--diff3` markers.
+pub fn parse_diff3(text: &str) -> Vec<Region> {
+    enum Part {
+        Common,
+        Ours,
+        Base,
+        Theirs,
+    }
+    let mut regions = Vec::new();
+    let mut common = Vec::new();
+    let (mut ours, mut base, mut theirs) = (Vec::new(), Vec::new(), Vec::new());
+    let mut part = Part::Common;
+    for line in text.lines() {
+        match part {
+            Part::Common if line.starts_with("<<<<<<<") => {
+                if !common.is_empty() {
+                    regions.push(Region::Common(std::mem::take(&mut common)));
+                }
+                part = Part::Ours;
+            }
+            Part::Ours if line.starts_with("|||||||") => part = Part::Base,
+            Part::Ours | Part::Base if line == "=======" => part = Part::Theirs,
+            Part::Theirs if line.starts_with(">>>>>>>") => {
+                regions.push(Region::Conflict {
+                    ours: std::mem::take(&mut ours),
+                    base: std::mem::take(&mut base),
+                    theirs: std::mem::take(&mut theirs),
+                });
+                part = Part::Common;
+            }
+            Part::Common => common.push(line.to_string()),
+            Part::Ours => ours.push(line.to_string()),
+            Part::Base => base.push(line.to_string()),
+            Part::Theirs => theirs.push(line.to_string()),
+        }
+    }
+    if !common.is_empty() {
+        regions.push(Region::Common(common));
+    }
+    regions
+}
+
+/// How many of `regions` are conflicts.
+pub fn count(regions: &[Region]) -> usize {
+    regions
+        .iter()
+        .filter(|r| matches!(r, Region::Conflict { .. }))
+        .count()
+}
+
+/// The file with diff3 markers, each version in its own color: ours green,
+/// the base dimmed and theirs blue.
+pub fn render_diff3(regions: &[Region]) -> String {
+    let mut out = String::new();
+    for region in regions {
+        match region {
+            Region::Common(lines) => {
+                for line in lines {
+                    out.push_str(&format!("{}\n", line));
+                }
+            }
+            Region::Conflict { ours, base, theirs } => {
+                out.push_str(&format!("{}\n", "<<<<<<< ours".green().bold()));
+                for line in ours {
+                    out.push_str(&format!("{}\n", line.green()));
+                }
+                out.push_str(&format!("{}\n", "||||||| base".bold()));
+                for line in base {
+                    out.push_str(&format!("{}\n", line.dimmed()));
+                }
+                out.push_str(&format!("{}\n", "=======".bold()));
+                for line in theirs {
+                    out.push_str(&format!("{}\n", line.blue()));
+                }
+                out.push_str(&format!("{}\n", ">>>>>>> theirs".blue().bold()));
+            }
+        }
+    }
+    out
+}
+
+/// The file with each conflict as three columns, base, ours and theirs,
+/// fitted to `width`. The lines around them span the full width.
+pub fn render_panes(regions: &[Region], width: usize) -> String {
+    let column = width.saturating_sub(7) / 3;
+    let cell = |line: Option<&String>| {
+        let line = line.map(|l| l.replace('\t', "    ")).unwrap_or_default();
+        pad(&truncate_end(&line, column), column)
+    };
+    let mut out = String::new();
+    for region in regions {
+        match region {
+            Region::Common(lines) => {
+                for line in lines {
+                    out.push_str(&format!("{}\n", truncate_end(line, width)));
+                }
+            }
+            Region::Conflict { ours, base, theirs } => {
+                let rule = "─".repeat(column);
+                out.push_str(&format!(
+                    "{}\n",
+                    format!("─{}─┬─{}─┬─{}", rule, rule, rule).dimmed()
+                ));
+                out.push_str(&format!(
+                    " {} {} {} {} {}\n",
+                    pad("base", column).bold(),
+                    "│".dimmed(),
+                    pad("ours", column).green().bold(),
+                    "│".dimmed(),
+                    pad("theirs", column).blue().bold()
+                ));
+                out.push_str(&format!(
+                    "{}\n",
+                    format!("─{}─┼─{}─┼─{}", rule, rule, rule).dimmed()
+                ));
+                let rows = base.len().max(ours.len()).max(theirs.len());
+                for row in 0..rows {
+                    out.push_str(&format!(
+                        " {} {} {} {} {}\n",
+                        cell(base.get(row)).dimmed(),
+                        "│".dimmed(),
+                        cell(ours.get(row)).green(),
+                        "│".dimmed(),
+                        cell(theirs.get(row)).blue()
+                    ));
+                }
+                out.push_str(&format!(
+                    "{}\n",
+                    format!("─{}─┴─{}─┴─{}", rule, rule, rule).dimmed()
+                ));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::{assert_snapshot, colors};
+
+    const MERGED: &str = "fn main() {\n\
+                          <<<<<<< ours\n    \
+                          one();\n\
+                          ||||||| base\n    \
+                          zero();\n\
+                          =======\n    \
+                          two();\n    \
+                          three();\n\
+                          >>>>>>> theirs\n\
+                          }\n";
+
+    #[test]
+    fn test_first_marker() {
+        let text = "fn main() {\n<<<<<<< HEAD\n    one();\n=======\n    two();\n>>>>>>> topic\n}\n";
+        assert_eq!(first_marker(text), Some(2));
+        assert_eq!(first_marker("resolved\n"), None);
+    }
+
+    #[test]
+    fn test_parse_diff3() {
+        let regions = parse_diff3(MERGED);
+        assert_eq!(
+            regions,
+            [
+                Region::Common(vec!["fn main() {".to_string()]),
+                Region::Conflict {
+                    ours: vec!["    one();".to_string()],
+                    base: vec!["    zero();".to_string()],
+                    theirs: vec!["    two();".to_string(), "    three();".to_string()],
+                },
+                Region::Common(vec!["}".to_string()]),
+            ]
+        );
+        assert_eq!(count(&regions), 1);
+    }
+
+    #[test]
+    fn test_render_diff3() {
+        let _colors = colors(true);
+        assert_snapshot(
+            "conflicts__render_diff3",
+            &render_diff3(&parse_diff3(MERGED)),
+        );
+    }
+
+    #[test]
+    fn test_render_panes() {
+        let _colors = colors(true);
+        assert_snapshot(
+            "conflicts__render_panes",
+            &render_panes(&parse_diff3(MERGED), 42),
+        );
+    }
+}
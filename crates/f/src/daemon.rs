@@ -0,0 +1,170 @@
+use crate::anydir;
+use crate::cache::StatusCache;
+use crate::config::Config;
+use crate::error::Error;
+use crate::git_status::{GitFile, get_all_files, get_git_dir, get_git_root};
+use crate::json::{self, Value};
+use crate::serve;
+use crate::watcher::{self, GIT_FILES};
+use anyhow::{Context, Result, bail};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Socket file, created inside the repository's git directory.
+const SOCKET_NAME: &str = "f.sock";
+
+/// The daemon rescans at least this often even without filesystem events,
+/// matching watch mode's fallback.
+const DAEMON_TTL: Duration = Duration::from_secs(30);
+
+/// Clients give up on an unresponsive daemon after this long and scan
+/// directly instead.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Set to skip the daemon and always scan directly.
+const DISABLE_ENV: &str = "F_NO_DAEMON";
+
+struct State {
+    cache: StatusCache,
+    git_stamp: Vec<Option<SystemTime>>,
+    /// Cleared if the watcher stops, after which every request rescans
+    /// rather than answer from a list nothing keeps current.
+    watching: bool,
+}
+
+impl State {
+    /// Index and HEAD changes made by git itself (e.g. `f <id> a`) are
+    /// checked synchronously on every request, so a command run right after
+    /// another never sees the list from before it.
+    fn check_git_files(&mut self, git_dir: &Path) {
+        let stamp = git_stamp(git_dir);
+        if !self.watching || stamp != self.git_stamp {
+            self.git_stamp = stamp;
+            self.cache.invalidate();
+        }
+    }
+}
+
+fn git_stamp(git_dir: &Path) -> Vec<Option<SystemTime>> {
+    GIT_FILES
+        .iter()
+        .map(|f| {
+            std::fs::metadata(git_dir.join(f))
+                .and_then(|m| m.modified())
+                .ok()
+        })
+        .collect()
+}
+
+pub fn socket_path() -> Result<PathBuf> {
+    Ok(get_git_dir()?.join(SOCKET_NAME))
+}
+
+/// Keeps the repo's file list in memory, refreshed by filesystem events, and
+/// answers `f serve`-style JSON-RPC requests on a unix socket until killed.
+pub fn run(config: &Config) -> Result<()> {
+    let root = get_git_root()?;
+    let git_dir = get_git_dir()?;
+    let path = git_dir.join(SOCKET_NAME);
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            bail!("f daemon is already running ({})", path.display());
+        }
+        // Left behind by a daemon that was killed
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Cannot remove stale socket {}", path.display()))?;
+    }
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Cannot listen on {}", path.display()))?;
+
+    let state = Arc::new(Mutex::new(State {
+        cache: StatusCache::new(config.id_chars(), DAEMON_TTL),
+        git_stamp: git_stamp(&git_dir),
+        watching: true,
+    }));
+
+    // Without a watcher there's nothing for the daemon to add over a scan
+    let changes = watcher::spawn(&root, &git_dir).context("Cannot watch the work tree")?;
+    let invalidator = Arc::clone(&state);
+    std::thread::spawn(move || {
+        for () in changes {
+            lock(&invalidator).cache.invalidate();
+        }
+        eprintln!("f daemon stopped watching, rescanning on every request");
+        lock(&invalidator).watching = false;
+    });
+
+    eprintln!("f daemon listening on {}", path.display());
+    for stream in listener.incoming().flatten() {
+        let state = Arc::clone(&state);
+        let git_dir = git_dir.clone();
+        std::thread::spawn(move || {
+            let _ = serve_connection(stream, &state, &git_dir);
+        });
+    }
+    Ok(())
+}
+
+fn lock(state: &Mutex<State>) -> std::sync::MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn serve_connection(stream: UnixStream, state: &Mutex<State>, git_dir: &Path) -> Result<()> {
+    let reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = {
+            let mut state = lock(state);
+            state.check_git_files(git_dir);
+            serve::handle_line(&line, &mut state.cache)
+        };
+        if let Some(response) = response {
+            writeln!(writer, "{}", response)?;
+        }
+    }
+    Ok(())
+}
+
+/// The current file list: from the daemon when one is running for this
+/// repository, otherwise by scanning directly. Outside a repository, the
+/// plain directory listing under `--any-dir`.
+pub fn get_files(id_chars: &[char]) -> Result<Vec<GitFile>> {
+    // The daemon only knows the usual index, not one git is committing
+    // from, as under `commit -a`
+    if std::env::var_os(DISABLE_ENV).is_none()
+        && std::env::var_os("GIT_INDEX_FILE").is_none()
+        && let Some(files) = query_list()
+    {
+        log::debug!("{} files from the daemon", files.len());
+        return Ok(files);
+    }
+    match get_all_files(id_chars) {
+        Err(e) if anydir::enabled() && matches!(e.downcast_ref(), Some(Error::NotARepo)) => {
+            anydir::get_files(id_chars)
+        }
+        result => result,
+    }
+}
+
+/// Any failure (no daemon, stale socket, timeout, error response) yields
+/// `None` so the caller falls back to a direct scan.
+fn query_list() -> Option<Vec<GitFile>> {
+    let path = socket_path().ok()?;
+    let mut stream = UnixStream::connect(path).ok()?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT)).ok()?;
+    writeln!(stream, r#"{{"jsonrpc":"2.0","id":1,"method":"list"}}"#).ok()?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).ok()?;
+    match json::parse(&line).ok()?.get("result")? {
+        Value::Array(items) => items.iter().map(serve::file_from_json).collect(),
+        _ => None,
+    }
+}
@@ -0,0 +1,220 @@
+use crate::git_status::{StatusSummary, get_status_summary_in};
+use crate::prompt;
+use crate::watch::{self, Wake};
+use crate::width;
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+/// An idle repo is polled progressively less often, up to this multiple of
+/// the base interval, so a dashboard of many quiet repos stays cheap.
+const MAX_BACKOFF: u32 = 8;
+
+/// How often key presses are checked between repo updates.
+const KEY_POLL: Duration = Duration::from_millis(50);
+
+type Update = (usize, Result<StatusSummary, String>);
+
+struct Repo {
+    name: String,
+    state: Option<Result<StatusSummary, String>>,
+}
+
+/// Shows branch and dirty counts for every git repo directly under `dir`.
+/// Each repo is polled on its own thread and schedule, so one slow repo
+/// never holds up the others.
+pub fn run(dir: &Path, interval: Duration) -> Result<()> {
+    let paths = discover_repos(dir)?;
+    if paths.is_empty() {
+        bail!("No git repositories found under {}", dir.display());
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut repos = Vec::new();
+    for (index, path) in paths.into_iter().enumerate() {
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |n| n.to_string_lossy().into_owned(),
+        );
+        repos.push(Repo { name, state: None });
+        spawn_poller(index, path, interval, tx.clone());
+    }
+    drop(tx);
+
+    watch::fullscreen(|| dashboard_loop(&mut repos, &rx, dir))
+}
+
+fn dashboard_loop(repos: &mut [Repo], updates: &Receiver<Update>, dir: &Path) -> Result<()> {
+    let header = format!(
+        "{}",
+        format!("{} · {} repos · q to quit", dir.display(), repos.len()).dimmed()
+    );
+    let mut dirty = true;
+    loop {
+        while let Ok((index, state)) = updates.try_recv() {
+            repos[index].state = Some(state);
+            dirty = true;
+        }
+        if dirty {
+            watch::draw(&header, &render(repos))?;
+            dirty = false;
+        }
+        match watch::poll_keys(KEY_POLL)? {
+            Some(Wake::Quit) => return Ok(()),
+            Some(Wake::Refresh) => dirty = true,
+            None => {}
+        }
+    }
+}
+
+/// Git repositories one level below `dir`, sorted by name. `dir` itself is
+/// included first when it is a repository.
+fn discover_repos(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("Cannot read {}", dir.display()))?;
+    let mut repos: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir() && p.join(".git").exists())
+        .collect();
+    repos.sort();
+    if dir.join(".git").exists() {
+        repos.insert(0, dir.to_path_buf());
+    }
+    Ok(repos)
+}
+
+fn spawn_poller(index: usize, path: PathBuf, base: Duration, tx: Sender<Update>) {
+    std::thread::spawn(move || {
+        let mut interval = base;
+        let mut last = None;
+        loop {
+            let state = get_status_summary_in(&path).map_err(|e| e.to_string());
+            let changed = last.as_ref() != Some(&state);
+            interval = next_interval(interval, base, changed);
+            if changed {
+                if tx.send((index, state.clone())).is_err() {
+                    return;
+                }
+                last = Some(state);
+            }
+            std::thread::sleep(interval);
+        }
+    });
+}
+
+/// Back to `base` after a change; otherwise double, capped at MAX_BACKOFF.
+fn next_interval(current: Duration, base: Duration, changed: bool) -> Duration {
+    if changed {
+        base
+    } else {
+        (current * 2).min(base * MAX_BACKOFF)
+    }
+}
+
+fn render(repos: &[Repo]) -> String {
+    let width = repos
+        .iter()
+        .map(|r| width::str_width(&r.name))
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for repo in repos {
+        let status = match &repo.state {
+            None => "…".dimmed().to_string(),
+            Some(Err(e)) => e.red().to_string(),
+            Some(Ok(summary)) if is_clean(summary) => {
+                format!("{} {}", prompt::format_prompt(summary), "clean".dimmed())
+            }
+            Some(Ok(summary)) => prompt::format_prompt(summary),
+        };
+        let _ = writeln!(
+            out,
+            "  {}  {}",
+            width::pad(&repo.name, width).bold(),
+            status
+        );
+    }
+    out
+}
+
+fn is_clean(summary: &StatusSummary) -> bool {
+    summary.staged + summary.unstaged + summary.untracked + summary.conflicted == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_repos_back_off_and_reset_on_change() {
+        let base = Duration::from_secs(2);
+        let mut interval = base;
+        for _ in 0..10 {
+            interval = next_interval(interval, base, false);
+        }
+        assert_eq!(interval, base * MAX_BACKOFF);
+        assert_eq!(next_interval(interval, base, true), base);
+    }
+
+    #[test]
+    fn discovers_repos_one_level_down() {
+        let root = std::env::temp_dir().join(format!("f-dashboard-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in ["b/.git", "a/.git", "plain", "a/nested/.git"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        let repos = discover_repos(&root).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(repos, vec![root.join("a"), root.join("b")]);
+    }
+
+    #[test]
+    fn render_aligns_names_and_marks_clean_repos() {
+        let _colors = crate::snapshot::colors(false);
+        let repos = vec![
+            Repo {
+                name: "api".to_string(),
+                state: Some(Ok(StatusSummary {
+                    branch: Some("main".to_string()),
+                    unstaged: 2,
+                    ..Default::default()
+                })),
+            },
+            Repo {
+                name: "frontend".to_string(),
+                state: Some(Ok(StatusSummary {
+                    branch: Some("dev".to_string()),
+                    ..Default::default()
+                })),
+            },
+            Repo {
+                name: "docs".to_string(),
+                state: None,
+            },
+        ];
+        assert_eq!(
+            render(&repos),
+            "  api       main ●2\n  frontend  dev clean\n  docs      …\n"
+        );
+    }
+
+    #[test]
+    fn render_aligns_wide_names() {
+        let _colors = crate::snapshot::colors(false);
+        let repos = vec![
+            Repo {
+                name: "文档".to_string(),
+                state: None,
+            },
+            Repo {
+                name: "cafe\u{301}".to_string(),
+                state: None,
+            },
+        ];
+        assert_eq!(render(&repos), "  文档  …\n  cafe\u{301}  …\n");
+    }
+}
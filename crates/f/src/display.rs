@@ -1,27 +1,63 @@
-use crate::git_status::{FileType, GitFile};
-use colored::Colorize;
+use crate::git_status::{FileType, GitFile, first_changed_lines, relative_to};
+use crate::glob::Glob;
+use crate::messages::{Msg, t, tf};
+use colored::{ColoredString, Colorize};
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `--root-relative`: show paths relative to the repository root
+/// rather than to the current directory.
+static ROOT_RELATIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_root_relative(on: bool) {
+    ROOT_RELATIVE.store(on, Ordering::Relaxed);
+}
+
+/// The path to show for `file`: relative to the current directory when
+/// that's inside the repository, e.g. `../lib.rs` from `src/bin`, otherwise
+/// relative to the root.
+pub fn display_path(file: &GitFile) -> String {
+    if ROOT_RELATIVE.load(Ordering::Relaxed) {
+        return file.rel_path.clone();
+    }
+    let Ok(cwd) = std::env::current_dir() else {
+        return file.rel_path.clone();
+    };
+    cwd_relative(file, &cwd).unwrap_or_else(|| file.rel_path.clone())
+}
+
+fn cwd_relative(file: &GitFile, cwd: &Path) -> Option<String> {
+    let depth = Path::new(&file.rel_path).components().count();
+    let root = file.abs_path.ancestors().nth(depth)?;
+    if cwd == root || !cwd.starts_with(root) {
+        return None;
+    }
+    Some(
+        relative_to(&file.abs_path, cwd)
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
 
 fn get_inline_diff(file: &GitFile) -> Vec<String> {
     let output = if file.file_type == FileType::Untracked {
-        Command::new("git")
-            .args([
-                "diff",
-                "--no-index",
-                "--color=always",
-                "/dev/null",
-                file.abs_path.to_string_lossy().as_ref(),
-            ])
-            .output()
+        crate::exec::run_output(Command::new("git").args([
+            "diff",
+            "--no-index",
+            "--color=always",
+            "/dev/null",
+            file.abs_path.to_string_lossy().as_ref(),
+        ]))
     } else {
-        Command::new("git")
-            .args([
-                "diff",
-                "--color=always",
-                "--",
-                file.abs_path.to_string_lossy().as_ref(),
-            ])
-            .output()
+        crate::exec::run_output(Command::new("git").args([
+            "diff",
+            "--color=always",
+            "--",
+            file.abs_path.to_string_lossy().as_ref(),
+        ]))
     };
 
     let Ok(output) = output else {
@@ -53,44 +89,184 @@ fn get_inline_diff(file: &GitFile) -> Vec<String> {
     lines
 }
 
-pub fn list_files(files: &[GitFile]) {
+/// Renders the sectioned file listing, including inline diffs for small
+/// changes, exactly as `f` prints it.
+pub fn render_files(files: &[GitFile]) -> String {
+    render_files_highlighted(files, |_| false)
+}
+
+/// What a listing leaves out: the files matching `collapse`, rolled up
+/// into a row per pattern, and those past the first `limit` in a section.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    pub limit: Option<usize>,
+    pub collapse: Vec<Glob>,
+}
+
+/// The listing `f` prints: `render_files`, but with the files matching
+/// `limits.collapse` rolled up at the end of their section, and at most
+/// `limits.limit` others in each section followed by a line saying how
+/// many more there are.
+pub fn render_listing(files: &[GitFile], limits: &Limits) -> String {
     if files.is_empty() {
-        println!("{}", "No changed files".dimmed());
-        return;
+        return render_files(files);
+    }
+    let theme = crate::theme::get();
+    let mut out = String::new();
+    for section in files.chunk_by(|a, b| a.file_type == b.file_type) {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let (listed, rollups) = crate::collapse::split(section, &limits.collapse);
+        let shown = limits.limit.unwrap_or(usize::MAX).min(listed.len());
+        if shown == 0 {
+            let _ = writeln!(out, "{}", section_header(section[0].file_type));
+        } else {
+            out.push_str(&render_files(&listed[..shown]));
+        }
+        for rollup in &rollups {
+            let _ = writeln!(out, "        {}", theme.muted.paint(&rollup.row()));
+        }
+        if listed.len() > shown {
+            let more = format!(
+                "  {} (f l --all)",
+                tf(Msg::More, &[&(listed.len() - shown).to_string()])
+            );
+            let _ = writeln!(out, "{}", theme.muted.paint(&more));
+        }
+    }
+    out
+}
+
+fn section_header(file_type: FileType) -> ColoredString {
+    let theme = crate::theme::get();
+    match file_type {
+        FileType::Unstaged => theme.unstaged.paint(&format!("── {} ──", t(Msg::Unstaged))),
+        FileType::Untracked => theme
+            .untracked
+            .paint(&format!("── {} ──", t(Msg::Untracked))),
+        FileType::Staged => theme.staged.paint(&format!("── {} ──", t(Msg::Staged))),
+    }
+}
+
+/// `path` cut from the start to fit on a terminal line next to `used`
+/// columns of other text. Unchanged when stdout isn't a terminal, so piped
+/// output keeps full paths.
+pub fn fit_path(path: String, used: usize) -> String {
+    match crate::width::terminal_width() {
+        Some(columns) => crate::width::truncate_start(&path, columns.saturating_sub(used).max(10)),
+        None => path,
+    }
+}
+
+/// Like `render_files`, but rows for which `highlight` returns true have
+/// their path drawn in bold.
+pub fn render_files_highlighted(files: &[GitFile], highlight: impl Fn(&GitFile) -> bool) -> String {
+    let theme = crate::theme::get();
+    let mut out = String::new();
+    if files.is_empty() {
+        let _ = writeln!(out, "{}", theme.muted.paint(t(Msg::NoChangedFiles)));
+        return out;
     }
 
     let mut last_type: Option<FileType> = None;
+    let cone = crate::sparse::Cone::load();
+    let owners = crate::owners::Owners::load();
 
     for file in files {
         if last_type != Some(file.file_type) {
             if last_type.is_some() {
-                println!();
+                let _ = writeln!(out);
             }
-            let header = match file.file_type {
-                FileType::Unstaged => format!("── {} ──", "Unstaged").yellow(),
-                FileType::Untracked => format!("── {} ──", "Untracked").green(),
-                FileType::Staged => format!("── {} ──", "Staged").cyan(),
-            };
-            println!("{}", header);
+            let _ = writeln!(out, "{}", section_header(file.file_type));
             last_type = Some(file.file_type);
         }
 
         let id_str = format!("{:<5}", file.stable_id);
-        let stats_str = match &file.diff_stats {
+        let (stats_str, stats_width) = match &file.diff_stats {
+            // The diff stats of an LFS file are those of its pointer
+            _ if let Some(size) = file.lfs_size => {
+                let label = format!("LFS · {}", crate::lfs::format_size(size));
+                let width = 1 + crate::width::str_width(&label);
+                (format!(" {}", label.blue()), width)
+            }
             Some(stats) if stats.added > 0 || stats.removed > 0 => {
-                format!(
-                    " {}{}",
-                    format!("+{}", stats.added).green(),
-                    format!("/-{}", stats.removed).red()
+                let added = format!("+{}", stats.added);
+                let removed = format!("/-{}", stats.removed);
+                let width = 1 + added.len() + removed.len();
+                (
+                    format!(
+                        " {}{}",
+                        theme.added.paint(&added),
+                        theme.removed.paint(&removed)
+                    ),
+                    width,
                 )
             }
             Some(stats) if stats.added > 0 => {
-                format!(" {}", format!("{} lines", stats.added).green())
+                let lines = format!("{} lines", stats.added);
+                let width = 1 + lines.len();
+                (format!(" {}", theme.added.paint(&lines)), width)
+            }
+            _ => (String::new(), 0),
+        };
+        let (stats_str, stats_width) = match file.mode_change {
+            Some((old, new)) => {
+                let label = mode_label(old, new);
+                (
+                    format!("{} {}", stats_str, label.magenta()),
+                    stats_width + 1 + crate::width::str_width(&label),
+                )
+            }
+            None => (stats_str, stats_width),
+        };
+        let (stats_str, stats_width) = match &cone {
+            Some(cone) if !cone.contains(&file.rel_path) => {
+                let note = " outside sparse checkout";
+                (
+                    format!("{}{}", stats_str, theme.muted.paint(note)),
+                    stats_width + note.len(),
+                )
+            }
+            _ => (stats_str, stats_width),
+        };
+        let (stats_str, stats_width) = match owners.as_ref().map(|o| o.of(&file.rel_path)) {
+            Some(names) if !names.is_empty() => {
+                let note = format!(" {}", names.join(" "));
+                (
+                    format!("{}{}", stats_str, theme.muted.paint(&note)),
+                    stats_width + crate::width::str_width(&note),
+                )
             }
+            _ => (stats_str, stats_width),
+        };
+
+        // Staged files say what the commit will do with them. Elsewhere the
+        // section already does
+        let status = match file.file_type {
+            FileType::Staged => format!("{} ", status_letter(file.status)),
             _ => String::new(),
         };
+        let status_width = if status.is_empty() { 0 } else { 2 };
 
-        println!("  {} {}{}", id_str.cyan(), file.rel_path, stats_str);
+        // Two spaces, the ID and a space come before the path
+        let path = fit_path(
+            display_path(file),
+            2 + id_str.len() + 1 + status_width + stats_width,
+        );
+        let path = if highlight(file) {
+            path.bold().to_string()
+        } else {
+            path
+        };
+        let _ = writeln!(
+            out,
+            "  {} {}{}{}",
+            theme.id.paint(&id_str),
+            status,
+            path,
+            stats_str
+        );
 
         if file.file_type == FileType::Unstaged || file.file_type == FileType::Untracked {
             let total_changes = file
@@ -99,12 +275,295 @@ pub fn list_files(files: &[GitFile]) {
                 .map(|s| s.added + s.removed)
                 .unwrap_or(0);
 
-            if total_changes > 0 && total_changes <= 6 {
+            // A symlink's diff is its old and new target, or for a type
+            // change the old file's whole content, so the link is clearer
+            if let Some(target) = file.symlink_target() {
+                let _ = writeln!(
+                    out,
+                    "         → {}",
+                    theme.id.paint(&target.display().to_string())
+                );
+            } else if total_changes > 0 && total_changes <= 6 && file.lfs_size.is_none() {
                 let diff_lines = get_inline_diff(file);
                 for line in diff_lines {
-                    println!("         {}", line);
+                    let _ = writeln!(out, "         {}", line);
                 }
             }
         }
     }
+    out
+}
+
+/// The section `f list --ignored` adds after the changed files.
+pub fn render_ignored(ignored: &[crate::ignore::Ignored]) -> String {
+    let mut out = String::new();
+    if ignored.is_empty() {
+        return out;
+    }
+    let theme = crate::theme::get();
+    let _ = writeln!(
+        out,
+        "\n{}",
+        theme.muted.paint(&format!("── {} ──", t(Msg::Ignored)))
+    );
+    for entry in ignored {
+        let id = format!("{:<5}", entry.id);
+        let path = fit_path(entry.path.clone(), 2 + id.len() + 1);
+        let _ = writeln!(
+            out,
+            "  {} {}",
+            theme.id.paint(&id),
+            theme.muted.paint(&path)
+        );
+    }
+    out
+}
+
+/// A status letter colored by what it does: green for added, red for
+/// deleted, and so on.
+fn status_letter(status: char) -> ColoredString {
+    let theme = crate::theme::get();
+    let letter = status.to_string();
+    match status {
+        'A' => theme.added.paint(&letter),
+        'M' => theme.unstaged.paint(&letter),
+        'D' => theme.removed.paint(&letter),
+        'R' | 'C' => theme.staged.paint(&letter),
+        'T' => theme.warning.paint(&letter),
+        'U' => theme.removed.paint(&letter).bold(),
+        _ => letter.normal(),
+    }
+}
+
+/// `+x` or `-x` when only the executable bit changed, `file → symlink` and
+/// the like when the type did, otherwise both modes in git's octal notation.
+pub fn mode_label(old: u32, new: u32) -> String {
+    const EXEC: u32 = 0o111;
+    const TYPE: u32 = 0o170000;
+    if old & TYPE != new & TYPE {
+        return format!("{} → {}", kind_name(old), kind_name(new));
+    }
+    if (old ^ new) & !EXEC == 0 {
+        let sign = if new & EXEC != 0 { '+' } else { '-' };
+        return format!("mode {}x", sign);
+    }
+    format!("mode {:o} → {:o}", old, new)
+}
+
+/// What a git mode stores, going by its object type bits.
+fn kind_name(mode: u32) -> &'static str {
+    match mode & 0o170000 {
+        0o120000 => "symlink",
+        0o160000 => "submodule",
+        _ => "file",
+    }
+}
+
+/// Prints one record per file for consumption by scripts.
+///
+/// With `nul` set, records are `id\0type\0path\0` with paths written verbatim,
+/// mirroring git's `-z` output. Otherwise fields are tab-separated, one record
+/// per line, and paths containing special characters are quoted the way git
+/// quotes them.
+pub fn list_porcelain(files: &[GitFile], nul: bool) {
+    let mut out = std::io::stdout().lock();
+    for file in files {
+        let _ = if nul {
+            write!(
+                out,
+                "{}\0{}\0{}\0",
+                file.stable_id,
+                file.file_type.as_str(),
+                file.rel_path
+            )
+        } else {
+            writeln!(
+                out,
+                "{}\t{}\t{}",
+                file.stable_id,
+                file.file_type.as_str(),
+                crate::quoting::quote_path(&file.rel_path)
+            )
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ListFormat {
+    /// Sectioned, colored listing with inline diffs
+    Human,
+    /// `path:line: message` entries for Vim's quickfix list
+    VimQuickfix,
+    /// `path:line:col: message` entries for Emacs compilation-mode
+    Emacs,
+}
+
+/// Prints one navigation entry per file pointing at its first changed line.
+/// Paths are relative to the current directory so editors can open them.
+pub fn list_locations(files: &[GitFile], format: ListFormat) {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let lines = first_changed_lines(files);
+    for (file, line) in files.iter().zip(lines) {
+        let path = relative_to(&file.abs_path, &cwd);
+        let line = line.unwrap_or(1);
+        let message = location_message(file);
+        match format {
+            ListFormat::VimQuickfix => println!("{}:{}: {}", path.display(), line, message),
+            ListFormat::Emacs => println!("{}:{}:1: {}", path.display(), line, message),
+            ListFormat::Human => {}
+        }
+    }
+}
+
+fn location_message(file: &GitFile) -> String {
+    let mut message = format!("[{}] {}", file.stable_id, file.file_type.as_str());
+    if let Some(stats) = &file.diff_stats {
+        message.push_str(&format!(" +{}/-{}", stats.added, stats.removed));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_status::{DiffStats, StableId};
+    use crate::snapshot::{self, assert_snapshot};
+    use std::path::PathBuf;
+
+    fn file(rel_path: &str) -> GitFile {
+        GitFile {
+            mtime: 0,
+            rel_path: rel_path.to_string(),
+            abs_path: PathBuf::from("/repo").join(rel_path),
+            file_type: FileType::Unstaged,
+            stable_id: StableId {
+                display: "a".into(),
+                full_hash: "a".into(),
+            },
+            diff_stats: None,
+            lfs_size: None,
+            mode_change: None,
+            status: 'M',
+        }
+    }
+
+    #[test]
+    fn test_cwd_relative() {
+        let lib = file("crates/foo/src/lib.rs");
+        let at = |cwd: &str| cwd_relative(&lib, Path::new(cwd));
+        assert_eq!(at("/repo/crates/foo/src").as_deref(), Some("lib.rs"));
+        assert_eq!(at("/repo/crates/bar").as_deref(), Some("../foo/src/lib.rs"));
+        // At the root or outside the repository, the root-relative path is used
+        assert_eq!(at("/repo"), None);
+        assert_eq!(at("/elsewhere"), None);
+    }
+
+    fn changed(rel_path: &str, file_type: FileType, id: &str, stats: (u32, u32)) -> GitFile {
+        GitFile {
+            file_type,
+            stable_id: StableId {
+                display: id.into(),
+                full_hash: id.into(),
+            },
+            // No such files, so small changes have no inline diff to show
+            abs_path: PathBuf::from("/nonexistent").join(rel_path),
+            diff_stats: Some(DiffStats {
+                added: stats.0,
+                removed: stats.1,
+            }),
+            ..file(rel_path)
+        }
+    }
+
+    #[test]
+    fn test_mode_label() {
+        assert_eq!(mode_label(0o100644, 0o100755), "mode +x");
+        assert_eq!(mode_label(0o100755, 0o100644), "mode -x");
+        assert_eq!(mode_label(0o100644, 0o120000), "file → symlink");
+        assert_eq!(mode_label(0o120000, 0o160000), "symlink → submodule");
+        assert_eq!(mode_label(0o100664, 0o100644), "mode 100664 → 100644");
+    }
+
+    #[test]
+    fn snapshot_render_files() {
+        let _colors = snapshot::colors(true);
+        let lfs = GitFile {
+            lfs_size: Some(3 * 1024 * 1024),
+            ..changed("assets/logo.psd", FileType::Unstaged, "k2", (3, 1))
+        };
+        let files = [
+            changed("src/main.rs", FileType::Unstaged, "a", (12, 4)),
+            lfs,
+            changed("notes/文档.md", FileType::Untracked, "qx", (40, 0)),
+            changed("empty.txt", FileType::Untracked, "b", (0, 0)),
+            GitFile {
+                mode_change: Some((0o100644, 0o100755)),
+                ..changed("build.sh", FileType::Staged, "y", (0, 0))
+            },
+            changed("Cargo.toml", FileType::Staged, "z", (1, 1)),
+            GitFile {
+                status: 'A',
+                ..changed("src/new.rs", FileType::Staged, "w", (5, 0))
+            },
+            GitFile {
+                status: 'D',
+                ..changed("old.txt", FileType::Staged, "v", (0, 3))
+            },
+        ];
+        assert_snapshot("display__render_files", &render_files(&files));
+        let highlighted = render_files_highlighted(&files[..2], |f| f.rel_path == "src/main.rs");
+        assert_snapshot("display__render_files_highlighted", &highlighted);
+        assert_snapshot("display__render_files_empty", &render_files(&[]));
+    }
+
+    #[test]
+    fn test_render_listing() {
+        let _colors = snapshot::colors(false);
+        let files = [
+            changed("empty.txt", FileType::Untracked, "b", (0, 0)),
+            changed("a.rs", FileType::Staged, "x", (1, 0)),
+            changed("b.rs", FileType::Staged, "y", (1, 0)),
+            changed("c.rs", FileType::Staged, "z", (1, 0)),
+        ];
+        let out = render_listing(
+            &files,
+            &Limits {
+                limit: Some(2),
+                ..Limits::default()
+            },
+        );
+        assert!(out.contains("empty.txt\n\n── Staged ──"), "{}", out);
+        assert!(
+            out.contains("b.rs +1/-0\n  … and 1 more (f l --all)\n"),
+            "{}",
+            out
+        );
+        assert!(!out.contains("c.rs"));
+        assert_eq!(
+            render_listing(
+                &files,
+                &Limits {
+                    limit: Some(5),
+                    ..Limits::default()
+                }
+            ),
+            render_files(&files)
+        );
+
+        let limits = Limits {
+            limit: None,
+            collapse: crate::glob::compile_all(&["*.txt".into(), "[ab].rs".into()]),
+        };
+        let out = render_listing(&files, &limits);
+        assert!(
+            out.starts_with("── Untracked ──\n        *.txt · 1 file\n\n── Staged ──\n"),
+            "{}",
+            out
+        );
+        assert!(
+            out.ends_with("c.rs +1/-0\n        [ab].rs · 2 files\n"),
+            "{}",
+            out
+        );
+    }
 }
@@ -0,0 +1,541 @@
+use crate::config::Config;
+use crate::git_status::{get_git_root, git_output};
+use crate::maintenance;
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+    Skip,
+}
+
+/// Oldest git with everything f runs, e.g. `git sparse-checkout list`.
+const MIN_GIT: (u32, u32) = (2, 25);
+
+/// Tracked files above which `git status` benefits from fsmonitor.
+const LARGE_REPO: usize = 20_000;
+
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    /// What to do about a warning or failure
+    fix: Option<String>,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn fix(mut self, fix: impl Into<String>) -> Self {
+        self.fix = Some(fix.into());
+        self
+    }
+
+    fn render(&self) -> String {
+        let mark = match self.status {
+            Status::Ok => "✓".green(),
+            Status::Warn => "!".yellow(),
+            Status::Fail => "✗".red(),
+            Status::Skip => "-".dimmed(),
+        };
+        let line = format!("{} {:<10} {}", mark, self.name, self.detail);
+        match &self.fix {
+            Some(fix) if matches!(self.status, Status::Warn | Status::Fail) => {
+                format!("{}\n             {} {}", line, "→".dimmed(), fix)
+            }
+            _ => line,
+        }
+    }
+}
+
+/// Prints a line per environment check. Returns false if any check failed.
+pub fn run(config: &Config) -> bool {
+    let checks = [
+        check_git(),
+        check_repo(),
+        check_config(),
+        check_id_chars(config),
+        check_editor(config),
+        check_pager(),
+        check_diff_tool(),
+        check_watch(),
+        check_fsmonitor(),
+        check_maintenance(),
+        check_colors(),
+        check_signing(config),
+    ];
+    for check in &checks {
+        println!("{}", check.render());
+    }
+    !checks.iter().any(|c| c.status == Status::Fail)
+}
+
+fn check_git() -> Check {
+    let Some(version) = git_output(&["--version"]) else {
+        return Check::new("git", Status::Fail, "git not found on PATH")
+            .fix("install git, or add its directory to PATH");
+    };
+    match parse_git_version(&version) {
+        Some(found) if found < MIN_GIT => Check::new(
+            "git",
+            Status::Fail,
+            format!("{}, f needs {}.{} or later", version, MIN_GIT.0, MIN_GIT.1),
+        )
+        .fix("upgrade git"),
+        Some(_) => Check::new("git", Status::Ok, version),
+        None => Check::new(
+            "git",
+            Status::Warn,
+            format!("unrecognized version: {}", version),
+        ),
+    }
+}
+
+/// Major and minor version from `git --version` output, e.g.
+/// `git version 2.39.3 (Apple Git-146)`.
+fn parse_git_version(output: &str) -> Option<(u32, u32)> {
+    let version = output
+        .strip_prefix("git version ")?
+        .split_whitespace()
+        .next()?;
+    let mut parts = version.split('.').map(|p| p.parse::<u32>().ok());
+    Some((parts.next()??, parts.next()??))
+}
+
+fn check_repo() -> Check {
+    match get_git_root() {
+        Ok(root) => Check::new("repo", Status::Ok, root.display().to_string()),
+        Err(_) => Check::new("repo", Status::Warn, "not inside a git repository")
+            .fix("run f doctor from a repository to check it too"),
+    }
+}
+
+fn check_config() -> Check {
+    let Some(path) = Config::config_path() else {
+        return Check::new(
+            "config",
+            Status::Warn,
+            "no config directory; using defaults",
+        );
+    };
+    let content = match std::fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(_) if !path.exists() => {
+            return Check::new(
+                "config",
+                Status::Ok,
+                format!("{} not found; using defaults", path.display()),
+            );
+        }
+        Err(e) => return Check::new("config", Status::Fail, format!("{}: {}", path.display(), e)),
+    };
+    match toml::from_str::<Config>(&content) {
+        Ok(_) => Check::new("config", Status::Ok, path.display().to_string()),
+        Err(e) => Check::new(
+            "config",
+            Status::Fail,
+            format!("{}: {}", path.display(), e.message()),
+        )
+        .fix("fix the setting, or remove it to use the default"),
+    }
+}
+
+/// Checks the configured `id_chars` as written: `Config::id_chars` quietly
+/// falls back to the default when there are too few.
+fn check_id_chars(config: &Config) -> Check {
+    let id_chars = &config.id_chars;
+    match id_chars_problem(id_chars) {
+        None => Check::new("id_chars", Status::Ok, id_chars.as_str()),
+        Some(problem) => Check::new(
+            "id_chars",
+            Status::Warn,
+            format!("{:?}: {}", id_chars, problem),
+        )
+        .fix("set id_chars in f.toml to two or more distinct letters other than q"),
+    }
+}
+
+fn id_chars_problem(id_chars: &str) -> Option<String> {
+    let chars: Vec<char> = id_chars.chars().collect();
+    if chars.len() < 2 {
+        return Some("fewer than 2 characters, so the default is used".to_string());
+    }
+    if let Some(c) =
+        (1..chars.len()).find_map(|i| chars[..i].contains(&chars[i]).then_some(chars[i]))
+    {
+        return Some(format!("{} appears more than once, so IDs can collide", c));
+    }
+    if let Some(c) = chars.iter().find(|c| !c.is_alphanumeric()) {
+        return Some(format!("{:?} makes IDs look like paths or flags", c));
+    }
+    if chars.contains(&'q') {
+        return Some("q quits the interactive picker, so IDs with q can't be typed there".into());
+    }
+    None
+}
+
+fn check_editor(config: &Config) -> Check {
+    let editor = config.editor();
+    let program = editor.split_whitespace().next().unwrap_or("");
+    if program.is_empty() {
+        return Check::new("editor", Status::Fail, "$EDITOR is set but empty");
+    }
+    match find_program(program) {
+        Some(path) => Check::new(
+            "editor",
+            Status::Ok,
+            format!("{} ({})", editor, path.display()),
+        ),
+        None => Check::new(
+            "editor",
+            Status::Fail,
+            format!("'{}' not found on PATH", program),
+        )
+        .fix("set editor in f.toml, or $EDITOR, to an installed editor"),
+    }
+}
+
+/// The pager git, and so `f diff`, will use.
+fn check_pager() -> Check {
+    let Some(pager) = git_output(&["var", "GIT_PAGER"]) else {
+        return Check::new("pager", Status::Warn, "git could not resolve a pager");
+    };
+    let program = pager.split_whitespace().next().unwrap_or("");
+    if program.is_empty() || program == "cat" {
+        return Check::new("pager", Status::Ok, "none (output is not paged)");
+    }
+    match find_program(program) {
+        Some(_) => Check::new("pager", Status::Ok, pager),
+        None => Check::new(
+            "pager",
+            Status::Fail,
+            format!("'{}' not found on PATH", program),
+        )
+        .fix("install it, or point core.pager or GIT_PAGER at one that is"),
+    }
+}
+
+fn check_diff_tool() -> Check {
+    let Some(tool) = git_output(&["config", "diff.tool"]) else {
+        return Check::new("difftool", Status::Skip, "diff.tool not set");
+    };
+    // A configured command is run through the shell; otherwise git knows
+    // the tool by name and runs the program of the same name
+    let cmd = git_output(&["config", &format!("difftool.{}.cmd", tool)]);
+    let program = match &cmd {
+        Some(cmd) => cmd.split_whitespace().next().unwrap_or("").to_string(),
+        None => tool.clone(),
+    };
+    match find_program(&program) {
+        Some(path) => Check::new(
+            "difftool",
+            Status::Ok,
+            format!("{} ({})", tool, path.display()),
+        ),
+        None => Check::new(
+            "difftool",
+            Status::Warn,
+            format!("'{}' not found on PATH", program),
+        )
+        .fix(format!("install {}, or change diff.tool", program)),
+    }
+}
+
+/// Whether `f watch --events` can get file notifications for every
+/// directory it would watch.
+fn check_watch() -> Check {
+    let Ok(root) = get_git_root() else {
+        return Check::new("watch", Status::Skip, "not inside a git repository");
+    };
+    if !cfg!(target_os = "linux") {
+        return Check::new("watch", Status::Ok, "polling for changes");
+    }
+    let dirs = crate::watcher::watched_dirs(&root).len();
+    let limit = std::fs::read_to_string("/proc/sys/fs/inotify/max_user_watches")
+        .ok()
+        .and_then(|s| s.trim().parse::<usize>().ok());
+    match limit {
+        Some(limit) if dirs > limit => Check::new(
+            "watch",
+            Status::Warn,
+            format!("{} directories to watch but inotify allows {}", dirs, limit),
+        )
+        .fix(format!(
+            "sudo sysctl fs.inotify.max_user_watches={}",
+            (dirs * 2).max(524_288)
+        )),
+        Some(limit) => Check::new(
+            "watch",
+            Status::Ok,
+            format!("inotify, {} of {} watches", dirs, limit),
+        ),
+        None => Check::new(
+            "watch",
+            Status::Ok,
+            format!("inotify, {} directories", dirs),
+        ),
+    }
+}
+
+fn check_fsmonitor() -> Check {
+    if get_git_root().is_err() {
+        return Check::new("fsmonitor", Status::Skip, "not inside a git repository");
+    }
+    if let Some(value) = git_output(&["config", "core.fsmonitor"])
+        && value != "false"
+    {
+        return Check::new("fsmonitor", Status::Ok, format!("enabled ({})", value));
+    }
+    let files = git_output(&["ls-files"]).map_or(0, |out| out.lines().count());
+    if files < LARGE_REPO {
+        return Check::new(
+            "fsmonitor",
+            Status::Ok,
+            format!("off, fine for {} files", files),
+        );
+    }
+    // git's own daemon only exists on macOS and Windows
+    let fix = if cfg!(target_os = "linux") {
+        "git config core.untrackedCache true, or set up Watchman as core.fsmonitor"
+    } else {
+        "git config core.fsmonitor true"
+    };
+    Check::new(
+        "fsmonitor",
+        Status::Warn,
+        format!("off with {} files, so every status scans the tree", files),
+    )
+    .fix(fix)
+}
+
+fn check_maintenance() -> Check {
+    let (Ok(root), Ok(git_dir)) = (get_git_root(), crate::git_status::get_git_dir()) else {
+        return Check::new("maintenance", Status::Skip, "not inside a git repository");
+    };
+    if let Some(task) = maintenance::running(&git_dir) {
+        return Check::new(
+            "maintenance",
+            Status::Ok,
+            format!("git {} is running now", task),
+        );
+    }
+    let Some(objects) = maintenance::objects() else {
+        return Check::new("maintenance", Status::Warn, "git count-objects failed");
+    };
+    let limit = maintenance::loose_limit();
+    let scheduled = if maintenance::scheduled(&root) {
+        "scheduled"
+    } else {
+        "not scheduled"
+    };
+    let detail = format!(
+        "{}, {} loose objects ({} KiB) and {} packs",
+        scheduled, objects.loose, objects.loose_size, objects.packs
+    );
+    if objects.loose > limit {
+        return Check::new(
+            "maintenance",
+            Status::Warn,
+            format!("{}, more than gc.auto's {}", detail, limit),
+        )
+        .fix("f maintenance run, or f maintenance start to keep it done");
+    }
+    Check::new("maintenance", Status::Ok, detail)
+}
+
+fn check_colors() -> Check {
+    use std::io::IsTerminal;
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let (status, detail, fix) = color_support(
+        std::io::stdout().is_terminal(),
+        var("NO_COLOR").is_some(),
+        var("CLICOLOR_FORCE").is_some_and(|v| v != "0"),
+        var("TERM").as_deref(),
+    );
+    let check = Check::new("colors", status, detail);
+    match fix {
+        Some(fix) => check.fix(fix),
+        None => check,
+    }
+}
+
+/// Whether output is colored, following the same variables as `colored`.
+fn color_support(
+    tty: bool,
+    no_color: bool,
+    force: bool,
+    term: Option<&str>,
+) -> (Status, String, Option<&'static str>) {
+    if no_color {
+        return (Status::Ok, "off (NO_COLOR is set)".into(), None);
+    }
+    if force {
+        return (Status::Ok, "on (CLICOLOR_FORCE is set)".into(), None);
+    }
+    if !tty {
+        return (Status::Ok, "off when piped".into(), None);
+    }
+    match term {
+        Some("dumb") => (
+            Status::Warn,
+            "TERM=dumb, escape codes may show up as text".into(),
+            Some("set TERM to your terminal's type, e.g. xterm-256color"),
+        ),
+        Some(term) => (Status::Ok, format!("on (TERM={})", term), None),
+        None => (
+            Status::Warn,
+            "TERM is unset".into(),
+            Some("set TERM to your terminal's type, e.g. xterm-256color"),
+        ),
+    }
+}
+
+fn find_program(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        return Path::new(program).is_file().then(|| PathBuf::from(program));
+    }
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Signs a throwaway commit object (never referenced by any branch) with
+/// git's own signing path, so gpg, ssh and x509 setups are all exercised
+/// exactly as `f c -S` would.
+fn check_signing(config: &Config) -> Check {
+    let git_wants = git_output(&["config", "--bool", "commit.gpgsign"]).as_deref() == Some("true");
+    if !config.commit.sign && !git_wants {
+        return Check::new("signing", Status::Skip, "commit signing not enabled");
+    }
+    let format = git_output(&["config", "gpg.format"]).unwrap_or_else(|| "openpgp".to_string());
+    let key = git_output(&["config", "user.signingkey"]);
+    if format == "ssh" && key.is_none() {
+        return Check::new(
+            "signing",
+            Status::Fail,
+            "gpg.format is ssh but user.signingkey is unset",
+        )
+        .fix("git config user.signingkey ~/.ssh/id_ed25519.pub");
+    }
+    let Some(tree) = git_output(&["mktree"]) else {
+        return Check::new(
+            "signing",
+            Status::Warn,
+            "cannot test signing outside a repository",
+        );
+    };
+
+    let output = crate::exec::run_output(Command::new("git").args([
+        "commit-tree",
+        "-S",
+        "-m",
+        "f doctor signing check",
+        &tree,
+    ]));
+    let key_desc = key.map_or_else(|| "default key".to_string(), |k| format!("key {}", k));
+    match output {
+        Ok(o) if o.status.success() => Check::new(
+            "signing",
+            Status::Ok,
+            format!("{} {} can sign", format, key_desc),
+        ),
+        Ok(o) => {
+            let stderr = String::from_utf8_lossy(&o.stderr);
+            let reason = stderr
+                .lines()
+                .find(|l| !l.trim().is_empty())
+                .unwrap_or("signing failed");
+            Check::new(
+                "signing",
+                Status::Fail,
+                format!("{} {}: {}", format, key_desc, reason.trim()),
+            )
+            .fix("check the key with git commit-tree -S, or turn signing off")
+        }
+        Err(e) => Check::new("signing", Status::Fail, format!("failed to run git: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_aligns_names() {
+        let _colors = crate::snapshot::colors(false);
+        let check = Check::new("git", Status::Ok, "git version 2.45.0");
+        assert_eq!(check.render(), "✓ git        git version 2.45.0");
+        let check = Check::new("signing", Status::Skip, "off");
+        assert_eq!(check.render(), "- signing    off");
+        let check = Check::new("pager", Status::Fail, "'less' not found").fix("install it");
+        assert_eq!(
+            check.render(),
+            "✗ pager      'less' not found\n             → install it"
+        );
+        // Fixes are only shown for problems
+        let check = Check::new("watch", Status::Ok, "inotify").fix("unused");
+        assert_eq!(check.render(), "✓ watch      inotify");
+    }
+
+    #[test]
+    fn test_parse_git_version() {
+        assert_eq!(parse_git_version("git version 2.45.0"), Some((2, 45)));
+        assert_eq!(
+            parse_git_version("git version 2.39.3 (Apple Git-146)"),
+            Some((2, 39))
+        );
+        assert_eq!(
+            parse_git_version("git version 2.47.0.windows.1"),
+            Some((2, 47))
+        );
+        assert_eq!(parse_git_version("hub version 2.14"), None);
+        assert!(parse_git_version("git version 2.20.1").unwrap() < MIN_GIT);
+    }
+
+    #[test]
+    fn test_id_chars_problem() {
+        assert_eq!(id_chars_problem("dfghklsa"), None);
+        assert_eq!(id_chars_problem("ab12"), None);
+        assert!(id_chars_problem("a").unwrap().contains("fewer than 2"));
+        assert!(id_chars_problem("abca").unwrap().contains("a appears more"));
+        assert!(id_chars_problem("ab-").unwrap().contains("paths or flags"));
+        assert!(id_chars_problem("asdq").unwrap().contains("q quits"));
+    }
+
+    #[test]
+    fn test_color_support() {
+        let status = |tty, no_color, force, term| color_support(tty, no_color, force, term).0;
+        assert_eq!(
+            status(true, false, false, Some("xterm-256color")),
+            Status::Ok
+        );
+        assert_eq!(status(true, false, false, Some("dumb")), Status::Warn);
+        assert_eq!(status(true, false, false, None), Status::Warn);
+        // NO_COLOR wins over everything, and piped output is never a problem
+        assert_eq!(status(true, true, true, Some("dumb")), Status::Ok);
+        assert_eq!(status(false, false, false, None), Status::Ok);
+        assert_eq!(
+            color_support(false, false, true, None).1,
+            "on (CLICOLOR_FORCE is set)"
+        );
+    }
+
+    #[test]
+    fn find_program_searches_path_and_explicit_paths() {
+        assert!(find_program("sh").is_some());
+        assert!(find_program("/bin/sh").is_some());
+        assert!(find_program("definitely-not-a-real-editor").is_none());
+    }
+}
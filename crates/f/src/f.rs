@@ -1,50 +1,94 @@
-mod config;
+mod absorb;
+mod anydir;
+mod archive;
+mod batch;
+mod bookmarks;
+mod cache;
+mod clipboard;
+mod collapse;
+mod commit;
+mod commits;
+mod completions;
+mod conflicts;
+mod daemon;
+mod dashboard;
 mod display;
-mod git_status;
+mod doctor;
+mod filter;
+mod focus;
+mod forge;
+mod glob;
+mod history;
+mod hooks;
+mod ignore;
+mod images;
+mod interactive;
+mod json;
+mod lint;
+mod logging;
+mod maintenance;
+mod man;
+mod mcp;
+mod messages;
+mod notes;
+mod notify;
+mod owners;
+mod pager;
+mod patch;
+mod plugin;
+mod pr;
+mod prompt;
+mod quoting;
+mod rebase;
+mod remotes;
+mod serve;
+mod session;
+mod settings;
+mod shell;
+#[cfg(test)]
+mod snapshot;
+mod sparse;
+mod split;
+mod state;
+mod summary;
+mod tags;
+mod theme;
+mod tools;
+mod trash;
+mod undo;
+mod watch;
+mod watcher;
+mod width;
 
-use clap::builder::styling::{AnsiColor, Color, Styles};
-use clap::{Parser, Subcommand};
-use std::os::unix::process::CommandExt;
+// The modules shared with editor plugins live in f-core; imported here so
+// `crate::git_status` and the like resolve the same as local modules
+use f_core::{config, error, exec, git_status, lfs, repo_state};
+
+use clap::builder::styling::Styles;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
 use std::process::{self, Command};
 
-use config::Config;
+use config::{Config, DefaultTarget};
+use error::Error;
 use git_status::{
-    FileType, GitFile, IdMatch, find_file_by_id, get_all_files, get_first_actionable_file,
+    FileType, GitFile, IdMatch, Pick, find_file_by_id, find_file_by_path,
+    get_first_actionable_file, get_first_staged_file, relative_to,
 };
+use messages::Msg;
+use std::io::BufRead;
 
 fn help_styles() -> Styles {
+    let theme = theme::get();
     Styles::styled()
-        .header(
-            anstyle::Style::new()
-                .bold()
-                .fg_color(Some(Color::Ansi(AnsiColor::Green))),
-        )
-        .usage(
-            anstyle::Style::new()
-                .bold()
-                .fg_color(Some(Color::Ansi(AnsiColor::Green))),
-        )
-        .literal(
-            anstyle::Style::new()
-                .bold()
-                .fg_color(Some(Color::Ansi(AnsiColor::Cyan))),
-        )
-        .placeholder(anstyle::Style::new().fg_color(Some(Color::Ansi(AnsiColor::Cyan))))
-        .error(
-            anstyle::Style::new()
-                .bold()
-                .fg_color(Some(Color::Ansi(AnsiColor::Red))),
-        )
-        .valid(
-            anstyle::Style::new()
-                .bold()
-                .fg_color(Some(Color::Ansi(AnsiColor::Green))),
-        )
-        .invalid(
-            anstyle::Style::new()
-                .bold()
-                .fg_color(Some(Color::Ansi(AnsiColor::Yellow))),
-        )
+        .header(theme.help.anstyle())
+        .usage(theme.help.anstyle())
+        .literal(theme.id.anstyle().bold())
+        .placeholder(theme.id.anstyle())
+        .error(theme.removed.anstyle().bold())
+        .valid(theme.added.anstyle().bold())
+        .invalid(theme.warning.anstyle().bold())
 }
 
 #[derive(Parser)]
@@ -59,18 +103,361 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    #[arg(global = true, short, long, help = "Enable verbose output")]
-    verbose: bool,
+    #[arg(
+        global = true,
+        short,
+        long,
+        action = clap::ArgAction::Count,
+        help = "Log what f does to stderr (-vv for more detail)"
+    )]
+    verbose: u8,
+
+    #[arg(
+        global = true,
+        short = 'C',
+        long = "repo",
+        value_name = "PATH",
+        help = "Run as if f was started in PATH (like git -C)"
+    )]
+    repo: Vec<PathBuf>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "PATH",
+        help = "Path to the repository's git directory (like git --git-dir)"
+    )]
+    git_dir: Option<PathBuf>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "PATH",
+        help = "Path to the work tree, e.g. for dotfiles in a bare repo (like git --work-tree)"
+    )]
+    work_tree: Option<PathBuf>,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Print the git and editor commands instead of running them"
+    )]
+    print: bool,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Never prompt or take over the terminal, as when stdout isn't one (for CI)"
+    )]
+    non_interactive: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "SECS",
+        help = "Kill any command f runs that takes longer, overriding timeout in f.toml"
+    )]
+    timeout: Option<u64>,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Show paths relative to the repository root, not the current directory"
+    )]
+    root_relative: bool,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Outside a git repository, list the directory's files by modification time"
+    )]
+    any_dir: bool,
+}
+
+#[derive(Args)]
+struct FilterArgs {
+    #[arg(
+        value_name = "PATTERN",
+        help = "Only show files matching these globs (repo-relative, e.g. 'src/**')"
+    )]
+    patterns: Vec<String>,
+    #[arg(long, conflicts_with = "unstaged", help = "Only show staged files")]
+    staged: bool,
+    #[arg(long, help = "Only show unstaged and untracked files")]
+    unstaged: bool,
+    #[arg(long, help = "Show files outside the f focus too")]
+    no_focus: bool,
+    #[arg(long, help = "Only show files CODEOWNERS says are yours")]
+    mine: bool,
+}
+
+impl FilterArgs {
+    fn build(&self, config: &Config) -> filter::FileFilter {
+        let mut filter = filter::FileFilter::new(&self.patterns, self.staged, self.unstaged)
+            .unwrap_or_else(|e| fail(e));
+        if self.mine {
+            let Some(owners) = owners::Owners::load() else {
+                fail(Error::NotFound(
+                    "This repository has no CODEOWNERS file".into(),
+                ));
+            };
+            let me = owners::identities(config);
+            if me.is_empty() {
+                fail(Error::Config(
+                    "--mine needs [owners] me in the config or a git user.email".into(),
+                ));
+            }
+            filter = filter.with_owner(owners, me);
+        }
+        if self.no_focus {
+            filter
+        } else {
+            focused(filter)
+        }
+    }
+}
+
+/// `filter` narrowed to the paths of `f focus`, if any.
+fn focused(filter: filter::FileFilter) -> filter::FileFilter {
+    filter
+        .with_focus(&focus::Focus::current())
+        .unwrap_or_else(|e| fail(Error::Config(format!("Bad f focus pattern: {:#}", e))))
+}
+
+#[derive(Subcommand)]
+enum TrashAction {
+    #[command(about = "List trash entries, newest first")]
+    List,
+    #[command(about = "Copy an entry's files back into the work tree")]
+    Restore {
+        #[arg(help = "Entry name from f trash list (default: the newest)")]
+        entry: Option<String>,
+        #[arg(help = "Restore only this path")]
+        path: Option<String>,
+        #[arg(long, help = "Overwrite files that have changed since")]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SparseAction {
+    #[command(about = "List the checked-out directories")]
+    List,
+    #[command(about = "Check out the directory of a file, or a directory")]
+    Add {
+        #[arg(help = "File ID, or a path in the repository that isn't checked out")]
+        target: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    #[command(about = "Show each setting f uses and whether f.toml, git or a default set it")]
+    Show,
+}
+
+#[derive(Subcommand)]
+enum NotesAction {
+    #[command(about = "List the notes by file")]
+    List,
+    #[command(about = "Print the notes as markdown, or as a GitHub review")]
+    Export {
+        #[arg(
+            long,
+            help = "A review body for gh api repos/{owner}/{repo}/pulls/<n>/reviews --input -"
+        )]
+        gh: bool,
+    },
+    #[command(about = "Delete every note")]
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum TagAction {
+    #[command(about = "Show a tag and the commit it points at")]
+    Show {
+        #[arg(help = "Tag name or ID")]
+        tag: String,
+    },
+    #[command(about = "Create a tag, annotated unless --lightweight")]
+    New {
+        name: String,
+        #[arg(help = "Commit to tag (default: HEAD)")]
+        rev: Option<String>,
+        #[arg(short, long, help = "Tag message (opens the editor when omitted)")]
+        message: Option<String>,
+        #[arg(
+            short = 's',
+            long,
+            conflicts_with = "lightweight",
+            help = "GPG-sign the tag"
+        )]
+        sign: bool,
+        #[arg(
+            long,
+            conflicts_with = "message",
+            help = "A plain ref, without a message"
+        )]
+        lightweight: bool,
+    },
+    #[command(about = "Push tags to the remote")]
+    Push {
+        #[arg(help = "Tag names or IDs (default: the newest tag)")]
+        tags: Vec<String>,
+        #[arg(long, default_value = "origin", help = "Remote to push to")]
+        remote: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum RemoteAction {
+    #[command(about = "Add a remote")]
+    Add { name: String, url: String },
+    #[command(about = "Remove a remote and its remote-tracking branches")]
+    Remove { name: String },
+    #[command(about = "Rename a remote")]
+    Rename { old: String, new: String },
+    #[command(about = "Delete remote-tracking branches whose branch is gone from the remote")]
+    Prune {
+        #[arg(help = "Remotes to prune (default: all)")]
+        names: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PrAction {
+    #[command(about = "Open a PR titled and described from the commits going out")]
+    Create {
+        #[arg(long, help = "Open it as a draft")]
+        draft: bool,
+        #[arg(
+            long,
+            value_name = "BRANCH",
+            help = "Branch to merge into (default: the remote's)"
+        )]
+        base: Option<String>,
+    },
+    #[command(about = "Show the PR of the current branch")]
+    Status,
+    #[command(about = "Show the PR's CI checks")]
+    Checks,
+    #[command(about = "List the files the PR changes, or act on one by ID")]
+    Files {
+        #[arg(help = "File ID from the list")]
+        id: Option<String>,
+        #[arg(help = "Action on that file (e, path, or any action when it has changes)")]
+        action: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SessionAction {
+    #[command(about = "List recorded sessions")]
+    List,
+    #[command(about = "Start recording f actions into a session")]
+    Record {
+        #[arg(help = "Session name (an earlier session of this name is replaced)")]
+        name: String,
+    },
+    #[command(about = "Stop recording")]
+    Stop,
+    #[command(about = "Print a session's script")]
+    Show {
+        #[arg(help = "Session name")]
+        name: String,
+    },
+    #[command(about = "Run a session's actions again, all or nothing")]
+    Replay {
+        #[arg(help = "Session name")]
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum GenerateTarget {
+    #[command(about = "Write completion scripts, with file ID completion, for one shell or all")]
+    Completions {
+        #[arg(value_enum, help = "Only this shell")]
+        shell: Option<completions::Shell>,
+        #[arg(short, long, default_value = ".", help = "Directory to write to")]
+        out_dir: PathBuf,
+    },
+    #[command(about = "Write man pages for f and each of its commands")]
+    Man {
+        #[arg(short, long, default_value = ".", help = "Directory to write to")]
+        out_dir: PathBuf,
+    },
 }
 
 #[derive(Subcommand)]
 enum Commands {
     #[command(visible_alias = "l", about = "List changed files")]
-    List,
+    List {
+        #[arg(long, help = "Machine-readable output: id, type and path per file")]
+        porcelain: bool,
+        #[arg(
+            short = 'z',
+            help = "NUL-terminate porcelain fields (implies --porcelain)"
+        )]
+        nul: bool,
+        #[arg(long, value_enum, default_value = "human", help = "Output format")]
+        format: display::ListFormat,
+        #[arg(
+            long,
+            help = "Add ignored files and directories, with IDs for f unignore"
+        )]
+        ignored: bool,
+        #[arg(
+            long,
+            value_name = "N",
+            help = "Show at most N files per section (default: [list] limit)"
+        )]
+        limit: Option<usize>,
+        #[arg(
+            long,
+            conflicts_with = "limit",
+            help = "Show every file, whatever the limit"
+        )]
+        all: bool,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
     #[command(visible_alias = "d", about = "Show diff for a file")]
     Diff {
-        #[arg(help = "File ID (defaults to first unstaged)")]
+        #[arg(help = "File ID, or - to read IDs/paths from stdin (defaults to first unstaged)")]
         id: Option<String>,
+        #[arg(
+            long,
+            value_name = "A..B",
+            help = "Diff the file between two commits or branches; without B, against the work tree"
+        )]
+        range: Option<String>,
+        #[arg(
+            long,
+            conflicts_with = "range",
+            help = "Side by side, old on the left and new on the right, sized to the terminal"
+        )]
+        split: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["range", "split"],
+            help = "Outline the diff: each hunk's line, function and changes"
+        )]
+        summary: bool,
+        #[arg(
+            long,
+            value_name = "N",
+            value_delimiter = ',',
+            conflicts_with_all = ["range", "split"],
+            help = "Show hunk N of the outline in full (implies --summary)"
+        )]
+        hunk: Vec<usize>,
+        #[arg(
+            long,
+            conflicts_with_all = ["summary", "hunk"],
+            help = "The whole diff, however long [diff] summary_lines says is too long"
+        )]
+        full: bool,
     },
     #[command(visible_alias = "sd", about = "Show staged diff for a file")]
     StagedDiff {
@@ -79,27 +466,459 @@ enum Commands {
     },
     #[command(visible_alias = "a", about = "Stage a file")]
     Add {
-        #[arg(help = "File ID (defaults to first unstaged)")]
+        #[arg(help = "File ID, or - to read IDs/paths from stdin (defaults to first unstaged)")]
+        id: Option<String>,
+    },
+    #[command(visible_alias = "u", about = "Unstage a file")]
+    Unstage {
+        #[arg(help = "File ID, or - to read IDs/paths from stdin (defaults to first staged)")]
+        id: Option<String>,
+    },
+    #[command(about = "Set or clear a file's executable bit, in the index and the work tree")]
+    Chmod {
+        #[arg(help = "File ID or path, or - to read IDs/paths from stdin")]
+        id: String,
+        #[arg(
+            value_parser = parse_exec_bit,
+            action = clap::ArgAction::Set,
+            value_name = "+x|-x",
+            allow_hyphen_values = true,
+            help = "+x to make the file executable, -x to clear the bit"
+        )]
+        mode: bool,
+    },
+    #[command(about = "Discard working tree changes to a file")]
+    Discard {
+        #[arg(help = "File ID, or - to read IDs/paths from stdin (defaults to first unstaged)")]
         id: Option<String>,
     },
     #[command(visible_aliases = ["e", "v"], about = "Edit a file in $EDITOR")]
     Edit {
+        #[arg(help = "File ID, or . for every changed file (defaults to first unstaged)")]
+        id: Option<String>,
+        #[arg(
+            long,
+            conflicts_with = "id",
+            help = "Open every unstaged and untracked file"
+        )]
+        all: bool,
+        #[arg(
+            long,
+            conflicts_with = "id",
+            help = "Open every staged file (with --all or on its own)"
+        )]
+        staged: bool,
+    },
+    #[command(about = "Print a file's path for shell substitution")]
+    Path {
+        #[arg(help = "File ID (defaults to first unstaged)")]
+        id: Option<String>,
+        #[arg(long, help = "Print the path relative to the current directory")]
+        relative: bool,
+    },
+    #[command(about = "Change the shell's directory to a file's (needs f shell-init)")]
+    Cd {
+        #[arg(help = "File ID or path (defaults to first unstaged)")]
+        id: Option<String>,
+    },
+    #[command(about = "Run a command on a file: f exec <id> -- bat {}")]
+    Exec {
+        #[arg(help = "File ID or path (defaults to first unstaged)")]
+        id: Option<String>,
+        #[arg(
+            last = true,
+            required = true,
+            value_name = "COMMAND",
+            help = "Command to run, with {} replaced by the file's path (appended if absent)"
+        )]
+        command: Vec<String>,
+    },
+    #[command(about = "Run the formatter configured for a file in [[tools]]")]
+    Fmt {
+        #[arg(help = "File ID, or - to read IDs/paths from stdin (defaults to first unstaged)")]
+        id: Option<String>,
+    },
+    #[command(about = "Run the tests configured for a file in [[tools]]")]
+    Test {
+        #[arg(help = "File ID, or - to read IDs/paths from stdin (defaults to first unstaged)")]
+        id: Option<String>,
+    },
+    #[command(about = "Only show files under these paths in f, f i and f w, until cleared")]
+    Focus {
+        #[arg(help = "Directories, files or repo-relative globs (none: show the focus)")]
+        paths: Vec<String>,
+        #[arg(long, conflicts_with = "paths", help = "Show every file again")]
+        clear: bool,
+    },
+    #[command(about = "Stop ignoring a file, by its ID from f list --ignored or its path")]
+    Unignore {
+        #[arg(help = "Ignored file ID, or a path")]
+        target: String,
+    },
+    #[command(about = "Run every [[tools]] command for the changed files and report per file")]
+    Check,
+    #[command(about = "Install pre-commit and commit-msg hooks running f's checks on git commit")]
+    InitHooks {
+        #[arg(long, help = "Replace hooks that weren't installed by f")]
+        force: bool,
+    },
+    #[command(
+        hide = true,
+        about = "Run as the hook NAME, for the hooks f init-hooks installs"
+    )]
+    Hook {
+        name: String,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    #[command(about = "Bookmark a file by name, for f @<name> <action>")]
+    Bookmark {
+        #[arg(
+            required_unless_present = "delete",
+            help = "File ID, or the path of any file in the repository"
+        )]
+        target: Option<String>,
+        #[arg(required_unless_present = "delete", help = "Bookmark name")]
+        name: Option<String>,
+        #[arg(
+            short,
+            long,
+            value_name = "NAME",
+            conflicts_with_all = ["target", "name"],
+            help = "Delete a bookmark"
+        )]
+        delete: Option<String>,
+    },
+    #[command(about = "List bookmarked files")]
+    Bookmarks,
+    #[command(about = "List recently used files with their current IDs (f - is the last)")]
+    History,
+    #[command(about = "Copy a file's path (or diff) to the clipboard")]
+    Copy {
         #[arg(help = "File ID (defaults to first unstaged)")]
         id: Option<String>,
+        #[arg(long, help = "Copy the file's diff instead of its path")]
+        diff: bool,
+    },
+    #[command(about = "Print the forge web URL for a file")]
+    Url {
+        #[arg(help = "File ID (defaults to first unstaged)")]
+        id: Option<String>,
+        #[arg(long, help = "Open the URL in the browser")]
+        open: bool,
+        #[arg(long, help = "Link to the first changed line")]
+        line: bool,
+        #[arg(long, default_value = "origin", help = "Remote to link to")]
+        remote: String,
     },
     #[command(visible_alias = "c", about = "Commit staged changes")]
     Commit {
-        #[arg(help = "Commit message")]
+        #[arg(
+            short,
+            long,
+            help = "Open the editor (prefilled with the message, if given)"
+        )]
+        edit: bool,
+        #[arg(long, help = "Skip the [commit] message checks from the config")]
+        no_verify_msg: bool,
+        #[arg(short = 'n', long, help = "Skip the pre-commit and commit-msg hooks")]
+        no_verify: bool,
+        #[arg(
+            short = 'S',
+            long,
+            help = "Sign the commit (also enabled by sign = true under [commit])"
+        )]
+        sign: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["by_owner", "message_flag", "edit"],
+            help = "Make one commit per top-level directory of the staged files"
+        )]
+        by_dir: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["message_flag", "edit"],
+            help = "Make one commit per CODEOWNERS owner of the staged files"
+        )]
+        by_owner: bool,
+        #[arg(
+            short,
+            long = "message",
+            value_name = "MSG",
+            help = "Commit message; the arguments are then file IDs to commit on their own"
+        )]
+        message_flag: Option<String>,
+        #[arg(
+            help = "Commit message (opens the editor when omitted), file IDs with -m, or the default message with --by-dir/--by-owner ({group} is replaced)"
+        )]
         message: Vec<String>,
     },
     #[command(visible_alias = "p", about = "Push to remote")]
-    Push,
+    Push {
+        #[arg(short = 'n', long, help = "Skip the pre-push hook")]
+        no_verify: bool,
+        #[arg(long, help = "GPG-sign the push (git push --signed)")]
+        signed: bool,
+        #[arg(
+            short,
+            long,
+            help = "Overwrite the remote branch if it hasn't moved (--force-with-lease)"
+        )]
+        force: bool,
+        #[arg(long, help = "Show what would be pushed without pushing")]
+        dry_run: bool,
+        #[arg(
+            short = 'u',
+            long,
+            help = "Set the upstream without asking when the branch has none"
+        )]
+        set_upstream: bool,
+        #[arg(
+            short,
+            long,
+            help = "Push without asking, whatever [push] confirm says"
+        )]
+        yes: bool,
+    },
+    #[command(about = "Edit the last commit's message")]
+    Reword {
+        #[arg(long, help = "Skip the [commit] message checks from the config")]
+        no_verify_msg: bool,
+        #[arg(help = "New message (prompts, or opens the editor, when omitted)")]
+        message: Vec<String>,
+    },
+    #[command(about = "Show, continue, skip or abort an in-progress rebase")]
+    Rebase {
+        #[arg(
+            long = "continue",
+            group = "action",
+            help = "Continue after resolving conflicts"
+        )]
+        cont: bool,
+        #[arg(long, group = "action", help = "Skip the current commit")]
+        skip: bool,
+        #[arg(long, group = "action", help = "Abort and restore the original branch")]
+        abort: bool,
+        #[arg(
+            short,
+            long,
+            value_name = "REF",
+            group = "action",
+            help = "Rebase onto REF, editing the todo list in f's picker"
+        )]
+        interactive: Option<String>,
+    },
+    #[command(about = "Turn staged hunks into fixups for the commits they amend")]
+    Absorb {
+        #[arg(long, help = "Show which commits the hunks would go to")]
+        dry_run: bool,
+        #[arg(short, long, help = "Autosquash the fixups afterwards")]
+        rebase: bool,
+    },
+    #[command(
+        name = "cherry-pick",
+        about = "Pick a commit from another branch onto HEAD"
+    )]
+    CherryPick {
+        #[arg(help = "Branch to pick from")]
+        branch: String,
+        #[arg(help = "Commit ID (opens the picker when omitted)")]
+        id: Option<String>,
+        #[arg(
+            short = 'n',
+            long,
+            default_value_t = 20,
+            help = "Number of commits to list"
+        )]
+        limit: usize,
+    },
+    #[command(about = "Revert a recent commit, picked by ID")]
+    Revert {
+        #[arg(help = "Commit ID (opens the picker when omitted)")]
+        id: Option<String>,
+        #[arg(
+            short = 'n',
+            long,
+            default_value_t = 20,
+            help = "Number of commits to list"
+        )]
+        limit: usize,
+    },
+    #[command(about = "Show a recent commit, or diff two, picked by ID")]
+    Commits {
+        #[arg(
+            num_args = 0..=2,
+            help = "One commit ID to show, or two to diff (opens the picker when omitted)"
+        )]
+        ids: Vec<String>,
+        #[arg(
+            short = 'n',
+            long,
+            default_value_t = 20,
+            help = "Number of commits to list"
+        )]
+        limit: usize,
+    },
+    #[command(about = "Apply a diff from stdin or the clipboard, three-way where it doesn't fit")]
+    PasteApply {
+        #[arg(long, help = "Read the diff from the clipboard instead of stdin")]
+        clipboard: bool,
+        #[arg(long, help = "Only show which files the diff changes")]
+        dry_run: bool,
+    },
+    #[command(about = "Show a conflicted file's base, ours and theirs versions in the pager")]
+    Conflict {
+        #[arg(help = "File ID or path (defaults to the first conflicted file)")]
+        id: Option<String>,
+        #[arg(long, help = "Each conflict as three columns instead of diff3 markers")]
+        panes: bool,
+    },
+    #[command(about = "Pack the changed files into a .tar.gz, .tar or .zip")]
+    Archive {
+        #[arg(help = "Archive to write, e.g. wip.tar.gz")]
+        output: PathBuf,
+        #[arg(long, help = "Only the staged files, as staged")]
+        staged: bool,
+    },
+    #[command(about = "List remotes with their URLs and when they were fetched, or change them")]
+    Remote {
+        #[command(subcommand)]
+        action: Option<RemoteAction>,
+    },
+    #[command(about = "List tags (picker on a terminal), create or push them")]
+    Tag {
+        #[command(subcommand)]
+        action: Option<TagAction>,
+    },
+    #[command(about = "Delete all untracked files (recoverable from f trash)")]
+    Clean,
+    #[command(about = "List or restore files saved before discard and clean")]
+    Trash {
+        #[command(subcommand)]
+        action: Option<TrashAction>,
+    },
+    #[command(about = "Run a script of edit, add, unstage and commit lines, all or nothing")]
+    Batch {
+        #[arg(help = "Script to run (default: stdin)")]
+        file: Option<PathBuf>,
+    },
+    #[command(about = "Record a sequence of f actions and replay it later")]
+    Session {
+        #[command(subcommand)]
+        action: Option<SessionAction>,
+    },
+    #[command(about = "List or extend the directories of a sparse checkout")]
+    Sparse {
+        #[command(subcommand)]
+        action: Option<SparseAction>,
+    },
+    #[command(about = "Reverse the last add, unstage, discard or commit")]
+    Undo {
+        #[arg(long, help = "List what can be undone, most recent first")]
+        list: bool,
+    },
+    #[command(about = "Show commits a push would send")]
+    Outgoing,
+    #[command(about = "Fetch and show commits a pull would bring in")]
+    Incoming,
+    #[command(about = "Create and inspect pull requests with gh or glab")]
+    Pr {
+        #[command(subcommand)]
+        action: PrAction,
+    },
     #[command(visible_alias = "i", about = "Interactive file picker")]
     Interactive,
+    #[command(about = "Step through every changed hunk, taking notes with n")]
+    Review,
+    #[command(about = "Show the settings f resolved from f.toml, git's config and defaults")]
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+    },
+    #[command(about = "List or export the notes taken in f review")]
+    Notes {
+        #[command(subcommand)]
+        action: Option<NotesAction>,
+    },
+    #[command(about = "Print a compact status summary for shell prompts")]
+    Prompt {
+        #[arg(long, help = "Colorize the output even when not on a terminal")]
+        color: bool,
+    },
+    #[command(
+        about = "Print the branch, upstream, section counts and any operation in progress",
+        long_about = "Print the branch, upstream, ahead/behind counts, files per section and \
+                      any rebase, merge or other operation in progress as one JSON or TOML \
+                      document, for scripts and status bars. Fields that don't apply are null \
+                      in JSON and left out of TOML."
+    )]
+    State {
+        #[arg(long, value_enum, default_value = "json")]
+        format: state::Format,
+    },
+    #[command(about = "Serve JSON-RPC over stdio for editor integrations")]
+    Serve,
+    #[command(about = "Serve list, diff, stage and commit as MCP tools over stdio")]
+    Mcp {
+        #[arg(long, help = "Only offer the tools that don't change the repository")]
+        read_only: bool,
+    },
+    #[command(about = "Keep the file list in memory so other f commands return instantly")]
+    Daemon,
+    #[command(about = "Create a git repository here, or in DIR (like git init)")]
+    Init {
+        #[arg(help = "Directory to create the repository in")]
+        dir: Option<PathBuf>,
+    },
+    #[command(about = "Check git, config, editor and commit signing setup")]
+    Doctor,
+    #[command(about = "Run git maintenance: run (the default), start, stop, register...")]
+    Maintenance {
+        #[arg(
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            value_name = "ARGS",
+            help = "Arguments for git maintenance, e.g. run --task=gc"
+        )]
+        args: Vec<String>,
+    },
+    #[command(about = "Print a shell completion script")]
+    Completions {
+        #[arg(value_enum, help = "Target shell")]
+        shell: completions::Shell,
+    },
+    #[command(about = "Print a shell wrapper for f cd and $F_LAST, to eval from your rc file")]
+    ShellInit {
+        #[arg(value_enum, help = "Target shell")]
+        shell: completions::Shell,
+    },
+    #[command(about = "Write completion scripts or man pages to a directory, for packaging")]
+    Generate {
+        #[command(subcommand)]
+        target: GenerateTarget,
+    },
+    #[command(external_subcommand)]
+    External(Vec<String>),
     #[command(visible_alias = "w", about = "Watch file status")]
     Watch {
-        #[arg(short, long, default_value = "2", help = "Refresh interval in seconds")]
-        interval: u32,
+        #[arg(
+            short,
+            long,
+            default_value = "2",
+            help = "Refresh interval in seconds (fractions allowed)"
+        )]
+        interval: f64,
+        #[arg(long, help = "Refresh when files change instead of on an interval")]
+        events: bool,
+        #[arg(
+            long,
+            value_name = "DIR",
+            conflicts_with_all = ["events", "patterns", "staged", "unstaged"],
+            help = "Dashboard of every git repo directly under DIR"
+        )]
+        repos: Option<PathBuf>,
+        #[command(flatten)]
+        filter: FilterArgs,
     },
 }
 
@@ -107,462 +926,3527 @@ fn get_editor(config: &Config) -> String {
     config.editor()
 }
 
-enum ResolveResult {
-    Found(GitFile),
-    Ambiguous(usize),
-    NotFound,
-    Error(String),
+fn resolve_file(id: Option<String>, config: &Config) -> anyhow::Result<GitFile> {
+    resolve_file_or(id, config, |files| default_file(files, config))
 }
 
-fn resolve_file(id: Option<String>, config: &Config) -> ResolveResult {
-    let files = match get_all_files(&config.id_chars()) {
-        Ok(f) => f,
-        Err(e) => return ResolveResult::Error(e.to_string()),
+fn resolve_staged_file(id: Option<String>, config: &Config) -> anyhow::Result<GitFile> {
+    resolve_file_or(id, config, |files| Ok(get_first_staged_file(files)))
+}
+
+/// The file an action without an ID takes, by `default_target`.
+fn default_file(files: &[GitFile], config: &Config) -> anyhow::Result<Option<GitFile>> {
+    let actionable = git_status::actionable_files(files);
+    let recent;
+    let pick = match config.default_target {
+        DefaultTarget::FirstUnstaged => Pick::First,
+        DefaultTarget::LastUsed => {
+            recent = history::History::open()?.entries().to_vec();
+            Pick::LastUsed(&recent)
+        }
+        DefaultTarget::LargestDiff => Pick::LargestDiff,
+        // With one file or none there's nothing to ask
+        DefaultTarget::Prompt if actionable.len() < 2 => Pick::First,
+        DefaultTarget::Prompt => {
+            if !exec::interactive() {
+                return Err(Error::Usage(
+                    "default_target = \"prompt\" needs a terminal to ask, so give an ID".into(),
+                )
+                .into());
+            }
+            let changed: Vec<GitFile> = actionable.into_iter().cloned().collect();
+            return match interactive::pick_changed(&changed, config)? {
+                Some(file) => Ok(Some(file)),
+                None => process::exit(1),
+            };
+        }
     };
-    match id {
+    Ok(get_first_actionable_file(&actionable, pick))
+}
+
+fn resolve_file_or(
+    id: Option<String>,
+    config: &Config,
+    default: impl FnOnce(&[GitFile]) -> anyhow::Result<Option<GitFile>>,
+) -> anyhow::Result<GitFile> {
+    let files = daemon::get_files(&config.id_chars())?;
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let file = match id {
+        Some(id) if let Some(file) = find_file_by_path(&files, &id, &cwd) => {
+            log::debug!("{} is a path to {}", id, file.rel_path);
+            file
+        }
         Some(id) => match find_file_by_id(&files, &id) {
-            IdMatch::Unique(f) => ResolveResult::Found(f),
-            IdMatch::Ambiguous(n) => ResolveResult::Ambiguous(n),
-            IdMatch::NotFound => ResolveResult::NotFound,
-        },
-        None => match get_first_actionable_file(&files) {
-            Some(f) => ResolveResult::Found(f),
-            None => ResolveResult::NotFound,
+            IdMatch::Unique(f) => f,
+            IdMatch::Ambiguous(matches) => {
+                let ambiguous = Error::AmbiguousId {
+                    id: id.clone(),
+                    matches,
+                    what: "files",
+                };
+                // At a terminal, choosing costs a keystroke instead of a rerun
+                if !exec::interactive() {
+                    return Err(ambiguous.into());
+                }
+                match interactive::disambiguate(&id, &files, config)? {
+                    Some(file) => file,
+                    None => return Err(ambiguous.into()),
+                }
+            }
+            IdMatch::NotFound => return Err(no_file_matches(&files, &id).into()),
         },
-    }
+        None => default(&files)?.ok_or_else(|| Error::NotFound("No matching file found".into()))?,
+    };
+    shell::record_last(&file.abs_path);
+    history::record(&file.rel_path);
+    Ok(file)
 }
 
-fn exec_git(args: &[&str]) -> ! {
-    let err = Command::new("git").args(args).exec();
-    eprintln!("Failed to exec git: {}", err);
-    process::exit(1);
+/// The error for `id` matching no file, suggesting any file whose ID it
+/// nearly is.
+fn no_file_matches(files: &[GitFile], id: &str) -> Error {
+    let near: Vec<String> = git_status::near_ids(files, id)
+        .iter()
+        .map(|f| format!("{} ({})", f.stable_id.display, display::display_path(f)))
+        .collect();
+    let message = format!("No file matches ID or path: {}", id);
+    Error::NotFound(match near.as_slice() {
+        [] => message,
+        [one] => format!("{}, did you mean {}?", message, one),
+        [rest @ .., last] => format!("{}, did you mean {} or {}?", message, rest.join(", "), last),
+    })
 }
 
-fn exec_editor(path: &str, config: &Config) -> ! {
-    let editor = get_editor(config);
-    // Run through shell to support EDITOR with arguments (e.g., "vim -u NONE")
-    let err = Command::new("sh")
-        .arg("-c")
-        .arg(format!("{} \"$1\"", editor))
-        .arg("sh") // $0
-        .arg(path) // $1
-        .exec();
-    eprintln!("Failed to exec {}: {}", editor, err);
-    process::exit(1);
+/// Resolves the files named by an ID argument: `-` reads IDs or paths from
+/// stdin, anything else resolves a single file via `resolve`.
+fn require_files(
+    id: Option<String>,
+    config: &Config,
+    resolve: fn(Option<String>, &Config) -> anyhow::Result<GitFile>,
+) -> Vec<GitFile> {
+    match id.as_deref() {
+        Some("-") => read_stdin_files(config),
+        _ => vec![require_file(resolve(id, config))],
+    }
 }
 
-fn require_file(result: ResolveResult) -> GitFile {
-    match result {
-        ResolveResult::Found(f) => f,
-        ResolveResult::Ambiguous(n) => {
-            eprintln!("ID matches {} files - be more specific", n);
-            process::exit(1);
-        }
-        ResolveResult::NotFound => {
-            eprintln!("No matching file found");
-            process::exit(1);
-        }
-        ResolveResult::Error(e) => {
-            eprintln!("Error: {}", e);
-            process::exit(1);
+/// Reads one ID or path per line from stdin. Only the first tab-separated
+/// field is used, so lines from `f list --porcelain` can be piped as-is.
+fn read_stdin_files(config: &Config) -> Vec<GitFile> {
+    let mut entries = Vec::new();
+    for line in std::io::stdin().lock().lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("Error reading stdin: {}", e);
+                process::exit(1);
+            }
+        };
+        let entry = line.split('\t').next().unwrap_or("").trim_end_matches('\r');
+        if !entry.is_empty() {
+            entries.push(quoting::maybe_unquote(entry));
         }
     }
-}
 
-fn cmd_list(config: &Config) {
-    match get_all_files(&config.id_chars()) {
-        Ok(files) => display::list_files(&files),
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            process::exit(1);
-        }
+    if entries.is_empty() {
+        eprintln!("No files given on stdin");
+        process::exit(1);
     }
+    select_files(&entries, config)
 }
 
-fn cmd_diff(id: Option<String>, config: &Config) -> ! {
-    let file = require_file(resolve_file(id, config));
-    if file.file_type == FileType::Untracked {
-        exec_git(&[
-            "diff",
-            "--no-index",
-            "/dev/null",
-            &file.abs_path.to_string_lossy(),
-        ])
-    } else {
-        exec_git(&["diff", "--", &file.abs_path.to_string_lossy()])
+/// Resolves each entry as a path or an ID, dropping duplicates.
+fn select_files(entries: &[String], config: &Config) -> Vec<GitFile> {
+    let files = match daemon::get_files(&config.id_chars()) {
+        Ok(f) => f,
+        Err(e) => fail(e),
+    };
+    let cwd = std::env::current_dir().unwrap_or_default();
+
+    let mut selected: Vec<GitFile> = Vec::new();
+    for entry in entries {
+        let file = match find_file_by_path(&files, entry, &cwd) {
+            Some(f) => f,
+            None => match find_file_by_id(&files, entry) {
+                IdMatch::Unique(f) => f,
+                IdMatch::Ambiguous(matches) => fail(Error::AmbiguousId {
+                    id: entry.clone(),
+                    matches,
+                    what: "files",
+                }),
+                IdMatch::NotFound => fail(no_file_matches(&files, entry)),
+            },
+        };
+        if !selected.iter().any(|f| f.rel_path == file.rel_path) {
+            selected.push(file);
+        }
     }
+    selected
 }
 
-fn cmd_staged_diff(id: Option<String>, config: &Config) -> ! {
-    let file = require_file(resolve_file(id, config));
-    exec_git(&["diff", "--staged", "--", &file.abs_path.to_string_lossy()])
+fn abs_paths(files: &[&GitFile]) -> Vec<String> {
+    files
+        .iter()
+        .map(|f| f.abs_path.to_string_lossy().into_owned())
+        .collect()
 }
 
-fn cmd_add(id: Option<String>, config: &Config) -> ! {
-    let file = require_file(resolve_file(id, config));
-    println!("Adding: {}", file.rel_path);
-    exec_git(&["add", &file.abs_path.to_string_lossy()])
+fn exec_git_with_paths(args: &[&str], files: &[&GitFile]) -> ! {
+    let paths = abs_paths(files);
+    let mut full_args: Vec<&str> = args.to_vec();
+    full_args.push("--");
+    full_args.extend(paths.iter().map(String::as_str));
+    exec_git(&full_args)
 }
 
-fn cmd_edit(id: Option<String>, config: &Config) -> ! {
-    let file = require_file(resolve_file(id, config));
-    exec_editor(&file.abs_path.to_string_lossy(), config)
-}
+fn diff_files(files: &[GitFile], config: &Config) -> ! {
+    // git only says an image differs, so f describes or previews it
+    let (pictures, files): (Vec<&GitFile>, Vec<&GitFile>) =
+        files.iter().partition(|f| diffs_as_image(f));
+    for file in &pictures {
+        image_diff(file, config);
+    }
+    if !pictures.is_empty() && files.is_empty() {
+        process::exit(0);
+    }
 
-fn cmd_commit(message: Vec<String>) -> ! {
-    if message.is_empty() {
-        eprintln!("Commit message required");
-        process::exit(1);
+    let (untracked, tracked): (Vec<&GitFile>, Vec<&GitFile>) = files
+        .into_iter()
+        .partition(|f| f.file_type == FileType::Untracked);
+
+    let (smudged, tracked): (Vec<&GitFile>, Vec<&GitFile>) =
+        tracked.into_iter().partition(|f| lfs::diff_smudged(f));
+
+    // git can't diff a new symlink against /dev/null, so those are printed
+    // here
+    let (links, untracked): (Vec<&GitFile>, Vec<&GitFile>) = untracked
+        .into_iter()
+        .partition(|f| f.symlink_target().is_some());
+    for file in &links {
+        print!("{}", git_status::file_diff(file, false).unwrap_or_default());
+    }
+    if !links.is_empty() && untracked.is_empty() && smudged.is_empty() && tracked.is_empty() {
+        process::exit(0);
     }
-    let msg = message.join(" ");
-    exec_git(&["commit", "-m", &msg])
-}
 
-fn cmd_push() -> ! {
-    exec_git(&["push"])
+    // `git diff --no-index` compares a single pair, so untracked files are
+    // shown one at a time, then LFS files by content, before handing over
+    // to the tracked diff
+    let mut commands: Vec<Command> = untracked
+        .iter()
+        .map(|file| {
+            let mut cmd = Command::new("git");
+            cmd.args(["diff", "--no-index", "/dev/null"])
+                .arg(&file.abs_path);
+            cmd
+        })
+        .collect();
+    if !smudged.is_empty() {
+        let mut cmd = Command::new("git");
+        cmd.args(["-c", lfs::TEXTCONV, "diff", "--"])
+            .args(abs_paths(&smudged));
+        commands.push(cmd);
+    }
+    if !tracked.is_empty() || commands.is_empty() {
+        let mut cmd = Command::new("git");
+        cmd.args(["diff", "--"]).args(abs_paths(&tracked));
+        commands.push(cmd);
+    }
+    let mut last = commands.pop().expect("at least one diff to run");
+    for mut cmd in commands {
+        let _ = exec::status(&mut cmd);
+    }
+    exec::exec(&mut last)
 }
 
-fn cmd_watch(interval: u32) -> ! {
-    let exe = std::env::current_exe().unwrap_or_else(|_| "f".into());
-    let interval_arg = format!("-n{}", interval);
-    let err = Command::new("watch")
-        .args([&interval_arg, "-c", &exe.to_string_lossy()])
-        .env("CLICOLOR_FORCE", "1")
-        .exec();
-    eprintln!("Failed to exec watch: {}", err);
-    process::exit(1);
+/// Whether `file` is an image git would only say differs.
+fn diffs_as_image(file: &GitFile) -> bool {
+    images::is_image(&file.rel_path) && file.lfs_size.is_none() && file.symlink_target().is_none()
 }
 
-fn cmd_interactive(config: &Config) {
-    match interactive::run(config) {
-        Ok(()) => {}
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            process::exit(1);
+/// Shows how an image changed: with the configured `diff_tool`, or else
+/// git's `diff.tool`, when both versions exist, otherwise as a summary
+/// line and, in a terminal that can draw them, the two versions.
+fn image_diff(file: &GitFile, config: &Config) {
+    let (old, new) = images::versions(file);
+    if let (Some(tool), Some(old), Some(new)) = (&config.images.diff_tool, &old, &new) {
+        let status = images::tool_command(tool, file, old, new)
+            .and_then(|mut tool| Ok(exec::status(&mut tool.command)?));
+        match status {
+            Ok(status) if status.success() => return,
+            Ok(status) => exec::exit(status),
+            Err(e) => fail(e.context("Failed to run the image diff tool")),
+        }
+    }
+    if config.images.diff_tool.is_none()
+        && old.is_some()
+        && new.is_some()
+        && settings::diff_tool().is_some()
+    {
+        let path = file.abs_path.to_string_lossy();
+        let mut args = vec!["difftool", "--no-prompt"];
+        if file.file_type == FileType::Staged {
+            args.push("--cached");
+        }
+        args.extend(["--", &path]);
+        exec_git(&args)
+    }
+    println!(
+        "{} {}",
+        display::display_path(file).bold(),
+        images::summary(old.as_deref(), new.as_deref())
+    );
+    let protocol = images::Protocol::detect().filter(|_| {
+        use std::io::IsTerminal;
+        std::io::stdout().is_terminal()
+    });
+    for (label, bytes) in [("old", &old), ("new", &new)] {
+        if let (Some(protocol), Some(bytes)) = (protocol, bytes)
+            && let Some(preview) = images::preview(bytes, protocol, 10)
+        {
+            println!("{}", label.dimmed());
+            println!("{}", preview);
         }
     }
 }
 
-fn is_file_id(s: &str, config: &Config) -> bool {
-    let id_chars = config.id_chars();
-    !s.is_empty() && s.chars().all(|c| id_chars.contains(&c))
+/// Journals an action for `f undo`. Failing to record only warns, it
+/// doesn't stop the command itself.
+fn record_undo(record: impl FnOnce(&undo::Journal) -> anyhow::Result<()>) {
+    if exec::print_only() {
+        return;
+    }
+    if let Err(e) = undo::Journal::open().and_then(|journal| record(&journal)) {
+        eprintln!("Warning: f undo won't cover this: {:#}", e);
+    }
 }
 
-fn handle_id_first(id: &str, action: Option<&str>, config: &Config) {
-    let files = match get_all_files(&config.id_chars()) {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            process::exit(1);
-        }
+/// Adds an action to the session being recorded, if there is one.
+fn record_session(command: &str, args: &[&str]) {
+    if exec::print_only() {
+        return;
+    }
+    let Ok(sessions) = session::Sessions::open() else {
+        return;
     };
+    if let Err(e) = sessions.record(command, args) {
+        eprintln!("Warning: not recorded in the session: {:#}", e);
+    }
+}
 
-    let file = match find_file_by_id(&files, id) {
-        IdMatch::Unique(f) => f,
-        IdMatch::Ambiguous(n) => {
-            eprintln!("ID '{}' matches {} files - be more specific", id, n);
-            process::exit(1);
-        }
-        IdMatch::NotFound => {
-            eprintln!("No file matches ID: {}", id);
-            process::exit(1);
-        }
-    };
+fn rel_paths(files: &[GitFile]) -> Vec<&str> {
+    files.iter().map(|f| f.rel_path.as_str()).collect()
+}
 
-    match action {
-        Some("a" | "add") => {
-            println!("Adding: {}", file.rel_path);
-            exec_git(&["add", &file.abs_path.to_string_lossy()]);
-        }
-        Some("d" | "diff") => {
-            if file.file_type == FileType::Untracked {
-                exec_git(&[
-                    "diff",
-                    "--no-index",
-                    "/dev/null",
-                    &file.abs_path.to_string_lossy(),
-                ]);
-            } else {
-                exec_git(&["diff", "--", &file.abs_path.to_string_lossy()]);
-            }
-        }
-        Some("sd" | "staged-diff") => {
-            exec_git(&["diff", "--staged", "--", &file.abs_path.to_string_lossy()]);
-        }
-        Some("e" | "v" | "edit") => {
-            exec_editor(&file.abs_path.to_string_lossy(), config);
-        }
-        Some(other) => {
-            eprintln!("Unknown action: {}", other);
-            process::exit(1);
-        }
-        None => {
-            eprintln!("Action required (a, d, sd, e)");
-            process::exit(1);
-        }
+fn stage_files(files: &[GitFile]) -> ! {
+    record_undo(|j| j.record_index("add", &rel_paths(files)));
+    record_session("add", &rel_paths(files));
+    for file in files {
+        println!("Adding: {}", display::display_path(file));
     }
+    exec_git_with_paths(&["add"], &files.iter().collect::<Vec<_>>())
 }
 
-mod interactive {
-    use crate::config::Config;
-    use crate::git_status::{FileType, GitFile, get_all_files, get_git_root};
-    use anyhow::{Context, Result};
-    use colored::Colorize;
-    use crossterm::event::{self, Event, KeyCode, KeyModifiers};
-    use crossterm::terminal::{self, ClearType};
-    use crossterm::{cursor, execute};
-    use std::io::{Write, stdout};
-    use std::os::unix::process::CommandExt;
-    use std::process::Command;
-
-    macro_rules! raw_println {
-        () => {
-            print!("\r\n");
-            let _ = std::io::stdout().flush();
-        };
-        ($($arg:tt)*) => {{
-            print!($($arg)*);
-            print!("\r\n");
-            let _ = std::io::stdout().flush();
-        }};
+fn parse_exec_bit(value: &str) -> Result<bool, String> {
+    match value {
+        "+x" => Ok(true),
+        "-x" => Ok(false),
+        _ => Err("expected +x or -x".to_string()),
     }
+}
 
-    fn generate_keys(n: usize, id_chars: &[char]) -> Vec<String> {
-        if n == 0 {
-            return vec![];
+fn chmod_files(files: &[GitFile], executable: bool) -> ! {
+    let mut paths: Vec<(&str, bool)> = Vec::new();
+    for file in files {
+        if !paths.iter().any(|(p, _)| *p == file.rel_path) {
+            paths.push((&file.rel_path, file.file_type != FileType::Untracked));
         }
-        let mut length = 1;
-        while id_chars.len().pow(length as u32) < n {
-            length += 1;
-        }
-
-        (0..n)
-            .map(|i| {
-                let mut key = String::new();
-                let mut idx = i;
-                for _ in 0..length {
-                    key.insert(0, id_chars[idx % id_chars.len()]);
-                    idx /= id_chars.len();
-                }
-                key
-            })
-            .collect()
     }
+    chmod_paths(&paths, executable)
+}
 
-    fn clear_screen() {
-        let mut stdout = stdout();
-        let _ = execute!(
-            stdout,
-            terminal::Clear(ClearType::All),
-            cursor::MoveTo(0, 0)
-        );
+/// Sets or clears the executable bit of each repo-relative path in the
+/// work tree, then for tracked ones in the index with `git update-index
+/// --chmod`, so the file isn't left showing the old mode as an unstaged
+/// change.
+fn chmod_paths(paths: &[(&str, bool)], executable: bool) -> ! {
+    let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+    let flag = if executable { "+x" } else { "-x" };
+    let tracked: Vec<&str> = paths
+        .iter()
+        .filter(|(_, tracked)| *tracked)
+        .map(|(p, _)| *p)
+        .collect();
+    // Before the work tree changes, so its executable bits can be restored
+    if !tracked.is_empty() {
+        record_undo(|j| j.record_chmod(&tracked));
     }
-
-    fn display_files(files: &[GitFile], keys: &[String], prefix: &str) {
-        let matching: Vec<_> = keys
-            .iter()
-            .zip(files.iter())
-            .filter(|(k, _)| k.starts_with(prefix))
-            .collect();
-
-        raw_println!("{}", "── Select file ──".yellow());
-        if !prefix.is_empty() {
-            raw_println!("  Prefix: {}", prefix.cyan());
+    for (path, _) in paths {
+        let abs_path = root.join(path);
+        if executable {
+            println!("Making executable: {}", path);
+        } else {
+            println!("Making non-executable: {}", path);
         }
-
-        let mut last_type: Option<FileType> = None;
-        for (key, file) in &matching {
-            if last_type != Some(file.file_type) {
-                if last_type.is_some() {
-                    raw_println!();
-                }
-                let header = match file.file_type {
-                    FileType::Unstaged => "Unstaged".yellow(),
-                    FileType::Untracked => "Untracked".green(),
-                    FileType::Staged => "Staged".cyan(),
-                };
-                raw_println!("── {} ──", header);
-                last_type = Some(file.file_type);
-            }
-
-            let typed = &key[..prefix.len()];
-            let remaining = &key[prefix.len()..];
-            raw_println!(
-                "  {}{}  {}",
-                typed.cyan().bold(),
-                remaining.cyan(),
-                file.rel_path
+        if exec::print_only() {
+            println!(
+                "chmod {} {}",
+                flag,
+                exec::quote(&abs_path.to_string_lossy())
             );
+        } else if let Err(e) = set_exec_bit(&abs_path, executable) {
+            fail(anyhow::anyhow!("Cannot chmod {}: {}", path, e));
         }
-        raw_println!();
-        raw_println!("  {}   quit", "q".dimmed());
     }
+    if tracked.is_empty() {
+        process::exit(0);
+    }
+    let mode = format!("--chmod={}", flag);
+    let abs: Vec<String> = tracked
+        .iter()
+        .map(|p| root.join(p).to_string_lossy().into_owned())
+        .collect();
+    let mut args = vec!["update-index", &mode, "--"];
+    args.extend(abs.iter().map(String::as_str));
+    exec_git(&args)
+}
+
+/// Adds execute permission wherever there's read permission, like
+/// `chmod +x` under the usual umask, or removes it all. Symlinks and files
+/// deleted from the work tree are left alone.
+#[cfg(unix)]
+fn set_exec_bit(path: &Path, executable: bool) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if !meta.is_file() {
+        return Ok(());
+    }
+    let mode = meta.permissions().mode();
+    let mode = if executable {
+        mode | (mode & 0o444) >> 2
+    } else {
+        mode & !0o111
+    };
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_exec_bit(_path: &Path, _executable: bool) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Whether `path` is executable by anyone, `None` for a symlink or a file
+/// that isn't there, which `set_exec_bit` leaves alone.
+#[cfg(unix)]
+fn is_executable(path: &Path) -> Option<bool> {
+    use std::os::unix::fs::PermissionsExt;
+    let meta = std::fs::symlink_metadata(path).ok()?;
+    meta.is_file()
+        .then(|| meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> Option<bool> {
+    None
+}
+
+fn unstage_files(files: &[GitFile]) -> ! {
+    record_undo(|j| j.record_index("unstage", &rel_paths(files)));
+    record_session("unstage", &rel_paths(files));
+    for file in files {
+        println!("Unstaging: {}", display::display_path(file));
+    }
+    exec_git_with_paths(&["restore", "--staged"], &files.iter().collect::<Vec<_>>())
+}
+
+/// Restores tracked files from the index and deletes untracked ones, after
+/// copying them to the trash. If that copy fails nothing is touched.
+fn discard_files(files: &[GitFile], command: &str) -> ! {
+    if let Some(file) = only_staged(files).first() {
+        fail(Error::Usage(format!(
+            "{} has only staged changes, which discard leaves alone; unstage it first (f u {})",
+            display::display_path(file),
+            file.stable_id
+        )));
+    }
+    let backup = if exec::print_only() {
+        Ok(None)
+    } else {
+        trash::Trash::open().and_then(|t| t.save(command, &rel_paths(files)))
+    };
+    match backup {
+        Ok(Some(name)) => record_undo(|j| j.record_discard(command, &name)),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("Error: not discarding, the backup failed: {:#}", e);
+            process::exit(1);
+        }
+    }
+    let (untracked, tracked): (Vec<&GitFile>, Vec<&GitFile>) = files
+        .iter()
+        .partition(|f| f.file_type == FileType::Untracked);
+
+    for file in &untracked {
+        if exec::print_only() {
+            println!("rm -- {}", exec::quote(&file.abs_path.to_string_lossy()));
+            continue;
+        }
+        println!("Removing: {}", display::display_path(file));
+        if let Err(e) = std::fs::remove_file(&file.abs_path) {
+            eprintln!("Failed to remove {}: {}", display::display_path(file), e);
+            process::exit(1);
+        }
+    }
+    if tracked.is_empty() {
+        process::exit(0);
+    }
+    for file in &tracked {
+        println!("Discarding: {}", display::display_path(file));
+    }
+    exec_git_with_paths(&["restore"], &tracked)
+}
+
+/// The staged files among `files` with nothing to restore in the work
+/// tree, since `git restore` only touches the work tree.
+fn only_staged(files: &[GitFile]) -> Vec<&GitFile> {
+    let staged: Vec<&GitFile> = files
+        .iter()
+        .filter(|f| f.file_type == FileType::Staged)
+        .collect();
+    if staged.is_empty() {
+        return staged;
+    }
+    let paths = abs_paths(&staged);
+    let mut args = vec!["diff", "--name-only", "-z", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    let Some(changed) = git_status::git_output(&args) else {
+        return Vec::new();
+    };
+    staged
+        .into_iter()
+        .filter(|f| !changed.split('\0').any(|path| path == f.rel_path))
+        .collect()
+}
+
+fn exec_git(args: &[&str]) -> ! {
+    exec::exec(Command::new("git").args(args))
+}
+
+/// Runs a commit or push. Git is exec'd directly unless there is something
+/// to report: installed hooks are announced before they run (they can take
+/// a while and print nothing until done), and the outcome can be sent as a
+/// desktop notification.
+fn run_git_reporting(
+    args: &[&str],
+    hooks: &[&str],
+    config: &Config,
+    success: Option<&str>,
+    failure: &str,
+) -> ! {
+    if exec::print_only() || (hooks.is_empty() && !config.notifications) {
+        exec_git(args)
+    }
+    if !hooks.is_empty() {
+        let noun = if hooks.len() == 1 { "hook" } else { "hooks" };
+        eprintln!(
+            "{}",
+            format!("Running {} {}…", hooks.join(", "), noun).dimmed()
+        );
+    }
+    let status = match exec::status(Command::new("git").args(args)) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Failed to run git: {}", e);
+            process::exit(1);
+        }
+    };
+    if status.success() {
+        if let Some(message) = success.filter(|_| config.notifications) {
+            notify::send("f", message);
+        }
+    } else {
+        if !hooks.is_empty() {
+            eprintln!("{} (-n skips hooks)", failure.red());
+        }
+        if config.notifications {
+            notify::send("f", failure);
+        }
+    }
+    exec::exit(status)
+}
+
+/// Opens `files` in one editor invocation.
+/// Symlinks are refused rather than opened: the editor would follow the
+/// link and change whatever it points at.
+fn edit_files(files: &[GitFile], config: &Config) -> ! {
+    if let Some((file, target)) = files
+        .iter()
+        .find_map(|f| f.symlink_target().map(|target| (f, target)))
+    {
+        fail(Error::Usage(format!(
+            "{} is a symlink to {}, edit the target or change the link with ln -sfn",
+            file.rel_path,
+            target.display()
+        )))
+    }
+    record_session("edit", &rel_paths(files));
+    let paths = abs_paths(&files.iter().collect::<Vec<_>>());
+    let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+    let line = files.first().and_then(git_status::first_changed_line);
+    exec::exec(&mut config.editor_command(&paths, line))
+}
+
+fn require_conflict(file: &GitFile) {
+    if !git_status::conflicted_paths().contains(&file.rel_path) {
+        fail(Error::Usage(format!(
+            "{} has no conflict to resolve",
+            file.rel_path
+        )));
+    }
+}
+
+/// Resolves a conflicted file with `side`'s version (`ours` or `theirs`)
+/// and stages it. The file as it was, markers and all, goes to the trash
+/// first, in case it was partly resolved by hand.
+fn take_side(file: &GitFile, side: &str) -> ! {
+    require_conflict(file);
+    let paths = [file.rel_path.as_str()];
+    if !exec::print_only() {
+        match trash::Trash::open().and_then(|t| t.save(side, &paths)) {
+            Ok(name) => {
+                record_undo(|j| j.record_resolve(side, &file.rel_path, name.as_deref()));
+            }
+            Err(e) => fail(e.context("Not resolving, the backup failed")),
+        }
+    }
+    println!("Taking {}: {}", side, file.rel_path);
+    let path = file.abs_path.to_string_lossy();
+    let flag = format!("--{}", side);
+    let status = exec::status(Command::new("git").args(["checkout", &flag, "--", &path]));
+    match status {
+        Ok(status) if status.success() => exec_git(&["add", "--", &path]),
+        Ok(status) => exec::exit(status),
+        Err(e) => fail(anyhow::anyhow!("Failed to run git: {}", e)),
+    }
+}
+
+/// Opens a conflicted file at its first conflict marker, to keep parts of
+/// both sides by hand.
+fn edit_conflict(file: &GitFile, config: &Config) -> ! {
+    require_conflict(file);
+    let text = std::fs::read_to_string(&file.abs_path).unwrap_or_default();
+    let line = conflicts::first_marker(&text).unwrap_or(1);
+    record_session("edit", &[&file.rel_path]);
+    exec::exec(&mut config.editor_command_at(&file.abs_path.to_string_lossy(), line))
+}
+
+/// Resolves a conflicted file in the tool git's `merge.tool` names.
+fn merge_conflict(file: &GitFile) -> ! {
+    require_conflict(file);
+    if settings::merge_tool().is_none() {
+        fail(Error::Config(
+            "No merge.tool in git's config for f <id> mergetool (f <id> both edits by hand)".into(),
+        ));
+    }
+    record_session("edit", &[&file.rel_path]);
+    exec_git(&[
+        "mergetool",
+        "--no-prompt",
+        "--",
+        &file.abs_path.to_string_lossy(),
+    ])
+}
+
+fn require_file(result: anyhow::Result<GitFile>) -> GitFile {
+    result.unwrap_or_else(|e| fail(e))
+}
+
+/// Prints `err` and exits with its code from `error::Error`, or 1.
+fn fail(err: impl Into<anyhow::Error>) -> ! {
+    let err = err.into();
+    eprintln!("Error: {:#}", err);
+    if matches!(err.downcast_ref(), Some(Error::NotARepo)) {
+        eprintln!(
+            "{}",
+            "Run f init to create one here, or f --any-dir to list this directory's files".dimmed()
+        );
+    }
+    process::exit(error::exit_code(&err))
+}
+
+fn cmd_list(
+    porcelain: bool,
+    nul: bool,
+    format: display::ListFormat,
+    ignored: bool,
+    limits: &display::Limits,
+    filter: &filter::FileFilter,
+    config: &Config,
+) {
+    let ignored = if ignored {
+        ignore::list(&config.id_chars()).unwrap_or_else(|e| fail(e))
+    } else {
+        Vec::new()
+    };
+    let all = daemon::get_files(&config.id_chars()).unwrap_or_else(|e| fail(e));
+    let hidden = filter.hidden_by_focus(&all);
+    let files = filter.apply(all);
+    if porcelain || nul {
+        display::list_porcelain(&files, nul);
+        for entry in &ignored {
+            if nul {
+                print!("{}\0ignored\0{}\0", entry.id, entry.path);
+            } else {
+                println!(
+                    "{}\tignored\t{}",
+                    entry.id,
+                    quoting::quote_path(&entry.path)
+                );
+            }
+        }
+        return;
+    }
+    if format != display::ListFormat::Human {
+        return display::list_locations(&files, format);
+    }
+    let mut text = String::new();
+    if anydir::enabled() && git_status::get_git_root().is_err() {
+        text.push_str(&format!("{}\n\n", messages::t(Msg::NotARepo).dimmed()));
+    }
+    if let Some(banner) = repo_state_banner() {
+        text.push_str(&format!("{}\n\n", banner));
+    }
+    if config.maintenance.hint
+        && let Some(hint) = maintenance::hint()
+    {
+        text.push_str(&format!("{}\n\n", theme::get().warning.paint(&hint)));
+    }
+    let stale_days = (config.branch.stale_days > 0).then_some(config.branch.stale_days);
+    if let Some(warning) = repo_state::branch_warning(stale_days) {
+        let line = format!("{} · {}", warning.describe(), warning.hint());
+        text.push_str(&format!("{}\n\n", theme::get().warning.paint(&line)));
+    }
+    text.push_str(&display::render_listing(&files, limits));
+    text.push_str(&display::render_ignored(&ignored));
+    if hidden > 0 {
+        let note = format!(
+            "{} more outside the focus on {} (f l --no-focus shows them)",
+            hidden,
+            filter.focus().join(" ")
+        );
+        text.push_str(&format!("\n{}\n", note.dimmed()));
+    }
+    if config.list.pager {
+        pager::page_if_tall(&text).unwrap_or_else(|e| fail(e));
+    } else {
+        print!("{}", text);
+    }
+}
+
+fn cmd_focus(paths: &[String], clear: bool) {
+    let mut focus = focus::Focus::open().unwrap_or_else(|e| fail(e));
+    if clear {
+        focus.clear().unwrap_or_else(|e| fail(e));
+        println!("Showing every file");
+        return;
+    }
+    if paths.is_empty() {
+        match focus.patterns() {
+            [] => println!("{}", "No focus, f focus <path>... sets one".dimmed()),
+            patterns => println!("Focused on {}", patterns.join(" ").bold()),
+        }
+        return;
+    }
+    let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let patterns: Vec<String> = paths
+        .iter()
+        .map(|p| focus::pattern(p, &cwd, &root).unwrap_or_else(|e| fail(e)))
+        .collect();
+    // Checked now rather than on every listing after
+    filter::FileFilter::default()
+        .with_focus(&patterns)
+        .unwrap_or_else(|e| fail(Error::Usage(format!("{:#}", e))));
+    focus.set(patterns).unwrap_or_else(|e| fail(e));
+    println!("Focused on {}", focus.patterns().join(" ").bold());
+}
+
+fn cmd_unignore(target: &str, config: &Config) {
+    let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+    let git_dir = git_status::get_git_dir().unwrap_or_else(|e| fail(e));
+    // An ID from f list --ignored, or else a path
+    let ignored = ignore::list(&config.id_chars()).unwrap_or_else(|e| fail(e));
+    let path = match ignore::find_by_id(&ignored, target) {
+        Ok(entry) if is_file_id(target, config) => entry.path.clone(),
+        result => {
+            let cwd = std::env::current_dir().unwrap_or_default();
+            match git_status::repo_path(target, &cwd, &root) {
+                Some(path) if root.join(&path).exists() => path,
+                _ => fail(result.err().unwrap_or_else(|| {
+                    Error::NotFound(format!("{} isn't in the repository", target)).into()
+                })),
+            }
+        }
+    };
+    let (source, edit) = ignore::unignore_path(&path, &root, &git_dir).unwrap_or_else(|e| fail(e));
+    let source = source
+        .strip_prefix(&root)
+        .map(Path::to_path_buf)
+        .unwrap_or(source);
+    match edit {
+        ignore::Edit::Removed(pattern) => {
+            println!("Removed {} from {}", pattern.bold(), source.display())
+        }
+        ignore::Edit::Negated(negation) => {
+            println!("Added {} to {}", negation.bold(), source.display())
+        }
+    }
+}
+
+/// A banner for a paused rebase, merge etc., with the commands to resume.
+fn current_repo_state() -> Option<repo_state::RepoState> {
+    git_status::get_git_dir()
+        .ok()
+        .and_then(|dir| repo_state::detect(&dir))
+}
+
+/// The line above the listing naming the operation in progress, if any.
+fn repo_state_banner() -> Option<String> {
+    let state = current_repo_state()?;
+    let mut banner = state.describe();
+    if let Some(hint) = state.hint() {
+        banner = format!("{} · {}", banner, hint);
+    }
+    Some(theme::get().warning.paint(&banner).bold().to_string())
+}
+
+/// Lists unresolved conflicts with their IDs. Returns false if there are none.
+fn print_conflicts(config: &Config) -> bool {
+    let paths = git_status::conflicted_paths();
+    if paths.is_empty() {
+        return false;
+    }
+    let files = daemon::get_files(&config.id_chars()).unwrap_or_default();
+    println!("{}", "Conflicts:".red().bold());
+    for path in paths {
+        let id = files
+            .iter()
+            .find(|f| f.rel_path == path && f.file_type == FileType::Unstaged)
+            .map(|f| f.stable_id.display.clone())
+            .unwrap_or_default();
+        println!("  {} {}", format!("{:<5}", id).cyan(), path);
+    }
+    true
+}
+
+fn cmd_diff(id: Option<String>, range: Option<&str>, full: bool, config: &Config) -> ! {
+    let Some(range) = range else {
+        let files = require_files(id, config, resolve_file);
+        diff_files(
+            &if full {
+                files
+            } else {
+                summarize_long(&files, config)
+            },
+            config,
+        )
+    };
+    let mut args = vec!["diff".to_string()];
+    args.extend(commits::diff_revisions(range).unwrap_or_else(|e| fail(e)));
+    args.push("--".to_string());
+    // Any file has a history, changed or not, and one deleted since still
+    // has a path in the range
+    let paths: Vec<String> = match id.as_deref() {
+        Some("-") => abs_paths(&read_stdin_files(config).iter().collect::<Vec<_>>()),
+        Some(target) => match resolve_file(Some(target.to_string()), config) {
+            Ok(file) => vec![file.abs_path.to_string_lossy().into_owned()],
+            Err(e) => {
+                let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+                let cwd = std::env::current_dir().unwrap_or_default();
+                match git_status::repo_path(target, &cwd, &root) {
+                    Some(rel) if !rel.is_empty() => {
+                        vec![root.join(rel).to_string_lossy().into_owned()]
+                    }
+                    _ => fail(e),
+                }
+            }
+        },
+        None => fail(Error::Usage("--range needs a file ID or path".into())),
+    };
+    args.extend(paths);
+    exec_git(&args.iter().map(String::as_str).collect::<Vec<_>>())
+}
+
+/// `f diff --split`: each file's diff side by side in the pager, the
+/// staged one for a staged ID.
+fn cmd_split_diff(id: Option<String>, config: &Config) {
+    let width = width::terminal_width().unwrap_or(120);
+    let mut text = String::new();
+    for file in require_files(id, config, resolve_file) {
+        let diff = git_status::file_diff(&file, file.file_type == FileType::Staged)
+            .unwrap_or_else(|e| fail(e));
+        let hunks = interactive::parse_hunks(&file.rel_path, &diff);
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&split::render(&display::display_path(&file), &hunks, width));
+    }
+    pager::page(&text).unwrap_or_else(|e| fail(e));
+}
+
+/// `f diff --summary`: each file's outline in the pager, with the hunks
+/// numbered in `expand` shown in full.
+fn cmd_summary_diff(id: Option<String>, expand: &[usize], config: &Config) {
+    let mut text = String::new();
+    let mut most = 0;
+    for file in require_files(id, config, resolve_file) {
+        let diff = git_status::file_diff(&file, file.file_type == FileType::Staged)
+            .unwrap_or_else(|e| fail(e));
+        let hunks = interactive::parse_hunks(&file.rel_path, &diff);
+        most = most.max(hunks.len());
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&summary::render(
+            &display::display_path(&file),
+            &hunks,
+            expand,
+        ));
+    }
+    if let Some(n) = expand.iter().find(|&&n| n == 0 || n > most) {
+        fail(Error::Usage(format!(
+            "There's no hunk {}, the outline numbers them 1 to {}",
+            n, most
+        )))
+    }
+    pager::page(&text).unwrap_or_else(|e| fail(e));
+}
+
+/// Outlines, as `f d --summary` does, the files whose diff is longer than
+/// `[diff] summary_lines`, and returns the others to be diffed in full.
+/// With nothing left, the outlines go to the pager and f exits.
+fn summarize_long(files: &[GitFile], config: &Config) -> Vec<GitFile> {
+    let Some(limit) = config.diff.summary_lines else {
+        return files.to_vec();
+    };
+    let mut text = String::new();
+    let mut rest = Vec::new();
+    for file in files {
+        // Images and LFS files aren't diffed as text here
+        let diff = (!images::is_image(&file.rel_path) && file.lfs_size.is_none())
+            .then(|| git_status::file_diff(file, false).ok())
+            .flatten();
+        match diff {
+            Some(diff) if diff.lines().count() > limit => {
+                let hunks = interactive::parse_hunks(&file.rel_path, &diff);
+                if !text.is_empty() {
+                    text.push('\n');
+                }
+                text.push_str(&summary::render(&display::display_path(file), &hunks, &[]));
+            }
+            _ => rest.push(file.clone()),
+        }
+    }
+    if rest.is_empty() {
+        pager::page(&text).unwrap_or_else(|e| fail(e));
+        process::exit(0);
+    }
+    print!("{}", text);
+    rest
+}
+
+fn cmd_staged_diff(id: Option<String>, config: &Config) -> ! {
+    staged_diff(&require_file(resolve_staged_file(id, config)), config)
+}
+
+/// The index diff of `file`'s path, deletions included. An ID for the
+/// unstaged side works when the path has staged changes too; one with
+/// nothing staged is an error rather than an empty diff.
+fn staged_diff(file: &GitFile, config: &Config) -> ! {
+    if file.file_type != FileType::Staged {
+        let files = daemon::get_files(&config.id_chars()).unwrap_or_else(|e| fail(e));
+        if !files
+            .iter()
+            .any(|f| f.file_type == FileType::Staged && f.rel_path == file.rel_path)
+        {
+            fail(Error::NotFound(format!(
+                "Nothing staged for {}, f d shows its unstaged changes",
+                file.rel_path
+            )))
+        }
+    }
+    if diffs_as_image(file) {
+        // HEAD against the index, whichever side the ID was for
+        let staged = GitFile {
+            file_type: FileType::Staged,
+            ..file.clone()
+        };
+        image_diff(&staged, config);
+        process::exit(0);
+    }
+    let path = file.abs_path.to_string_lossy();
+    if lfs::diff_smudged(file) {
+        exec_git(&["-c", lfs::TEXTCONV, "diff", "--staged", "--", &path])
+    }
+    exec_git(&["diff", "--staged", "--", &path])
+}
+
+fn cmd_add(id: Option<String>, config: &Config) -> ! {
+    stage_files(&require_files(id, config, resolve_file))
+}
+
+fn cmd_unstage(id: Option<String>, config: &Config) -> ! {
+    unstage_files(&require_files(id, config, resolve_staged_file))
+}
+
+/// Like bookmarks, takes a path to any file, changed or not: making a
+/// committed script executable is the usual case.
+fn cmd_chmod(id: String, executable: bool, config: &Config) -> ! {
+    if id == "-" {
+        chmod_files(&read_stdin_files(config), executable);
+    }
+    match resolve_file(Some(id.clone()), config) {
+        Ok(file) => chmod_files(&[file], executable),
+        Err(e) => {
+            let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+            let cwd = std::env::current_dir().unwrap_or_default();
+            let Some(path) = git_status::repo_path(&id, &cwd, &root)
+                .filter(|rel| !rel.is_empty() && root.join(rel).is_file())
+            else {
+                fail(e)
+            };
+            let tracked =
+                git_status::git_output(&["ls-files", "--", &root.join(&path).to_string_lossy()])
+                    .is_some_and(|out| !out.is_empty());
+            chmod_paths(&[(&path, tracked)], executable)
+        }
+    }
+}
+
+fn cmd_discard(id: Option<String>, config: &Config) -> ! {
+    discard_files(&require_files(id, config, resolve_file), "discard")
+}
+
+fn cmd_clean(config: &Config) -> ! {
+    let untracked: Vec<GitFile> = match daemon::get_files(&config.id_chars()) {
+        Ok(files) => files
+            .into_iter()
+            .filter(|f| f.file_type == FileType::Untracked)
+            .collect(),
+        Err(e) => fail(e),
+    };
+    if untracked.is_empty() {
+        println!("{}", "No untracked files".dimmed());
+        process::exit(0);
+    }
+    discard_files(&untracked, "clean")
+}
+
+fn cmd_trash(action: Option<TrashAction>) {
+    let trash = trash::Trash::open().unwrap_or_else(|e| fail(e));
+    match action.unwrap_or(TrashAction::List) {
+        TrashAction::List => {
+            let entries = trash.entries();
+            if entries.is_empty() {
+                println!("{}", "Trash is empty".dimmed());
+            }
+            for entry in entries {
+                println!(
+                    "{}  {} {}",
+                    entry.name.yellow(),
+                    entry.command,
+                    trash::ago(entry.time).dimmed()
+                );
+                for path in &entry.paths {
+                    println!("    {}", path);
+                }
+            }
+        }
+        TrashAction::Restore { entry, path, force } => {
+            let entry = trash.find(entry.as_deref()).unwrap_or_else(|e| fail(e));
+            if exec::print_only() {
+                for p in entry
+                    .paths
+                    .iter()
+                    .filter(|p| path.as_ref().is_none_or(|o| o == *p))
+                {
+                    println!("Would restore: {}", p);
+                }
+                return;
+            }
+            let restored = trash
+                .restore(&entry, path.as_deref(), force)
+                .unwrap_or_else(|e| fail(e));
+            for path in restored {
+                println!("Restored: {}", path);
+            }
+        }
+    }
+}
+
+fn cmd_edit(id: Option<String>, all: bool, staged: bool, config: &Config) -> ! {
+    if !all && !staged && id.as_deref() != Some(".") {
+        edit_files(&[require_file(resolve_file(id, config))], config)
+    }
+    let files: Vec<GitFile> = daemon::get_files(&config.id_chars())
+        .unwrap_or_else(|e| fail(e))
+        .into_iter()
+        .filter(|f| (f.file_type == FileType::Staged) == staged)
+        .filter(|f| f.abs_path.is_file() && f.symlink_target().is_none())
+        .collect();
+    if files.is_empty() {
+        let what = if staged {
+            "staged"
+        } else {
+            "unstaged or untracked"
+        };
+        fail(Error::NotFound(format!("No {} files to edit", what)))
+    }
+    edit_files(&files, config)
+}
+
+fn cmd_path(id: Option<String>, relative: bool, config: &Config) {
+    let file = require_file(resolve_file(id, config));
+    print_path(&file, relative);
+}
+
+fn print_path(file: &GitFile, relative: bool) {
+    if relative {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        println!("{}", relative_to(&file.abs_path, &cwd).display());
+    } else {
+        println!("{}", file.abs_path.display());
+    }
+}
+
+fn cmd_cd(id: Option<String>, config: &Config) {
+    let file = require_file(resolve_file(id, config));
+    let dir = file.abs_path.parent().unwrap_or(&file.abs_path);
+    if !shell::change_dir(dir) {
+        // Still useful as cd "$(f cd <id>)"
+        println!("{}", dir.display());
+        eprintln!(
+            "{}",
+            "f cd needs the shell wrapper to change directory: eval \"$(f shell-init bash)\""
+                .dimmed()
+        );
+    }
+}
+
+fn cmd_exec(id: Option<String>, command: &[String], config: &Config) -> ! {
+    let file = require_file(resolve_file(id, config));
+    let args = exec::substitute(command, &file.abs_path.to_string_lossy());
+    exec::exec(Command::new(&args[0]).args(&args[1..]))
+}
+
+/// Runs `tool`'s command for each file through the shell, from the
+/// repository root so `{path}` resolves. Files that expand to the
+/// same command, like two files in one crate under `cargo test -p`, run it
+/// once. Stops at the first command that fails, with its exit code.
+fn cmd_tool(tool: tools::Tool, id: Option<String>, config: &Config) {
+    let files = require_files(id, config, resolve_file);
+    let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+    let mut commands: Vec<String> = Vec::new();
+    for file in &files {
+        let template = tools::template(&config.tools, tool, &file.rel_path)
+            .unwrap_or_else(|e| fail(e))
+            .unwrap_or_else(|| {
+                fail(Error::Config(format!(
+                    "No {} command configured for {}, add one under [[tools]] in f.toml",
+                    tool.name(),
+                    file.rel_path
+                )))
+            });
+        let package = tools::find_package(&file.abs_path, &root);
+        let target = tools::Target {
+            file: &file.abs_path,
+            path: &file.rel_path,
+            root: &root,
+            package: package.as_ref(),
+        };
+        let command = tools::expand(template, &target).unwrap_or_else(|e| fail(e));
+        if !commands.contains(&command) {
+            commands.push(command);
+        }
+    }
+    for command in commands {
+        let status = exec::status(Command::new("sh").args(["-c", &command]).current_dir(&root))
+            .unwrap_or_else(|e| fail(anyhow::anyhow!("Can't run sh: {}", e)));
+        if !status.success() {
+            exec::exit(status);
+        }
+    }
+}
+
+fn cmd_check(config: &Config) {
+    let files = daemon::get_files(&config.id_chars()).unwrap_or_else(|e| fail(e));
+    let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+    // A file both staged and unstaged is checked once, and deleted ones
+    // aren't there to check
+    let mut files: Vec<&GitFile> = files.iter().filter(|f| f.abs_path.exists()).collect();
+    files.sort_by(|a, b| a.rel_path.cmp(&b.rel_path));
+    files.dedup_by(|a, b| a.rel_path == b.rel_path);
+    if files.is_empty() {
+        println!("{}", "No changed files to check".dimmed());
+        return;
+    }
+    let checks = file_checks(&files, &root, config);
+    if checks.is_empty() {
+        fail(Error::Config(
+            "No lint or test command matches the changed files, add some under [[tools]] in f.toml"
+                .into(),
+        ));
+    }
+    if exec::print_only() {
+        for check in &checks {
+            println!("{}", check.command);
+        }
+        return;
+    }
+    if !run_checks(&files, &checks, &root) {
+        process::exit(1);
+    }
+}
+
+/// The `[[tools]]` commands for `files`.
+fn file_checks(files: &[&GitFile], root: &Path, config: &Config) -> Vec<tools::Check> {
+    let packages: Vec<Option<tools::Package>> = files
+        .iter()
+        .map(|f| tools::find_package(&f.abs_path, root))
+        .collect();
+    let targets: Vec<tools::Target> = files
+        .iter()
+        .zip(&packages)
+        .map(|(file, package)| tools::Target {
+            file: &file.abs_path,
+            path: &file.rel_path,
+            root,
+            package: package.as_ref(),
+        })
+        .collect();
+    tools::checks(&config.tools, &targets).unwrap_or_else(|e| fail(e))
+}
+
+/// Runs `checks` and reports them per file, then the output of any that
+/// failed. Returns whether all passed.
+fn run_checks(files: &[&GitFile], checks: &[tools::Check], root: &Path) -> bool {
+    let mut passed = Vec::new();
+    let mut failures = Vec::new();
+    for check in checks {
+        eprintln!("{} {}", "Running".dimmed(), check.command);
+        let output = exec::run_output(
+            Command::new("sh")
+                .args(["-c", &format!("{} 2>&1", check.command)])
+                .current_dir(root),
+        );
+        // A check that hangs fails like any other, and the rest still run
+        let failure = match output {
+            Ok(output) if output.status.success() => None,
+            Ok(output) => Some(output.stdout),
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => Some(
+                format!(
+                    "{} timed out after {}s\n",
+                    check.command,
+                    exec::timeout().map_or(0, |t| t.as_secs())
+                )
+                .into_bytes(),
+            ),
+            Err(e) => fail(anyhow::anyhow!("Can't run {}: {}", check.command, e)),
+        };
+        passed.push(failure.is_none());
+        if let Some(output) = failure {
+            failures.push((check, output));
+        }
+    }
+
+    let rows: Vec<(&str, &str)> = files
+        .iter()
+        .map(|f| (f.stable_id.display.as_str(), f.rel_path.as_str()))
+        .collect();
+    println!("{}", "── Check ──".magenta());
+    print!("{}", tools::report(&rows, checks, &passed));
+    for (check, output) in &failures {
+        println!();
+        println!("{}", format!("── ✗ {} ──", check.command).red());
+        print!("{}", String::from_utf8_lossy(output));
+    }
+    failures.is_empty()
+}
+
+fn cmd_init_hooks(force: bool) {
+    let results = hooks::install(force).unwrap_or_else(|e| fail(e));
+    for (name, outcome) in &results {
+        match outcome {
+            hooks::Installed::New => println!("{} {}", "✓".green(), name),
+            hooks::Installed::Updated => println!("{} {} (updated)", "✓".green(), name),
+            hooks::Installed::Replaced => {
+                println!("{} {} (replaced the existing hook)", "✓".green(), name)
+            }
+            hooks::Installed::Kept => println!(
+                "{} {} left alone, it isn't f's (--force replaces it)",
+                "✗".yellow(),
+                name
+            ),
+        }
+    }
+    if results.iter().any(|(_, o)| *o == hooks::Installed::Kept) {
+        process::exit(1);
+    }
+}
+
+/// The hooks `f init-hooks` installs: `commit-msg` runs the `[commit]`
+/// rules on the message file git passes, `pre-commit` the `[[tools]]`
+/// commands for the staged files. Either passes when there's nothing
+/// configured.
+fn cmd_hook(name: &str, args: &[String], config: &Config) {
+    match name {
+        "commit-msg" => {
+            if std::env::var_os(hooks::NO_VERIFY_MSG).is_some() {
+                return;
+            }
+            let Some(path) = args.first() else {
+                fail(Error::Usage("commit-msg needs the message file".into()));
+            };
+            let text = std::fs::read_to_string(path)
+                .unwrap_or_else(|e| fail(anyhow::anyhow!("Can't read {}: {}", path, e)));
+            let comment_char = git_status::git_output(&["config", "core.commentChar"])
+                .and_then(|c| c.chars().next())
+                .unwrap_or('#');
+            let msg = hooks::message_from_file(&text, comment_char);
+            let violations = lint::check(&msg, &config.commit).unwrap_or_else(|e| fail(e));
+            reject_message(&violations, "git commit --no-verify skips the checks");
+        }
+        "pre-commit" => {
+            // The checks run on what's being committed, not the work tree,
+            // which may have more changes or fewer
+            let staged = hooks::StagedTree::check_out().unwrap_or_else(|e| fail(e));
+            let files = daemon::get_files(&config.id_chars()).unwrap_or_else(|e| fail(e));
+            let files: Vec<GitFile> = files
+                .into_iter()
+                .filter(|f| f.file_type == FileType::Staged)
+                .map(|f| GitFile {
+                    abs_path: staged.root.join(&f.rel_path),
+                    ..f
+                })
+                .filter(|f| f.abs_path.exists())
+                .collect();
+            let files: Vec<&GitFile> = files.iter().collect();
+            let checks = file_checks(&files, &staged.root, config);
+            let passed = checks.is_empty() || run_checks(&files, &checks, &staged.root);
+            drop(staged);
+            if !passed {
+                eprintln!("git commit --no-verify commits anyway");
+                process::exit(1);
+            }
+        }
+        _ => fail(Error::Usage(format!(
+            "Unknown hook '{}', expected one of {}",
+            name,
+            hooks::F_HOOKS.join(", ")
+        ))),
+    }
+}
+
+fn cmd_bookmark(
+    target: Option<String>,
+    name: Option<String>,
+    delete: Option<String>,
+    config: &Config,
+) {
+    let mut bookmarks = bookmarks::Bookmarks::open().unwrap_or_else(|e| fail(e));
+    if let Some(name) = delete {
+        let path = bookmarks.remove(&name).unwrap_or_else(|e| fail(e));
+        println!("Deleted bookmark @{} ({})", name, path);
+        return;
+    }
+    let (Some(target), Some(name)) = (target, name) else {
+        fail(Error::Usage(
+            "A file and a bookmark name are required".into(),
+        ));
+    };
+    // Any file can be bookmarked, changed or not, by its path
+    let path = match resolve_file(Some(target.clone()), config) {
+        Ok(file) => file.rel_path,
+        Err(e) => {
+            let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+            let cwd = std::env::current_dir().unwrap_or_default();
+            match git_status::repo_path(&target, &cwd, &root) {
+                Some(rel) if root.join(&rel).is_file() => rel,
+                _ => fail(e),
+            }
+        }
+    };
+    bookmarks.set(&name, &path).unwrap_or_else(|e| fail(e));
+    println!("Bookmarked {} as @{}", path, name.bold());
+}
+
+fn cmd_bookmarks(config: &Config) {
+    let bookmarks = bookmarks::Bookmarks::open().unwrap_or_else(|e| fail(e));
+    if bookmarks.entries().is_empty() {
+        println!(
+            "{}",
+            "No bookmarks, add one with f bookmark <id> <name>".dimmed()
+        );
+        return;
+    }
+    let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+    let files = daemon::get_files(&config.id_chars()).unwrap_or_else(|e| fail(e));
+    let width = bookmarks
+        .entries()
+        .iter()
+        .map(|(name, _)| width::str_width(name) + 1)
+        .max()
+        .unwrap_or(0);
+    println!("{}", format!("── {} ──", "Bookmarks").magenta());
+    for (name, path) in bookmarks.entries() {
+        let state: Vec<&str> = files
+            .iter()
+            .filter(|f| f.rel_path == *path)
+            .map(|f| f.file_type.as_str())
+            .collect();
+        let state = if !root.join(path).exists() {
+            format!(" {}", "missing".red())
+        } else if state.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", state.join(", ").yellow())
+        };
+        println!(
+            "  {} {}{}",
+            width::pad(&format!("@{}", name), width).cyan(),
+            path,
+            state
+        );
+    }
+}
+
+fn cmd_history(config: &Config) {
+    let theme = theme::get();
+    let history = history::History::open().unwrap_or_else(|e| fail(e));
+    if history.entries().is_empty() {
+        println!(
+            "{}",
+            theme
+                .muted
+                .paint("No history yet, files are added as f commands use them")
+        );
+        return;
+    }
+    let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+    let files = daemon::get_files(&config.id_chars()).unwrap_or_else(|e| fail(e));
+    let width = files
+        .iter()
+        .map(|f| width::str_width(&f.stable_id.display))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    println!("{}", theme.heading.paint("── History ──"));
+    for (i, path) in history.entries().iter().enumerate() {
+        let changed: Vec<&GitFile> = files.iter().filter(|f| f.rel_path == *path).collect();
+        let id = match (i, changed.first()) {
+            (_, Some(file)) => file.stable_id.display.clone(),
+            (0, None) => "-".to_string(),
+            _ => String::new(),
+        };
+        let types: Vec<&str> = changed.iter().map(|f| f.file_type.as_str()).collect();
+        let state = if !root.join(path).exists() {
+            format!(" {}", theme.removed.paint("missing"))
+        } else if types.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", theme.warning.paint(&types.join(", ")))
+        };
+        println!(
+            "  {} {}{}",
+            theme.id.paint(&width::pad(&id, width)),
+            path,
+            state
+        );
+    }
+}
+
+fn cmd_copy(id: Option<String>, diff: bool, config: &Config) {
+    let file = require_file(resolve_file(id, config));
+    let text = if diff {
+        let staged = file.file_type == FileType::Staged;
+        match git_status::file_diff(&file, staged) {
+            Ok(d) => d,
+            Err(e) => fail(e),
+        }
+    } else {
+        file.rel_path.clone()
+    };
+
+    let what = if diff { "diff of" } else { "path of" };
+    match clipboard::copy(&text) {
+        Ok(clipboard::Method::Tool(tool)) => {
+            println!(
+                "Copied {} {} (via {})",
+                what,
+                display::display_path(&file),
+                tool
+            )
+        }
+        Ok(clipboard::Method::Osc52) => {
+            println!(
+                "Copied {} {} (via terminal)",
+                what,
+                display::display_path(&file)
+            )
+        }
+        Err(e) => {
+            eprintln!("Failed to copy: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn cmd_url(id: Option<String>, open: bool, line: bool, remote: &str, config: &Config) {
+    let file = require_file(resolve_file(id, config));
+
+    let Some(remote_url) = git_status::git_output(&["remote", "get-url", remote]) else {
+        fail(Error::NotFound(format!("No remote named '{}'", remote)));
+    };
+    let Some(repo) = forge::parse_remote_url(&remote_url) else {
+        eprintln!("Unrecognized forge for remote URL: {}", remote_url);
+        process::exit(1);
+    };
+
+    // Link to the branch when on one, otherwise to the exact commit
+    let git_ref = match git_status::git_output(&["symbolic-ref", "--short", "-q", "HEAD"]) {
+        Some(branch) => branch,
+        None => git_status::git_output(&["rev-parse", "HEAD"]).unwrap_or_else(|| "HEAD".into()),
+    };
+    let line = if line {
+        git_status::first_changed_line(&file)
+    } else {
+        None
+    };
+
+    let url = repo.file_url(&git_ref, &file.rel_path, line);
+    if open {
+        let opener = if cfg!(target_os = "macos") {
+            "open"
+        } else {
+            "xdg-open"
+        };
+        if let Err(e) = exec::status(Command::new(opener).arg(&url)) {
+            eprintln!("Failed to run {}: {}", opener, e);
+            process::exit(1);
+        }
+    } else {
+        println!("{}", url);
+    }
+}
+
+fn cmd_commit(
+    message: Vec<String>,
+    message_flag: Option<String>,
+    edit: bool,
+    verify_msg: bool,
+    flags: CommitFlags,
+    config: &Config,
+) -> ! {
+    check_commit_state(message_flag.is_some(), config);
+    if let Some(msg) = message_flag {
+        if verify_msg && !edit {
+            lint_message(&msg, config);
+        }
+        commit_selected(&message, &msg, edit, flags, config)
+    }
+    if message.is_empty() || edit {
+        commit_in_editor(message, flags, config)
+    }
+    let msg = message.join(" ");
+    if verify_msg {
+        lint_message(&msg, config);
+    }
+    record_session("commit", &[&msg]);
+    run_commit(&["commit", "-m", &msg], flags, config)
+}
+
+/// Steers `f commit` during an operation git has paused. Rebases,
+/// cherry-picks and reverts are usually finished with `--continue`, which
+/// commits the resolved changes itself, so that's offered first; declining
+/// commits as asked, e.g. to split a commit at a rebase `edit` stop. A merge
+/// can only be committed as a whole.
+fn check_commit_state(partial: bool, config: &Config) {
+    let Some(state) = current_repo_state() else {
+        return;
+    };
+    if state == repo_state::RepoState::Merge && partial {
+        fail(Error::Usage(
+            "Can't commit single files during a merge, stage them and run f c <msg>".into(),
+        ));
+    }
+    if let Some(cont) = state.continue_command() {
+        eprintln!("{}", state.describe().yellow());
+        if confirm(&messages::tf(Msg::RunInstead, &[&cont.join(" ")])) {
+            if exec::print_only() {
+                exec_git(&cont);
+            }
+            let git_dir = git_status::get_git_dir().unwrap_or_else(|e| fail(e));
+            let status = exec::status(Command::new("git").args(cont))
+                .unwrap_or_else(|e| fail(anyhow::anyhow!("Failed to run git: {}", e)));
+            // Stopped again on the next conflict
+            if let Some(state) = repo_state::detect(&git_dir) {
+                println!("\n{}", state.describe().yellow().bold());
+                print_conflicts(config);
+            }
+            exec::exit(status)
+        }
+    }
+}
+
+/// Exits listing every failed `[commit]` rule. Messages written in the
+/// editor are git's to handle and aren't checked.
+fn lint_message(msg: &str, config: &Config) {
+    let violations = match lint::check(msg, &config.commit) {
+        Ok(v) => v,
+        Err(e) => fail(e),
+    };
+    reject_message(&violations, "Use --no-verify-msg to commit anyway");
+}
+
+/// Exits listing `violations`, if there are any, then `hint`.
+fn reject_message(violations: &[lint::Violation], hint: &str) {
+    if violations.is_empty() {
+        return;
+    }
+    eprintln!("Commit message rejected:");
+    for v in violations {
+        eprintln!("  {}: {}", v.rule, v.message);
+    }
+    eprintln!("{}", hint);
+    process::exit(1);
+}
+
+/// Lets git open the editor on a template listing the staged files, so
+/// commit hooks, `commit.template` and git's cleanup all still apply.
+fn commit_in_editor(message: Vec<String>, flags: CommitFlags, config: &Config) -> ! {
+    let staged: Vec<GitFile> = match daemon::get_files(&config.id_chars()) {
+        Ok(files) => files
+            .into_iter()
+            .filter(|f| f.file_type == FileType::Staged)
+            .collect(),
+        Err(e) => fail(e),
+    };
+    let head = if message.is_empty() {
+        commit::user_template()
+    } else {
+        Some(message.join(" "))
+    };
+    let contents = commit::template(&staged, commit::comment_char(), head.as_deref());
+    let path = match commit::write_template(&contents) {
+        Ok(p) => p.to_string_lossy().into_owned(),
+        Err(e) => fail(e),
+    };
+
+    let mut args = Vec::new();
+    let editor_arg;
+    if let Some(editor) = commit::editor_override(&get_editor(config)) {
+        editor_arg = format!("core.editor={}", editor);
+        args.extend(["-c", editor_arg.as_str()]);
+    }
+    args.push("commit");
+    // An inline message is a commit message to edit, not a template: git
+    // would abort if a template came back unchanged
+    if message.is_empty() {
+        args.extend(["-t", path.as_str()]);
+    } else {
+        args.extend(["-e", "-F", path.as_str()]);
+    }
+    run_commit(&args, flags, config)
+}
+
+/// Commits just the named files, as they are in the work tree, leaving
+/// anything else that is staged for a later commit.
+fn commit_selected(
+    ids: &[String],
+    msg: &str,
+    edit: bool,
+    flags: CommitFlags,
+    config: &Config,
+) -> ! {
+    if ids.is_empty() {
+        fail(Error::Usage(
+            "File IDs required with -m (use f c <msg> to commit everything staged)".into(),
+        ));
+    }
+    let files = match ids {
+        [id] if id == "-" => read_stdin_files(config),
+        _ => select_files(ids, config),
+    };
+
+    // `git commit -- <path>` only accepts paths git already knows about
+    let untracked: Vec<&GitFile> = files
+        .iter()
+        .filter(|f| f.file_type == FileType::Untracked)
+        .collect();
+    if !untracked.is_empty() {
+        let status = exec::status(
+            Command::new("git")
+                .args(["add", "--"])
+                .args(untracked.iter().map(|f| &f.abs_path)),
+        );
+        match status {
+            Ok(s) if s.success() => {}
+            Ok(s) => {
+                eprintln!("Failed to add untracked files");
+                exec::exit(s);
+            }
+            Err(e) => {
+                eprintln!("Failed to run git: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+
+    let paths = abs_paths(&files.iter().collect::<Vec<_>>());
+    let mut args = vec!["commit", "-m", msg];
+    if edit {
+        args.push("-e");
+    }
+    args.push("--");
+    args.extend(paths.iter().map(String::as_str));
+    run_commit(&args, flags, config)
+}
+
+/// `f c --by-dir` / `--by-owner`: the staged files committed one group at
+/// a time. Every message is asked for before anything is committed; each
+/// commit is made with the other groups unstaged, and whatever hasn't been
+/// committed is staged again if one fails.
+fn commit_grouped(
+    by: commit::GroupBy,
+    template: Vec<String>,
+    verify_msg: bool,
+    flags: CommitFlags,
+    config: &Config,
+) -> ! {
+    check_commit_state(true, config);
+    let paths = commit::staged_paths().unwrap_or_else(|e| fail(e));
+    if paths.is_empty() {
+        fail(Error::Usage("Nothing staged to commit".into()));
+    }
+    if git_status::git_output(&["rev-parse", "--verify", "-q", "HEAD"]).is_none() {
+        fail(Error::Usage(
+            "Make the first commit with f c <msg>, then split the rest".into(),
+        ));
+    }
+    let owners = match by {
+        commit::GroupBy::Owner => Some(owners::Owners::load().unwrap_or_else(|| {
+            fail(Error::NotFound(
+                "This repository has no CODEOWNERS file".into(),
+            ))
+        })),
+        commit::GroupBy::Dir => None,
+    };
+    let groups = commit::group(&paths, by, owners.as_ref());
+    let template = match template.is_empty() {
+        true => commit::GROUP_TEMPLATE.to_string(),
+        false => template.join(" "),
+    };
+
+    let mut messages = Vec::new();
+    for (label, files) in &groups {
+        let noun = if files.len() == 1 { "file" } else { "files" };
+        println!("{} ({} {})", label.bold(), files.len(), noun);
+        for file in files {
+            println!("    {}", file);
+        }
+        let msg = ask_line("Message", &template.replace("{group}", label));
+        if verify_msg {
+            lint_message(&msg, config);
+        }
+        messages.push(msg);
+    }
+    if exec::print_only() {
+        process::exit(0);
+    }
+
+    let entries = commit::index_entries(&paths).unwrap_or_else(|e| fail(e));
+    let entries_of = |group: &[(String, Vec<String>)]| -> Vec<commit::IndexEntry> {
+        entries
+            .iter()
+            .filter(|e| group.iter().any(|(_, files)| files.contains(&e.path)))
+            .cloned()
+            .collect()
+    };
+    let reset = |files: Vec<&String>| {
+        exec::output(Command::new("git").args(["reset", "-q", "--"]).args(files))
+    };
+    if let Err(e) = reset(groups[1..].iter().flat_map(|(_, f)| f).collect()) {
+        let _ = commit::restore_index(&entries);
+        fail(e);
+    }
+    let hooks = if flags.no_verify {
+        vec![]
+    } else {
+        hooks::installed(hooks::COMMIT_HOOKS)
+    };
+    for (i, msg) in messages.iter().enumerate() {
+        if i > 0
+            && let Err(e) = commit::restore_index(&entries_of(&groups[i..=i]))
+        {
+            let _ = commit::restore_index(&entries_of(&groups[i..]));
+            fail(e);
+        }
+        record_undo(|j| j.record_commit());
+        let mut args = vec!["commit", "-q", "-m", msg];
+        if flags.sign {
+            args.push("-S");
+        }
+        if flags.no_verify {
+            args.push("--no-verify");
+        }
+        if !hooks.is_empty() {
+            let noun = if hooks.len() == 1 { "hook" } else { "hooks" };
+            eprintln!(
+                "{}",
+                format!("Running {} {}…", hooks.join(", "), noun).dimmed()
+            );
+        }
+        let status = exec::status(Command::new("git").args(&args))
+            .unwrap_or_else(|e| fail(anyhow::anyhow!("Failed to run git: {}", e)));
+        if !status.success() {
+            let _ = commit::restore_index(&entries_of(&groups[i..]));
+            eprintln!(
+                "{}",
+                format!(
+                    "Commit for {} failed after {} of {}, the rest is staged again",
+                    groups[i].0,
+                    i,
+                    groups.len()
+                )
+                .red()
+            );
+            exec::exit(status);
+        }
+        println!("Committed {}: {}", groups[i].0, msg);
+    }
+    process::exit(0)
+}
+
+/// Reads a line on the terminal, `default` when the answer is empty or
+/// there's no terminal to ask on.
+fn ask_line(question: &str, default: &str) -> String {
+    if !exec::interactive() {
+        return default.to_string();
+    }
+    eprint!("{} [{}]: ", question, default.dimmed());
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return default.to_string();
+    }
+    match answer.trim() {
+        "" => default.to_string(),
+        answer => answer.to_string(),
+    }
+}
+
+/// Options that apply however the commit message is supplied.
+#[derive(Clone, Copy)]
+struct CommitFlags {
+    no_verify: bool,
+    sign: bool,
+}
+
+/// `args` must contain `commit`; flags are inserted right after it so they
+/// stay ahead of any `--` pathspec.
+fn run_commit(args: &[&str], flags: CommitFlags, config: &Config) -> ! {
+    if !args.contains(&"--amend") {
+        record_undo(|j| j.record_commit());
+    }
+    let mut args = args.to_vec();
+    let mut at = args
+        .iter()
+        .position(|a| *a == "commit")
+        .map_or(0, |i| i + 1);
+    if flags.sign {
+        args.insert(at, "-S");
+        at += 1;
+    }
+    let hooks = if flags.no_verify {
+        args.insert(at, "--no-verify");
+        vec![]
+    } else {
+        hooks::installed(hooks::COMMIT_HOOKS)
+    };
+    run_git_reporting(
+        &args,
+        &hooks,
+        config,
+        None,
+        "Commit failed - check hook output",
+    )
+}
+
+fn cmd_absorb(dry_run: bool, rebase: bool, config: &Config) {
+    let git_dir = git_status::get_git_dir().unwrap_or_else(|e| fail(e));
+    if let Some(state) = repo_state::detect(&git_dir) {
+        fail(anyhow::anyhow!("{}", state.describe()));
+    }
+    let plan = absorb::plan().unwrap_or_else(|e| fail(e));
+
+    for fixup in &plan.fixups {
+        println!(
+            "{} {}",
+            absorb::short(&fixup.commit).yellow(),
+            fixup.subject
+        );
+        for (path, hunks) in fixup.files(&plan) {
+            let noun = if hunks == 1 { "hunk" } else { "hunks" };
+            println!("    {} ({} {})", path, hunks, noun);
+        }
+    }
+    if !plan.skipped.is_empty() {
+        println!("{}", "Left staged:".dimmed());
+        for skipped in &plan.skipped {
+            println!("    {}", skipped.dimmed());
+        }
+    }
+    if plan.fixups.is_empty() {
+        eprintln!("No staged hunks could be matched to a commit");
+        process::exit(1);
+    }
+    // The fixups are built with plumbing, so --print stops at the plan
+    if dry_run || exec::print_only() {
+        return;
+    }
+
+    let sign = config.commit.sign;
+    let oldest = absorb::apply(&plan, &git_dir, sign).unwrap_or_else(|e| fail(e));
+    println!("Created {} fixup commit(s)", plan.fixups.len());
+    if !rebase {
+        println!("{}", "Run f absorb -r next time to squash them in".dimmed());
+        return;
+    }
+
+    // The sequence editor accepts the todo list as autosquash arranged it;
+    // --autostash carries anything left staged or unstaged across
+    let parent = format!("{}^", oldest);
+    let mut args = vec!["rebase", "-i", "--autosquash", "--autostash"];
+    if git_status::git_output(&["rev-parse", "--verify", "-q", &parent]).is_some() {
+        args.push(&parent);
+    } else {
+        args.push("--root");
+    }
+    let status = exec::status(
+        Command::new("git")
+            .args(&args)
+            .env("GIT_SEQUENCE_EDITOR", "true"),
+    );
+    match status {
+        Ok(s) if s.success() => {}
+        Ok(s) => {
+            print_conflicts(config);
+            exec::exit(s);
+        }
+        Err(e) => fail(anyhow::anyhow!("Failed to run git: {}", e)),
+    }
+}
+
+struct PushFlags {
+    no_verify: bool,
+    signed: bool,
+    force: bool,
+    dry_run: bool,
+    set_upstream: bool,
+    yes: bool,
+}
+
+fn cmd_push(flags: PushFlags, config: &Config) -> ! {
+    if let Some(state) = current_repo_state() {
+        if state.detaches_head() {
+            eprintln!(
+                "Can't push: {}, HEAD isn't on a branch",
+                state.describe().to_lowercase()
+            );
+            if let Some(hint) = state.hint() {
+                eprintln!("Finish it first: {}", hint);
+            }
+            process::exit(1);
+        }
+        eprintln!(
+            "{}",
+            format!("{}, it won't be part of the push", state.describe()).yellow()
+        );
+        if !flags.dry_run && !confirm(messages::t(Msg::PushAnyway)) {
+            eprintln!("{}", messages::t(Msg::NotPushed));
+            process::exit(1);
+        }
+    }
+    match git_status::head() {
+        Some(git_status::Head::Detached(commit)) => {
+            eprintln!(
+                "HEAD is detached at {}, so there's no branch to push.",
+                commit
+            );
+            eprintln!("  git switch -c <name>              start a branch here, then f p");
+            eprintln!("  git push <remote> HEAD:<branch>   push this commit to a branch");
+            process::exit(1);
+        }
+        Some(git_status::Head::Unborn(branch)) => {
+            eprintln!("No commits on {} yet, nothing to push", branch);
+            process::exit(1);
+        }
+        _ => {}
+    }
+    let mut args = vec!["push"];
+    if flags.force {
+        args.push("--force-with-lease");
+    }
+    if flags.dry_run {
+        args.push("--dry-run");
+    }
+    if flags.signed {
+        args.push("--signed");
+    }
+    let hooks = if flags.no_verify {
+        args.push("--no-verify");
+        vec![]
+    } else {
+        hooks::installed(hooks::PUSH_HOOKS)
+    };
+
+    // A new branch would make bare `git push` fail, unless git is already
+    // configured to set the upstream itself
+    let auto_setup = git_status::git_output(&["config", "--bool", "push.autoSetupRemote"])
+        .is_some_and(|v| v == "true");
+    let remote;
+    if let Some(branch) = git_status::branch_without_upstream().filter(|_| !auto_setup) {
+        remote = match git_status::push_remote(&branch) {
+            Some(r) => r,
+            None => {
+                eprintln!(
+                    "Branch '{}' has no upstream and no push remote could be chosen",
+                    branch
+                );
+                process::exit(1);
+            }
+        };
+        let question = format!(
+            "Branch '{}' has no upstream. Push to {} and track it?",
+            branch, remote
+        );
+        if !flags.dry_run {
+            if !flags.set_upstream && !confirm(&question) {
+                eprintln!("Not pushed (f p -u sets the upstream without asking)");
+                process::exit(1);
+            }
+            args.push("-u");
+        }
+        args.extend([remote.as_str(), "HEAD"]);
+    } else if flags.set_upstream {
+        args.push("-u");
+    }
+
+    // Says what's going out, so a forgotten amend or an extra commit shows
+    // up before it's on the remote
+    if let Some(outgoing) = git_status::outgoing().filter(|o| o.ahead > 0) {
+        eprintln!("{}", outgoing.describe());
+        if outgoing.behind > 0 {
+            let note = if flags.force {
+                format!("This overwrites the commits only on {}", outgoing.upstream)
+            } else {
+                format!(
+                    "{} has commits HEAD doesn't, pull first or the push will be rejected",
+                    outgoing.upstream
+                )
+            };
+            eprintln!("{}", note.yellow());
+        }
+        if config.push.confirm && !flags.yes && !flags.dry_run && !confirm(messages::t(Msg::Push)) {
+            eprintln!("Not pushed (f p -y doesn't ask, nor does confirm = false under [push])");
+            process::exit(1);
+        }
+    }
+
+    run_git_reporting(&args, &hooks, config, Some("Push finished"), "Push failed")
+}
+
+/// Asks a yes/no question on the terminal, defaulting to yes. Without a
+/// terminal to ask on, or non-interactively, the answer is no, and the
+/// caller names the flag that answers it.
+fn confirm(question: &str) -> bool {
+    // Show what answering yes would run
+    if exec::print_only() {
+        return true;
+    }
+    if !exec::interactive() {
+        eprintln!("{} no, not asking non-interactively", question);
+        return false;
+    }
+    eprint!("{} [{}] ", question, messages::t(Msg::YesNo));
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    messages::is_yes(&answer)
+}
+
+fn cmd_reword(message: Vec<String>, verify_msg: bool, config: &Config) -> ! {
+    // Amending mid-rebase would rewrite the commit being replayed onto
+    if let Some(state) = current_repo_state() {
+        eprintln!("Can't reword: {}", state.describe().to_lowercase());
+        if let Some(hint) = state.hint() {
+            eprintln!("Finish it first: {}", hint);
+        }
+        process::exit(1);
+    }
+    let Some(current) = git_status::git_output(&["log", "-1", "--format=%B"]) else {
+        eprintln!("No commit to reword");
+        process::exit(1);
+    };
+    let pushed = git_status::git_output(&["merge-base", "--is-ancestor", "HEAD", "@{u}"]).is_some();
+    if pushed {
+        eprintln!(
+            "{}",
+            "HEAD is already pushed; pushing the reworded commit will need f p -f".yellow()
+        );
+    }
+
+    let msg = if message.is_empty() {
+        prompt_message(&current)
+    } else {
+        Some(message.join(" "))
+    };
+    let flags = CommitFlags {
+        no_verify: false,
+        sign: config.commit.sign,
+    };
+    // --only keeps anything staged out of the amended commit
+    match msg {
+        Some(msg) => {
+            if verify_msg {
+                lint_message(&msg, config);
+            }
+            run_commit(&["commit", "--amend", "--only", "-m", &msg], flags, config)
+        }
+        None => run_commit(&["commit", "--amend", "--only"], flags, config),
+    }
+}
+
+/// Shows the current subject and reads a replacement. An empty answer, or
+/// no terminal, means "use the editor".
+fn prompt_message(current: &str) -> Option<String> {
+    if !exec::interactive() {
+        return None;
+    }
+    let subject = current.lines().next().unwrap_or("");
+    eprintln!("Current: {}", subject.dimmed());
+    eprint!("New message (empty opens the editor): ");
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).ok()?;
+    let answer = answer.trim();
+    (!answer.is_empty()).then(|| answer.to_string())
+}
+
+fn cmd_rebase(cont: bool, skip: bool, abort: bool, config: &Config) -> ! {
+    let git_dir = match git_status::get_git_dir() {
+        Ok(d) => d,
+        Err(e) => fail(e),
+    };
+    match repo_state::detect(&git_dir) {
+        Some(state @ repo_state::RepoState::Rebase { .. }) => {
+            if !(cont || skip || abort) {
+                println!("{}", state.describe().yellow().bold());
+                if !print_conflicts(config) {
+                    println!("No conflicts left - run f rebase --continue");
+                }
+                process::exit(0);
+            }
+        }
+        Some(other) => {
+            eprintln!(
+                "No rebase in progress ({})",
+                other.describe().to_lowercase()
+            );
+            process::exit(1);
+        }
+        None => {
+            eprintln!("No rebase in progress");
+            process::exit(1);
+        }
+    }
+
+    if abort {
+        exec_git(&["rebase", "--abort"])
+    }
+    let action = if cont { "--continue" } else { "--skip" };
+    let status = match exec::status(Command::new("git").args(["rebase", action])) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Failed to run git: {}", e);
+            process::exit(1);
+        }
+    };
+    // Stopped again: show where, so the next conflicts can be opened by ID
+    if let Some(state) = repo_state::detect(&git_dir) {
+        println!("\n{}", state.describe().yellow().bold());
+        print_conflicts(config);
+    }
+    exec::exit(status)
+}
+
+/// `f rebase -i <base>`: the commits since `base` in the todo list editor,
+/// then `git rebase -i` with the edited list in place of its own.
+fn cmd_rebase_interactive(base: &str, config: &Config) -> ! {
+    let git_dir = git_status::get_git_dir().unwrap_or_else(|e| fail(e));
+    if let Some(state) = repo_state::detect(&git_dir) {
+        fail(anyhow::anyhow!("{}", state.describe()));
+    }
+    // git leaves merges out of the todo list unless told to keep them
+    let range = format!("{}..HEAD", base);
+    let commits = commits::log(&["--no-merges", &range], usize::MAX, &config.id_chars())
+        .unwrap_or_else(|e| fail(e));
+    if commits.is_empty() {
+        println!(
+            "{}",
+            format!("Nothing to rebase, HEAD is on {}", base).dimmed()
+        );
+        process::exit(0);
+    }
+    if !exec::interactive() {
+        fail(Error::Usage(
+            "f rebase -i needs a terminal, git rebase -i works without one".into(),
+        ));
+    }
+    let Some(steps) =
+        interactive::run_edit_todo(rebase::steps(commits)).unwrap_or_else(|e| fail(e))
+    else {
+        println!("Rebase cancelled");
+        process::exit(0);
+    };
+    rebase::check(&steps).unwrap_or_else(|e| fail(e));
+    let editor = rebase::sequence_editor(&steps).unwrap_or_else(|e| fail(e));
+    let status = exec::status(
+        Command::new("git")
+            .args(["rebase", "-i", base])
+            .env("GIT_SEQUENCE_EDITOR", editor),
+    )
+    .unwrap_or_else(|e| fail(anyhow::anyhow!("Failed to run git: {}", e)));
+    // Stopped on a conflict, or at an edit or reword git couldn't finish
+    if let Some(state) = repo_state::detect(&git_dir) {
+        println!("\n{}", state.describe().yellow().bold());
+        print_conflicts(config);
+    }
+    exec::exit(status)
+}
+
+/// Shows one commit of HEAD's recent history, or the diff from the older
+/// of two to the newer. Without IDs or a terminal for the picker, lists the
+/// commits so their IDs can be passed back.
+fn cmd_commits(ids: &[String], limit: usize, config: &Config) -> ! {
+    use interactive::Pickable;
+    if let Some(git_status::Head::Unborn(branch)) = git_status::head() {
+        fail(Error::NotFound(format!("No commits on {} yet", branch)));
+    }
+    let commits = commits::log(&["HEAD"], limit, &config.id_chars()).unwrap_or_else(|e| fail(e));
+    let mut picked: Vec<commits::Commit> = if !ids.is_empty() {
+        ids.iter()
+            .map(|id| {
+                commits::find_by_id(&commits, id)
+                    .unwrap_or_else(|e| fail(e))
+                    .clone()
+            })
+            .collect()
+    } else if exec::interactive() {
+        interactive::pick_many("Commits", &commits, 2, config).unwrap_or_else(|e| fail(e))
+    } else {
+        let width = commits
+            .iter()
+            .map(|c| c.id.display.len())
+            .max()
+            .unwrap_or(0);
+        for commit in &commits {
+            let id = format!("{:<width$}", commit.id.display);
+            println!("{}  {}", id.cyan(), commit.describe());
+        }
+        process::exit(0)
+    };
+    // Newest first, as listed
+    picked.sort_by_key(|c| commits.iter().position(|listed| listed.sha == c.sha));
+    picked.dedup_by(|a, b| a.sha == b.sha);
+    match picked.as_slice() {
+        [] => process::exit(0),
+        [commit] => exec_git(&["show", &commit.sha]),
+        [newer, older, ..] => exec_git(&["diff", &older.sha, &newer.sha]),
+    }
+}
+
+/// Runs `git <action> <commit>` on a commit from `range`, chosen by ID or
+/// in the picker, then lists any conflicts it left.
+fn cmd_pick_commit(
+    action: &str,
+    range: &[&str],
+    title: &str,
+    id: Option<String>,
+    limit: usize,
+    config: &Config,
+) -> ! {
+    if let Some(git_status::Head::Unborn(branch)) = git_status::head() {
+        fail(Error::NotFound(format!("No commits on {} yet", branch)));
+    }
+    let commits = commits::log(range, limit, &config.id_chars()).unwrap_or_else(|e| fail(e));
+    if commits.is_empty() {
+        println!("{}", "No commits to pick from".dimmed());
+        process::exit(0);
+    }
+    let commit = match id {
+        Some(id) => commits::find_by_id(&commits, &id)
+            .unwrap_or_else(|e| fail(e))
+            .clone(),
+        None if exec::interactive() => {
+            match interactive::pick(title, &commits, config).unwrap_or_else(|e| fail(e)) {
+                Some(commit) => commit,
+                None => process::exit(0),
+            }
+        }
+        None => fail(anyhow::anyhow!(
+            "No terminal for the picker, pass a commit ID"
+        )),
+    };
 
-    fn display_actions(file: &GitFile) {
-        raw_println!();
-        raw_println!("{} {}", "Selected:".green(), file.rel_path);
-        raw_println!("{}", "── Action ──".yellow());
-        raw_println!("  {}  add", "a".cyan());
-        raw_println!("  {}  diff", "d".cyan());
-        raw_println!("  {}  staged diff", "s".cyan());
-        raw_println!("  {}  edit", "e".cyan());
-        raw_println!("  {}  quit", "q".dimmed());
+    println!("{} {} {}", action, commit.short.yellow(), commit.subject);
+    let status = match exec::status(Command::new("git").args([action, &commit.sha])) {
+        Ok(s) => s,
+        Err(e) => fail(anyhow::anyhow!("Failed to run git: {}", e)),
+    };
+    if !status.success() {
+        print_conflicts(config);
     }
+    exec::exit(status)
+}
 
-    pub fn run(config: &Config) -> Result<()> {
-        let id_chars = config.id_chars();
-        let files = get_all_files(&id_chars)?;
-        if files.is_empty() {
-            println!("{}", "No changed files".dimmed());
-            return Ok(());
+fn cmd_undo(list: bool) {
+    let journal = match undo::Journal::open() {
+        Ok(j) => j,
+        Err(e) => fail(e),
+    };
+    if list {
+        for entry in journal.entries().iter().rev() {
+            println!("{}", entry.action.describe());
         }
+        return;
+    }
+    if exec::print_only() {
+        match journal.entries().last() {
+            Some(entry) => println!("Would undo: {}", entry.action.describe()),
+            None => println!("Nothing to undo"),
+        }
+        return;
+    }
+    match journal.undo() {
+        Ok(entry) => println!("Undid: {}", entry.action.describe()),
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(1);
+        }
+    }
+}
 
-        let keys = generate_keys(files.len(), &id_chars);
-        let key_len = keys.first().map(|k| k.len()).unwrap_or(0);
-
-        terminal::enable_raw_mode().context("Terminal error")?;
+fn cmd_outgoing() -> ! {
+    if let Some(git_status::Head::Unborn(_)) = git_status::head() {
+        println!("No commits yet");
+        process::exit(0);
+    }
+    // Without an upstream, everything not on any remote would be pushed
+    let has_upstream = git_status::branch_without_upstream().is_none();
+    let range: &[&str] = if has_upstream {
+        &["@{u}..HEAD"]
+    } else {
+        &["HEAD", "--not", "--remotes"]
+    };
+    show_commits(range, "Nothing to push")
+}
 
-        let result = (|| -> Result<Option<GitFile>> {
-            clear_screen();
-            display_files(&files, &keys, "");
+fn cmd_incoming() -> ! {
+    if git_status::branch_without_upstream().is_some() {
+        eprintln!("Current branch has no upstream");
+        process::exit(1);
+    }
+    match exec::status(Command::new("git").args(["fetch", "--quiet"])) {
+        Ok(s) if s.success() => {}
+        Ok(s) => {
+            eprintln!("Fetch failed");
+            exec::exit(s);
+        }
+        Err(e) => {
+            eprintln!("Failed to run git: {}", e);
+            process::exit(1);
+        }
+    }
+    show_commits(&["HEAD..@{u}"], "Nothing to pull")
+}
 
-            let mut prefix = String::new();
-            loop {
-                if event::poll(std::time::Duration::from_millis(100)).context("Event error")?
-                    && let Event::Key(key_event) = event::read().context("Read error")?
-                {
-                    if key_event.modifiers.contains(KeyModifiers::CONTROL)
-                        && key_event.code == KeyCode::Char('c')
-                    {
-                        return Ok(None);
-                    }
+/// Lists the commits in `range` one per line with ref decorations, or
+/// prints `empty` when there are none.
+fn show_commits(range: &[&str], empty: &str) -> ! {
+    let mut count_args = vec!["rev-list", "--count"];
+    count_args.extend(range);
+    match git_status::git_output(&count_args).as_deref() {
+        Some("0") => {
+            println!("{}", empty);
+            process::exit(0);
+        }
+        Some(_) => {}
+        None => {
+            eprintln!("Failed to list commits (is HEAD a commit?)");
+            process::exit(1);
+        }
+    }
+    let mut args = vec!["log", "--oneline", "--decorate"];
+    args.extend(range);
+    exec_git(&args)
+}
 
-                    match key_event.code {
-                        KeyCode::Char('q') => return Ok(None),
-                        KeyCode::Char(c) if id_chars.contains(&c) => {
-                            prefix.push(c);
-
-                            if prefix.len() == key_len {
-                                if let Some(idx) = keys.iter().position(|k| k == &prefix) {
-                                    return Ok(Some(files[idx].clone()));
-                                }
-                                prefix.clear();
-                            }
-
-                            let matches: Vec<_> =
-                                keys.iter().filter(|k| k.starts_with(&prefix)).collect();
-                            if matches.is_empty() {
-                                prefix.clear();
-                            }
-
-                            clear_screen();
-                            display_files(&files, &keys, &prefix);
-                        }
-                        KeyCode::Esc => {
-                            prefix.clear();
-                            clear_screen();
-                            display_files(&files, &keys, "");
-                        }
-                        _ => {}
-                    }
+fn cmd_pr(action: PrAction, config: &Config) {
+    let Some(remote_url) = git_status::git_output(&["remote", "get-url", "origin"]) else {
+        fail(Error::NotFound("No remote named 'origin'".into()));
+    };
+    let Some(repo) = forge::parse_remote_url(&remote_url) else {
+        fail(Error::Usage(format!(
+            "Unrecognized forge for remote URL: {}",
+            remote_url
+        )));
+    };
+    let tool = pr::Tool::for_forge(repo.forge).unwrap_or_else(|e| fail(e));
+    tool.check_installed().unwrap_or_else(|e| fail(e));
+    let run = |args: &[&str]| -> ! { exec::exec(Command::new(tool.program()).args(args)) };
+    match action {
+        PrAction::Create { draft, base } => {
+            let base_ref = base.as_ref().map(|b| format!("origin/{}", b)).or_else(|| {
+                git_status::git_output(&[
+                    "symbolic-ref",
+                    "--short",
+                    "-q",
+                    "refs/remotes/origin/HEAD",
+                ])
+            });
+            let range = match &base_ref {
+                Some(base_ref) => vec![format!("{}..HEAD", base_ref)],
+                None => ["HEAD", "--not", "--remotes"].map(String::from).to_vec(),
+            };
+            let range: Vec<&str> = range.iter().map(String::as_str).collect();
+            let commits = pr::messages(&range).unwrap_or_else(|e| fail(e));
+            if commits.is_empty() {
+                fail(Error::NotFound("No commits to open a PR for".into()));
+            }
+            let (title, body) = pr::prefill(&commits);
+            let args = tool.create_args(&title, &body, draft, base.as_deref());
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            run(&args)
+        }
+        PrAction::Status => run(tool.status_args()),
+        PrAction::Checks => run(tool.checks_args()),
+        PrAction::Files { id, action } => {
+            let base = tool.base_branch().unwrap_or_else(|e| fail(e));
+            let range = format!("origin/{}...HEAD", base);
+            let Ok(output) =
+                exec::output(Command::new("git").args(["diff", "--name-only", "-z", &range]))
+            else {
+                fail(Error::NotFound(format!(
+                    "Can't compare with origin/{} (git fetch origin {} first?)",
+                    base, base
+                )));
+            };
+            let paths: Vec<String> = String::from_utf8_lossy(&output)
+                .split('\0')
+                .filter(|path| !path.is_empty())
+                .map(String::from)
+                .collect();
+            let ids = git_status::generate_ids(&paths, &config.id_chars());
+            let Some(id) = id else {
+                let width = ids.iter().map(|(d, _)| d.len()).max().unwrap_or(0);
+                for (path, (display, _)) in paths.iter().zip(&ids) {
+                    println!("{}  {}", format!("{:<width$}", display).cyan(), path);
                 }
+                return;
+            };
+            let matches: Vec<&String> = paths
+                .iter()
+                .zip(&ids)
+                .filter(|(_, (_, full_hash))| full_hash.starts_with(&id))
+                .map(|(path, _)| path)
+                .collect();
+            match matches.as_slice() {
+                [path] => path_action(path, action.as_deref(), &[], "was deleted", config),
+                [] => fail(Error::NotFound(format!(
+                    "No file with ID '{}' in the PR",
+                    id
+                ))),
+                _ => fail(Error::AmbiguousId {
+                    id,
+                    matches: matches.len(),
+                    what: "files",
+                }),
             }
-        })();
+        }
+    }
+}
+
+fn cmd_watch(
+    interval: f64,
+    events: bool,
+    repos: Option<&Path>,
+    filter: &filter::FileFilter,
+    config: &Config,
+) {
+    let interval = match std::time::Duration::try_from_secs_f64(interval) {
+        Ok(d) if !d.is_zero() => d,
+        _ => fail(Error::Usage(
+            "Interval must be a positive number of seconds".into(),
+        )),
+    };
+    let result = match repos {
+        Some(dir) => dashboard::run(dir, interval),
+        None => watch::run(config, interval, events, filter),
+    };
+    if let Err(e) = result {
+        fail(e);
+    }
+}
+
+fn cmd_prompt(color: bool) {
+    colored::control::set_override(color);
+    // Prompts are redrawn constantly, so stay silent outside a repository
+    if let Ok(summary) = git_status::get_status_summary() {
+        println!("{}", prompt::format_prompt(&summary));
+    }
+}
+
+fn cmd_state(format: state::Format) {
+    let summary = git_status::get_status_summary().unwrap_or_else(|e| fail(e));
+    let state = state::state(&summary, current_repo_state().as_ref());
+    print!("{}", state::render(&state, format));
+}
+
+fn cmd_serve(config: &Config) {
+    if let Err(e) = serve::run(config) {
+        fail(e);
+    }
+}
+
+fn cmd_mcp(read_only: bool, config: &Config) {
+    if let Err(e) = mcp::Server::new(config, read_only).run() {
+        fail(e);
+    }
+}
+
+fn cmd_daemon(config: &Config) {
+    if let Err(e) = daemon::run(config) {
+        fail(e);
+    }
+}
+
+fn cmd_doctor(config: &Config) {
+    if !doctor::run(config) {
+        process::exit(1);
+    }
+}
 
-        terminal::disable_raw_mode().context("Terminal error")?;
+/// `f maintenance`: `git maintenance`, `run` when given nothing else.
+fn cmd_maintenance(args: &[String]) -> ! {
+    let mut git_args = vec!["maintenance"];
+    if args.is_empty() {
+        git_args.push("run");
+    }
+    git_args.extend(args.iter().map(String::as_str));
+    if git_args[1] != "run" || exec::print_only() {
+        exec_git(&git_args)
+    }
+    let git_dir = git_status::get_git_dir().unwrap_or_else(|e| fail(e));
+    let lock = maintenance::Lock::take(&git_dir).unwrap_or_else(|e| fail(e));
+    let status = exec::status(Command::new("git").args(&git_args))
+        .unwrap_or_else(|e| fail(anyhow::anyhow!("Failed to run git: {}", e)));
+    drop(lock);
+    exec::exit(status)
+}
 
-        let selected = result?;
-        if let Some(file) = selected {
-            clear_screen();
-            display_actions(&file);
+fn cmd_batch(file: Option<&Path>, config: &Config) {
+    let script = match file {
+        Some(path) if path != Path::new("-") => std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Can't read {}: {}", path.display(), e)),
+        _ => std::io::read_to_string(std::io::stdin())
+            .map_err(|e| anyhow::anyhow!("Can't read stdin: {}", e)),
+    };
+    let lines = script
+        .and_then(|script| batch::parse(&script))
+        .unwrap_or_else(|e| fail(e));
+    if let Err(e) = batch::run(&lines, config) {
+        fail(e);
+    }
+}
 
-            terminal::enable_raw_mode().context("Terminal error")?;
+fn cmd_session(action: Option<SessionAction>, config: &Config) {
+    let sessions = session::Sessions::open().unwrap_or_else(|e| fail(e));
+    match action.unwrap_or(SessionAction::List) {
+        SessionAction::List => {
+            let names = sessions.list();
+            if names.is_empty() {
+                println!("{}", "No sessions recorded".dimmed());
+            }
+            let active = sessions.active();
+            for name in names {
+                if active.as_ref() == Some(&name) {
+                    println!("{} {}", name, "(recording)".yellow());
+                } else {
+                    println!("{}", name);
+                }
+            }
+        }
+        SessionAction::Record { name } => {
+            sessions.start(&name).unwrap_or_else(|e| fail(e));
+            println!(
+                "Recording session {}, run f session stop when done",
+                name.bold()
+            );
+        }
+        SessionAction::Stop => {
+            let name = sessions.stop().unwrap_or_else(|e| fail(e));
+            println!("Stopped recording {}", name.bold());
+            println!(
+                "{}",
+                format!("Edit {} to adjust it", sessions.path(&name).display()).dimmed()
+            );
+        }
+        SessionAction::Show { name } => {
+            print!("{}", sessions.read(&name).unwrap_or_else(|e| fail(e)));
+        }
+        SessionAction::Replay { name } => {
+            if sessions.active().as_ref() == Some(&name) {
+                fail(Error::Usage(format!(
+                    "Session {} is still being recorded, run f session stop first",
+                    name
+                )));
+            }
+            let lines = sessions
+                .read(&name)
+                .and_then(|script| batch::parse(&script))
+                .unwrap_or_else(|e| fail(e));
+            // Paths were recorded relative to the root, wherever f ran
+            let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+            if let Err(e) = std::env::set_current_dir(&root) {
+                fail(anyhow::anyhow!(
+                    "Cannot change to {}: {}",
+                    root.display(),
+                    e
+                ));
+            }
+            if let Err(e) = batch::run(&lines, config) {
+                fail(e);
+            }
+        }
+    }
+}
 
-            let action_result = (|| -> Result<Option<char>> {
-                loop {
-                    if event::poll(std::time::Duration::from_millis(100)).context("Event error")?
-                        && let Event::Key(key_event) = event::read().context("Read error")?
-                    {
-                        match key_event.code {
-                            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
-                            KeyCode::Char(c @ ('a' | 'd' | 's' | 'e')) => return Ok(Some(c)),
-                            _ => {}
-                        }
+fn cmd_sparse(action: Option<SparseAction>, config: &Config) {
+    let Some(cone) = sparse::Cone::load() else {
+        fail(Error::Usage(
+            "This work tree isn't a cone-mode sparse checkout".into(),
+        ));
+    };
+    match action.unwrap_or(SparseAction::List) {
+        SparseAction::List => {
+            if cone.dirs().is_empty() {
+                println!("{}", "Only files at the root are checked out".dimmed());
+            }
+            for dir in cone.dirs() {
+                println!("{}/", dir);
+            }
+        }
+        SparseAction::Add { target } => {
+            let (path, is_file) = sparse_target(&target, config);
+            let Some(dir) = sparse::dir_to_add(&path, is_file) else {
+                println!("{} is already checked out", path);
+                return;
+            };
+            match exec::status(Command::new("git").args(["sparse-checkout", "add", "--", &dir])) {
+                Ok(status) if status.success() => {
+                    if !exec::print_only() {
+                        println!("Checked out {}/", dir.green());
                     }
                 }
-            })();
+                Ok(status) => exec::exit(status),
+                Err(e) => fail(e),
+            }
+        }
+    }
+}
+
+/// The repo-relative path `target` names and whether it's a file: a listed
+/// file's ID or path, or else any path in the repository, which is usually
+/// one outside the checkout and so not on disk.
+fn sparse_target(target: &str, config: &Config) -> (String, bool) {
+    let files = daemon::get_files(&config.id_chars()).unwrap_or_else(|e| fail(e));
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let file =
+        find_file_by_path(&files, target, &cwd).or_else(|| match find_file_by_id(&files, target) {
+            IdMatch::Unique(f) => Some(f),
+            _ => None,
+        });
+    if let Some(file) = file {
+        return (file.rel_path, true);
+    }
+    let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+    let Some(path) = git_status::repo_path(target, &cwd, &root) else {
+        fail(Error::NotFound(format!(
+            "{} is outside the repository",
+            target
+        )));
+    };
+    // ls-files lists index entries whether checked out or not
+    let listed = git_status::git_output(&["ls-files", "-z", "--", &path]).unwrap_or_default();
+    let mut entries = listed.split('\0').filter(|e| !e.is_empty());
+    match entries.next() {
+        None => fail(Error::NotFound(format!(
+            "No file or directory {} in the index",
+            path
+        ))),
+        Some(first) => {
+            let is_file = first == path && entries.next().is_none();
+            (path, is_file)
+        }
+    }
+}
 
-            terminal::disable_raw_mode().context("Terminal error")?;
+fn cmd_init(dir: Option<&Path>) -> ! {
+    let mut cmd = Command::new("git");
+    cmd.arg("init");
+    if let Some(dir) = dir {
+        cmd.arg("--").arg(dir);
+    }
+    exec::exec(&mut cmd)
+}
 
-            if let Some(action) = action_result? {
-                println!();
-                let git_root = get_git_root()?;
-                std::env::set_current_dir(&git_root).ok();
+fn cmd_completions(shell: completions::Shell) {
+    print!("{}", completions::generate(shell, &Cli::command()));
+}
 
-                match action {
-                    'a' => {
-                        println!("Adding: {}", file.rel_path);
-                        let _ = Command::new("git")
-                            .args(["add", &file.abs_path.to_string_lossy()])
-                            .exec();
-                    }
-                    'd' => {
-                        let _ = Command::new("git")
-                            .args(["diff", "--", &file.abs_path.to_string_lossy()])
-                            .exec();
-                    }
-                    's' => {
-                        let _ = Command::new("git")
-                            .args(["diff", "--staged", "--", &file.abs_path.to_string_lossy()])
-                            .exec();
-                    }
-                    'e' => {
-                        let editor = config.editor();
-                        let _ = Command::new("sh")
-                            .arg("-c")
-                            .arg(format!("{} \"$1\"", editor))
-                            .arg("sh")
-                            .arg(&file.abs_path)
-                            .exec();
-                    }
-                    _ => {}
+fn cmd_shell_init(shell: completions::Shell) {
+    print!("{}", shell::init(shell, Cli::command().get_name()));
+}
+
+fn cmd_generate(target: GenerateTarget) {
+    let mut cli = Cli::command();
+    let (out_dir, files) = match target {
+        GenerateTarget::Completions { shell, out_dir } => {
+            let shells = match shell {
+                Some(shell) => vec![shell],
+                None => completions::Shell::value_variants().to_vec(),
+            };
+            let files = shells
+                .into_iter()
+                .map(|shell| {
+                    (
+                        completions::file_name(shell, cli.get_name()),
+                        completions::generate(shell, &cli),
+                    )
+                })
+                .collect();
+            (out_dir, files)
+        }
+        GenerateTarget::Man { out_dir } => {
+            cli.build();
+            (out_dir, man::pages(&cli))
+        }
+    };
+    if let Err(e) = std::fs::create_dir_all(&out_dir) {
+        fail(anyhow::anyhow!(
+            "Cannot create {}: {}",
+            out_dir.display(),
+            e
+        ));
+    }
+    for (name, content) in files {
+        let path = out_dir.join(name);
+        if let Err(e) = std::fs::write(&path, content) {
+            fail(anyhow::anyhow!("Cannot write {}: {}", path.display(), e));
+        }
+        println!("{}", path.display());
+    }
+}
+
+fn cmd_interactive(config: &Config) {
+    match interactive::run(config, &focused(filter::FileFilter::default())) {
+        Ok(()) => {}
+        Err(e) => fail(e),
+    }
+}
+
+fn cmd_tag(action: Option<TagAction>, config: &Config) -> ! {
+    use interactive::Pickable;
+    let tags = || tags::list(&config.id_chars()).unwrap_or_else(|e| fail(e));
+    match action {
+        None => {
+            let tags = tags();
+            if tags.is_empty() {
+                println!("{}", "No tags, f tag new <name> makes one".dimmed());
+                process::exit(0);
+            }
+            if exec::interactive() {
+                match interactive::pick("Tags", &tags, config).unwrap_or_else(|e| fail(e)) {
+                    Some(tag) => exec_git(&["show", &format!("refs/tags/{}", tag.name)]),
+                    None => process::exit(0),
                 }
             }
+            let width = tags.iter().map(|t| t.id.display.len()).max().unwrap_or(0);
+            for tag in &tags {
+                let id = format!("{:<width$}", tag.id.display);
+                println!("{}  {}", id.cyan(), tag.describe());
+            }
+            process::exit(0)
+        }
+        Some(TagAction::Show { tag }) => {
+            let tags = tags();
+            let tag = tags::find(&tags, &tag).unwrap_or_else(|e| fail(e));
+            exec_git(&["show", &format!("refs/tags/{}", tag.name)])
+        }
+        Some(TagAction::New {
+            name,
+            rev,
+            message,
+            sign,
+            lightweight,
+        }) => {
+            let args =
+                tags::create_args(&name, rev.as_deref(), message.as_deref(), sign, lightweight);
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            exec_git(&args)
+        }
+        Some(TagAction::Push {
+            tags: names,
+            remote,
+        }) => {
+            let tags = tags();
+            let picked: Vec<&tags::Tag> = match names.as_slice() {
+                [] => match tags.first() {
+                    Some(newest) => vec![newest],
+                    None => fail(Error::NotFound("No tags to push".into())),
+                },
+                names => names
+                    .iter()
+                    .map(|name| tags::find(&tags, name).unwrap_or_else(|e| fail(e)))
+                    .collect(),
+            };
+            let refs: Vec<String> = picked
+                .iter()
+                .map(|tag| format!("refs/tags/{}", tag.name))
+                .collect();
+            let mut args = vec!["push", remote.as_str()];
+            args.extend(refs.iter().map(String::as_str));
+            let hooks = hooks::installed(hooks::PUSH_HOOKS);
+            run_git_reporting(
+                &args,
+                &hooks,
+                config,
+                Some("Tags pushed"),
+                "Tag push failed",
+            )
+        }
+    }
+}
+
+fn cmd_paste_apply(clipboard: bool, dry_run: bool, config: &Config) {
+    use std::io::{IsTerminal, Read};
+    let diff = if clipboard {
+        clipboard::paste().unwrap_or_else(|e| fail(Error::NotFound(format!("{:#}", e))))
+    } else {
+        if std::io::stdin().is_terminal() {
+            fail(Error::Usage(
+                "Pipe a diff in, or pass --clipboard to read one from the clipboard".into(),
+            ));
+        }
+        let mut diff = String::new();
+        std::io::stdin()
+            .read_to_string(&mut diff)
+            .unwrap_or_else(|e| fail(anyhow::anyhow!("Failed to read stdin: {}", e)));
+        diff
+    };
+    if diff.trim().is_empty() {
+        fail(Error::Usage("The diff is empty".into()));
+    }
+    let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+    let changes = patch::preview(&diff, &root).unwrap_or_else(|e| fail(e));
+    if changes.is_empty() {
+        fail(Error::Usage("That doesn't change any files".into()));
+    }
+    let noun = if changes.len() == 1 { "file" } else { "files" };
+    println!(
+        "{}",
+        format!("── Changes {} {} ──", changes.len(), noun).yellow()
+    );
+    for change in &changes {
+        let stats = match (change.added, change.removed) {
+            (Some(added), Some(removed)) => format!(
+                " {}{}",
+                format!("+{}", added).green(),
+                format!("/-{}", removed).red()
+            ),
+            _ => format!(" {}", "binary".dimmed()),
+        };
+        println!("  {}{}", change.path, stats);
+    }
+    if dry_run || exec::print_only() {
+        return;
+    }
+    println!();
+    let status = patch::apply(&diff, &root).unwrap_or_else(|e| fail(e));
+    if !print_conflicts(config) && status.success() {
+        println!("Applied and staged");
+    }
+    if !status.success() {
+        exec::exit(status);
+    }
+}
+
+/// `f conflict`: the conflict stages merged again with diff3 markers, or
+/// laid out in columns with `--panes`, in the pager.
+fn cmd_conflict(id: Option<String>, panes: bool, config: &Config) {
+    let path = match id {
+        Some(id) => require_file(resolve_file(Some(id), config)).rel_path,
+        None => git_status::conflicted_paths()
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| fail(Error::NotFound("No conflicted files".into()))),
+    };
+    let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+    let git_dir = git_status::get_git_dir().unwrap_or_else(|e| fail(e));
+    let (regions, missing) = conflicts::merge(&path, &root, &git_dir).unwrap_or_else(|e| fail(e));
+    let count = conflicts::count(&regions);
+    let mut text = format!(
+        "{} {}\n",
+        path.bold(),
+        format!("({} conflict{})", count, if count == 1 { "" } else { "s" }).dimmed()
+    );
+    for (gone, note) in [
+        (missing.ours, "deleted on our side"),
+        (missing.theirs, "deleted on their side"),
+        (missing.base, "added on both sides, no base"),
+    ] {
+        if gone {
+            text.push_str(&format!("{}\n", note.yellow()));
+        }
+    }
+    text.push('\n');
+    text.push_str(&if panes {
+        conflicts::render_panes(&regions, width::terminal_width().unwrap_or(120))
+    } else {
+        conflicts::render_diff3(&regions)
+    });
+    pager::page(&text).unwrap_or_else(|e| fail(e));
+}
+
+fn cmd_archive(output: &Path, staged: bool, config: &Config) {
+    let format = archive::format_for(output).unwrap_or_else(|e| fail(e));
+    let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+    let git_dir = git_status::get_git_dir().unwrap_or_else(|e| fail(e));
+    let files = daemon::get_files(&config.id_chars()).unwrap_or_else(|e| fail(e));
+    let mut paths: Vec<&str> = Vec::new();
+    let mut deleted: Vec<&str> = Vec::new();
+    for file in &files {
+        if staged && file.file_type != FileType::Staged {
+            continue;
+        }
+        let gone = if staged {
+            file.status == 'D'
+        } else {
+            file.abs_path.symlink_metadata().is_err()
+        };
+        let list = if gone { &mut deleted } else { &mut paths };
+        if !list.contains(&file.rel_path.as_str()) {
+            list.push(&file.rel_path);
+        }
+    }
+    paths.sort();
+    if paths.is_empty() {
+        let what = if staged { "staged" } else { "changed" };
+        fail(Error::NotFound(format!("No {} files to archive", what)));
+    }
+    // git archive runs at the root, so the output is taken from here first
+    let absolute = std::env::current_dir()
+        .map(|cwd| cwd.join(output))
+        .unwrap_or_else(|_| output.to_path_buf());
+    let tree = archive::tree(&paths, staged, &root, &git_dir).unwrap_or_else(|e| fail(e));
+    archive::write(&tree, &paths, format, &absolute, &root).unwrap_or_else(|e| fail(e));
+    let noun = if paths.len() == 1 { "file" } else { "files" };
+    println!("Packed {} {} into {}", paths.len(), noun, output.display());
+    if !deleted.is_empty() {
+        println!(
+            "{}",
+            format!("Deleted, so left out: {}", deleted.join(" ")).dimmed()
+        );
+    }
+}
+
+fn cmd_remote(action: Option<RemoteAction>) {
+    match action {
+        Some(RemoteAction::Add { name, url }) => exec_git(&["remote", "add", &name, &url]),
+        Some(RemoteAction::Remove { name }) => exec_git(&["remote", "remove", &name]),
+        Some(RemoteAction::Rename { old, new }) => exec_git(&["remote", "rename", &old, &new]),
+        Some(RemoteAction::Prune { names }) => {
+            let names = match names.is_empty() {
+                false => names,
+                true => git_status::git_output(&["remote"])
+                    .map(|out| out.lines().map(str::to_string).collect())
+                    .unwrap_or_default(),
+            };
+            if names.is_empty() {
+                fail(Error::NotFound("No remotes to prune".into()));
+            }
+            let mut args = vec!["remote", "prune"];
+            args.extend(names.iter().map(String::as_str));
+            exec_git(&args)
+        }
+        None => {}
+    }
+    let git_dir = git_status::get_git_dir().unwrap_or_else(|e| fail(e));
+    let remotes = remotes::list(&git_dir).unwrap_or_else(|e| fail(e));
+    if remotes.is_empty() {
+        println!(
+            "{}",
+            "No remotes, f remote add <name> <url> adds one".dimmed()
+        );
+        return;
+    }
+    let width = remotes.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    for remote in &remotes {
+        let forge = forge::parse_remote_url(&remote.fetch_url)
+            .map(|repo| format!(" ({})", repo.forge.name()))
+            .unwrap_or_default();
+        println!(
+            "{}  {}{}",
+            format!("{:<width$}", remote.name).bold(),
+            remote.fetch_url,
+            forge.dimmed()
+        );
+        let indent = " ".repeat(width + 2);
+        if let Some(push) = &remote.push_url {
+            println!("{}{} {}", indent, "push".dimmed(), push);
+        }
+        let noun = if remote.branches == 1 {
+            "branch"
+        } else {
+            "branches"
+        };
+        let fetched = match remote.fetched {
+            Some(time) => format!("fetched {}", trash::ago(time)),
+            None => "never fetched".to_string(),
+        };
+        let fetched = if remotes::is_stale(remote.fetched) {
+            fetched.yellow()
         } else {
-            clear_screen();
+            fetched.dimmed()
+        };
+        println!(
+            "{}{}, {}",
+            indent,
+            format!("{} {}", remote.branches, noun).dimmed(),
+            fetched
+        );
+    }
+}
+
+fn cmd_config(action: Option<ConfigAction>, config: &Config) {
+    match action.unwrap_or(ConfigAction::Show) {
+        ConfigAction::Show => print!("{}", settings::render(&settings::effective(config))),
+    }
+}
+
+fn cmd_notes(action: Option<NotesAction>) {
+    let mut notes = notes::Notes::open().unwrap_or_else(|e| fail(e));
+    match action.unwrap_or(NotesAction::List) {
+        NotesAction::List => {
+            if notes.notes().is_empty() {
+                println!("{}", "No notes, take some with n in f review".dimmed());
+            }
+            for note in notes.notes() {
+                let place = match note.line {
+                    Some(line) => format!("{}:{}", note.path, line),
+                    None => note.path.clone(),
+                };
+                println!("{}  {}", place.cyan(), note.text);
+            }
+        }
+        NotesAction::Export { gh: false } => print!("{}", notes::markdown(notes.notes())),
+        NotesAction::Export { gh: true } => println!("{}", notes::github_review(notes.notes())),
+        NotesAction::Clear => {
+            let count = notes.notes().len();
+            notes.clear().unwrap_or_else(|e| fail(e));
+            println!(
+                "Deleted {} note{}",
+                count,
+                if count == 1 { "" } else { "s" }
+            );
+        }
+    }
+}
+
+fn is_file_id(s: &str, config: &Config) -> bool {
+    let id_chars = config.id_chars();
+    !s.is_empty() && s.chars().all(|c| id_chars.contains(&c))
+}
+
+/// Whether `arg` is the path or ID of a file, ambiguous IDs included. Unlike
+/// `resolve_file` it neither asks which file was meant nor records one, so
+/// it can tell an ID from a plugin's own arguments.
+fn names_file(arg: &str, config: &Config) -> bool {
+    let Ok(files) = daemon::get_files(&config.id_chars()) else {
+        return false;
+    };
+    let cwd = std::env::current_dir().unwrap_or_default();
+    find_file_by_path(&files, arg, &cwd).is_some()
+        || !matches!(find_file_by_id(&files, arg), IdMatch::NotFound)
+}
+
+fn cmd_external(args: &[String], config: &Config) {
+    // Clap doesn't parse inside the ID-first form, so `f df e --print` is
+    // picked out here
+    if args.iter().any(|a| a == "--print") {
+        exec::set_print_only(true);
+    }
+    let args: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|a| *a != "--print")
+        .collect();
+    let Some(&first) = args.first() else {
+        return;
+    };
+    let plugin = plugin::find(first);
+    // An ID that could also be a plugin's name only wins if it names a file
+    let id_first = is_file_id(first, config) && (plugin.is_none() || names_file(first, config));
+    if first == "-" {
+        return handle_previous(args.get(1).copied(), &args[args.len().min(2)..], config);
+    }
+    if let Some(name) = first.strip_prefix('@') {
+        return handle_bookmark(
+            name,
+            args.get(1).copied(),
+            &args[args.len().min(2)..],
+            config,
+        );
+    }
+    match plugin {
+        _ if id_first => handle_id_first(
+            first,
+            args.get(1).copied(),
+            &args[args.len().min(2)..],
+            config,
+        ),
+        Some(plugin) => run_plugin(&plugin, &args[1..], config),
+        None => fail(Error::Usage(format!(
+            "Unknown command: {} (and no f-{} on PATH)",
+            first, first
+        ))),
+    }
+}
+
+/// Runs an `f-<name>` plugin. When its first argument is the ID or path of
+/// a changed file, the file is passed on in `F_FILE` and `F_ID` too.
+fn run_plugin(plugin: &Path, args: &[&str], config: &Config) -> ! {
+    let file = args
+        .first()
+        .filter(|arg| names_file(arg, config))
+        .and_then(|arg| resolve_file(Some(arg.to_string()), config).ok());
+    let root = git_status::get_git_root().ok();
+    exec::exec(&mut plugin::command(
+        plugin,
+        args,
+        root.as_deref(),
+        file.as_ref(),
+    ))
+}
+
+/// True when run as `git-f`, i.e. as the `git f` external command.
+fn invoked_as_git_subcommand() -> bool {
+    std::env::args_os()
+        .next()
+        .and_then(|arg0| Path::new(&arg0).file_name().map(|n| n == "git-f"))
+        .unwrap_or(false)
+}
+
+fn parse_cli() -> Cli {
+    let mut command = Cli::command();
+    if invoked_as_git_subcommand() {
+        command = command.bin_name("git f");
+    }
+    Cli::from_arg_matches(&command.get_matches()).unwrap_or_else(|e| e.exit())
+}
+
+/// Exports `--git-dir` / `--work-tree` as GIT_DIR / GIT_WORK_TREE so every
+/// git (and editor) child process sees them, the same way git passes them on
+/// to its own external commands.
+fn export_path(var: &str, path: &Path) {
+    let abs = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    // SAFETY: called from main before any threads are spawned
+    unsafe { std::env::set_var(var, abs) };
+}
+
+/// `f <id> <action> [args]`. An action f doesn't know is tried as a
+/// plugin, so `f df blame` runs the same as `f blame df`.
+fn handle_id_first(id: &str, action: Option<&str>, rest: &[&str], config: &Config) {
+    let file = require_file(resolve_file(Some(id.to_string()), config));
+    file_action(file, id, action, rest, config)
+}
+
+/// Runs `action` on `file`, which `arg` named.
+fn file_action(file: GitFile, arg: &str, action: Option<&str>, rest: &[&str], config: &Config) {
+    match action {
+        Some("a" | "add") => stage_files(&[file]),
+        Some("u" | "unstage") => unstage_files(&[file]),
+        Some("discard") => discard_files(&[file], "discard"),
+        Some("chmod") => match rest.first().map(|mode| parse_exec_bit(mode)) {
+            Some(Ok(executable)) => chmod_files(&[file], executable),
+            _ => fail(Error::Usage("Usage: f <id> chmod +x|-x".into())),
+        },
+        Some("d" | "diff") => diff_files(&summarize_long(&[file], config), config),
+        Some("sd" | "staged-diff") => staged_diff(&file, config),
+        Some("e" | "v" | "edit") => edit_files(&[file], config),
+        Some("path") => print_path(&file, false),
+        Some(side @ ("ours" | "theirs")) => take_side(&file, side),
+        Some("both") => edit_conflict(&file, config),
+        Some("mergetool") => merge_conflict(&file),
+        Some(other) if let Some(plugin) = plugin::find(other) => {
+            run_plugin(&plugin, &[&[arg], rest].concat(), config)
         }
+        Some(other) => fail(Error::Usage(format!("Unknown action: {}", other))),
+        None => fail(Error::Usage(
+            "Action required (a, u, d, sd, e, path, discard, chmod, ours, theirs, both, mergetool)"
+                .into(),
+        )),
+    }
+}
+
+/// `f - <action> [args]`: the file an f command resolved last, found by
+/// path since its ID may have changed.
+fn handle_previous(action: Option<&str>, rest: &[&str], config: &Config) {
+    let history = history::History::open().unwrap_or_else(|e| fail(e));
+    let Some(path) = history.last() else {
+        fail(Error::NotFound(
+            "No previous file yet, f - names the last file an f command used".into(),
+        ));
+    };
+    path_action(
+        path,
+        action,
+        rest,
+        "was the previous file, but no longer exists",
+        config,
+    )
+}
+
+/// `f @<name> <action> [args]`. A bookmarked file with changes takes every
+/// action an ID does; an unchanged one can still be edited, printed or
+/// handed to a plugin.
+fn handle_bookmark(name: &str, action: Option<&str>, rest: &[&str], config: &Config) {
+    let bookmarks = bookmarks::Bookmarks::open().unwrap_or_else(|e| fail(e));
+    let path = bookmarks.get(name).unwrap_or_else(|e| fail(e));
+    let missing = format!("is bookmarked as {}, but no longer exists", name);
+    path_action(path, action, rest, &missing, config)
+}
 
-        Ok(())
+/// Runs `action` on the repo-relative `path`: any action when the file has
+/// changes, otherwise e, path and plugins. `missing` finishes the error for
+/// a file that isn't there.
+fn path_action(path: &str, action: Option<&str>, rest: &[&str], missing: &str, config: &Config) {
+    let root = git_status::get_git_root().unwrap_or_else(|e| fail(e));
+    let abs_path = root.join(path);
+    let files = daemon::get_files(&config.id_chars()).unwrap_or_else(|e| fail(e));
+    if let Some(file) = find_file_by_path(&files, &abs_path.to_string_lossy(), &root) {
+        file_action(file, path, action, rest, config);
+        return;
+    }
+    if !abs_path.exists() {
+        fail(Error::NotFound(format!("{} {}", path, missing)));
+    }
+    match action {
+        Some("e" | "v" | "edit") => {
+            record_session("edit", &[path]);
+            exec::exec(&mut config.editor_command(&[&abs_path.to_string_lossy()], None))
+        }
+        Some("path") => println!("{}", abs_path.display()),
+        Some(other) if let Some(plugin) = plugin::find(other) => {
+            run_plugin(&plugin, &[&[path], rest].concat(), config)
+        }
+        Some(other) => fail(Error::Usage(format!(
+            "{} has no changes, so only e and path apply (not {})",
+            path, other
+        ))),
+        None => fail(Error::Usage("Action required (e, path)".into())),
     }
 }
 
 fn main() {
     let config = Config::load();
-    let args: Vec<String> = std::env::args().collect();
+    theme::init(&config.theme);
+    messages::init(config.locale.as_deref());
+    let cli = parse_cli();
 
-    if args.len() >= 3 && is_file_id(&args[1], &config) {
-        let action = args.get(2).map(|s| s.as_str());
-        handle_id_first(&args[1], action, &config);
-        return;
+    // Like git, each -C is relative to the previous one and --git-dir to
+    // the directory they lead to
+    for dir in &cli.repo {
+        if let Err(e) = std::env::set_current_dir(dir) {
+            fail(Error::NotFound(format!(
+                "Cannot change to {}: {}",
+                dir.display(),
+                e
+            )));
+        }
+    }
+    if let Some(git_dir) = &cli.git_dir {
+        export_path("GIT_DIR", git_dir);
     }
+    if let Some(work_tree) = &cli.work_tree {
+        export_path("GIT_WORK_TREE", work_tree);
+    }
+    exec::set_print_only(cli.print);
+    exec::set_non_interactive(
+        cli.non_interactive || {
+            use std::io::IsTerminal;
+            !std::io::stdout().is_terminal()
+        },
+    );
+    exec::set_timeout(
+        cli.timeout
+            .or(config.timeout)
+            .map(std::time::Duration::from_secs),
+    );
+    display::set_root_relative(cli.root_relative);
+    anydir::set_enabled(cli.any_dir);
 
-    let cli = Cli::parse();
+    logging::init(cli.verbose, config.log_path().as_deref());
 
-    if cli.verbose {
-        env_logger::Builder::new()
-            .filter_level(log::LevelFilter::Debug)
-            .init();
+    // Tells the commit-msg hook from f init-hooks that the message checks
+    // were skipped on purpose
+    if let Some(
+        Commands::Commit {
+            no_verify_msg: true,
+            ..
+        }
+        | Commands::Reword {
+            no_verify_msg: true,
+            ..
+        },
+    ) = &cli.command
+    {
+        // SAFETY: called from main before any threads are spawned
+        unsafe { std::env::set_var(hooks::NO_VERIFY_MSG, "1") };
     }
 
     match cli.command {
-        None | Some(Commands::List) => cmd_list(&config),
-        Some(Commands::Diff { id }) => cmd_diff(id, &config),
+        None => cmd_list(
+            false,
+            false,
+            display::ListFormat::Human,
+            false,
+            &display::Limits {
+                limit: config.list.limit,
+                collapse: glob::compile_all(&config.collapse),
+            },
+            &focused(filter::FileFilter::default()),
+            &config,
+        ),
+        Some(Commands::List {
+            porcelain,
+            nul,
+            format,
+            ignored,
+            limit,
+            all,
+            filter,
+        }) => {
+            // --all shows what the limit and collapse patterns would hide
+            let limits = if all {
+                display::Limits::default()
+            } else {
+                display::Limits {
+                    limit: limit.or(config.list.limit),
+                    collapse: glob::compile_all(&config.collapse),
+                }
+            };
+            cmd_list(
+                porcelain,
+                nul,
+                format,
+                ignored,
+                &limits,
+                &filter.build(&config),
+                &config,
+            )
+        }
+        Some(Commands::Diff {
+            id,
+            range,
+            split,
+            summary,
+            hunk,
+            full,
+        }) => {
+            if split {
+                cmd_split_diff(id, &config)
+            } else if summary || !hunk.is_empty() {
+                cmd_summary_diff(id, &hunk, &config)
+            } else {
+                cmd_diff(id, range.as_deref(), full, &config)
+            }
+        }
         Some(Commands::StagedDiff { id }) => cmd_staged_diff(id, &config),
         Some(Commands::Add { id }) => cmd_add(id, &config),
-        Some(Commands::Edit { id }) => cmd_edit(id, &config),
-        Some(Commands::Commit { message }) => cmd_commit(message),
-        Some(Commands::Push) => cmd_push(),
-        Some(Commands::Watch { interval }) => cmd_watch(interval),
+        Some(Commands::Unstage { id }) => cmd_unstage(id, &config),
+        Some(Commands::Chmod { id, mode }) => cmd_chmod(id, mode, &config),
+        Some(Commands::Discard { id }) => cmd_discard(id, &config),
+        Some(Commands::Edit { id, all, staged }) => cmd_edit(id, all, staged, &config),
+        Some(Commands::Path { id, relative }) => cmd_path(id, relative, &config),
+        Some(Commands::Cd { id }) => cmd_cd(id, &config),
+        Some(Commands::Exec { id, command }) => cmd_exec(id, &command, &config),
+        Some(Commands::Fmt { id }) => cmd_tool(tools::Tool::Fmt, id, &config),
+        Some(Commands::Test { id }) => cmd_tool(tools::Tool::Test, id, &config),
+        Some(Commands::Check) => cmd_check(&config),
+        Some(Commands::InitHooks { force }) => cmd_init_hooks(force),
+        Some(Commands::Hook { name, args }) => cmd_hook(&name, &args, &config),
+        Some(Commands::Unignore { target }) => cmd_unignore(&target, &config),
+        Some(Commands::Focus { paths, clear }) => cmd_focus(&paths, clear),
+        Some(Commands::Bookmark {
+            target,
+            name,
+            delete,
+        }) => cmd_bookmark(target, name, delete, &config),
+        Some(Commands::Bookmarks) => cmd_bookmarks(&config),
+        Some(Commands::History) => cmd_history(&config),
+        Some(Commands::Copy { id, diff }) => cmd_copy(id, diff, &config),
+        Some(Commands::Url {
+            id,
+            open,
+            line,
+            remote,
+        }) => cmd_url(id, open, line, &remote, &config),
+        Some(Commands::Commit {
+            no_verify_msg,
+            no_verify,
+            sign,
+            by_dir,
+            by_owner,
+            message,
+            ..
+        }) if by_dir || by_owner => commit_grouped(
+            if by_dir {
+                commit::GroupBy::Dir
+            } else {
+                commit::GroupBy::Owner
+            },
+            message,
+            !no_verify_msg,
+            CommitFlags {
+                no_verify,
+                sign: sign || config.commit.sign,
+            },
+            &config,
+        ),
+        Some(Commands::Commit {
+            edit,
+            no_verify_msg,
+            no_verify,
+            sign,
+            message_flag,
+            message,
+            ..
+        }) => cmd_commit(
+            message,
+            message_flag,
+            edit,
+            !no_verify_msg,
+            CommitFlags {
+                no_verify,
+                sign: sign || config.commit.sign,
+            },
+            &config,
+        ),
+        Some(Commands::Push {
+            no_verify,
+            signed,
+            force,
+            dry_run,
+            set_upstream,
+            yes,
+        }) => cmd_push(
+            PushFlags {
+                no_verify,
+                signed,
+                force,
+                dry_run,
+                set_upstream,
+                yes,
+            },
+            &config,
+        ),
+        Some(Commands::Watch {
+            interval,
+            events,
+            repos,
+            filter,
+        }) => cmd_watch(
+            interval,
+            events,
+            repos.as_deref(),
+            &filter.build(&config),
+            &config,
+        ),
+        Some(Commands::Reword {
+            no_verify_msg,
+            message,
+        }) => cmd_reword(message, !no_verify_msg, &config),
+        Some(Commands::Rebase {
+            interactive: Some(base),
+            ..
+        }) => cmd_rebase_interactive(&base, &config),
+        Some(Commands::Rebase {
+            cont, skip, abort, ..
+        }) => cmd_rebase(cont, skip, abort, &config),
+        Some(Commands::Absorb { dry_run, rebase }) => cmd_absorb(dry_run, rebase, &config),
+        Some(Commands::CherryPick { branch, id, limit }) => {
+            let range = format!("HEAD..{}", branch);
+            let title = format!("Cherry-pick from {}", branch);
+            cmd_pick_commit("cherry-pick", &[&range], &title, id, limit, &config)
+        }
+        Some(Commands::Revert { id, limit }) => {
+            cmd_pick_commit("revert", &["HEAD"], "Revert", id, limit, &config)
+        }
+        Some(Commands::Commits { ids, limit }) => cmd_commits(&ids, limit, &config),
+        Some(Commands::Tag { action }) => cmd_tag(action, &config),
+        Some(Commands::Remote { action }) => cmd_remote(action),
+        Some(Commands::Conflict { id, panes }) => cmd_conflict(id, panes, &config),
+        Some(Commands::Archive { output, staged }) => cmd_archive(&output, staged, &config),
+        Some(Commands::PasteApply { clipboard, dry_run }) => {
+            cmd_paste_apply(clipboard, dry_run, &config)
+        }
+        Some(Commands::Clean) => cmd_clean(&config),
+        Some(Commands::Trash { action }) => cmd_trash(action),
+        Some(Commands::Batch { file }) => cmd_batch(file.as_deref(), &config),
+        Some(Commands::Session { action }) => cmd_session(action, &config),
+        Some(Commands::Sparse { action }) => cmd_sparse(action, &config),
+        Some(Commands::Undo { list }) => cmd_undo(list),
+        Some(Commands::Outgoing) => cmd_outgoing(),
+        Some(Commands::Incoming) => cmd_incoming(),
+        Some(Commands::Pr { action }) => cmd_pr(action, &config),
         Some(Commands::Interactive) => cmd_interactive(&config),
+        Some(Commands::Review) => interactive::run_review(&config).unwrap_or_else(|e| fail(e)),
+        Some(Commands::Config { action }) => cmd_config(action, &config),
+        Some(Commands::Notes { action }) => cmd_notes(action),
+        Some(Commands::Prompt { color }) => cmd_prompt(color),
+        Some(Commands::State { format }) => cmd_state(format),
+        Some(Commands::Serve) => cmd_serve(&config),
+        Some(Commands::Mcp { read_only }) => cmd_mcp(read_only, &config),
+        Some(Commands::Daemon) => cmd_daemon(&config),
+        Some(Commands::Init { dir }) => cmd_init(dir.as_deref()),
+        Some(Commands::Doctor) => cmd_doctor(&config),
+        Some(Commands::Maintenance { args }) => cmd_maintenance(&args),
+        Some(Commands::Completions { shell }) => cmd_completions(shell),
+        Some(Commands::ShellInit { shell }) => cmd_shell_init(shell),
+        Some(Commands::Generate { target }) => cmd_generate(target),
+        Some(Commands::External(args)) => cmd_external(&args, &config),
     }
 }
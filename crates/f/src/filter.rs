@@ -0,0 +1,208 @@
+use crate::git_status::{FileType, GitFile};
+use crate::glob::Glob;
+use crate::owners::Owners;
+use anyhow::Result;
+
+/// Narrows a file list by path globs and staging state. Shared by `f list`
+/// and `f watch` so both accept the same arguments. `f focus` patterns
+/// and `--mine` narrow it further.
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    patterns: Vec<String>,
+    globs: Vec<Glob>,
+    types: Vec<FileType>,
+    focus_patterns: Vec<String>,
+    focus: Vec<Glob>,
+    /// CODEOWNERS and who counts as me, for `--mine`
+    mine: Option<(Owners, Vec<String>)>,
+}
+
+impl FileFilter {
+    /// `staged` keeps only staged files and `unstaged` keeps modified and
+    /// untracked ones; with neither flag every state is kept. Patterns are
+    /// matched against repo-relative paths and a file passes if any matches.
+    pub fn new(patterns: &[String], staged: bool, unstaged: bool) -> Result<Self> {
+        let globs = patterns
+            .iter()
+            .map(|p| Glob::new(p))
+            .collect::<Result<_>>()?;
+        let mut types = Vec::new();
+        if staged {
+            types.push(FileType::Staged);
+        }
+        if unstaged {
+            types.extend([FileType::Unstaged, FileType::Untracked]);
+        }
+        Ok(Self {
+            patterns: patterns.to_vec(),
+            globs,
+            types,
+            ..Self::default()
+        })
+    }
+
+    /// Also requires a path to match one of the focus `patterns`.
+    pub fn with_focus(mut self, patterns: &[String]) -> Result<Self> {
+        self.focus = patterns
+            .iter()
+            .map(|p| Glob::new(p))
+            .collect::<Result<_>>()?;
+        self.focus_patterns = patterns.to_vec();
+        Ok(self)
+    }
+
+    /// Also requires a path to be owned by one of `me` in `owners`.
+    pub fn with_owner(mut self, owners: Owners, me: Vec<String>) -> Self {
+        self.mine = Some((owners, me));
+        self
+    }
+
+    pub fn focus(&self) -> &[String] {
+        &self.focus_patterns
+    }
+
+    /// How many of `files` pass everything but the focus.
+    pub fn hidden_by_focus(&self, files: &[GitFile]) -> usize {
+        files
+            .iter()
+            .filter(|f| self.matches_unfocused(f) && !self.in_focus(f))
+            .count()
+    }
+
+    /// A short description for headers, e.g. `src/** (staged)`.
+    pub fn describe(&self) -> String {
+        let mut parts = self.patterns.clone();
+        match self.types.as_slice() {
+            [] => {}
+            [FileType::Staged] => parts.push("(staged)".to_string()),
+            [_, _] => parts.push("(unstaged)".to_string()),
+            _ => {}
+        }
+        if self.mine.is_some() {
+            parts.push("(mine)".to_string());
+        }
+        if !self.focus_patterns.is_empty() {
+            parts.push(format!("focus {}", self.focus_patterns.join(" ")));
+        }
+        parts.join(" ")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.globs.is_empty()
+            && self.types.is_empty()
+            && self.focus.is_empty()
+            && self.mine.is_none()
+    }
+
+    pub fn matches(&self, file: &GitFile) -> bool {
+        self.matches_unfocused(file) && self.in_focus(file)
+    }
+
+    fn matches_unfocused(&self, file: &GitFile) -> bool {
+        (self.types.is_empty() || self.types.contains(&file.file_type))
+            && (self.globs.is_empty() || self.globs.iter().any(|g| g.matches(&file.rel_path)))
+            && self
+                .mine
+                .as_ref()
+                .is_none_or(|(owners, me)| owners.owned_by(&file.rel_path, me))
+    }
+
+    fn in_focus(&self, file: &GitFile) -> bool {
+        self.focus.is_empty() || self.focus.iter().any(|g| g.matches(&file.rel_path))
+    }
+
+    pub fn apply(&self, files: Vec<GitFile>) -> Vec<GitFile> {
+        if self.is_empty() {
+            return files;
+        }
+        files.into_iter().filter(|f| self.matches(f)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_status::StableId;
+    use std::path::PathBuf;
+
+    fn file(path: &str, file_type: FileType) -> GitFile {
+        GitFile {
+            mtime: 0,
+            rel_path: path.to_string(),
+            abs_path: PathBuf::from(path),
+            file_type,
+            stable_id: StableId {
+                display: "aa".to_string(),
+                full_hash: "aaaa".to_string(),
+            },
+            diff_stats: None,
+            lfs_size: None,
+            mode_change: None,
+            status: 'M',
+        }
+    }
+
+    fn paths(filter: &FileFilter, files: Vec<GitFile>) -> Vec<String> {
+        filter
+            .apply(files)
+            .into_iter()
+            .map(|f| f.rel_path)
+            .collect()
+    }
+
+    #[test]
+    fn empty_filter_keeps_everything() {
+        let filter = FileFilter::new(&[], false, false).unwrap();
+        assert!(filter.is_empty());
+        let files = vec![file("a", FileType::Staged), file("b", FileType::Untracked)];
+        assert_eq!(paths(&filter, files), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn globs_and_types_combine() {
+        let filter = FileFilter::new(&["src/**".to_string()], false, true).unwrap();
+        let files = vec![
+            file("src/a.rs", FileType::Unstaged),
+            file("src/b.rs", FileType::Staged),
+            file("src/c.rs", FileType::Untracked),
+            file("README.md", FileType::Unstaged),
+        ];
+        assert_eq!(paths(&filter, files), vec!["src/a.rs", "src/c.rs"]);
+        assert_eq!(filter.describe(), "src/** (unstaged)");
+    }
+
+    #[test]
+    fn staged_only() {
+        let filter = FileFilter::new(&[], true, false).unwrap();
+        let files = vec![file("a", FileType::Staged), file("b", FileType::Unstaged)];
+        assert_eq!(paths(&filter, files), vec!["a"]);
+    }
+
+    #[test]
+    fn focus_narrows_further_and_counts_what_it_hides() {
+        let filter = FileFilter::new(&["**/*.rs".to_string()], false, false)
+            .unwrap()
+            .with_focus(&["crates/f/**".to_string()])
+            .unwrap();
+        let files = vec![
+            file("crates/f/src/a.rs", FileType::Unstaged),
+            file("crates/g/src/b.rs", FileType::Unstaged),
+            file("crates/g/README.md", FileType::Unstaged),
+        ];
+        assert_eq!(filter.hidden_by_focus(&files), 1);
+        assert_eq!(paths(&filter, files), vec!["crates/f/src/a.rs"]);
+        assert_eq!(filter.describe(), "**/*.rs focus crates/f/**");
+    }
+
+    #[test]
+    fn mine_keeps_files_i_own() {
+        let owners = Owners::parse("* @acme/core\n/docs/ @alice\n");
+        let filter = FileFilter::default().with_owner(owners, vec!["@alice".to_string()]);
+        let files = vec![
+            file("docs/guide.md", FileType::Unstaged),
+            file("src/lib.rs", FileType::Unstaged),
+        ];
+        assert_eq!(paths(&filter, files), vec!["docs/guide.md"]);
+        assert_eq!(filter.describe(), "(mine)");
+    }
+}
@@ -0,0 +1,132 @@
+//! `f focus`: path patterns kept in `.git/f/focus` that narrow `f`, `f i`
+//! and `f w` to part of the repository until cleared, for monorepos where
+//! changes elsewhere are someone else's.
+
+use crate::error::Error;
+use crate::json::{self, Value};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct Focus {
+    path: PathBuf,
+    /// Repo-relative globs, a file passing if any matches
+    patterns: Vec<String>,
+}
+
+impl Focus {
+    pub fn open() -> Result<Focus> {
+        let path = crate::git_status::get_git_dir()?.join("f").join("focus");
+        let patterns = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| json::parse(&content).ok())
+            .map(|value| from_json(&value))
+            .unwrap_or_default();
+        Ok(Focus { path, patterns })
+    }
+
+    /// The patterns in effect, none outside a repository or when unset.
+    pub fn current() -> Vec<String> {
+        Focus::open().map(|f| f.patterns).unwrap_or_default()
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    pub fn set(&mut self, patterns: Vec<String>) -> Result<()> {
+        self.patterns = patterns;
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let value = Value::Array(
+            self.patterns
+                .iter()
+                .map(|p| Value::from(p.as_str()))
+                .collect(),
+        );
+        fs::write(&self.path, format!("{}\n", value))
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.patterns.clear();
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to remove {}", self.path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn from_json(value: &Value) -> Vec<String> {
+    let Value::Array(items) = value else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| Some(item.as_str()?.to_string()))
+        .collect()
+}
+
+/// The glob for `arg` as typed in `cwd`: a directory covers everything
+/// under it, a file just itself, and a glob is taken as repo-relative.
+pub fn pattern(arg: &str, cwd: &Path, root: &Path) -> Result<String> {
+    if arg.contains(['*', '?', '[']) {
+        return Ok(arg.to_string());
+    }
+    let Some(rel) = crate::git_status::repo_path(arg, cwd, root) else {
+        return Err(Error::Usage(format!("{} is outside the repository", arg)).into());
+    };
+    if rel.is_empty() {
+        return Err(Error::Usage(
+            "That's the whole repository, f focus --clear shows everything".into(),
+        )
+        .into());
+    }
+    if arg.ends_with('/') || root.join(&rel).is_dir() {
+        Ok(format!("{}/**", rel.trim_end_matches('/')))
+    } else {
+        Ok(rel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern() {
+        let root = std::env::temp_dir().join(format!("f-focus-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("crates/f/src")).unwrap();
+        fs::write(root.join("crates/f/Cargo.toml"), "").unwrap();
+
+        assert_eq!(pattern("crates/f", &root, &root).unwrap(), "crates/f/**");
+        assert_eq!(
+            pattern("src/", &root.join("crates/f"), &root).unwrap(),
+            "crates/f/src/**"
+        );
+        assert_eq!(
+            pattern("Cargo.toml", &root.join("crates/f"), &root).unwrap(),
+            "crates/f/Cargo.toml"
+        );
+        // Not there yet, but named as a directory
+        assert_eq!(pattern("docs/", &root, &root).unwrap(), "docs/**");
+        assert_eq!(
+            pattern("web/*.ts", &root.join("crates"), &root).unwrap(),
+            "web/*.ts"
+        );
+        assert!(pattern(".", &root, &root).is_err());
+        assert!(pattern("../elsewhere", &root, &root).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_from_json() {
+        let value = json::parse(r#"["src/**", 3, "docs/**"]"#).unwrap();
+        assert_eq!(from_json(&value), ["src/**", "docs/**"]);
+        assert!(from_json(&json::parse("{}").unwrap()).is_empty());
+    }
+}
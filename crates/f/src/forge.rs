@@ -0,0 +1,157 @@
+/// Code hosting services whose web URLs f knows how to build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Forge {
+    GitHub,
+    GitLab,
+    Bitbucket,
+}
+
+impl Forge {
+    pub fn name(self) -> &'static str {
+        match self {
+            Forge::GitHub => "GitHub",
+            Forge::GitLab => "GitLab",
+            Forge::Bitbucket => "Bitbucket",
+        }
+    }
+}
+
+/// A repository on a forge, parsed from a remote URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteRepo {
+    pub forge: Forge,
+    pub host: String,
+    /// `owner/repo`, or `group/subgroup/repo` on GitLab
+    pub path: String,
+}
+
+/// Parses scp-style (`git@host:owner/repo.git`), `ssh://` and `http(s)://`
+/// remote URLs. The forge is inferred from the host name, which also covers
+/// self-hosted instances like `gitlab.example.com`.
+pub fn parse_remote_url(url: &str) -> Option<RemoteRepo> {
+    let url = url.trim();
+    let (host, path) = if let Some((_, rest)) = url.split_once("://") {
+        let (authority, path) = rest.split_once('/')?;
+        (authority, path)
+    } else {
+        // scp-like syntax: [user@]host:path
+        let (authority, path) = url.split_once(':')?;
+        (authority, path)
+    };
+
+    // Drop user info and port
+    let host = host.rsplit('@').next()?;
+    let host = host.split(':').next()?.to_lowercase();
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path).to_string();
+    if host.is_empty() || !path.contains('/') {
+        return None;
+    }
+
+    let forge = if host.contains("github") {
+        Forge::GitHub
+    } else if host.contains("gitlab") {
+        Forge::GitLab
+    } else if host.contains("bitbucket") {
+        Forge::Bitbucket
+    } else {
+        return None;
+    };
+
+    Some(RemoteRepo { forge, host, path })
+}
+
+impl RemoteRepo {
+    /// Web URL for `file` (repo-relative) at `git_ref`, optionally anchored
+    /// to `line`.
+    pub fn file_url(&self, git_ref: &str, file: &str, line: Option<u32>) -> String {
+        let file = encode_path(file);
+        let git_ref = encode_path(git_ref);
+        let base = format!("https://{}/{}", self.host, self.path);
+        let (url, anchor) = match self.forge {
+            Forge::GitHub => (format!("{}/blob/{}/{}", base, git_ref, file), "L"),
+            Forge::GitLab => (format!("{}/-/blob/{}/{}", base, git_ref, file), "L"),
+            Forge::Bitbucket => (format!("{}/src/{}/{}", base, git_ref, file), "lines-"),
+        };
+        match line {
+            Some(n) => format!("{}#{}{}", url, anchor, n),
+            None => url,
+        }
+    }
+}
+
+/// Percent-encodes everything except unreserved characters and `/`.
+fn encode_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~' | b'/') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(forge: Forge, host: &str, path: &str) -> RemoteRepo {
+        RemoteRepo {
+            forge,
+            host: host.to_string(),
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_scp_style() {
+        assert_eq!(
+            parse_remote_url("git@github.com:davidbeesley/f.git"),
+            Some(repo(Forge::GitHub, "github.com", "davidbeesley/f"))
+        );
+    }
+
+    #[test]
+    fn parse_https_with_user_and_no_suffix() {
+        assert_eq!(
+            parse_remote_url("https://someone@bitbucket.org/team/project"),
+            Some(repo(Forge::Bitbucket, "bitbucket.org", "team/project"))
+        );
+    }
+
+    #[test]
+    fn parse_ssh_with_port_and_subgroups() {
+        assert_eq!(
+            parse_remote_url("ssh://git@gitlab.example.com:2222/group/sub/repo.git"),
+            Some(repo(Forge::GitLab, "gitlab.example.com", "group/sub/repo"))
+        );
+    }
+
+    #[test]
+    fn parse_rejects_unknown_hosts_and_local_paths() {
+        assert_eq!(parse_remote_url("https://example.com/a/b.git"), None);
+        assert_eq!(parse_remote_url("/srv/git/repo.git"), None);
+        assert_eq!(parse_remote_url("git@github.com:repo"), None);
+    }
+
+    #[test]
+    fn file_url_per_forge() {
+        let gh = repo(Forge::GitHub, "github.com", "o/r");
+        assert_eq!(
+            gh.file_url("main", "src/my file.rs", Some(12)),
+            "https://github.com/o/r/blob/main/src/my%20file.rs#L12"
+        );
+        let gl = repo(Forge::GitLab, "gitlab.com", "g/s/r");
+        assert_eq!(
+            gl.file_url("feature/x", "a.rs", None),
+            "https://gitlab.com/g/s/r/-/blob/feature/x/a.rs"
+        );
+        let bb = repo(Forge::Bitbucket, "bitbucket.org", "t/p");
+        assert_eq!(
+            bb.file_url("main", "a.rs", Some(3)),
+            "https://bitbucket.org/t/p/src/main/a.rs#lines-3"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// A gitignore-style path pattern, matched against repo-relative paths.
+///
+/// `*` and `?` stay within one path segment, `**` spans directories, and
+/// `[abc]` / `[!abc]` are character classes. A pattern without a `/` matches
+/// the file name at any depth (`*.lock`), one ending in `/` matches
+/// everything below that directory (`vendor/`), and anything else is
+/// anchored at the repo root (`src/**/*.rs`).
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pattern: String,
+    regex: Regex,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let regex = Regex::new(&to_regex(pattern))
+            .with_context(|| format!("Invalid glob pattern '{}'", pattern))?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex,
+        })
+    }
+
+    /// The pattern as written.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        self.regex.is_match(path)
+    }
+}
+
+/// Compiles every pattern, skipping (and warning about) invalid ones so a
+/// single typo in the config doesn't disable the whole feature.
+pub fn compile_all(patterns: &[String]) -> Vec<Glob> {
+    patterns
+        .iter()
+        .filter_map(|p| match Glob::new(p) {
+            Ok(g) => Some(g),
+            Err(e) => {
+                eprintln!("Warning: {:#}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+pub fn any_match(globs: &[Glob], path: &str) -> bool {
+    globs.iter().any(|g| g.matches(path))
+}
+
+fn to_regex(pattern: &str) -> String {
+    let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+    let (body, dir_only) = match pattern.strip_suffix('/') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let anchored = body.contains('/');
+    let body = body.strip_prefix('/').unwrap_or(body);
+
+    let mut re = String::from("^");
+    if !anchored {
+        re.push_str("(?:.*/)?");
+    }
+
+    let chars: Vec<char> = body.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    re.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    re.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            '[' => {
+                if let Some(end) = chars[i + 1..].iter().position(|&c| c == ']') {
+                    let class: String = chars[i + 1..i + 1 + end].iter().collect();
+                    let class = class
+                        .strip_prefix('!')
+                        .map_or(class.clone(), |c| format!("^{}", c));
+                    re.push('[');
+                    re.push_str(&class.replace('\\', "\\\\"));
+                    re.push(']');
+                    i += end + 2;
+                    continue;
+                }
+                re.push_str("\\[");
+            }
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+
+    if dir_only {
+        re.push_str("/.*");
+    } else {
+        // A pattern naming a directory also covers everything inside it
+        re.push_str("(?:/.*)?");
+    }
+    re.push('$');
+    re
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(pattern: &str, path: &str) -> bool {
+        Glob::new(pattern).unwrap().matches(path)
+    }
+
+    #[test]
+    fn basename_patterns_match_at_any_depth() {
+        assert!(m("*.lock", "Cargo.lock"));
+        assert!(m("*.lock", "web/yarn.lock"));
+        assert!(!m("*.lock", "lockfile"));
+    }
+
+    #[test]
+    fn double_star_spans_directories() {
+        assert!(m("generated/**", "generated/a/b.rs"));
+        assert!(!m("generated/**", "src/generated/a.rs"));
+        assert!(m("src/**/*.rs", "src/main.rs"));
+        assert!(m("src/**/*.rs", "src/a/b/c.rs"));
+        assert!(!m("src/**/*.rs", "src/a/b/c.txt"));
+        assert!(m("**/fixtures/*", "a/b/fixtures/x.json"));
+    }
+
+    #[test]
+    fn single_star_stays_in_segment() {
+        assert!(m("src/*.rs", "src/main.rs"));
+        assert!(!m("src/*.rs", "src/a/main.rs"));
+    }
+
+    #[test]
+    fn directory_patterns_cover_contents() {
+        assert!(m("vendor/", "vendor/lib/x.c"));
+        assert!(!m("vendor/", "vendor"));
+        assert!(m("target", "target/debug/f"));
+        assert!(m("crates/f", "crates/f/src/f.rs"));
+        assert!(!m("crates/f", "crates/foo/src/lib.rs"));
+    }
+
+    #[test]
+    fn classes_and_question_marks() {
+        assert!(m("file?.txt", "file1.txt"));
+        assert!(!m("file?.txt", "file10.txt"));
+        assert!(m("[ab].rs", "a.rs"));
+        assert!(m("[!ab].rs", "c.rs"));
+        assert!(!m("[!ab].rs", "a.rs"));
+    }
+
+    #[test]
+    fn literal_characters_are_escaped() {
+        assert!(m("a+b (1).txt", "a+b (1).txt"));
+        assert!(!m("a.b", "axb"));
+    }
+}
@@ -0,0 +1,101 @@
+//! The files f has resolved lately, most recent first, kept in
+//! `.git/f/history` so `f -` can name the last one again and `f history`
+//! can list them with their current IDs.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+/// How many files are remembered.
+pub const LIMIT: usize = 50;
+
+pub struct History {
+    path: PathBuf,
+    /// Repo-relative paths, most recent first
+    entries: Vec<String>,
+}
+
+impl History {
+    pub fn open() -> Result<History> {
+        let path = crate::git_status::get_git_dir()?.join("f").join("history");
+        let entries = fs::read_to_string(&path)
+            .map(|content| parse(&content))
+            .unwrap_or_default();
+        Ok(History { path, entries })
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// The file resolved before this run.
+    pub fn last(&self) -> Option<&str> {
+        self.entries.first().map(String::as_str)
+    }
+
+    /// Moves `path` to the front, saving unless it was already there.
+    pub fn record(&mut self, path: &str) -> Result<()> {
+        if self.last() == Some(path) {
+            return Ok(());
+        }
+        push(&mut self.entries, path);
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut content = self.entries.join("\n");
+        content.push('\n');
+        fs::write(&self.path, content)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+fn parse(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter(|line| !line.is_empty())
+        .take(LIMIT)
+        .map(str::to_string)
+        .collect()
+}
+
+fn push(entries: &mut Vec<String>, path: &str) {
+    entries.retain(|p| p != path);
+    entries.insert(0, path.to_string());
+    entries.truncate(LIMIT);
+}
+
+/// Notes that `path` was just resolved. Under `--print` nothing is
+/// written, and a history that can't be saved is only logged, since it
+/// never matters to the command at hand.
+pub fn record(path: &str) {
+    if crate::exec::print_only() {
+        return;
+    }
+    let recorded = History::open().and_then(|mut history| history.record(path));
+    if let Err(e) = recorded {
+        log::debug!("can't record {} in the history: {:#}", path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_moves_to_front_and_caps() {
+        let mut entries = parse("b.rs\na.rs\n\nc.rs\n");
+        assert_eq!(entries, ["b.rs", "a.rs", "c.rs"]);
+        push(&mut entries, "a.rs");
+        assert_eq!(entries, ["a.rs", "b.rs", "c.rs"]);
+
+        for i in 0..LIMIT {
+            push(&mut entries, &format!("{}.rs", i));
+        }
+        assert_eq!(entries.len(), LIMIT);
+        assert_eq!(entries[0], format!("{}.rs", LIMIT - 1));
+    }
+}
@@ -0,0 +1,176 @@
+//! git's hooks: which are installed, and `f init-hooks`, which installs
+//! thin ones calling back into f so raw `git commit` gets its checks too.
+
+use crate::git_status::git_output;
+use anyhow::{Context, Result};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// Hooks git runs during `git commit`, in order.
+pub const COMMIT_HOOKS: &[&str] = &["pre-commit", "prepare-commit-msg", "commit-msg"];
+pub const PUSH_HOOKS: &[&str] = &["pre-push"];
+
+/// The hooks `f init-hooks` installs.
+pub const F_HOOKS: &[&str] = &["pre-commit", "commit-msg"];
+
+/// Set by f while it commits with `--no-verify-msg`, so the `commit-msg`
+/// hook it installed doesn't check the message after all.
+pub const NO_VERIFY_MSG: &str = "F_NO_VERIFY_MSG";
+
+/// The line marking a hook as f's, so installing again replaces it but
+/// never someone else's.
+const MARKER: &str = "# Installed by f init-hooks";
+
+/// Where git looks for hooks: `.git/hooks`, or `core.hooksPath`.
+fn dir() -> Option<PathBuf> {
+    git_output(&["rev-parse", "--git-path", "hooks"]).map(PathBuf::from)
+}
+
+/// The subset of `names` installed as executable hooks, honoring
+/// `core.hooksPath`. Git silently skips non-executable hook files, so they
+/// are skipped here too.
+pub fn installed(names: &[&'static str]) -> Vec<&'static str> {
+    let Some(dir) = dir() else {
+        return vec![];
+    };
+    names
+        .iter()
+        .copied()
+        .filter(|name| {
+            std::fs::metadata(dir.join(name))
+                .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        })
+        .collect()
+}
+
+/// The script for hook `name`: a call to `f hook`, through the `f` that
+/// installed it or else the one on `PATH`. A teammate without f commits
+/// unchecked rather than not at all.
+pub fn script(name: &str, f: &Path) -> String {
+    format!(
+        "#!/bin/sh\n\
+         {MARKER}: the same checks as f commit, for commits made with git too\n\
+         f={}\n\
+         [ -x \"$f\" ] || f=$(command -v f) || {{\n    \
+         echo \"f not found, skipping its {name} checks\" >&2\n    \
+         exit 0\n\
+         }}\n\
+         exec \"$f\" hook {name} \"$@\"\n",
+        crate::exec::quote(&f.to_string_lossy()),
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Installed {
+    New,
+    /// An earlier `f init-hooks` hook, rewritten
+    Updated,
+    /// Someone else's hook, left alone
+    Kept,
+    /// Someone else's hook, replaced by `--force`
+    Replaced,
+}
+
+/// Writes f's hooks, keeping any other hook already there unless `force`.
+pub fn install(force: bool) -> Result<Vec<(&'static str, Installed)>> {
+    let dir = dir().context("Not a git repository")?;
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let f = std::env::current_exe().context("Can't find the f binary")?;
+    let mut results = Vec::new();
+    for &name in F_HOOKS {
+        let path = dir.join(name);
+        let outcome = match fs::read_to_string(&path) {
+            Ok(existing) if existing.contains(MARKER) => Installed::Updated,
+            Ok(_) if !force => {
+                results.push((name, Installed::Kept));
+                continue;
+            }
+            Ok(_) => Installed::Replaced,
+            Err(_) => Installed::New,
+        };
+        fs::write(&path, script(name, &f))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        results.push((name, outcome));
+    }
+    Ok(results)
+}
+
+/// A copy of what's being committed, for `pre-commit` to check: the
+/// index git is committing, which is its own temporary one under
+/// `commit -a` or `commit <paths>`, checked out away from the work tree.
+/// Removed when dropped.
+pub struct StagedTree {
+    pub root: PathBuf,
+}
+
+impl StagedTree {
+    pub fn check_out() -> Result<StagedTree> {
+        let root = crate::git_status::get_git_dir()?
+            .join("f")
+            .join(format!("pre-commit-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root)?;
+        let tree = StagedTree { root };
+        // `--prefix` is taken literally, so the slash makes it a directory
+        let prefix = format!("--prefix={}/", tree.root.display());
+        let output = crate::exec::run_output(std::process::Command::new("git").args([
+            "checkout-index",
+            "--all",
+            &prefix,
+        ]))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "git checkout-index failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(tree)
+    }
+}
+
+impl Drop for StagedTree {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// The message git will record from the `commit-msg` hook's file: without
+/// comment lines, or anything below the scissors line of `commit -v`.
+pub fn message_from_file(text: &str, comment_char: char) -> String {
+    let scissors = format!(
+        "{} ------------------------ >8 ------------------------",
+        comment_char
+    );
+    let lines: Vec<&str> = text
+        .lines()
+        .take_while(|line| *line != scissors)
+        .filter(|line| !line.starts_with(comment_char))
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_from_file() {
+        let text = "Fix the parser\n\nIt dropped quotes.\n# Please enter the commit message\n\
+                    # ------------------------ >8 ------------------------\ndiff --git a/x b/x\n";
+        assert_eq!(
+            message_from_file(text, '#'),
+            "Fix the parser\n\nIt dropped quotes."
+        );
+        assert_eq!(message_from_file("; note\nAdd x\n", ';'), "Add x");
+    }
+
+    #[test]
+    fn script_falls_back_to_path() {
+        let script = script("commit-msg", Path::new("/opt/f bin/f"));
+        assert!(script.starts_with("#!/bin/sh\n# Installed by f init-hooks"));
+        assert!(script.contains("f='/opt/f bin/f'\n"));
+        assert!(script.ends_with("exec \"$f\" hook commit-msg \"$@\"\n"));
+    }
+}
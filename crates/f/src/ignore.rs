@@ -0,0 +1,245 @@
+//! Ignored files for `f list --ignored`, and `f unignore`, which finds the
+//! rule ignoring one and removes it, or overrides it when it ignores more
+//! than that file.
+
+use crate::error::Error;
+use crate::git_status::{StableId, generate_ids};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// An ignored file, or a directory (ending in `/`) whose contents all are,
+/// as `git status --ignored` collapses them.
+#[derive(Debug, Clone)]
+pub struct Ignored {
+    /// Repo-relative
+    pub path: String,
+    pub id: StableId,
+}
+
+pub fn list(id_chars: &[char]) -> Result<Vec<Ignored>> {
+    let output = crate::exec::run_output(Command::new("git").args([
+        "status",
+        "--porcelain=v1",
+        "-z",
+        "--ignored",
+    ]))
+    .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git status failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let paths = parse_status(&String::from_utf8_lossy(&output.stdout));
+    Ok(paths
+        .iter()
+        .zip(generate_ids(&paths, id_chars))
+        .map(|(path, (display, full_hash))| Ignored {
+            path: path.clone(),
+            id: StableId { display, full_hash },
+        })
+        .collect())
+}
+
+fn parse_status(output: &str) -> Vec<String> {
+    let mut paths: Vec<String> = output
+        .split('\0')
+        .filter_map(|entry| entry.strip_prefix("!! "))
+        .map(str::to_string)
+        .collect();
+    paths.sort();
+    paths
+}
+
+pub fn find_by_id<'a>(ignored: &'a [Ignored], id: &str) -> Result<&'a Ignored> {
+    let matches: Vec<_> = ignored.iter().filter(|i| i.id.matches(id)).collect();
+    match matches.as_slice() {
+        [entry] => Ok(entry),
+        [] => Err(Error::NotFound(format!(
+            "No ignored file with ID '{}' (f list --ignored shows them)",
+            id
+        ))
+        .into()),
+        _ => Err(Error::AmbiguousId {
+            id: id.to_string(),
+            matches: matches.len(),
+            what: "ignored files",
+        }
+        .into()),
+    }
+}
+
+/// The line of an ignore file that ignores a path, from `git check-ignore`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub source: PathBuf,
+    /// 1-based
+    pub line: usize,
+    pub pattern: String,
+}
+
+/// The rule ignoring the repo-relative `path`. `root` is the work tree,
+/// which `git check-ignore` reports sources relative to.
+pub fn rule_for(path: &str, root: &Path) -> Result<Rule> {
+    let output = crate::exec::run_output(
+        Command::new("git")
+            .args(["check-ignore", "-v", "--no-index", "--", path])
+            .current_dir(root),
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    parse_check_ignore(&String::from_utf8_lossy(&output.stdout), root)
+        .ok_or_else(|| Error::NotFound(format!("{} isn't ignored", path)).into())
+}
+
+/// `<source>:<line>:<pattern>\t<path>`. A negated pattern means the path
+/// was matched but isn't ignored.
+fn parse_check_ignore(output: &str, root: &Path) -> Option<Rule> {
+    let (rule, _) = output.lines().next()?.split_once('\t')?;
+    let mut fields = rule.splitn(3, ':');
+    let (source, line, pattern) = (fields.next()?, fields.next()?, fields.next()?);
+    if source.is_empty() || pattern.starts_with('!') {
+        return None;
+    }
+    Some(Rule {
+        source: root.join(source),
+        line: line.parse().ok()?,
+        pattern: pattern.to_string(),
+    })
+}
+
+/// What `f unignore` did to an ignore file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    /// The rule was only for this file, so it's gone
+    Removed(String),
+    /// The rule covers other files too, so a negation was added after it
+    Negated(String),
+}
+
+/// `content` of the ignore file with `rule` no longer ignoring `path`,
+/// which is relative to the directory of the ignore file.
+pub fn unignore(content: &str, rule: &Rule, path: &str) -> (String, Edit) {
+    let path = path.trim_end_matches('/');
+    // Without a slash before the end, a pattern matches at any depth, so
+    // removing it would unignore more than `path`
+    let body = rule.pattern.trim_end_matches('/');
+    let anchored = body.contains('/');
+    if anchored && body.trim_start_matches('/') == path {
+        let content: String = content
+            .split_inclusive('\n')
+            .enumerate()
+            .filter(|(i, _)| i + 1 != rule.line)
+            .map(|(_, line)| line)
+            .collect();
+        return (content, Edit::Removed(rule.pattern.clone()));
+    }
+    let negation = format!("!/{}", path);
+    let mut content = content.to_string();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&negation);
+    content.push('\n');
+    (content, Edit::Negated(negation))
+}
+
+/// Stops `path` (repo-relative) being ignored, editing the ignore file in
+/// the repository or `.git/info/exclude` that ignores it. The user's global
+/// excludes file is left alone.
+pub fn unignore_path(path: &str, root: &Path, git_dir: &Path) -> Result<(PathBuf, Edit)> {
+    let rule = rule_for(path, root)?;
+    let canonical = |p: &Path| fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+    let (source, root, git_dir) = (canonical(&rule.source), canonical(root), canonical(git_dir));
+    let in_repo = source.starts_with(&root) || source.starts_with(&git_dir);
+    if !in_repo {
+        return Err(Error::Usage(format!(
+            "{} is ignored by {} in {}, outside the repository, so f leaves it alone",
+            path,
+            rule.pattern,
+            rule.source.display()
+        ))
+        .into());
+    }
+    // .gitignore patterns are relative to their directory, info/exclude's
+    // to the root
+    let base = match source.strip_prefix(&root) {
+        Ok(rel) if !source.starts_with(&git_dir) => rel.parent().unwrap_or(Path::new("")),
+        _ => Path::new(""),
+    };
+    let relative = Path::new(path)
+        .strip_prefix(base)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string());
+    let content = fs::read_to_string(&source)
+        .with_context(|| format!("Failed to read {}", source.display()))?;
+    let (content, edit) = unignore(&content, &rule, &relative);
+    fs::write(&source, content).with_context(|| format!("Failed to write {}", source.display()))?;
+    Ok((source, edit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(line: usize, pattern: &str) -> Rule {
+        Rule {
+            source: PathBuf::from("/repo/.gitignore"),
+            line,
+            pattern: pattern.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let output = "?? new.txt\0!! target/\0 M src/lib.rs\0!! .env\0";
+        assert_eq!(parse_status(output), [".env", "target/"]);
+    }
+
+    #[test]
+    fn test_parse_check_ignore() {
+        let root = Path::new("/repo");
+        assert_eq!(
+            parse_check_ignore("sub/.gitignore:3:*.o\tsub/a.o\n", root),
+            Some(Rule {
+                source: PathBuf::from("/repo/sub/.gitignore"),
+                line: 3,
+                pattern: "*.o".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_check_ignore(".gitignore:2:!keep.o\tkeep.o\n", root),
+            None
+        );
+        assert_eq!(parse_check_ignore("", root), None);
+    }
+
+    #[test]
+    fn unignore_removes_a_rule_for_just_that_path() {
+        let content = "*.log\n/target/\nnode_modules\n";
+        assert_eq!(
+            unignore(content, &rule(2, "/target/"), "target/"),
+            (
+                "*.log\nnode_modules\n".to_string(),
+                Edit::Removed("/target/".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn unignore_negates_a_rule_for_other_files_too() {
+        // "target" would also ignore crates/a/target
+        assert_eq!(
+            unignore("target\n", &rule(1, "target"), "target/").1,
+            Edit::Negated("!/target".to_string())
+        );
+        assert_eq!(
+            unignore("*.log", &rule(1, "*.log"), "logs/keep.log"),
+            (
+                "*.log\n!/logs/keep.log\n".to_string(),
+                Edit::Negated("!/logs/keep.log".to_string())
+            )
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! `f d` on a changed image: each version's format, dimensions and size,
+//! previews in terminals with an image protocol, or a configured tool.
+
+use crate::exec::quote;
+use crate::git_status::{FileType, GitFile};
+use crate::lfs::format_size;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// File extensions taken for images. SVGs are text, and diff as such.
+const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp"];
+
+pub fn is_image(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// What the header of an image says about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Info {
+    pub format: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The format and dimensions of `bytes`, read from the header, for PNG,
+/// JPEG, GIF, WebP and BMP.
+pub fn info(bytes: &[u8]) -> Option<Info> {
+    let at = |start: usize, len: usize| bytes.get(start..start + len);
+    let be16 = |start| at(start, 2).map(|b| u16::from_be_bytes([b[0], b[1]]) as u32);
+    let le16 = |start| at(start, 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as u32);
+    let be32 = |start| at(start, 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+    let le32 = |start| at(start, 4).map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    let le24 = |start| at(start, 3).map(|b| b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16);
+    let info = |format, width, height| {
+        Some(Info {
+            format,
+            width,
+            height,
+        })
+    };
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return info("PNG", be32(16)?, be32(20)?);
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return info("GIF", le16(6)?, le16(8)?);
+    }
+    if bytes.starts_with(b"BM") {
+        // Negative heights are stored top-down
+        return info("BMP", le32(18)?.unsigned_abs(), le32(22)?.unsigned_abs());
+    }
+    if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        return match bytes.get(12..16)? {
+            b"VP8 " => info("WebP", le16(26)? & 0x3fff, le16(28)? & 0x3fff),
+            b"VP8L" => {
+                let b = bytes.get(21..25)?;
+                let width = 1 + ((b[1] as u32 & 0x3f) << 8 | b[0] as u32);
+                let height = 1
+                    + ((b[3] as u32 & 0xf) << 10 | (b[2] as u32) << 2 | (b[1] as u32 & 0xc0) >> 6);
+                info("WebP", width, height)
+            }
+            b"VP8X" => info("WebP", 1 + le24(24)?, 1 + le24(27)?),
+            _ => None,
+        };
+    }
+    if bytes.starts_with(b"\xff\xd8") {
+        // The size is in the start-of-frame segment, after any metadata
+        let mut segment = 2;
+        while *bytes.get(segment)? == 0xff {
+            let marker = *bytes.get(segment + 1)?;
+            if matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+                return info("JPEG", be16(segment + 7)?, be16(segment + 5)?);
+            }
+            segment += 2 + be16(segment + 2)? as usize;
+        }
+    }
+    None
+}
+
+/// The version of `file` before and after its change: the index and the
+/// work tree for an unstaged change, HEAD and the index for a staged one.
+/// A side is `None` where the file didn't or doesn't exist.
+pub fn versions(file: &GitFile) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
+    let blob = |rev: String| {
+        let output =
+            crate::exec::run_output(Command::new("git").args(["cat-file", "blob", &rev])).ok()?;
+        output.status.success().then_some(output.stdout)
+    };
+    let index = || blob(format!(":{}", file.rel_path));
+    match file.file_type {
+        FileType::Untracked => (None, fs::read(&file.abs_path).ok()),
+        FileType::Unstaged => (index(), fs::read(&file.abs_path).ok()),
+        FileType::Staged => (blob(format!("HEAD:{}", file.rel_path)), index()),
+    }
+}
+
+fn describe(bytes: &[u8]) -> String {
+    let size = format_size(bytes.len() as u64);
+    match info(bytes) {
+        Some(info) => format!("{} {}×{}, {}", info.format, info.width, info.height, size),
+        None => size,
+    }
+}
+
+/// One line saying how the image changed, old → new, with the change in
+/// size.
+pub fn summary(old: Option<&[u8]>, new: Option<&[u8]>) -> String {
+    match (old, new) {
+        (Some(old), Some(new)) => {
+            let (old_len, new_len) = (old.len() as u64, new.len() as u64);
+            let delta = if new_len >= old_len {
+                format!("+{}", format_size(new_len - old_len)).green()
+            } else {
+                format!("-{}", format_size(old_len - new_len)).red()
+            };
+            format!("{} → {} ({})", describe(old), describe(new), delta)
+        }
+        (None, Some(new)) => format!("{} {}", "new".green(), describe(new)),
+        (Some(old), None) => format!("{} {}", "deleted".red(), describe(old)),
+        (None, None) => "unreadable".dimmed().to_string(),
+    }
+}
+
+/// The image protocols f can draw with. Sixel needs the pixels themselves,
+/// which would mean decoding every format, so sixel-only terminals get the
+/// summary alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// kitty's graphics protocol, also in Ghostty and Konsole; PNG only
+    Kitty,
+    /// iTerm2's inline images, also in WezTerm
+    Iterm,
+}
+
+impl Protocol {
+    /// The protocol of the terminal f runs in, from the variables it sets.
+    pub fn detect() -> Option<Protocol> {
+        let var = |name| std::env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        if std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || std::env::var_os("KONSOLE_VERSION").is_some()
+        {
+            return Some(Protocol::Kitty);
+        }
+        match var("TERM_PROGRAM").as_str() {
+            "iTerm.app" | "WezTerm" => Some(Protocol::Iterm),
+            _ => None,
+        }
+    }
+}
+
+/// The escape sequence drawing `bytes` `rows` lines tall, or `None` when
+/// `protocol` can't show its format.
+pub fn preview(bytes: &[u8], protocol: Protocol, rows: u32) -> Option<String> {
+    let data = crate::clipboard::base64_encode(bytes);
+    match protocol {
+        Protocol::Kitty => {
+            if info(bytes)?.format != "PNG" {
+                return None;
+            }
+            // Sent in chunks of at most 4096 bytes, each but the last
+            // saying more follow
+            let chunks: Vec<&str> = data
+                .as_bytes()
+                .chunks(4096)
+                .map(|c| std::str::from_utf8(c).unwrap_or_default())
+                .collect();
+            let mut out = String::new();
+            for (i, chunk) in chunks.iter().enumerate() {
+                let more = u8::from(i + 1 < chunks.len());
+                if i == 0 {
+                    out.push_str(&format!(
+                        "\x1b_Gf=100,a=T,r={},m={};{}\x1b\\",
+                        rows, more, chunk
+                    ));
+                } else {
+                    out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+                }
+            }
+            Some(out)
+        }
+        Protocol::Iterm => Some(format!(
+            "\x1b]1337;File=inline=1;size={};height={};preserveAspectRatio=1:{}\x07",
+            bytes.len(),
+            rows,
+            data
+        )),
+    }
+}
+
+/// A `diff_tool` command, and the copies of the two versions it compares,
+/// removed once it's dropped.
+pub struct ToolCommand {
+    pub command: Command,
+    paths: [PathBuf; 2],
+}
+
+impl Drop for ToolCommand {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// The `diff_tool` command comparing `old` and `new`, which are written to
+/// `.git/f/image-diff` under the file's extension so the tool can tell the
+/// format.
+pub fn tool_command(template: &str, file: &GitFile, old: &[u8], new: &[u8]) -> Result<ToolCommand> {
+    let dir = crate::git_status::get_git_dir()?
+        .join("f")
+        .join("image-diff");
+    fs::create_dir_all(&dir)?;
+    let ext = Path::new(&file.rel_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("img");
+    let old_path = dir.join(format!("old.{}", ext));
+    let new_path = dir.join(format!("new.{}", ext));
+    for (path, bytes) in [(&old_path, old), (&new_path, new)] {
+        fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    let script = template
+        .replace("{old}", &quote(&old_path.to_string_lossy()))
+        .replace("{new}", &quote(&new_path.to_string_lossy()));
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(script);
+    Ok(ToolCommand {
+        command,
+        paths: [old_path, new_path],
+    })
+}
+
+#[cfg(test)]
+#[path = "../tests/support/png.rs"]
+mod png;
+
+#[cfg(test)]
+mod tests {
+    use super::png::png;
+    use super::*;
+
+    #[test]
+    fn test_is_image() {
+        assert!(is_image("assets/logo.PNG"));
+        assert!(is_image("photo.jpeg"));
+        assert!(!is_image("icon.svg"));
+        assert!(!is_image("png"));
+    }
+
+    #[test]
+    fn test_info() {
+        let info_of = |bytes: &[u8]| info(bytes).map(|i| (i.format, i.width, i.height));
+        assert_eq!(info_of(&png(640, 480)), Some(("PNG", 640, 480)));
+        assert_eq!(info_of(b"GIF89a\x20\x00\x10\x00"), Some(("GIF", 32, 16)));
+        // SOI, an APP0 segment of 4 bytes, then SOF0 with 100x200
+        let jpeg = b"\xff\xd8\xff\xe0\x00\x04ab\xff\xc0\x00\x11\x08\x00\xc8\x00\x64";
+        assert_eq!(info_of(jpeg), Some(("JPEG", 100, 200)));
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\0\0\0\0\0\0\0\0".to_vec();
+        webp.extend([0x7f, 0x02, 0x00, 0xdf, 0x01, 0x00]);
+        assert_eq!(info_of(&webp), Some(("WebP", 640, 480)));
+        assert_eq!(info_of(b"not an image"), None);
+    }
+
+    #[test]
+    fn test_summary() {
+        let _colors = crate::snapshot::colors(false);
+        let (small, large) = (png(16, 16), [png(32, 32), vec![0; 1500]].concat());
+        assert_eq!(
+            summary(Some(&small), Some(&large)),
+            "PNG 16×16, 29 B → PNG 32×32, 1.5 kB (+1.5 kB)"
+        );
+        assert_eq!(summary(None, Some(&small)), "new PNG 16×16, 29 B");
+    }
+
+    #[test]
+    fn test_preview() {
+        let image = png(1, 1);
+        let kitty = preview(&image, Protocol::Kitty, 8).unwrap();
+        assert!(kitty.starts_with("\x1b_Gf=100,a=T,r=8,m=0;iVBORw0KGgo"));
+        assert!(kitty.ends_with("\x1b\\"));
+        assert_eq!(preview(b"GIF89a\x01\x00\x01\x00", Protocol::Kitty, 8), None);
+        let iterm = preview(&image, Protocol::Iterm, 8).unwrap();
+        assert!(iterm.starts_with("\x1b]1337;File=inline=1;size=29;height=8;"));
+    }
+}
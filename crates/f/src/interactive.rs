@@ -0,0 +1,1496 @@
+//! The interactive picker: choose a file by typing its key, then an
+//! action, or one or more entries of a list such as commits. Frames are
+//! built as strings and keys come through `EventSource`, so tests can
+//! drive the picker without a terminal.
+//!
+//! Review mode is driven the same way: it steps through the hunks of every
+//! changed file, taking notes on the way. So is the todo list editor of
+//! `f rebase -i`.
+
+use crate::collapse::{self, Rollup};
+use crate::commits::Commit;
+use crate::config::Config;
+use crate::filter::FileFilter;
+use crate::git_status::{FileType, GitFile, file_diff, get_git_root};
+use crate::glob::Glob;
+use crate::messages::{Msg, t, tf};
+use crate::notes::Note;
+use crate::rebase::{Step, Verb};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, execute};
+use std::fmt::Write as _;
+use std::io::{Write, stdout};
+use std::process::Command;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Duration;
+
+/// How long a burst of work tree changes can hold back a refresh of the
+/// file list, and how often keys are polled while waiting on both.
+const DEBOUNCE_QUIET: Duration = Duration::from_millis(100);
+const DEBOUNCE_MAX: Duration = Duration::from_secs(1);
+const KEY_POLL: Duration = Duration::from_millis(50);
+
+fn generate_keys(n: usize, id_chars: &[char]) -> Vec<String> {
+    if n == 0 {
+        return vec![];
+    }
+    let mut length = 1;
+    while id_chars.len().pow(length as u32) < n {
+        length += 1;
+    }
+
+    (0..n)
+        .map(|i| {
+            let mut key = String::new();
+            let mut idx = i;
+            for _ in 0..length {
+                key.insert(0, id_chars[idx % id_chars.len()]);
+                idx /= id_chars.len();
+            }
+            key
+        })
+        .collect()
+}
+
+/// Clears the screen and draws `frame`. Raw mode disables the implicit
+/// carriage return on newline, so each line gets one.
+fn show(frame: &str) {
+    let mut stdout = stdout();
+    let _ = execute!(
+        stdout,
+        terminal::Clear(ClearType::All),
+        cursor::MoveTo(0, 0)
+    );
+    for line in frame.lines() {
+        let _ = write!(stdout, "{}\r\n", line);
+    }
+    let _ = stdout.flush();
+}
+
+fn clear_screen() {
+    show("");
+}
+
+fn files_frame(files: &[GitFile], keys: &[String], prefix: &str, rollups: &[Rollup]) -> String {
+    let matching: Vec<_> = keys
+        .iter()
+        .zip(files.iter())
+        .filter(|(k, _)| k.starts_with(prefix))
+        .collect();
+
+    let theme = crate::theme::get();
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{}",
+        theme
+            .heading
+            .paint(&format!("── {} ──", t(Msg::SelectFile)))
+    );
+    if !prefix.is_empty() {
+        let _ = writeln!(out, "  {} {}", t(Msg::Prefix), theme.id.paint(prefix));
+    }
+
+    let mut last_type: Option<FileType> = None;
+    for (key, file) in &matching {
+        if last_type != Some(file.file_type) {
+            if last_type.is_some() {
+                let _ = writeln!(out);
+            }
+            let header = match file.file_type {
+                FileType::Unstaged => theme.unstaged.paint(t(Msg::Unstaged)),
+                FileType::Untracked => theme.untracked.paint(t(Msg::Untracked)),
+                FileType::Staged => theme.staged.paint(t(Msg::Staged)),
+            };
+            let _ = writeln!(out, "── {} ──", header);
+            last_type = Some(file.file_type);
+        }
+
+        let typed = &key[..prefix.len()];
+        let remaining = &key[prefix.len()..];
+        let path = crate::display::fit_path(crate::display::display_path(file), 2 + key.len() + 2);
+        let _ = writeln!(
+            out,
+            "  {}{}  {}",
+            theme.id.paint(typed).bold(),
+            theme.id.paint(remaining),
+            path
+        );
+    }
+    if !rollups.is_empty() {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "── {} ──", theme.muted.paint(t(Msg::Collapsed)));
+        for rollup in rollups {
+            let _ = writeln!(out, "  {}", theme.muted.paint(&rollup.row()));
+        }
+    }
+    let _ = writeln!(out);
+    if !rollups.is_empty() {
+        let _ = writeln!(
+            out,
+            "  {}   {}",
+            theme.muted.paint("+"),
+            t(Msg::ShowCollapsed)
+        );
+    }
+    let _ = writeln!(out, "  {}   {}", theme.muted.paint("q"), t(Msg::Quit));
+    out
+}
+
+fn actions_frame(file: &GitFile, custom: &[(String, std::path::PathBuf)]) -> String {
+    let theme = crate::theme::get();
+    let mut out = String::new();
+    let _ = writeln!(out);
+    let _ = writeln!(
+        out,
+        "{} {}",
+        theme.added.paint(t(Msg::Selected)),
+        crate::display::fit_path(crate::display::display_path(file), 10)
+    );
+    let _ = writeln!(
+        out,
+        "{}",
+        theme.heading.paint(&format!("── {} ──", t(Msg::Action)))
+    );
+    let _ = writeln!(out, "  {}  {}", theme.id.paint("a"), t(Msg::Add));
+    let _ = writeln!(out, "  {}  {}", theme.id.paint("d"), t(Msg::Diff));
+    let _ = writeln!(out, "  {}  {}", theme.id.paint("s"), t(Msg::StagedDiff));
+    let _ = writeln!(out, "  {}  {}", theme.id.paint("e"), t(Msg::Edit));
+    for (key, (name, _)) in ('1'..='9').zip(custom) {
+        let _ = writeln!(out, "  {}  {}", theme.id.paint(&key.to_string()), name);
+    }
+    let _ = writeln!(out, "  {}  {}", theme.muted.paint("q"), t(Msg::Quit));
+    out
+}
+
+/// A row of a list picker: the stable key typed to choose it, and the text
+/// shown after the key. Keys of one list must be prefix-free, as IDs from
+/// `generate_ids` are.
+pub trait Pickable {
+    fn key(&self) -> &str;
+    fn describe(&self) -> String;
+}
+
+impl Pickable for Commit {
+    fn key(&self) -> &str {
+        &self.id.display
+    }
+
+    fn describe(&self) -> String {
+        let theme = crate::theme::get();
+        format!(
+            "{} {} {}",
+            theme.heading.paint(&self.short),
+            self.subject,
+            theme
+                .muted
+                .paint(&format!("({}, {})", self.date, self.author))
+        )
+    }
+}
+
+/// The list narrowed to keys starting with `prefix`, with `marked` rows
+/// ticked. `multi` adds the hint for finishing a selection early.
+fn list_frame<T: Pickable>(
+    title: &str,
+    items: &[T],
+    prefix: &str,
+    marked: &[usize],
+    multi: bool,
+) -> String {
+    let theme = crate::theme::get();
+    let mut out = String::new();
+    let _ = writeln!(out, "{}", theme.heading.paint(&format!("── {} ──", title)));
+    if !prefix.is_empty() {
+        let _ = writeln!(out, "  {} {}", t(Msg::Prefix), theme.id.paint(prefix));
+    }
+    let width = items.iter().map(|i| i.key().len()).max().unwrap_or(0);
+    for (i, item) in items.iter().enumerate() {
+        if !item.key().starts_with(prefix) {
+            continue;
+        }
+        let mark = if marked.contains(&i) {
+            theme.added.paint("✓").to_string()
+        } else {
+            " ".to_string()
+        };
+        let remaining = format!("{:<width$}", &item.key()[prefix.len()..]);
+        let _ = writeln!(
+            out,
+            " {}{}{}  {}",
+            mark,
+            theme.id.paint(prefix).bold(),
+            theme.id.paint(&remaining),
+            item.describe()
+        );
+    }
+    let _ = writeln!(out);
+    if multi {
+        let _ = writeln!(out, "  {}  {}", theme.muted.paint("enter"), t(Msg::Done));
+    }
+    let _ = writeln!(out, "  {}   {}", theme.muted.paint("q"), t(Msg::Quit));
+    out
+}
+
+/// What the file picker waits on: a key, or a change to the work tree or
+/// index that makes the list stale.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    Key(KeyEvent),
+    Changed,
+}
+
+/// Where the picker's key presses come from: the terminal, or a script in
+/// tests.
+pub trait EventSource {
+    /// Waits for the next key press, or `None` when there will be no more.
+    fn next_key(&mut self) -> Result<Option<KeyEvent>>;
+
+    /// Waits for a key press or a change. Sources that don't watch the
+    /// work tree only ever return keys.
+    fn next_input(&mut self) -> Result<Option<Input>> {
+        Ok(self.next_key()?.map(Input::Key))
+    }
+}
+
+/// Where the picker's frames go.
+pub trait Renderer {
+    /// Replaces whatever was drawn before with `frame`.
+    fn draw(&mut self, frame: &str);
+}
+
+/// Why the picker won't start without a terminal to take over.
+pub const NEEDS_TERMINAL: &str = "This needs a terminal, and f is running non-interactively (--non-interactive, or stdout isn't a terminal)";
+
+/// Key presses from the terminal, which stays in raw mode until this is
+/// dropped, and the work tree changes from `watcher` when there is one.
+struct Keyboard {
+    changes: Option<Receiver<()>>,
+}
+
+impl Keyboard {
+    fn new() -> Result<Keyboard> {
+        if !crate::exec::interactive() {
+            return Err(crate::error::Error::Usage(NEEDS_TERMINAL.into()).into());
+        }
+        terminal::enable_raw_mode().context("Terminal error")?;
+        Ok(Keyboard { changes: None })
+    }
+
+    fn watching(changes: Option<Receiver<()>>) -> Result<Keyboard> {
+        let mut keyboard = Keyboard::new()?;
+        keyboard.changes = changes;
+        Ok(keyboard)
+    }
+}
+
+impl Drop for Keyboard {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl EventSource for Keyboard {
+    fn next_key(&mut self) -> Result<Option<KeyEvent>> {
+        loop {
+            if let Event::Key(key) = event::read().context("Read error")? {
+                return Ok(Some(key));
+            }
+        }
+    }
+
+    fn next_input(&mut self) -> Result<Option<Input>> {
+        let Some(changes) = &self.changes else {
+            return Ok(self.next_key()?.map(Input::Key));
+        };
+        loop {
+            match changes.try_recv() {
+                Ok(()) => {
+                    crate::watcher::debounce(changes, DEBOUNCE_QUIET, DEBOUNCE_MAX);
+                    return Ok(Some(Input::Changed));
+                }
+                Err(TryRecvError::Disconnected) => {
+                    self.changes = None;
+                    return Ok(self.next_key()?.map(Input::Key));
+                }
+                Err(TryRecvError::Empty) => {}
+            }
+            if event::poll(KEY_POLL).context("Event error")?
+                && let Event::Key(key) = event::read().context("Read error")?
+            {
+                return Ok(Some(Input::Key(key)));
+            }
+        }
+    }
+}
+
+struct Screen;
+
+impl Renderer for Screen {
+    fn draw(&mut self, frame: &str) {
+        show(frame);
+    }
+}
+
+/// What to do with the picked file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Add,
+    Diff,
+    StagedDiff,
+    Edit,
+    /// A custom action, by its index in `plugin::actions()`
+    Custom(usize),
+}
+
+fn is_interrupt(key: &KeyEvent) -> bool {
+    key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c')
+}
+
+/// Picks a file by typing its key. Keys all have the same length, so the
+/// choice is made once that many have been typed. When the work tree
+/// changes the list comes again from `reload`, keeping what was typed if
+/// the files it narrowed to still have keys starting with it. Files
+/// matching `collapse` are rolled up until `+` lists them too.
+pub fn pick_file(
+    mut all: Vec<GitFile>,
+    id_chars: &[char],
+    collapse: &[Glob],
+    reload: &mut impl FnMut() -> Result<Vec<GitFile>>,
+    events: &mut impl EventSource,
+    renderer: &mut impl Renderer,
+) -> Result<Option<GitFile>> {
+    let mut expanded = false;
+    let split = |all: &[GitFile], expanded: bool| {
+        if expanded {
+            (all.to_vec(), Vec::new())
+        } else {
+            collapse::split(all, collapse)
+        }
+    };
+    let (mut files, mut rollups) = split(&all, expanded);
+    let mut keys = generate_keys(files.len(), id_chars);
+    renderer.draw(&files_frame(&files, &keys, "", &rollups));
+
+    let mut prefix = String::new();
+    while let Some(input) = events.next_input()? {
+        let key = match input {
+            Input::Key(key) => key,
+            Input::Changed => {
+                let narrowed: Vec<String> = keys
+                    .iter()
+                    .zip(&files)
+                    .filter(|(k, _)| k.starts_with(&prefix))
+                    .map(|(_, f)| f.rel_path.clone())
+                    .collect();
+                all = reload()?;
+                (files, rollups) = split(&all, expanded);
+                keys = generate_keys(files.len(), id_chars);
+                let still_there: Vec<&String> = keys
+                    .iter()
+                    .zip(&files)
+                    .filter(|(_, f)| narrowed.contains(&f.rel_path))
+                    .map(|(k, _)| k)
+                    .collect();
+                if still_there.is_empty() || still_there.iter().any(|k| !k.starts_with(&prefix)) {
+                    prefix.clear();
+                }
+                renderer.draw(&files_frame(&files, &keys, &prefix, &rollups));
+                continue;
+            }
+        };
+        if is_interrupt(&key) {
+            return Ok(None);
+        }
+        let key_len = keys.first().map(|k| k.len()).unwrap_or(0);
+        match key.code {
+            KeyCode::Char('q') => return Ok(None),
+            KeyCode::Char('+') if !rollups.is_empty() => {
+                // Keys grow with the list, so any typed so far are dropped
+                expanded = true;
+                (files, rollups) = split(&all, expanded);
+                keys = generate_keys(files.len(), id_chars);
+                prefix.clear();
+                renderer.draw(&files_frame(&files, &keys, "", &rollups));
+            }
+            KeyCode::Char(c) if id_chars.contains(&c) => {
+                prefix.push(c);
+
+                if prefix.len() == key_len {
+                    if let Some(idx) = keys.iter().position(|k| k == &prefix) {
+                        return Ok(Some(files.swap_remove(idx)));
+                    }
+                    prefix.clear();
+                }
+                if !keys.iter().any(|k| k.starts_with(&prefix)) {
+                    prefix.clear();
+                }
+                renderer.draw(&files_frame(&files, &keys, &prefix, &rollups));
+            }
+            KeyCode::Esc => {
+                prefix.clear();
+                renderer.draw(&files_frame(&files, &keys, "", &rollups));
+            }
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+/// Picks what to do with `file`. `custom` are the custom actions, reached
+/// with 1 to 9.
+pub fn pick_action(
+    file: &GitFile,
+    custom: &[(String, std::path::PathBuf)],
+    events: &mut impl EventSource,
+    renderer: &mut impl Renderer,
+) -> Result<Option<Action>> {
+    renderer.draw(&actions_frame(file, custom));
+    while let Some(key) = events.next_key()? {
+        if is_interrupt(&key) {
+            return Ok(None);
+        }
+        let action = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+            KeyCode::Char('a') => Action::Add,
+            KeyCode::Char('d') => Action::Diff,
+            KeyCode::Char('s') => Action::StagedDiff,
+            KeyCode::Char('e') => Action::Edit,
+            KeyCode::Char(c @ '1'..='9') if (c as usize - '1' as usize) < custom.len() => {
+                Action::Custom(c as usize - '1' as usize)
+            }
+            _ => continue,
+        };
+        return Ok(Some(action));
+    }
+    Ok(None)
+}
+
+/// Picks up to `max` items by typing their keys, each full key ticking
+/// (or unticking) its row. Returns once `max` are ticked, or on Enter with
+/// however many are; quitting returns none. Items come back in list order.
+pub fn select_many<'a, T: Pickable>(
+    title: &str,
+    items: &'a [T],
+    max: usize,
+    id_chars: &[char],
+    events: &mut impl EventSource,
+    renderer: &mut impl Renderer,
+) -> Result<Vec<&'a T>> {
+    let multi = max > 1;
+    let mut marked: Vec<usize> = Vec::new();
+    let done = |marked: &[usize]| {
+        let mut marked = marked.to_vec();
+        marked.sort_unstable();
+        marked.into_iter().map(|i| &items[i]).collect()
+    };
+    renderer.draw(&list_frame(title, items, "", &marked, multi));
+
+    let mut prefix = String::new();
+    while let Some(key) = events.next_key()? {
+        if is_interrupt(&key) {
+            return Ok(Vec::new());
+        }
+        match key.code {
+            KeyCode::Char('q') => return Ok(Vec::new()),
+            KeyCode::Enter if multi => return Ok(done(&marked)),
+            KeyCode::Char(c) if id_chars.contains(&c) => {
+                prefix.push(c);
+                if let Some(i) = items.iter().position(|item| item.key() == prefix) {
+                    prefix.clear();
+                    match marked.iter().position(|&m| m == i) {
+                        Some(at) => {
+                            marked.remove(at);
+                        }
+                        None => marked.push(i),
+                    }
+                    if marked.len() == max {
+                        return Ok(done(&marked));
+                    }
+                }
+                if !items.iter().any(|item| item.key().starts_with(&prefix)) {
+                    prefix.clear();
+                }
+                renderer.draw(&list_frame(title, items, &prefix, &marked, multi));
+            }
+            KeyCode::Esc => {
+                prefix.clear();
+                renderer.draw(&list_frame(title, items, "", &marked, multi));
+            }
+            _ => {}
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// `select_many` on the terminal.
+pub fn pick_many<T: Pickable + Clone>(
+    title: &str,
+    items: &[T],
+    max: usize,
+    config: &Config,
+) -> Result<Vec<T>> {
+    let result = {
+        let mut keys = Keyboard::new()?;
+        select_many(
+            title,
+            items,
+            max,
+            &config.id_chars(),
+            &mut keys,
+            &mut Screen,
+        )
+    };
+    clear_screen();
+    Ok(result?.into_iter().cloned().collect())
+}
+
+/// Picks one item on the terminal, as soon as its key is typed.
+pub fn pick<T: Pickable + Clone>(title: &str, items: &[T], config: &Config) -> Result<Option<T>> {
+    Ok(pick_many(title, items, 1, config)?.into_iter().next())
+}
+
+/// A file an ambiguous ID matched, keyed by the rest of its ID.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    rest: String,
+    pub file: GitFile,
+}
+
+impl Pickable for Candidate {
+    fn key(&self) -> &str {
+        &self.rest
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} {}",
+            crate::display::display_path(&self.file),
+            crate::theme::get()
+                .muted
+                .paint(self.file.file_type.as_str())
+        )
+    }
+}
+
+/// The files whose IDs start with `id`, one per path. An ambiguous ID is
+/// shorter than each of their IDs, so what follows it tells them apart.
+pub fn candidates(id: &str, files: &[GitFile]) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for file in files.iter().filter(|f| f.stable_id.matches(id)) {
+        if candidates.iter().any(|c| c.file.rel_path == file.rel_path) {
+            continue;
+        }
+        let rest = file.stable_id.display.get(id.len()..).unwrap_or_default();
+        candidates.push(Candidate {
+            rest: rest.to_string(),
+            file: file.clone(),
+        });
+    }
+    candidates
+}
+
+/// Asks which of the files `id` matches was meant, `None` if none was.
+pub fn disambiguate(id: &str, files: &[GitFile], config: &Config) -> Result<Option<GitFile>> {
+    let candidates = candidates(id, files);
+    let title = tf(Msg::WhichFile, &[id, &candidates.len().to_string()]);
+    Ok(pick(&title, &candidates, config)?.map(|c| c.file))
+}
+
+/// Asks for one of `files` by its ID.
+pub fn pick_changed(files: &[GitFile], config: &Config) -> Result<Option<GitFile>> {
+    let candidates = candidates("", files);
+    Ok(pick(t(Msg::SelectFile), &candidates, config)?.map(|c| c.file))
+}
+
+/// The file picker then the action picker, over the files `filter` passes.
+pub fn run(config: &Config, filter: &FileFilter) -> Result<()> {
+    let id_chars = config.id_chars();
+    let files = filter.apply(crate::daemon::get_files(&id_chars)?);
+    if files.is_empty() {
+        println!(
+            "{}",
+            crate::theme::get().muted.paint(t(Msg::NoChangedFiles))
+        );
+        return Ok(());
+    }
+    let custom = crate::plugin::actions();
+    // Without a watcher the list just isn't refreshed
+    let changes = crate::watcher::spawn(&get_git_root()?, &crate::git_status::get_git_dir()?).ok();
+    let mut reload = || Ok(filter.apply(crate::git_status::get_all_files(&id_chars)?));
+
+    let choice = {
+        let mut keys = Keyboard::watching(changes)?;
+        match pick_file(
+            files,
+            &id_chars,
+            &crate::glob::compile_all(&config.collapse),
+            &mut reload,
+            &mut keys,
+            &mut Screen,
+        )? {
+            Some(file) => {
+                pick_action(&file, &custom, &mut keys, &mut Screen)?.map(|action| (file, action))
+            }
+            None => None,
+        }
+    };
+    let Some((file, action)) = choice else {
+        clear_screen();
+        return Ok(());
+    };
+
+    println!();
+    let git_root = get_git_root()?;
+    std::env::set_current_dir(&git_root).ok();
+
+    match action {
+        Action::Add => {
+            crate::record_undo(|j| j.record_index("add", &[&file.rel_path]));
+            crate::record_session("add", &[&file.rel_path]);
+            println!("Adding: {}", file.rel_path);
+            crate::exec::exec(Command::new("git").args(["add", &file.abs_path.to_string_lossy()]));
+        }
+        Action::Diff => {
+            crate::exec::exec(Command::new("git").args([
+                "diff",
+                "--",
+                &file.abs_path.to_string_lossy(),
+            ]));
+        }
+        Action::StagedDiff => {
+            crate::exec::exec(Command::new("git").args([
+                "diff",
+                "--staged",
+                "--",
+                &file.abs_path.to_string_lossy(),
+            ]));
+        }
+        Action::Edit => crate::edit_files(std::slice::from_ref(&file), config),
+        Action::Custom(idx) => {
+            let (_, path) = &custom[idx];
+            crate::exec::exec(&mut crate::plugin::command(
+                path,
+                &[&file.stable_id.display],
+                Some(&git_root),
+                Some(&file),
+            ));
+        }
+    }
+}
+
+/// One hunk of a file's diff, as review mode shows it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub path: String,
+    /// The `@@ -a,b +c,d @@` line
+    pub header: String,
+    pub lines: Vec<String>,
+    /// The first changed line in the new version, which notes attach to
+    pub line: u32,
+}
+
+/// Splits the diff of `path` into hunks. Binary files have none.
+pub fn parse_hunks(path: &str, diff: &str) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for line in diff.lines() {
+        if line.starts_with("@@ ") {
+            let start = line
+                .split(' ')
+                .find(|part| part.starts_with('+'))
+                .and_then(|range| range[1..].split(',').next()?.parse().ok())
+                .unwrap_or(1);
+            hunks.push(Hunk {
+                path: path.to_string(),
+                header: line.to_string(),
+                lines: Vec::new(),
+                line: start,
+            });
+        } else if let Some(hunk) = hunks.last_mut() {
+            hunk.lines.push(line.to_string());
+        }
+    }
+    for hunk in &mut hunks {
+        let context = hunk.lines.iter().take_while(|l| l.starts_with(' ')).count();
+        hunk.line = (hunk.line + context as u32).max(1);
+    }
+    hunks
+}
+
+/// Hunk `at` of `hunks`, cut to `max_lines`, with the notes already taken
+/// on it and, while one is being typed, the note so far.
+fn review_frame(
+    hunks: &[Hunk],
+    at: usize,
+    notes: &[Note],
+    input: Option<&str>,
+    max_lines: usize,
+) -> String {
+    let hunk = &hunks[at];
+    let theme = crate::theme::get();
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{} {}",
+        theme
+            .heading
+            .paint(&format!("── Review {}/{} ──", at + 1, hunks.len())),
+        format!("{}:{}", hunk.path, hunk.line).bold()
+    );
+    let _ = writeln!(out, "{}", theme.id.paint(&hunk.header));
+    for line in hunk.lines.iter().take(max_lines) {
+        let line = if line.starts_with('+') {
+            theme.added.paint(line)
+        } else if line.starts_with('-') {
+            theme.removed.paint(line)
+        } else {
+            line.normal()
+        };
+        let _ = writeln!(out, "{}", line);
+    }
+    if hunk.lines.len() > max_lines {
+        let more = hunk.lines.len() - max_lines;
+        let more = format!("… {} more line{}", more, if more == 1 { "" } else { "s" });
+        let _ = writeln!(out, "{}", theme.muted.paint(&more));
+    }
+    let _ = writeln!(out);
+    for note in notes
+        .iter()
+        .filter(|n| n.path == hunk.path && n.line == Some(hunk.line))
+    {
+        let _ = writeln!(out, "  {} {}", theme.muted.paint("✎"), note.text);
+    }
+    match input {
+        Some(text) => {
+            let _ = writeln!(out, "  Note on line {}: {}▏", hunk.line, text);
+            let _ = writeln!(
+                out,
+                "  {}  save   {}  cancel",
+                theme.muted.paint("enter"),
+                theme.muted.paint("esc")
+            );
+        }
+        None => {
+            let _ = writeln!(
+                out,
+                "  {}  next   {}  back   {}  note   {}  quit",
+                theme.muted.paint("space"),
+                theme.muted.paint("b"),
+                theme.muted.paint("n"),
+                theme.muted.paint("q")
+            );
+        }
+    }
+    out
+}
+
+/// Steps through `hunks`, space (or j, Enter) for the next and b (or k) for
+/// the one before, until past the last or quit. `n` starts a note on the
+/// hunk's first changed line. Returns the notes taken, quitting included.
+pub fn review(
+    hunks: &[Hunk],
+    max_lines: usize,
+    events: &mut impl EventSource,
+    renderer: &mut impl Renderer,
+) -> Result<Vec<Note>> {
+    let mut notes = Vec::new();
+    if hunks.is_empty() {
+        return Ok(notes);
+    }
+    let mut at = 0;
+    let mut input: Option<String> = None;
+    renderer.draw(&review_frame(hunks, at, &notes, None, max_lines));
+    while let Some(key) = events.next_key()? {
+        if is_interrupt(&key) {
+            break;
+        }
+        if let Some(text) = &mut input {
+            match key.code {
+                KeyCode::Enter => {
+                    let text = text.trim().to_string();
+                    if !text.is_empty() {
+                        notes.push(Note {
+                            path: hunks[at].path.clone(),
+                            line: Some(hunks[at].line),
+                            text,
+                        });
+                    }
+                    input = None;
+                }
+                KeyCode::Esc => input = None,
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) => text.push(c),
+                _ => continue,
+            }
+        } else {
+            match key.code {
+                KeyCode::Char('q') => break,
+                KeyCode::Char(' ' | 'j') | KeyCode::Enter => {
+                    if at + 1 == hunks.len() {
+                        break;
+                    }
+                    at += 1;
+                }
+                KeyCode::Char('b' | 'k') => at = at.saturating_sub(1),
+                KeyCode::Char('n') => input = Some(String::new()),
+                _ => continue,
+            }
+        }
+        renderer.draw(&review_frame(
+            hunks,
+            at,
+            &notes,
+            input.as_deref(),
+            max_lines,
+        ));
+    }
+    Ok(notes)
+}
+
+/// `f review`: every changed file's hunks, staged ones as staged, with the
+/// notes taken saved for `f notes`.
+pub fn run_review(config: &Config) -> Result<()> {
+    let files = crate::daemon::get_files(&config.id_chars())?;
+    let mut hunks = Vec::new();
+    for file in &files {
+        let diff = file_diff(file, file.file_type == FileType::Staged)?;
+        hunks.extend(parse_hunks(&file.rel_path, &diff));
+    }
+    if hunks.is_empty() {
+        println!(
+            "{}",
+            crate::theme::get().muted.paint(t(Msg::NoChangesToReview))
+        );
+        return Ok(());
+    }
+    // Room for the title, hunk header, notes and key hints
+    let max_lines = terminal::size()
+        .map(|(_, rows)| rows as usize)
+        .unwrap_or(24)
+        .saturating_sub(8)
+        .max(5);
+    let notes = {
+        let mut keys = Keyboard::new()?;
+        review(&hunks, max_lines, &mut keys, &mut Screen)
+    };
+    clear_screen();
+    let notes = notes?;
+    if !notes.is_empty() {
+        let count = notes.len();
+        crate::notes::Notes::open()?.add(notes)?;
+        println!(
+            "Saved {} note{} (f notes lists them)",
+            count,
+            if count == 1 { "" } else { "s" }
+        );
+    }
+    Ok(())
+}
+
+/// The todo list with the cursor on step `at`, oldest commit first as the
+/// rebase applies them.
+fn todo_frame(steps: &[Step], at: usize) -> String {
+    let theme = crate::theme::get();
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{}",
+        theme
+            .heading
+            .paint(&format!("── Rebase {} commits ──", steps.len()))
+    );
+    for (i, step) in steps.iter().enumerate() {
+        let cursor = if i == at { "›" } else { " " };
+        let verb = format!("{:<6}", step.verb.word());
+        let verb = match step.verb {
+            Verb::Pick => verb.normal(),
+            Verb::Reword => theme.id.paint(&verb),
+            Verb::Squash | Verb::Fixup => theme.added.paint(&verb),
+            Verb::Drop => theme.removed.paint(&verb),
+        };
+        let subject = if step.verb == Verb::Drop {
+            theme.muted.paint(&step.commit.subject).strikethrough()
+        } else {
+            step.commit.subject.normal()
+        };
+        let _ = writeln!(
+            out,
+            " {} {} {} {}",
+            cursor.bold(),
+            verb,
+            theme.heading.paint(&step.commit.short),
+            subject
+        );
+    }
+    let _ = writeln!(out);
+    let verbs: Vec<String> = Verb::ALL
+        .iter()
+        .map(|verb| {
+            format!(
+                "{} {}",
+                theme.muted.paint(&verb.key().to_string()),
+                verb.word()
+            )
+        })
+        .collect();
+    let _ = writeln!(out, "  {}", verbs.join("   "));
+    let _ = writeln!(
+        out,
+        "  {}  move   {}  reorder   {}  rebase   {}  cancel",
+        theme.muted.paint("j/k"),
+        theme.muted.paint("J/K"),
+        theme.muted.paint("enter"),
+        theme.muted.paint("q")
+    );
+    out
+}
+
+/// Edits the todo list: j/k (or the arrows) move the cursor, J/K move the
+/// commit under it, and p, r, s, f and d set what happens to it. Enter
+/// returns the list; q, Esc or Ctrl-C cancel the rebase.
+pub fn edit_todo(
+    mut steps: Vec<Step>,
+    events: &mut impl EventSource,
+    renderer: &mut impl Renderer,
+) -> Result<Option<Vec<Step>>> {
+    if steps.is_empty() {
+        return Ok(None);
+    }
+    let mut at = 0;
+    let last = steps.len() - 1;
+    renderer.draw(&todo_frame(&steps, at));
+    while let Some(key) = events.next_key()? {
+        if is_interrupt(&key) {
+            return Ok(None);
+        }
+        match key.code {
+            KeyCode::Enter => return Ok(Some(steps)),
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+            KeyCode::Char('j') | KeyCode::Down => at = (at + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => at = at.saturating_sub(1),
+            KeyCode::Char('J') if at < last => {
+                steps.swap(at, at + 1);
+                at += 1;
+            }
+            KeyCode::Char('K') if at > 0 => {
+                steps.swap(at, at - 1);
+                at -= 1;
+            }
+            KeyCode::Char(c) if let Some(verb) = Verb::from_key(c) => steps[at].verb = verb,
+            _ => continue,
+        }
+        renderer.draw(&todo_frame(&steps, at));
+    }
+    Ok(None)
+}
+
+/// `edit_todo` on the terminal.
+pub fn run_edit_todo(steps: Vec<Step>) -> Result<Option<Vec<Step>>> {
+    let edited = {
+        let mut keys = Keyboard::new()?;
+        edit_todo(steps, &mut keys, &mut Screen)
+    };
+    clear_screen();
+    edited
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_status::StableId;
+    use crate::snapshot::{self, assert_snapshot};
+    use std::collections::VecDeque;
+    use std::path::PathBuf;
+
+    /// Key presses typed one character at a time, `^` standing for Esc,
+    /// `!` for Ctrl-C and a newline for Enter. `~` is a change to the work
+    /// tree, which only `next_input` sees.
+    struct Script(VecDeque<Input>);
+
+    impl Script {
+        fn new(typed: &str) -> Script {
+            Script(
+                typed
+                    .chars()
+                    .map(|c| match c {
+                        '~' => Input::Changed,
+                        '^' => Input::Key(KeyEvent::from(KeyCode::Esc)),
+                        '!' => Input::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+                        '\n' => Input::Key(KeyEvent::from(KeyCode::Enter)),
+                        c => Input::Key(KeyEvent::from(KeyCode::Char(c))),
+                    })
+                    .collect(),
+            )
+        }
+    }
+
+    impl EventSource for Script {
+        fn next_key(&mut self) -> Result<Option<KeyEvent>> {
+            while let Some(input) = self.0.pop_front() {
+                if let Input::Key(key) = input {
+                    return Ok(Some(key));
+                }
+            }
+            Ok(None)
+        }
+
+        fn next_input(&mut self) -> Result<Option<Input>> {
+            Ok(self.0.pop_front())
+        }
+    }
+
+    /// Keeps every frame drawn.
+    #[derive(Default)]
+    struct Frames(Vec<String>);
+
+    impl Renderer for Frames {
+        fn draw(&mut self, frame: &str) {
+            self.0.push(frame.to_string());
+        }
+    }
+
+    const ID_CHARS: [char; 2] = ['a', 's'];
+
+    /// Runs the file picker then the action picker on `files()`, returning
+    /// the picked path and action.
+    fn drive(typed: &str) -> Option<(String, Action)> {
+        let custom = [("blame".to_string(), PathBuf::from("/actions/blame"))];
+        let mut script = Script::new(typed);
+        let mut frames = Frames::default();
+        let mut reload = || Ok(files());
+        let file = pick_file(
+            files(),
+            &ID_CHARS,
+            &[],
+            &mut reload,
+            &mut script,
+            &mut frames,
+        )
+        .unwrap()?;
+        let action = pick_action(&file, &custom, &mut script, &mut frames).unwrap()?;
+        Some((file.rel_path.clone(), action))
+    }
+
+    fn id(display: &str) -> StableId {
+        StableId {
+            display: display.to_string(),
+            full_hash: display.to_string(),
+        }
+    }
+
+    fn file(rel_path: &str, file_type: FileType) -> GitFile {
+        GitFile {
+            mtime: 0,
+            rel_path: rel_path.to_string(),
+            abs_path: PathBuf::from("/nonexistent").join(rel_path),
+            file_type,
+            stable_id: id("x"),
+            diff_stats: None,
+            lfs_size: None,
+            mode_change: None,
+            status: 'M',
+        }
+    }
+
+    fn files() -> Vec<GitFile> {
+        vec![
+            file("src/main.rs", FileType::Unstaged),
+            file("src/lib.rs", FileType::Unstaged),
+            file("notes.md", FileType::Untracked),
+            file("Cargo.toml", FileType::Staged),
+        ]
+    }
+
+    #[test]
+    fn test_generate_keys() {
+        assert_eq!(generate_keys(3, &['a', 'b', 'c']), ["a", "b", "c"]);
+        assert_eq!(generate_keys(4, &['a', 'b']), ["aa", "ab", "ba", "bb"]);
+        assert!(generate_keys(0, &['a']).is_empty());
+    }
+
+    #[test]
+    fn snapshot_files_frame() {
+        let _colors = snapshot::colors(true);
+        let files = files();
+        let keys = generate_keys(files.len(), &['a', 's']);
+        assert_snapshot(
+            "interactive__files_frame",
+            &files_frame(&files, &keys, "", &[]),
+        );
+        // Typing narrows the list to keys starting with what was typed
+        assert_snapshot(
+            "interactive__files_frame_prefix",
+            &files_frame(&files, &keys, "s", &[]),
+        );
+    }
+
+    #[test]
+    fn snapshot_actions_frame() {
+        let _colors = snapshot::colors(true);
+        let custom = [("blame".to_string(), PathBuf::from("/actions/blame"))];
+        assert_snapshot(
+            "interactive__actions_frame",
+            &actions_frame(&files()[0], &custom),
+        );
+    }
+
+    fn commit(id_display: &str, short: &str, subject: &str) -> Commit {
+        Commit {
+            sha: short.repeat(5),
+            short: short.to_string(),
+            subject: subject.to_string(),
+            author: "Ada".to_string(),
+            date: "2 days ago".to_string(),
+            id: id(id_display),
+        }
+    }
+
+    fn commits() -> [Commit; 3] {
+        [
+            commit("a", "1a2b3c4d", "Add parser"),
+            commit("sd", "5e6f7a8b", "Fix overflow in width"),
+            commit("ss", "9c0d1e2f", "Initial commit"),
+        ]
+    }
+
+    #[test]
+    fn snapshot_commits_frame() {
+        let _colors = snapshot::colors(true);
+        assert_snapshot(
+            "interactive__commits_frame",
+            &list_frame("Fixup into", &commits(), "s", &[], false),
+        );
+        assert_snapshot(
+            "interactive__commits_frame_marked",
+            &list_frame("Commits", &commits(), "", &[2], true),
+        );
+    }
+
+    #[test]
+    fn selecting_several_ticks_rows_until_enter_or_the_limit() {
+        let commits = commits();
+        let pick = |typed: &str| -> Vec<String> {
+            select_many(
+                "Commits",
+                &commits,
+                2,
+                &['a', 'd', 's'],
+                &mut Script::new(typed),
+                &mut Frames::default(),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|c| c.subject.clone())
+            .collect()
+        };
+        // In list order, whatever order they were typed in
+        assert_eq!(pick("ssa"), ["Add parser", "Initial commit"]);
+        assert_eq!(pick("sd\n"), ["Fix overflow in width"]);
+        // Typing a key again unticks it
+        assert_eq!(pick("aa\n"), Vec::<String>::new());
+        assert_eq!(pick("aq"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn ambiguous_ids_are_picked_by_the_rest_of_the_id() {
+        let mut files = files();
+        for (file, display) in files.iter_mut().zip(["sa", "sss", "ssa", "a"]) {
+            file.stable_id = id(display);
+        }
+        // Staged and unstaged halves of one file are one candidate
+        let mut staged = files[0].clone();
+        staged.file_type = FileType::Staged;
+        files.push(staged);
+
+        let candidates = candidates("s", &files);
+        let keys: Vec<&str> = candidates.iter().map(|c| c.key()).collect();
+        assert_eq!(keys, ["a", "ss", "sa"]);
+        let picked = select_many(
+            "s matches 3 files",
+            &candidates,
+            1,
+            &ID_CHARS,
+            &mut Script::new("sa"),
+            &mut Frames::default(),
+        )
+        .unwrap();
+        assert_eq!(picked[0].file.rel_path, "notes.md");
+    }
+
+    #[test]
+    fn typing_a_key_picks_the_file_then_the_action() {
+        assert_eq!(drive("sae"), Some(("notes.md".to_string(), Action::Edit)));
+        assert_eq!(
+            drive("aad"),
+            Some(("src/main.rs".to_string(), Action::Diff))
+        );
+        assert_eq!(
+            drive("ss1"),
+            Some(("Cargo.toml".to_string(), Action::Custom(0)))
+        );
+        // Keys that aren't actions, and custom actions that don't exist, are
+        // ignored
+        assert_eq!(
+            drive("asx2a"),
+            Some(("src/lib.rs".to_string(), Action::Add))
+        );
+    }
+
+    #[test]
+    fn esc_starts_the_key_over() {
+        assert_eq!(
+            drive("a^ssa"),
+            Some(("Cargo.toml".to_string(), Action::Add))
+        );
+        assert_eq!(drive("as^"), None);
+    }
+
+    #[test]
+    fn quitting_picks_nothing() {
+        assert_eq!(drive("q"), None);
+        assert_eq!(drive("a!"), None);
+        assert_eq!(drive("aaq"), None);
+        // Running out of keys is the same as quitting
+        assert_eq!(drive("a"), None);
+    }
+
+    #[test]
+    fn each_key_redraws_the_narrowed_list() {
+        let _colors = snapshot::colors(false);
+        let files = files();
+        let mut frames = Frames::default();
+        pick_file(
+            files.clone(),
+            &ID_CHARS,
+            &[],
+            &mut || Ok(files.clone()),
+            &mut Script::new("s"),
+            &mut frames,
+        )
+        .unwrap();
+        let keys = generate_keys(files.len(), &ID_CHARS);
+        assert_eq!(
+            frames.0,
+            [
+                files_frame(&files, &keys, "", &[]),
+                files_frame(&files, &keys, "s", &[])
+            ]
+        );
+    }
+
+    #[test]
+    fn plus_lists_the_collapsed_files_too() {
+        let _colors = snapshot::colors(false);
+        let collapse = crate::glob::compile_all(&["*.md".into()]);
+        let pick = |typed: &str| {
+            let mut frames = Frames::default();
+            let picked = pick_file(
+                files(),
+                &ID_CHARS,
+                &collapse,
+                &mut || Ok(files()),
+                &mut Script::new(typed),
+                &mut frames,
+            )
+            .unwrap()
+            .map(|f| f.rel_path);
+            (picked, frames.0)
+        };
+        let (picked, frames) = pick("sa");
+        assert_eq!(picked.as_deref(), Some("Cargo.toml"));
+        assert!(frames[0].contains("── Collapsed ──\n  *.md · 1 file\n"));
+        assert!(!frames[0].contains("notes.md"));
+        // Listed, notes.md takes Cargo.toml's key
+        let (picked, frames) = pick("+sa");
+        assert_eq!(picked.as_deref(), Some("notes.md"));
+        assert!(!frames[1].contains("Collapsed") && !frames[1].contains("show collapsed"));
+    }
+
+    #[test]
+    fn changes_reload_the_list_keeping_the_prefix_where_it_still_fits() {
+        // src/lib.rs is committed, so the keys of the files after it move up
+        let after = || -> Vec<GitFile> {
+            files()
+                .into_iter()
+                .filter(|f| f.rel_path != "src/lib.rs")
+                .collect()
+        };
+        let pick = |typed: &str| {
+            let mut frames = Frames::default();
+            let mut reload = || Ok(after());
+            pick_file(
+                files(),
+                &ID_CHARS,
+                &[],
+                &mut reload,
+                &mut Script::new(typed),
+                &mut frames,
+            )
+            .unwrap()
+            .map(|f| f.rel_path)
+        };
+        // "s" narrowed to notes.md and Cargo.toml, now "as" and "sa", so it's
+        // dropped and the new key typed afresh
+        assert_eq!(pick("s~sa").as_deref(), Some("Cargo.toml"));
+        // "a" narrowed to src/main.rs and src/lib.rs; main.rs is still "aa"
+        assert_eq!(pick("a~a").as_deref(), Some("src/main.rs"));
+        assert_eq!(pick("~as").as_deref(), Some("notes.md"));
+    }
+
+    #[test]
+    fn typing_a_full_commit_id_picks_it() {
+        let commits = [commit("a", "", "first"), commit("sa", "", "second")];
+        let pick = |typed: &str| {
+            select_many(
+                "Pick",
+                &commits,
+                1,
+                &ID_CHARS,
+                &mut Script::new(typed),
+                &mut Frames::default(),
+            )
+            .unwrap()
+            .first()
+            .map(|c| c.subject.clone())
+        };
+        assert_eq!(pick("a").as_deref(), Some("first"));
+        assert_eq!(pick("sa").as_deref(), Some("second"));
+        // No ID starts with "ss", so it's dropped and "a" is typed afresh
+        assert_eq!(pick("ssa").as_deref(), Some("first"));
+        assert_eq!(pick("sq"), None);
+    }
+
+    const DIFF: &str = "diff --git a/src/lib.rs b/src/lib.rs\n\
+                        --- a/src/lib.rs\n\
+                        +++ b/src/lib.rs\n\
+                        @@ -1,4 +1,4 @@\n\
+                        \x20use std::fmt;\n\
+                        -const LIMIT: u32 = 10;\n\
+                        +const LIMIT: u32 = 20;\n\
+                        \x20\n\
+                        @@ -40,3 +40,4 @@ fn check() {\n\
+                        +    debug_assert!(ok);\n\
+                        \x20    ok\n";
+
+    #[test]
+    fn test_parse_hunks() {
+        let hunks = parse_hunks("src/lib.rs", DIFF);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].header, "@@ -1,4 +1,4 @@");
+        assert_eq!(hunks[0].lines.len(), 4);
+        // Notes attach to the first changed line, past the leading context
+        assert_eq!((hunks[0].line, hunks[1].line), (2, 40));
+        assert!(parse_hunks("logo.png", "Binary files differ\n").is_empty());
+    }
+
+    #[test]
+    fn snapshot_review_frame() {
+        let _colors = snapshot::colors(true);
+        let hunks = parse_hunks("src/lib.rs", DIFF);
+        let notes = [Note {
+            path: "src/lib.rs".to_string(),
+            line: Some(2),
+            text: "Why 20?".to_string(),
+        }];
+        assert_snapshot(
+            "interactive__review_frame",
+            &review_frame(&hunks, 0, &notes, Some("Docs"), 3),
+        );
+    }
+
+    #[test]
+    fn review_takes_notes_on_the_current_hunk() {
+        let hunks = parse_hunks("src/lib.rs", DIFF);
+        let take = |typed: &str| -> Vec<(Option<u32>, String)> {
+            review(&hunks, 10, &mut Script::new(typed), &mut Frames::default())
+                .unwrap()
+                .into_iter()
+                .map(|note| (note.line, note.text))
+                .collect()
+        };
+        assert_eq!(
+            take("nWhy 20?\n nquit here\n"),
+            [
+                (Some(2), "Why 20?".to_string()),
+                (Some(40), "quit here".to_string())
+            ]
+        );
+        // Back from the second hunk, and Esc drops a note being typed
+        assert_eq!(take(" bnFirst\nnnope^q"), [(Some(2), "First".to_string())]);
+        // Past the last hunk the review is over, and Ctrl-C keeps the notes
+        assert_eq!(take("  nlate\n"), []);
+        assert_eq!(take("nkept\n!"), [(Some(2), "kept".to_string())]);
+    }
+
+    fn todo() -> Vec<Step> {
+        let step = |verb, short: &str, subject: &str| Step {
+            verb,
+            commit: Commit {
+                sha: short.repeat(6),
+                short: short.to_string(),
+                subject: subject.to_string(),
+                author: "Test".to_string(),
+                date: "2 days ago".to_string(),
+                id: StableId {
+                    display: "aa".to_string(),
+                    full_hash: "aaaa".to_string(),
+                },
+            },
+        };
+        vec![
+            step(Verb::Pick, "a1b2c3d", "Add the parser"),
+            step(Verb::Pick, "e4f5a6b", "Fix a typo in the parser"),
+            step(Verb::Pick, "c7d8e9f", "Document the parser"),
+        ]
+    }
+
+    fn todo_lines(steps: &[Step]) -> Vec<String> {
+        steps
+            .iter()
+            .map(|s| format!("{} {}", s.verb.word(), s.commit.short))
+            .collect()
+    }
+
+    #[test]
+    fn snapshot_todo_frame() {
+        let _colors = snapshot::colors(true);
+        let mut steps = todo();
+        steps[1].verb = Verb::Fixup;
+        steps[2].verb = Verb::Drop;
+        assert_snapshot("interactive__todo_frame", &todo_frame(&steps, 1));
+    }
+
+    #[test]
+    fn edit_todo_sets_verbs_and_reorders() {
+        let edit = |typed: &str| {
+            edit_todo(todo(), &mut Script::new(typed), &mut Frames::default())
+                .unwrap()
+                .map(|steps| todo_lines(&steps))
+        };
+        assert_eq!(
+            edit("jjKfjd\n"),
+            Some(vec![
+                "pick a1b2c3d".to_string(),
+                "fixup c7d8e9f".to_string(),
+                "drop e4f5a6b".to_string(),
+            ])
+        );
+        // Moving past either end stays put
+        assert_eq!(
+            edit("kKjjjJr\n"),
+            Some(vec![
+                "pick a1b2c3d".to_string(),
+                "pick e4f5a6b".to_string(),
+                "reword c7d8e9f".to_string(),
+            ])
+        );
+        assert_eq!(edit("sq"), None);
+        assert_eq!(edit("d!"), None);
+        assert_eq!(edit("d"), None);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::config::CommitConfig;
+use crate::error::Error;
+use anyhow::Result;
+use regex::Regex;
+
+/// Types from the Conventional Commits spec plus the common Angular set.
+const CONVENTIONAL: &str =
+    r"^(feat|fix|docs|style|refactor|perf|test|build|ci|chore|revert)(\([^()]+\))?!?: \S";
+
+/// Words ending in -s/-ed/-ing that are fine at the start of a subject.
+const IMPERATIVE_EXCEPTIONS: &[&str] = &[
+    "address", "bias", "bless", "bring", "bypass", "canvas", "compress", "discuss", "embed",
+    "express", "feed", "focus", "harness", "need", "pass", "process", "proceed", "redis", "seed",
+    "shed", "speed", "string", "succeed", "access", "alias", "progress", "toss", "miss",
+];
+
+/// A failed check: the rule's config key and what was wrong.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub message: String,
+}
+
+/// Checks `message` against every enabled rule in `rules`. Errors only if a
+/// configured regex is invalid.
+pub fn check(message: &str, rules: &CommitConfig) -> Result<Vec<Violation>> {
+    let subject = message.lines().next().unwrap_or("").trim_end();
+    let mut violations = Vec::new();
+    let mut fail = |rule, message: String| violations.push(Violation { rule, message });
+
+    if let Some(max) = rules.max_subject_length {
+        let len = subject.chars().count();
+        if len > max {
+            fail(
+                "max_subject_length",
+                format!("subject is {} characters (limit {})", len, max),
+            );
+        }
+    }
+
+    if rules.conventional && !Regex::new(CONVENTIONAL)?.is_match(subject) {
+        fail(
+            "conventional",
+            "subject should look like 'type(scope): description', e.g. 'fix(parser): handle tabs'"
+                .to_string(),
+        );
+    }
+
+    if let Some(pattern) = &rules.subject_pattern {
+        let re = compile("subject_pattern", pattern)?;
+        if !re.is_match(subject) {
+            fail(
+                "subject_pattern",
+                format!("subject does not match /{}/", pattern),
+            );
+        }
+    }
+
+    if rules.imperative
+        && let Some(word) = first_word(subject)
+        && !is_imperative(word)
+    {
+        fail(
+            "imperative",
+            format!(
+                "subject should use the imperative mood ('Fix', not '{}')",
+                word
+            ),
+        );
+    }
+
+    if let Some(pattern) = &rules.ticket {
+        let re = compile("ticket", pattern)?;
+        if !re.is_match(message) {
+            fail(
+                "ticket",
+                format!("message has no ticket reference matching /{}/", pattern),
+            );
+        }
+    }
+
+    Ok(violations)
+}
+
+/// The first word of the description, skipping a conventional `type:` prefix.
+fn compile(rule: &str, pattern: &str) -> Result<Regex> {
+    Regex::new(pattern)
+        .map_err(|e| Error::Config(format!("Invalid {} '{}': {}", rule, pattern, e)).into())
+}
+
+fn first_word(subject: &str) -> Option<&str> {
+    let description = match subject.split_once(": ") {
+        Some((prefix, rest)) if !prefix.contains(' ') => rest,
+        _ => subject,
+    };
+    description
+        .split_whitespace()
+        .next()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+}
+
+/// A heuristic: "Fixed", "Adds" and "Adding" are not imperative.
+fn is_imperative(word: &str) -> bool {
+    let word = word.to_lowercase();
+    if IMPERATIVE_EXCEPTIONS.contains(&word.as_str()) || word.len() < 4 {
+        return true;
+    }
+    let third_person = word.ends_with('s') && !word.ends_with("ss") && !word.ends_with("us");
+    !(word.ends_with("ed") || word.ends_with("ing") || third_person)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules_of(violations: Vec<Violation>) -> Vec<&'static str> {
+        violations.into_iter().map(|v| v.rule).collect()
+    }
+
+    #[test]
+    fn no_rules_means_no_violations() {
+        assert!(
+            check("anything goes", &CommitConfig::default())
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn subject_length_only_counts_first_line() {
+        let rules = CommitConfig {
+            max_subject_length: Some(10),
+            ..Default::default()
+        };
+        assert!(
+            check("Fix typo\n\nA much longer body line", &rules)
+                .unwrap()
+                .is_empty()
+        );
+        let violations = check("Fix a rather long typo", &rules).unwrap();
+        assert_eq!(violations[0].message, "subject is 22 characters (limit 10)");
+    }
+
+    #[test]
+    fn imperative_heuristic() {
+        for ok in [
+            "Fix bug",
+            "Add tests",
+            "Address review",
+            "fix: handle tabs",
+            "Process queue",
+        ] {
+            assert!(is_imperative(first_word(ok).unwrap()), "{}", ok);
+        }
+        for bad in [
+            "Fixed bug",
+            "Adds tests",
+            "Adding tests",
+            "feat(ui): added button",
+        ] {
+            assert!(!is_imperative(first_word(bad).unwrap()), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn conventional_and_ticket_rules() {
+        let rules = CommitConfig {
+            conventional: true,
+            ticket: Some(r"[A-Z]+-\d+".to_string()),
+            ..Default::default()
+        };
+        assert!(
+            check("feat(cli)!: add f c -m\n\nRefs ABC-12", &rules)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            rules_of(check("Add f c -m", &rules).unwrap()),
+            vec!["conventional", "ticket"]
+        );
+    }
+
+    #[test]
+    fn invalid_patterns_are_errors() {
+        let rules = CommitConfig {
+            subject_pattern: Some("(".to_string()),
+            ..Default::default()
+        };
+        assert!(check("msg", &rules).is_err());
+    }
+}
@@ -0,0 +1,70 @@
+//! Debug logging. `-v` logs at debug level and `-vv` at trace to stderr.
+//! With `log_file` set, logs are appended to that file instead, at debug
+//! level even without `-v`, so interactive and watch sessions, where stderr
+//! is the screen, can be debugged after the fact.
+
+use log::LevelFilter;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+/// The level to log at, or `None` for no logging.
+fn level(verbosity: u8, to_file: bool) -> Option<LevelFilter> {
+    match verbosity {
+        0 if to_file => Some(LevelFilter::Debug),
+        0 => None,
+        1 => Some(LevelFilter::Debug),
+        _ => Some(LevelFilter::Trace),
+    }
+}
+
+pub fn init(verbosity: u8, log_file: Option<&Path>) {
+    let file =
+        log_file.and_then(
+            |path| match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    eprintln!("Warning: Cannot open log file {}: {}", path.display(), e);
+                    None
+                }
+            },
+        );
+    let Some(level) = level(verbosity, file.is_some()) else {
+        return;
+    };
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(level);
+    if let Some(file) = file {
+        // Several f processes can share the file, so lines carry the pid
+        let pid = std::process::id();
+        builder
+            .target(env_logger::Target::Pipe(Box::new(file)))
+            .write_style(env_logger::WriteStyle::Never)
+            .format(move |buf, record| {
+                writeln!(
+                    buf,
+                    "{} {} {:<5} {}: {}",
+                    buf.timestamp_millis(),
+                    pid,
+                    record.level(),
+                    record.target(),
+                    record.args()
+                )
+            });
+    }
+    builder.init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level() {
+        assert_eq!(level(0, false), None);
+        assert_eq!(level(0, true), Some(LevelFilter::Debug));
+        assert_eq!(level(1, false), Some(LevelFilter::Debug));
+        assert_eq!(level(2, true), Some(LevelFilter::Trace));
+        assert_eq!(level(5, false), Some(LevelFilter::Trace));
+    }
+}
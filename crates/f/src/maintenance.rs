@@ -0,0 +1,283 @@
+//! What git's housekeeping is up to: a gc or `git maintenance` running in
+//! the background, and loose objects piling up until status slows down.
+
+use crate::git_status::{get_git_dir, git_output};
+use anyhow::{Result, bail};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The loose objects at which `git gc --auto` packs them, unless `gc.auto`
+/// says otherwise.
+const GC_AUTO: u64 = 6700;
+
+/// git treats a `gc.pid` older than this as left behind by a crash.
+const STALE_PID: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// How long a lock may go without its PID and start time, while the run
+/// that made it is still writing them.
+const UNWRITTEN_LOCK: Duration = Duration::from_secs(60);
+
+/// The object store's counts, from `git count-objects -v`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Objects {
+    pub loose: u64,
+    /// KiB taken by loose objects
+    pub loose_size: u64,
+    pub packs: u64,
+}
+
+pub fn parse_count_objects(output: &str) -> Objects {
+    let mut objects = Objects::default();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(": ") else {
+            continue;
+        };
+        let value = value.trim().parse().unwrap_or(0);
+        match key {
+            "count" => objects.loose = value,
+            "size" => objects.loose_size = value,
+            "packs" => objects.packs = value,
+            _ => {}
+        }
+    }
+    objects
+}
+
+pub fn objects() -> Option<Objects> {
+    git_output(&["count-objects", "-v"]).map(|out| parse_count_objects(&out))
+}
+
+/// The loose objects above which status is slow enough to say so: `gc.auto`,
+/// or git's default when it's unset or 0 (auto gc turned off).
+pub fn loose_limit() -> u64 {
+    git_output(&["config", "--type=int", "gc.auto"])
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(GC_AUTO)
+}
+
+/// The housekeeping running now, from the lock files git holds while it
+/// does, and f's own while `f maintenance run` does: `gc` or `maintenance`.
+pub fn running(git_dir: &Path) -> Option<&'static str> {
+    if younger_than(&git_dir.join("gc.pid"), STALE_PID) {
+        Some("gc")
+    } else if younger_than(&git_dir.join("objects").join("maintenance.lock"), STALE_PID)
+        || Lock::holder(&Lock::path(git_dir)).is_some_and(|holder| !holder.is_stale())
+    {
+        Some("maintenance")
+    } else {
+        None
+    }
+}
+
+fn younger_than(path: &Path, age: Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|t| SystemTime::now().duration_since(t).unwrap_or_default() < age)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// `.git/f/maintenance.lock`, held while `f maintenance run` runs so two
+/// runs don't overlap. It records the PID and start time of the run
+/// holding it, so a lock left behind by one that crashed is taken over
+/// instead of blocking maintenance for good. Dropping it unlocks.
+pub struct Lock {
+    path: PathBuf,
+}
+
+/// Who holds a lock, as written in it: `<pid> <unix seconds>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Holder {
+    pid: u32,
+    started: u64,
+}
+
+impl Holder {
+    fn parse(text: &str) -> Option<Holder> {
+        let (pid, started) = text.trim().split_once(' ')?;
+        Some(Holder {
+            pid: pid.parse().ok()?,
+            started: started.parse().ok()?,
+        })
+    }
+
+    /// Whether the run is over without having unlocked, or has run for
+    /// longer than any real one would and so likely hung.
+    fn is_stale(&self) -> bool {
+        now().saturating_sub(self.started) >= STALE_PID.as_secs() || !alive(self.pid)
+    }
+}
+
+#[cfg(unix)]
+fn alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 only checks whether the process exists
+    let sent = unsafe { libc::kill(pid, 0) } == 0;
+    sent || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn alive(_pid: u32) -> bool {
+    true
+}
+
+impl Lock {
+    fn path(git_dir: &Path) -> PathBuf {
+        git_dir.join("f").join("maintenance.lock")
+    }
+
+    fn holder(path: &Path) -> Option<Holder> {
+        Holder::parse(&fs::read_to_string(path).ok()?)
+    }
+
+    /// Takes the lock, or fails when another run holds it.
+    pub fn take(git_dir: &Path) -> Result<Lock> {
+        let path = Self::path(git_dir);
+        fs::create_dir_all(git_dir.join("f"))?;
+        // Just the one retry, after taking over a stale lock
+        for _ in 0..2 {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    writeln!(file, "{} {}", std::process::id(), now())?;
+                    return Ok(Lock { path });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    match Self::holder(&path) {
+                        Some(holder) if !holder.is_stale() => bail!(
+                            "f maintenance is already running (pid {}), see {}",
+                            holder.pid,
+                            path.display()
+                        ),
+                        None if younger_than(&path, UNWRITTEN_LOCK) => bail!(
+                            "f maintenance is just starting elsewhere, see {}",
+                            path.display()
+                        ),
+                        _ => log::debug!("taking over the stale {}", path.display()),
+                    }
+                    match fs::remove_file(&path) {
+                        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                        _ => {}
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        bail!("Couldn't take {}", path.display())
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Whether `git maintenance start` or `register` has put the repository
+/// on the background schedule.
+pub fn scheduled(root: &Path) -> bool {
+    let root = root.to_string_lossy();
+    git_output(&["config", "--get-all", "maintenance.repo"])
+        .is_some_and(|repos| repos.lines().any(|repo| repo == root))
+}
+
+/// The problem worth a line above the file list, if there is one.
+pub fn hint() -> Option<String> {
+    if let Some(task) = get_git_dir().ok().and_then(|dir| running(&dir)) {
+        return Some(format!("git {} is running, so status may be slow", task));
+    }
+    let objects = objects()?;
+    (objects.loose > loose_limit()).then(|| {
+        format!(
+            "{} loose objects are slowing git down · f maintenance run",
+            objects.loose
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_count_objects() {
+        let output = "count: 7012\nsize: 28816\nin-pack: 120400\npacks: 3\n\
+                      size-pack: 51200\nprune-packable: 0\ngarbage: 0\nsize-garbage: 0\n";
+        assert_eq!(
+            parse_count_objects(output),
+            Objects {
+                loose: 7012,
+                loose_size: 28816,
+                packs: 3,
+            }
+        );
+        assert_eq!(parse_count_objects(""), Objects::default());
+    }
+
+    #[test]
+    fn running_reads_the_lock_files() {
+        let dir = std::env::temp_dir().join(format!("f-maintenance-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("objects")).unwrap();
+        assert_eq!(running(&dir), None);
+        fs::write(dir.join("objects").join("maintenance.lock"), "").unwrap();
+        assert_eq!(running(&dir), Some("maintenance"));
+        fs::write(dir.join("gc.pid"), "4242 host").unwrap();
+        assert_eq!(running(&dir), Some("gc"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn lock_blocks_a_second_run_and_takes_over_stale_ones() {
+        let dir = std::env::temp_dir().join(format!("f-maintenance-lock-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = Lock::path(&dir);
+
+        let lock = Lock::take(&dir).unwrap();
+        let holder = Lock::holder(&path).unwrap();
+        assert_eq!(holder.pid, std::process::id());
+        assert!(now() - holder.started < 60);
+        assert_eq!(running(&dir), Some("maintenance"));
+        assert!(Lock::take(&dir).is_err());
+        drop(lock);
+        assert!(!path.exists());
+        assert_eq!(running(&dir), None);
+
+        // Above any pid_max, so never a live process
+        fs::write(&path, format!("99999999 {}\n", now())).unwrap();
+        assert_eq!(running(&dir), None);
+        drop(Lock::take(&dir).unwrap());
+        // Alive, but started longer ago than any run takes
+        fs::write(&path, format!("{} 1000\n", std::process::id())).unwrap();
+        drop(Lock::take(&dir).unwrap());
+        // Still being written
+        fs::write(&path, "").unwrap();
+        assert!(Lock::take(&dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_holder() {
+        assert_eq!(
+            Holder::parse("4242 1760400000\n"),
+            Some(Holder {
+                pid: 4242,
+                started: 1760400000
+            })
+        );
+        assert_eq!(Holder::parse(""), None);
+        assert_eq!(Holder::parse("4242"), None);
+    }
+}
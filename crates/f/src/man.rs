@@ -0,0 +1,289 @@
+//! Man pages rendered from the clap definition: `f.1` for the top level
+//! and `f-<command>.1` for each subcommand, `f-<command>-<subcommand>.1`
+//! for theirs, the way git splits its pages.
+
+use clap::{Arg, ArgAction, Command};
+use std::fmt::Write as _;
+
+/// Every page as `(file name, roff source)`. `cmd` must have been built
+/// (`Command::build`) so global options show up on subcommand pages.
+pub fn pages(cmd: &Command) -> Vec<(String, String)> {
+    let mut pages = Vec::new();
+    add_pages(&mut pages, cmd, &[cmd.get_name()], cmd);
+    pages
+}
+
+/// Adds the page for `cmd`, reached from `root` through the commands in
+/// `path`, then those of its subcommands.
+fn add_pages<'a>(
+    pages: &mut Vec<(String, String)>,
+    cmd: &'a Command,
+    path: &[&'a str],
+    root: &Command,
+) {
+    pages.push((format!("{}.1", path.join("-")), page(cmd, path, root)));
+    for sub in visible_subcommands(cmd) {
+        add_pages(pages, sub, &[path, &[sub.get_name()]].concat(), root);
+    }
+}
+
+/// Subcommands worth a page, leaving out the `help` clap adds on build.
+fn visible_subcommands(cmd: &Command) -> impl Iterator<Item = &Command> {
+    cmd.get_subcommands()
+        .filter(|c| !c.is_hide_set() && c.get_name() != "help")
+}
+
+fn page(cmd: &Command, path: &[&str], root: &Command) -> String {
+    let name = &path.join("-");
+    let version = root.get_version().unwrap_or_default();
+    let about = cmd.get_about().map(|a| a.to_string()).unwrap_or_default();
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        ".TH {} 1 \"\" \"{} {}\" \"User Commands\"",
+        name.to_uppercase(),
+        root.get_name(),
+        version
+    );
+    let _ = writeln!(out, ".SH NAME\n{} \\- {}", escape(name), escape(&about));
+
+    let _ = writeln!(out, ".SH SYNOPSIS\n{}", synopsis(cmd, path));
+    let aliases: Vec<&str> = cmd.get_visible_aliases().collect();
+    if !aliases.is_empty() {
+        let names: Vec<String> = aliases
+            .iter()
+            .map(|alias| {
+                let parent = path[..path.len() - 1].join(" ");
+                format!("\\fB{} {}\\fR", escape(&parent), escape(alias))
+            })
+            .collect();
+        let _ = writeln!(out, ".br\nAlso: {}", names.join(", "));
+    }
+
+    let description = cmd.get_long_about().or(cmd.get_about());
+    if let Some(description) = description {
+        let _ = writeln!(out, ".SH DESCRIPTION\n{}", escape(&description.to_string()));
+    }
+    if let Some(after) = cmd.get_after_help() {
+        // Keeps the indented examples lined up
+        let _ = writeln!(out, ".PP\n.nf\n{}\n.fi", escape(&after.to_string()));
+    }
+
+    let positionals: Vec<&Arg> = visible_args(cmd).filter(|a| a.is_positional()).collect();
+    if !positionals.is_empty() {
+        let _ = writeln!(out, ".SH ARGUMENTS");
+        for arg in positionals {
+            let _ = writeln!(out, ".TP\n{}\n{}", positional(arg), arg_help(arg));
+        }
+    }
+    let options: Vec<&Arg> = visible_args(cmd).filter(|a| !a.is_positional()).collect();
+    if !options.is_empty() {
+        let _ = writeln!(out, ".SH OPTIONS");
+        for arg in options {
+            let _ = writeln!(out, ".TP\n{}\n{}", option(arg), arg_help(arg));
+        }
+    }
+
+    let subs: Vec<&Command> = visible_subcommands(cmd).collect();
+    if !subs.is_empty() {
+        let _ = writeln!(out, ".SH COMMANDS");
+        for sub in subs {
+            let about = sub.get_about().map(|a| a.to_string()).unwrap_or_default();
+            let _ = writeln!(
+                out,
+                ".TP\n\\fB{}\\fR\n{}\nSee \\fB{}\\-{}\\fR(1).",
+                escape(sub.get_name()),
+                escape(&about),
+                escape(name),
+                escape(sub.get_name())
+            );
+        }
+    }
+    if !version.is_empty() {
+        let _ = writeln!(out, ".SH VERSION\n{}", escape(version));
+    }
+    out
+}
+
+fn visible_args(cmd: &Command) -> impl Iterator<Item = &Arg> {
+    cmd.get_arguments().filter(|a| {
+        !a.is_hide_set() && !matches!(a.get_action(), ArgAction::Help | ArgAction::Version)
+    })
+}
+
+fn synopsis(cmd: &Command, path: &[&str]) -> String {
+    let mut words = vec![format!("\\fB{}\\fR", escape(&path.join(" ")))];
+    if visible_args(cmd).any(|a| !a.is_positional()) {
+        words.push("[\\fIOPTIONS\\fR]".to_string());
+    }
+    for arg in visible_args(cmd).filter(|a| a.is_positional()) {
+        words.push(positional(arg));
+    }
+    if cmd.has_subcommands() {
+        words.push("[\\fICOMMAND\\fR]".to_string());
+    }
+    words.join(" ")
+}
+
+fn value_name(arg: &Arg) -> String {
+    match arg.get_value_names() {
+        Some(names) => names
+            .iter()
+            .map(|n| n.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+        None => arg.get_id().as_str().to_uppercase(),
+    }
+}
+
+fn positional(arg: &Arg) -> String {
+    let mut name = format!("\\fI{}\\fR", escape(&value_name(arg)));
+    if matches!(arg.get_action(), ArgAction::Append)
+        || arg.get_num_args().is_some_and(|n| n.max_values() > 1)
+    {
+        name.push_str("...");
+    }
+    if arg.is_required_set() {
+        name
+    } else {
+        format!("[{}]", name)
+    }
+}
+
+fn option(arg: &Arg) -> String {
+    let mut flags = Vec::new();
+    if let Some(short) = arg.get_short() {
+        flags.push(format!("\\fB\\-{}\\fR", short));
+    }
+    if let Some(long) = arg.get_long() {
+        flags.push(format!("\\fB\\-\\-{}\\fR", escape(long)));
+    }
+    let mut spec = flags.join(", ");
+    if arg.get_action().takes_values() {
+        let _ = write!(spec, " \\fI{}\\fR", escape(&value_name(arg)));
+    }
+    spec
+}
+
+fn arg_help(arg: &Arg) -> String {
+    let mut help = arg.get_help().map(|h| h.to_string()).unwrap_or_default();
+    let values: Vec<String> = arg
+        .get_possible_values()
+        .iter()
+        .filter(|v| !v.is_hide_set())
+        .map(|v| v.get_name().to_string())
+        .collect();
+    if !values.is_empty() {
+        let _ = write!(help, " (one of: {})", values.join(", "));
+    }
+    escape(help.trim())
+}
+
+/// `text` safe to put in roff: backslashes and dashes escaped, and lines
+/// that would start with a control character protected.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e")
+        .replace('-', "\\-")
+        .lines()
+        .map(|line| {
+            if line.starts_with('.') || line.starts_with('\'') {
+                format!("\\&{}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_command() -> Command {
+        let mut cmd = Command::new("f")
+            .version("1.2.3")
+            .about("A git file manager")
+            .after_help("ID-first syntax:\n  f <id> <cmd>")
+            .arg(
+                Arg::new("verbose")
+                    .short('v')
+                    .long("verbose")
+                    .global(true)
+                    .action(ArgAction::Count)
+                    .help("Log more"),
+            )
+            .subcommand(
+                Command::new("diff")
+                    .visible_alias("d")
+                    .about("Show a file's diff")
+                    .arg(Arg::new("id").help("File ID"))
+                    .arg(Arg::new("staged").long("staged").action(ArgAction::SetTrue)),
+            )
+            .subcommand(
+                Command::new("pr")
+                    .about("Work with pull requests")
+                    .subcommand(
+                        Command::new("files")
+                            .visible_alias("f")
+                            .about("List a pull request's files"),
+                    )
+                    .subcommand(Command::new("draft").hide(true)),
+            )
+            .subcommand(Command::new("secret").hide(true));
+        cmd.build();
+        cmd
+    }
+
+    #[test]
+    fn pages_cover_visible_subcommands() {
+        let names: Vec<String> = pages(&test_command()).into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, ["f.1", "f-diff.1", "f-pr.1", "f-pr-files.1"]);
+    }
+
+    #[test]
+    fn nested_subcommand_page() {
+        let pages = pages(&test_command());
+        let (_, pr) = &pages[2];
+        assert!(pr.contains(
+            "\\fBfiles\\fR\nList a pull request's files\nSee \\fBf\\-pr\\-files\\fR(1)."
+        ));
+        let (_, page) = &pages[3];
+        assert!(page.starts_with(".TH F-PR-FILES 1 "));
+        assert!(page.contains(".SH NAME\nf\\-pr\\-files \\- List a pull request's files\n"));
+        assert!(page.contains(
+            ".SH SYNOPSIS\n\\fBf pr files\\fR [\\fIOPTIONS\\fR]\n.br\nAlso: \\fBf pr f\\fR\n"
+        ));
+        assert!(page.contains("\\fB\\-\\-verbose\\fR"));
+    }
+
+    #[test]
+    fn top_level_page() {
+        let (_, page) = &pages(&test_command())[0];
+        assert!(page.starts_with(".TH F 1 \"\" \"f 1.2.3\" \"User Commands\"\n"));
+        assert!(page.contains(".SH NAME\nf \\- A git file manager\n"));
+        assert!(page.contains(".SH SYNOPSIS\n\\fBf\\fR [\\fIOPTIONS\\fR] [\\fICOMMAND\\fR]\n"));
+        assert!(page.contains(".nf\nID\\-first syntax:\n  f <id> <cmd>\n.fi"));
+        assert!(page.contains(".TP\n\\fB\\-v\\fR, \\fB\\-\\-verbose\\fR\nLog more\n"));
+        assert!(page.contains("\\fBdiff\\fR\nShow a file's diff\nSee \\fBf\\-diff\\fR(1)."));
+        assert!(!page.contains("secret"));
+    }
+
+    #[test]
+    fn subcommand_page_includes_global_options() {
+        let (_, page) = &pages(&test_command())[1];
+        assert!(page.contains(".SH NAME\nf\\-diff \\- Show a file's diff\n"));
+        assert!(
+            page.contains("\\fBf diff\\fR [\\fIOPTIONS\\fR] [\\fIID\\fR]\n.br\nAlso: \\fBf d\\fR")
+        );
+        assert!(page.contains(".TP\n[\\fIID\\fR]\nFile ID\n"));
+        assert!(page.contains("\\fB\\-\\-staged\\fR\n"));
+        assert!(page.contains("\\fB\\-\\-verbose\\fR"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a-b \\n"), "a\\-b \\en");
+        assert_eq!(escape(".hidden\n'quoted"), "\\&.hidden\n\\&'quoted");
+    }
+}
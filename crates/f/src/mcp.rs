@@ -0,0 +1,398 @@
+//! `f mcp`: the file list, diffs, staging and committing as Model Context
+//! Protocol tools over stdio, so coding agents can work with stable IDs
+//! instead of shelling out to git. The tools run through the same code as
+//! `f serve`'s methods.
+
+use crate::cache::StatusCache;
+use crate::config::Config;
+use crate::json::{self, Value};
+use crate::serve::{
+    self, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR, RpcError,
+};
+use anyhow::Result;
+use std::io::{BufRead, Write};
+
+/// Protocol revisions this server can speak. A client asking for one of
+/// them gets it back; any other gets the newest.
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+struct Tool {
+    name: &'static str,
+    description: &'static str,
+    /// Changes the index or history, so unavailable with `--read-only`
+    mutating: bool,
+    /// `(name, JSON type, description, required)` for each argument
+    params: &'static [(&'static str, &'static str, &'static str, bool)],
+}
+
+const ID_PARAM: (&str, &str, &str, bool) = (
+    "id",
+    "string",
+    "File ID from list_files, or a unique prefix of it",
+    true,
+);
+
+const TOOLS: &[Tool] = &[
+    Tool {
+        name: "list_files",
+        description: "List changed files (staged, unstaged and untracked) with their stable IDs, \
+                      paths and line counts. IDs stay the same for a file across calls.",
+        mutating: false,
+        params: &[],
+    },
+    Tool {
+        name: "diff_file",
+        description: "Show the diff of one changed file.",
+        mutating: false,
+        params: &[
+            ID_PARAM,
+            (
+                "staged",
+                "boolean",
+                "Diff what is staged instead of the working tree",
+                false,
+            ),
+        ],
+    },
+    Tool {
+        name: "stage_file",
+        description: "Stage a changed file (git add).",
+        mutating: true,
+        params: &[ID_PARAM],
+    },
+    Tool {
+        name: "unstage_file",
+        description: "Unstage a staged file, keeping its changes in the working tree.",
+        mutating: true,
+        params: &[ID_PARAM],
+    },
+    Tool {
+        name: "commit",
+        description: "Commit everything staged with the given message, after the repository's \
+                      commit message checks.",
+        mutating: true,
+        params: &[("message", "string", "Commit message", true)],
+    },
+];
+
+pub struct Server<'a> {
+    config: &'a Config,
+    read_only: bool,
+    cache: StatusCache,
+}
+
+impl<'a> Server<'a> {
+    pub fn new(config: &'a Config, read_only: bool) -> Self {
+        Self {
+            config,
+            read_only,
+            cache: StatusCache::new(config.id_chars(), serve::CACHE_TTL),
+        }
+    }
+
+    /// Serves newline-delimited JSON-RPC 2.0 over stdin/stdout, MCP's stdio
+    /// transport, until stdin closes.
+    pub fn run(&mut self) -> Result<()> {
+        let stdin = std::io::stdin();
+        let mut stdout = std::io::stdout();
+        for line in stdin.lock().lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle_line(&line) {
+                writeln!(stdout, "{}", response)?;
+                stdout.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles one message, returning the response to send (if any).
+    pub fn handle_line(&mut self, line: &str) -> Option<Value> {
+        let request = match json::parse(line) {
+            Ok(r) => r,
+            Err(e) => {
+                return Some(serve::error_response(
+                    Value::Null,
+                    RpcError::new(PARSE_ERROR, e.to_string()),
+                ));
+            }
+        };
+        // Notifications, like `notifications/initialized`, get no response
+        let id = request.get("id").cloned()?;
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Some(serve::error_response(
+                id,
+                RpcError::new(INVALID_REQUEST, "Missing method"),
+            ));
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        Some(match self.dispatch(method, &params) {
+            Ok(value) => Value::object([
+                ("jsonrpc", Value::from("2.0")),
+                ("id", id),
+                ("result", value),
+            ]),
+            Err(e) => serve::error_response(id, e),
+        })
+    }
+
+    fn dispatch(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => Ok(initialize(params)),
+            "ping" => Ok(Value::object::<&str>([])),
+            "tools/list" => Ok(Value::object([(
+                "tools",
+                Value::Array(self.tools().map(tool_json).collect()),
+            )])),
+            "tools/call" => self.call(params),
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method: {}", other),
+            )),
+        }
+    }
+
+    fn tools(&self) -> impl Iterator<Item = &'static Tool> + '_ {
+        TOOLS.iter().filter(|t| !(self.read_only && t.mutating))
+    }
+
+    /// Runs a tool. Failures of the tool itself, like an unknown ID or a
+    /// rejected commit message, are results with `isError` set so the agent
+    /// sees them; only a call to a tool that doesn't exist is a protocol
+    /// error.
+    fn call(&mut self, params: &Value) -> Result<Value, RpcError> {
+        let Some(name) = params.get("name").and_then(Value::as_str) else {
+            return Err(RpcError::new(INVALID_PARAMS, "Missing string param 'name'"));
+        };
+        let Some(tool) = TOOLS.iter().find(|t| t.name == name) else {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!("Unknown tool: {}", name),
+            ));
+        };
+        let args = params.get("arguments").cloned().unwrap_or(Value::Null);
+        let result = if self.read_only && tool.mutating {
+            Err(format!(
+                "{} is unavailable, f mcp was started with --read-only",
+                name
+            ))
+        } else {
+            self.run_tool(name, &args)
+        };
+        let (text, is_error) = match result {
+            Ok(text) => (text, false),
+            Err(message) => (message, true),
+        };
+        Ok(Value::object([
+            (
+                "content",
+                Value::Array(vec![Value::object([
+                    ("type", Value::from("text")),
+                    ("text", Value::from(text)),
+                ])]),
+            ),
+            ("isError", Value::from(is_error)),
+        ]))
+    }
+
+    fn run_tool(&mut self, name: &str, args: &Value) -> Result<String, String> {
+        let serve = |method, cache: &mut StatusCache| {
+            serve::dispatch(method, args, cache).map_err(|e| e.message)
+        };
+        match name {
+            "list_files" => Ok(serve("list", &mut self.cache)?.to_string()),
+            "diff_file" => {
+                let result = serve("diff", &mut self.cache)?;
+                let diff = result.get("diff").and_then(Value::as_str).unwrap_or("");
+                Ok(if diff.is_empty() {
+                    "No changes".to_string()
+                } else {
+                    diff.to_string()
+                })
+            }
+            "stage_file" => Ok(format!(
+                "Staged {}",
+                path(&serve("stage", &mut self.cache)?)
+            )),
+            "unstage_file" => Ok(format!(
+                "Unstaged {}",
+                path(&serve("unstage", &mut self.cache)?)
+            )),
+            "commit" => self.commit(args),
+            _ => unreachable!("every tool in TOOLS is handled"),
+        }
+    }
+
+    fn commit(&mut self, args: &Value) -> Result<String, String> {
+        let message = args
+            .get("message")
+            .and_then(Value::as_str)
+            .filter(|m| !m.trim().is_empty())
+            .ok_or("Missing string argument 'message'")?;
+        let violations =
+            crate::lint::check(message, &self.config.commit).map_err(|e| format!("{:#}", e))?;
+        if !violations.is_empty() {
+            let rules: Vec<String> = violations
+                .iter()
+                .map(|v| format!("{}: {}", v.rule, v.message))
+                .collect();
+            return Err(format!("Commit message rejected:\n{}", rules.join("\n")));
+        }
+        let mut git_args = vec!["commit", "-q"];
+        if self.config.commit.sign {
+            git_args.push("-S");
+        }
+        git_args.extend(["-m", message]);
+        serve::run_git(&git_args).map_err(|e| e.message)?;
+        self.cache.invalidate();
+        let head = serve::run_git(&["rev-parse", "--short", "HEAD"]).map_err(|e| e.message)?;
+        Ok(format!("Committed {}", head.trim()))
+    }
+}
+
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = PROTOCOL_VERSIONS
+        .iter()
+        .find(|v| Some(**v) == requested)
+        .unwrap_or(&PROTOCOL_VERSIONS[0]);
+    Value::object([
+        ("protocolVersion", Value::from(*version)),
+        (
+            "capabilities",
+            Value::object([("tools", Value::object::<&str>([]))]),
+        ),
+        (
+            "serverInfo",
+            Value::object([
+                ("name", Value::from("f")),
+                ("version", Value::from(env!("CARGO_PKG_VERSION"))),
+            ]),
+        ),
+    ])
+}
+
+fn tool_json(tool: &Tool) -> Value {
+    let properties = tool.params.iter().map(|(name, kind, description, _)| {
+        (
+            *name,
+            Value::object([
+                ("type", Value::from(*kind)),
+                ("description", Value::from(*description)),
+            ]),
+        )
+    });
+    let required: Vec<Value> = tool
+        .params
+        .iter()
+        .filter(|(.., required)| *required)
+        .map(|(name, ..)| Value::from(*name))
+        .collect();
+    Value::object([
+        ("name", Value::from(tool.name)),
+        ("description", Value::from(tool.description)),
+        (
+            "inputSchema",
+            Value::object([
+                ("type", Value::from("object")),
+                ("properties", Value::object(properties)),
+                ("required", Value::Array(required)),
+            ]),
+        ),
+    ])
+}
+
+fn path(file: &Value) -> &str {
+    file.get("path").and_then(Value::as_str).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(server: &mut Server, line: &str) -> Value {
+        server.handle_line(line).unwrap()
+    }
+
+    fn tool_names(response: &Value) -> Vec<String> {
+        let Some(Value::Array(tools)) = response.get("result").and_then(|r| r.get("tools")) else {
+            panic!("no tools in {}", response);
+        };
+        tools
+            .iter()
+            .filter_map(|t| t.get("name")?.as_str().map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn test_initialize_negotiates_version() {
+        let config = Config::default();
+        let mut server = Server::new(&config, false);
+        let response = request(
+            &mut server,
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2024-11-05"}}"#,
+        );
+        let result = response.get("result").unwrap();
+        assert_eq!(
+            result.get("protocolVersion").and_then(Value::as_str),
+            Some("2024-11-05")
+        );
+        assert!(
+            server
+                .handle_line(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_read_only_hides_and_refuses_mutating_tools() {
+        let config = Config::default();
+        let list = r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#;
+        let all = tool_names(&request(&mut Server::new(&config, false), list));
+        assert_eq!(
+            all,
+            [
+                "list_files",
+                "diff_file",
+                "stage_file",
+                "unstage_file",
+                "commit"
+            ]
+        );
+
+        let mut server = Server::new(&config, true);
+        assert_eq!(
+            tool_names(&request(&mut server, list)),
+            ["list_files", "diff_file"]
+        );
+        let response = request(
+            &mut server,
+            r#"{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"stage_file","arguments":{"id":"df"}}}"#,
+        );
+        let result = response.get("result").unwrap();
+        assert_eq!(result.get("isError").and_then(Value::as_bool), Some(true));
+    }
+
+    #[test]
+    fn test_tool_schema() {
+        let schema = tool_json(&TOOLS[1]);
+        let input = schema.get("inputSchema").unwrap();
+        assert_eq!(
+            input.to_string(),
+            r#"{"type":"object","properties":{"id":{"type":"string","description":"File ID from list_files, or a unique prefix of it"},"staged":{"type":"boolean","description":"Diff what is staged instead of the working tree"}},"required":["id"]}"#
+        );
+    }
+
+    #[test]
+    fn test_unknown_tool_is_a_protocol_error() {
+        let config = Config::default();
+        let response = request(
+            &mut Server::new(&config, false),
+            r#"{"jsonrpc":"2.0","id":4,"method":"tools/call","params":{"name":"push"}}"#,
+        );
+        let error = response.get("error").unwrap();
+        assert_eq!(error.get("code"), Some(&Value::from(INVALID_PARAMS)));
+    }
+}
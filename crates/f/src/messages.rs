@@ -0,0 +1,230 @@
+//! The messages f shows people, in English and Spanish. The language comes
+//! from `locale` in the config, or else the usual `LC_ALL`,
+//! `LC_MESSAGES` and `LANG`. Command names, flags and machine-readable
+//! output stay in English whatever the language.
+
+use std::sync::OnceLock;
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+}
+
+pub const LOCALES: [&str; 2] = ["en", "es"];
+
+impl Locale {
+    /// The locale for a tag like `es`, `es_MX.UTF-8` or `en-GB`, by its
+    /// language alone.
+    pub fn parse(tag: &str) -> Option<Locale> {
+        let language = tag.split(['_', '-', '.', '@']).next()?;
+        match language.to_ascii_lowercase().as_str() {
+            "en" => Some(Locale::En),
+            "es" => Some(Locale::Es),
+            _ => None,
+        }
+    }
+
+    /// The locale named by the first of `LC_ALL`, `LC_MESSAGES` and `LANG`
+    /// that's set, as the C library picks; English for any other language.
+    pub fn from_env(vars: [Option<String>; 3]) -> Locale {
+        vars.into_iter()
+            .flatten()
+            .find(|v| !v.is_empty())
+            .and_then(|tag| Locale::parse(&tag))
+            .unwrap_or_default()
+    }
+}
+
+/// A message with a translation. Those with `{}` take arguments through
+/// `tf`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    NoChangedFiles,
+    NoChangesToReview,
+    Unstaged,
+    Untracked,
+    Staged,
+    Ignored,
+    Collapsed,
+    /// A rollup's count of one file
+    OneFile,
+    /// A rollup's count of several
+    Files,
+    /// The line ending a section cut short by `--limit`
+    More,
+    NotARepo,
+    SelectFile,
+    /// The picker's title when an ID matches several files
+    WhichFile,
+    Action,
+    Selected,
+    Prefix,
+    Add,
+    Diff,
+    StagedDiff,
+    Edit,
+    ShowCollapsed,
+    Done,
+    Quit,
+    /// The answers to a yes/no question, yes first
+    YesNo,
+    Push,
+    PushAnyway,
+    NotPushed,
+    RunInstead,
+}
+
+impl Msg {
+    /// The English and Spanish text.
+    fn catalog(self) -> [&'static str; 2] {
+        match self {
+            Msg::NoChangedFiles => ["No changed files", "No hay archivos modificados"],
+            Msg::NoChangesToReview => ["No changes to review", "No hay cambios que revisar"],
+            Msg::Unstaged => ["Unstaged", "Sin preparar"],
+            Msg::Untracked => ["Untracked", "Sin seguimiento"],
+            Msg::Staged => ["Staged", "Preparados"],
+            Msg::Ignored => ["Ignored", "Ignorados"],
+            Msg::Collapsed => ["Collapsed", "Agrupados"],
+            Msg::OneFile => ["{} file", "{} archivo"],
+            Msg::Files => ["{} files", "{} archivos"],
+            Msg::More => ["… and {} more", "… y {} más"],
+            Msg::NotARepo => [
+                "Not a git repository, listing files by modification time",
+                "No es un repositorio git, archivos por fecha de modificación",
+            ],
+            Msg::SelectFile => ["Select file", "Elige un archivo"],
+            Msg::WhichFile => ["{} matches {} files", "{} coincide con {} archivos"],
+            Msg::Action => ["Action", "Acción"],
+            Msg::Selected => ["Selected:", "Elegido:"],
+            Msg::Prefix => ["Prefix:", "Prefijo:"],
+            Msg::Add => ["add", "añadir"],
+            Msg::Diff => ["diff", "diferencias"],
+            Msg::StagedDiff => ["staged diff", "diferencias preparadas"],
+            Msg::Edit => ["edit", "editar"],
+            Msg::ShowCollapsed => ["show collapsed", "ver agrupados"],
+            Msg::Done => ["done", "listo"],
+            Msg::Quit => ["quit", "salir"],
+            Msg::YesNo => ["Y/n", "S/n"],
+            Msg::Push => ["Push?", "¿Hacer push?"],
+            Msg::PushAnyway => ["Push anyway?", "¿Hacer push de todos modos?"],
+            Msg::NotPushed => ["Not pushed", "No se hizo push"],
+            Msg::RunInstead => ["Run git {} instead?", "¿Ejecutar git {} en su lugar?"],
+        }
+    }
+
+    pub fn text(self, locale: Locale) -> &'static str {
+        self.catalog()[locale as usize]
+    }
+}
+
+/// Sets the language for the rest of the run: `configured` if given and
+/// known, otherwise the environment's. Until then, and in tests, it's
+/// English.
+pub fn init(configured: Option<&str>) {
+    let from_config = configured.and_then(|tag| {
+        let locale = Locale::parse(tag);
+        if locale.is_none() {
+            eprintln!(
+                "Warning: Unknown locale '{}', expected one of {}",
+                tag,
+                LOCALES.join(", ")
+            );
+        }
+        locale
+    });
+    let locale = from_config.unwrap_or_else(|| {
+        Locale::from_env(["LC_ALL", "LC_MESSAGES", "LANG"].map(|v| std::env::var(v).ok()))
+    });
+    let _ = LOCALE.set(locale);
+}
+
+pub fn locale() -> Locale {
+    *LOCALE.get_or_init(Locale::default)
+}
+
+/// `msg` in the current language.
+pub fn t(msg: Msg) -> &'static str {
+    msg.text(locale())
+}
+
+/// `msg` in the current language, with each `{}` replaced by the next of
+/// `args`.
+pub fn tf(msg: Msg, args: &[&str]) -> String {
+    fill(t(msg), args)
+}
+
+fn fill(text: &str, args: &[&str]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut rest = text;
+    while let Some((before, after)) = rest.split_once("{}") {
+        out.push_str(before);
+        out.push_str(args.next().copied().unwrap_or_default());
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Whether `answer` to a `YesNo` question means yes: empty, or the
+/// language's yes or its first letter. English yes always counts.
+pub fn is_yes(answer: &str) -> bool {
+    let answer = answer.trim().to_lowercase();
+    let yes = match locale() {
+        Locale::En => &["y", "yes"][..],
+        Locale::Es => &["s", "si", "sí", "y", "yes"][..],
+    };
+    answer.is_empty() || yes.contains(&answer.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(Locale::parse("es_MX.UTF-8"), Some(Locale::Es));
+        assert_eq!(Locale::parse("en-GB"), Some(Locale::En));
+        assert_eq!(Locale::parse("ES"), Some(Locale::Es));
+        assert_eq!(Locale::parse("C"), None);
+    }
+
+    #[test]
+    fn environment_follows_the_c_library_order() {
+        let vars = |all: &str, messages: &str, lang: &str| {
+            [all, messages, lang].map(|v| (!v.is_empty()).then(|| v.to_string()))
+        };
+        assert_eq!(Locale::from_env(vars("", "", "es_ES.UTF-8")), Locale::Es);
+        assert_eq!(Locale::from_env(vars("", "C", "es_ES.UTF-8")), Locale::En);
+        assert_eq!(Locale::from_env(vars("es_AR", "C", "")), Locale::Es);
+        assert_eq!(Locale::from_env(vars("", "", "fr_FR")), Locale::En);
+        assert_eq!(Locale::from_env(vars("", "", "")), Locale::En);
+    }
+
+    #[test]
+    fn translations_take_the_same_arguments() {
+        for msg in [
+            Msg::OneFile,
+            Msg::Files,
+            Msg::More,
+            Msg::RunInstead,
+            Msg::WhichFile,
+        ] {
+            let [en, es] = msg.catalog();
+            assert_eq!(en.matches("{}").count(), es.matches("{}").count());
+        }
+        assert_eq!(Msg::Staged.text(Locale::Es), "Preparados");
+        assert_eq!(t(Msg::Staged), "Staged");
+    }
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(fill("… and {} more", &["3"]), "… and 3 more");
+        assert_eq!(fill("{} of {}", &["1"]), "1 of ");
+        assert_eq!(fill("none", &["x"]), "none");
+    }
+}
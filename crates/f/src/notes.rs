@@ -0,0 +1,206 @@
+//! Review notes taken in `f review`, kept in `.git/f/notes` until cleared
+//! and exported as markdown or as a GitHub review for `gh api`.
+
+use crate::json::{self, Value};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    /// Repo-relative path
+    pub path: String,
+    /// Line in the new version of the file, if the note is about one
+    pub line: Option<u32>,
+    pub text: String,
+}
+
+pub struct Notes {
+    path: PathBuf,
+    /// In the order they were taken
+    notes: Vec<Note>,
+}
+
+impl Notes {
+    pub fn open() -> Result<Notes> {
+        let path = crate::git_status::get_git_dir()?.join("f").join("notes");
+        let notes = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| json::parse(&content).ok())
+            .map(|value| from_json(&value))
+            .unwrap_or_default();
+        Ok(Notes { path, notes })
+    }
+
+    pub fn notes(&self) -> &[Note] {
+        &self.notes
+    }
+
+    pub fn add(&mut self, notes: impl IntoIterator<Item = Note>) -> Result<()> {
+        self.notes.extend(notes);
+        self.save()
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.notes.clear();
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to remove {}", self.path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, format!("{}\n", to_json(&self.notes)))
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// `notes` grouped by file, in the order each file was first noted.
+fn by_file(notes: &[Note]) -> Vec<(&str, Vec<&Note>)> {
+    let mut files: Vec<(&str, Vec<&Note>)> = Vec::new();
+    for note in notes {
+        match files.iter_mut().find(|(path, _)| *path == note.path) {
+            Some((_, group)) => group.push(note),
+            None => files.push((&note.path, vec![note])),
+        }
+    }
+    for (_, group) in &mut files {
+        group.sort_by_key(|note| note.line);
+    }
+    files
+}
+
+pub fn markdown(notes: &[Note]) -> String {
+    let mut out = String::from("# Review notes\n");
+    for (path, group) in by_file(notes) {
+        out.push_str(&format!("\n## `{}`\n\n", path));
+        for note in group {
+            match note.line {
+                Some(line) => out.push_str(&format!("- Line {}: {}\n", line, note.text)),
+                None => out.push_str(&format!("- {}\n", note.text)),
+            }
+        }
+    }
+    out
+}
+
+/// The request body of GitHub's "create a review" endpoint. Notes on a
+/// line become comments on it; notes on a whole file go in the review's
+/// own text, since GitHub comments need a line.
+pub fn github_review(notes: &[Note]) -> Value {
+    let general: Vec<String> = notes
+        .iter()
+        .filter(|note| note.line.is_none())
+        .map(|note| format!("`{}`: {}", note.path, note.text))
+        .collect();
+    let comments = notes
+        .iter()
+        .filter_map(|note| {
+            Some(Value::object([
+                ("path", Value::from(note.path.as_str())),
+                ("line", Value::from(note.line?)),
+                ("side", Value::from("RIGHT")),
+                ("body", Value::from(note.text.as_str())),
+            ]))
+        })
+        .collect();
+    Value::object([
+        ("event", Value::from("COMMENT")),
+        ("body", Value::from(general.join("\n\n"))),
+        ("comments", Value::Array(comments)),
+    ])
+}
+
+fn to_json(notes: &[Note]) -> Value {
+    Value::Array(
+        notes
+            .iter()
+            .map(|note| {
+                Value::object([
+                    ("path", Value::from(note.path.as_str())),
+                    ("line", Value::from(note.line)),
+                    ("text", Value::from(note.text.as_str())),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn from_json(value: &Value) -> Vec<Note> {
+    let Value::Array(items) = value else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            Some(Note {
+                path: item.get("path")?.as_str()?.to_string(),
+                line: item.get("line").and_then(Value::as_u64).map(|l| l as u32),
+                text: item.get("text")?.as_str()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(path: &str, line: Option<u32>, text: &str) -> Note {
+        Note {
+            path: path.to_string(),
+            line,
+            text: text.to_string(),
+        }
+    }
+
+    fn notes() -> Vec<Note> {
+        vec![
+            note("src/lib.rs", Some(40), "Off by one?"),
+            note("README.md", None, "Mention the new flag"),
+            note("src/lib.rs", Some(12), "Needs a doc comment"),
+        ]
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let text = to_json(&notes()).to_string();
+        assert_eq!(from_json(&json::parse(&text).unwrap()), notes());
+        assert!(from_json(&json::parse("{}").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_markdown() {
+        assert_eq!(
+            markdown(&notes()),
+            "# Review notes\n\
+             \n## `src/lib.rs`\n\n\
+             - Line 12: Needs a doc comment\n\
+             - Line 40: Off by one?\n\
+             \n## `README.md`\n\n\
+             - Mention the new flag\n"
+        );
+    }
+
+    #[test]
+    fn test_github_review() {
+        let review = github_review(&notes());
+        assert_eq!(
+            review.get("body").and_then(Value::as_str),
+            Some("`README.md`: Mention the new flag")
+        );
+        let Some(Value::Array(comments)) = review.get("comments") else {
+            panic!("no comments in {}", review);
+        };
+        assert_eq!(comments.len(), 2);
+        assert_eq!(
+            comments[0].to_string(),
+            "{\"path\":\"src/lib.rs\",\"line\":40,\"side\":\"RIGHT\",\"body\":\"Off by one?\"}"
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use std::process::{Command, Stdio};
+
+/// Shows a desktop notification, best effort: failures (no notification
+/// daemon, headless session) are silently ignored.
+pub fn send(title: &str, body: &str) {
+    let mut command = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification \"{}\" with title \"{}\"",
+            escape_applescript(body),
+            escape_applescript(title)
+        );
+        let mut c = Command::new("osascript");
+        c.args(["-e", &script]);
+        c
+    } else {
+        let mut c = Command::new("notify-send");
+        c.args(["--app-name=f", title, body]);
+        c
+    };
+    let _ = command.stdout(Stdio::null()).stderr(Stdio::null()).status();
+}
+
+fn escape_applescript(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
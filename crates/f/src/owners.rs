@@ -0,0 +1,131 @@
+//! Code owners from a CODEOWNERS file, shown next to changed files and
+//! used by `--mine` to hide files someone else owns.
+
+use crate::config::Config;
+use crate::git_status::{get_git_root, git_output};
+use crate::glob::Glob;
+use std::fs;
+use std::path::Path;
+
+/// Where GitHub and GitLab look, in the order they do.
+const LOCATIONS: [&str; 4] = [
+    ".github/CODEOWNERS",
+    "CODEOWNERS",
+    "docs/CODEOWNERS",
+    ".gitlab/CODEOWNERS",
+];
+
+#[derive(Debug, Clone)]
+pub struct Owners {
+    /// In file order, the last match winning
+    rules: Vec<(Glob, Vec<String>)>,
+}
+
+impl Owners {
+    /// The repository's CODEOWNERS, if it has one.
+    pub fn load() -> Option<Owners> {
+        let root = get_git_root().ok()?;
+        Owners::load_from(&root)
+    }
+
+    fn load_from(root: &Path) -> Option<Owners> {
+        LOCATIONS
+            .iter()
+            .find_map(|location| fs::read_to_string(root.join(location)).ok())
+            .map(|content| Owners::parse(&content))
+    }
+
+    /// CODEOWNERS patterns follow gitignore rules, as `Glob` does. GitLab
+    /// section headers are skipped, their rules applying like any other.
+    pub fn parse(content: &str) -> Owners {
+        let rules = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with(['#', '[', '^']))
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let glob = Glob::new(fields.next()?).ok()?;
+                let owners = fields
+                    .take_while(|field| !field.starts_with('#'))
+                    .map(str::to_string)
+                    .collect();
+                Some((glob, owners))
+            })
+            .collect();
+        Owners { rules }
+    }
+
+    /// The owners of the repo-relative `path`, none if no rule matches or
+    /// the last matching rule lists nobody.
+    pub fn of(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|(glob, _)| glob.matches(path))
+            .map_or(&[], |(_, owners)| owners.as_slice())
+    }
+
+    /// Whether one of `me` (usernames, teams or emails) owns `path`.
+    /// GitHub names aren't case sensitive, so neither is this.
+    pub fn owned_by(&self, path: &str, me: &[String]) -> bool {
+        self.of(path)
+            .iter()
+            .any(|owner| me.iter().any(|m| m.eq_ignore_ascii_case(owner)))
+    }
+}
+
+/// Who `--mine` means: `[owners] me` from the config and the git email.
+pub fn identities(config: &Config) -> Vec<String> {
+    let mut me = config.owners.me.clone();
+    me.extend(git_output(&["config", "user.email"]));
+    me
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODEOWNERS: &str = "\
+# Everything else is the core team's
+*       @acme/core
+*.md    @acme/docs  # prose
+/crates/f/  @alice @acme/cli
+docs/generated/
+
+[Frontend]
+web/**/*.ts  dev@example.com
+";
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let owners = Owners::parse(CODEOWNERS);
+        assert_eq!(owners.of("Cargo.toml"), ["@acme/core"]);
+        assert_eq!(owners.of("crates/f-core/README.md"), ["@acme/docs"]);
+        assert_eq!(owners.of("crates/f/README.md"), ["@alice", "@acme/cli"]);
+        assert_eq!(owners.of("web/app/main.ts"), ["dev@example.com"]);
+        // A rule without owners leaves a path unowned
+        assert!(owners.of("docs/generated/api.html").is_empty());
+        assert!(Owners::parse("").of("src/lib.rs").is_empty());
+    }
+
+    #[test]
+    fn test_owned_by() {
+        let owners = Owners::parse(CODEOWNERS);
+        let me = vec!["@Alice".to_string(), "alice@example.com".to_string()];
+        assert!(owners.owned_by("crates/f/src/f.rs", &me));
+        assert!(!owners.owned_by("crates/f-core/src/lib.rs", &me));
+        assert!(!owners.owned_by("docs/generated/api.html", &me));
+    }
+
+    #[test]
+    fn test_load_from() {
+        let root = std::env::temp_dir().join(format!("f-owners-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join(".github")).unwrap();
+        assert!(Owners::load_from(&root).is_none());
+        fs::write(root.join("CODEOWNERS"), "* @root\n").unwrap();
+        fs::write(root.join(".github/CODEOWNERS"), "* @github\n").unwrap();
+        assert_eq!(Owners::load_from(&root).unwrap().of("a"), ["@github"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,45 @@
+//! Output longer than a screen, shown through the pager git would use for
+//! its own, and printed as is when stdout isn't a terminal.
+
+use crate::git_status::git_output;
+use anyhow::Result;
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// Pages `text` only when it has more lines than the terminal, so a short
+/// listing stays on screen with the prompt after it.
+pub fn page_if_tall(text: &str) -> Result<()> {
+    let rows = crossterm::terminal::size().map_or(usize::MAX, |(_, rows)| rows as usize);
+    if !std::io::stdout().is_terminal() || text.lines().count() < rows {
+        print!("{}", text);
+        return Ok(());
+    }
+    page(text)
+}
+
+pub fn page(text: &str) -> Result<()> {
+    let pager = git_output(&["var", "GIT_PAGER"]).unwrap_or_default();
+    if !std::io::stdout().is_terminal() || pager.is_empty() || pager == "cat" {
+        print!("{}", text);
+        return Ok(());
+    }
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(&pager).stdin(Stdio::piped());
+    // The same defaults git sets, so colors come through and a short text
+    // doesn't wait for `q`
+    if std::env::var_os("LESS").is_none() {
+        cmd.env("LESS", "FRX");
+    }
+    if std::env::var_os("LV").is_none() {
+        cmd.env("LV", "-c");
+    }
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", pager, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A pager quit early closes the pipe, which isn't an error
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! `f paste-apply`: a unified diff from stdin or the clipboard, previewed
+//! with `git apply --numstat` and applied with `git apply --3way`.
+
+use anyhow::Result;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+
+/// A file the diff touches, with its line counts (none for binary files).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub path: String,
+    pub added: Option<u32>,
+    pub removed: Option<u32>,
+}
+
+/// The files `diff` would change, or git's complaint when it isn't a diff
+/// git can read.
+pub fn preview(diff: &str, root: &Path) -> Result<Vec<Change>> {
+    let output = crate::exec::output_with_input(
+        Command::new("git")
+            .args(["apply", "--numstat", "-z"])
+            .current_dir(root),
+        Some(diff.as_bytes()),
+    )?;
+    Ok(parse_numstat(&String::from_utf8_lossy(&output)))
+}
+
+/// `--numstat -z`: `added\tremoved\tpath\0`, a rename under its new path
+/// and a binary file with `-` for both counts.
+fn parse_numstat(output: &str) -> Vec<Change> {
+    output
+        .split('\0')
+        .filter_map(|record| {
+            let mut parts = record.splitn(3, '\t');
+            let (added, removed, path) = (parts.next()?, parts.next()?, parts.next()?);
+            Some(Change {
+                path: path.to_string(),
+                added: added.parse().ok(),
+                removed: removed.parse().ok(),
+            })
+        })
+        .collect()
+}
+
+/// Applies `diff` at `root`, falling back to a three-way merge where it
+/// doesn't apply cleanly. `--3way` implies `--index`, so what applies is
+/// staged too. git reports what it did on stderr, which is left to reach
+/// the user.
+pub fn apply(diff: &str, root: &Path) -> Result<ExitStatus> {
+    let mut child = Command::new("git")
+        .args(["apply", "--3way"])
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(diff.as_bytes())?;
+    }
+    Ok(child.wait()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_numstat() {
+        let output = "3\t1\tsrc/lib.rs\0-\t-\tlogo.png\0";
+        assert_eq!(
+            parse_numstat(output),
+            [
+                Change {
+                    path: "src/lib.rs".to_string(),
+                    added: Some(3),
+                    removed: Some(1),
+                },
+                Change {
+                    path: "logo.png".to_string(),
+                    added: None,
+                    removed: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! External subcommands: like git and cargo, `f <name>` runs an `f-<name>`
+//! executable found on PATH, with the rest of the arguments. Custom actions
+//! in `~/.config/f/actions` work the same way and also show up in the
+//! interactive menu. Either is told about the repository and the file it
+//! was pointed at through `F_ROOT`, `F_FILE` and `F_ID`.
+
+use crate::git_status::GitFile;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where custom actions live: `~/.config/f/actions` on Linux.
+pub fn actions_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("f").join("actions"))
+}
+
+/// The custom actions, by name, sorted. An action is any executable in
+/// `actions_dir`, named after its file without the extension, so
+/// `stage-fmt.sh` is run as `f stage-fmt`. f has no interpreter of its
+/// own: a script is run by its shebang, and one that isn't executable,
+/// like a bare `.rhai` file, isn't an action.
+pub fn actions() -> Vec<(String, PathBuf)> {
+    actions_dir().map_or_else(Vec::new, |dir| actions_in(&dir))
+}
+
+fn actions_in(dir: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut actions: Vec<(String, PathBuf)> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|path| is_executable(path))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            Some((name, path))
+        })
+        .collect();
+    actions.sort();
+    actions
+}
+
+/// The custom action or `f-<name>` executable on PATH called `name`, in
+/// that order.
+pub fn find(name: &str) -> Option<PathBuf> {
+    if name.is_empty() || name.starts_with('-') || name.contains(std::path::is_separator) {
+        return None;
+    }
+    if let Some((_, path)) = actions().into_iter().find(|(n, _)| n == name) {
+        return Some(path);
+    }
+    let file_name = format!("f-{}", name);
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| is_executable(path))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// The command running `plugin` with `args`. `file` is the changed file
+/// the first argument named, if it named one.
+pub fn command(
+    plugin: &Path,
+    args: &[&str],
+    root: Option<&Path>,
+    file: Option<&GitFile>,
+) -> Command {
+    let mut cmd = Command::new(plugin);
+    cmd.args(args);
+    if let Some(root) = root {
+        cmd.env("F_ROOT", root);
+    }
+    if let Some(file) = file {
+        cmd.env("F_FILE", &file.abs_path)
+            .env("F_ID", &file.stable_id.display);
+    }
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_rejects_paths_and_flags() {
+        assert_eq!(find(""), None);
+        assert_eq!(find("--help"), None);
+        assert_eq!(find("../sh"), None);
+        assert_eq!(find("no-such-plugin-for-sure"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_actions_are_the_executables() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("f-actions-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("subdir")).unwrap();
+        for (name, mode) in [
+            ("stage-fmt.sh", 0o755),
+            ("blame", 0o700),
+            ("notes.rhai", 0o644),
+        ] {
+            let path = dir.join(name);
+            std::fs::write(&path, "#!/bin/sh\n").unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        }
+        let names: Vec<String> = actions_in(&dir).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["blame", "stage-fmt"]);
+        assert!(actions_in(&dir.join("missing")).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
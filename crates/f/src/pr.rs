@@ -0,0 +1,245 @@
+//! `f pr`: pull requests through the forge's own CLI, `gh` on GitHub and
+//! `glab` for GitLab merge requests. f fills in what those can't know: a
+//! title and description from the commits going out, and IDs for the
+//! files a PR changes.
+
+use crate::error::Error;
+use crate::forge::Forge;
+use crate::json;
+use anyhow::Result;
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Gh,
+    Glab,
+}
+
+impl Tool {
+    pub fn for_forge(forge: Forge) -> Result<Tool> {
+        match forge {
+            Forge::GitHub => Ok(Tool::Gh),
+            Forge::GitLab => Ok(Tool::Glab),
+            Forge::Bitbucket => {
+                Err(Error::Usage("f pr supports GitHub (gh) and GitLab (glab)".into()).into())
+            }
+        }
+    }
+
+    pub fn program(self) -> &'static str {
+        match self {
+            Tool::Gh => "gh",
+            Tool::Glab => "glab",
+        }
+    }
+
+    /// Fails with where to get the tool when it isn't on PATH.
+    pub fn check_installed(self) -> Result<()> {
+        let installed = crate::exec::run_output(Command::new(self.program()).arg("--version"))
+            .is_ok_and(|o| o.status.success());
+        if installed {
+            return Ok(());
+        }
+        let url = match self {
+            Tool::Gh => "https://cli.github.com",
+            Tool::Glab => "https://gitlab.com/gitlab-org/cli",
+        };
+        Err(Error::NotFound(format!(
+            "f pr runs {}, which isn't installed ({})",
+            self.program(),
+            url
+        ))
+        .into())
+    }
+
+    pub fn create_args(
+        self,
+        title: &str,
+        body: &str,
+        draft: bool,
+        base: Option<&str>,
+    ) -> Vec<String> {
+        let (command, body_flag, base_flag) = match self {
+            Tool::Gh => (["pr", "create"], "--body", "--base"),
+            Tool::Glab => (["mr", "create"], "--description", "--target-branch"),
+        };
+        let mut args: Vec<String> = command.iter().map(|s| s.to_string()).collect();
+        args.extend([
+            "--title".into(),
+            title.into(),
+            body_flag.into(),
+            body.into(),
+        ]);
+        if draft {
+            args.push("--draft".into());
+        }
+        if let Some(base) = base {
+            args.extend([base_flag.into(), base.into()]);
+        }
+        args
+    }
+
+    pub fn status_args(self) -> &'static [&'static str] {
+        match self {
+            Tool::Gh => &["pr", "status"],
+            Tool::Glab => &["mr", "view"],
+        }
+    }
+
+    pub fn checks_args(self) -> &'static [&'static str] {
+        match self {
+            Tool::Gh => &["pr", "checks"],
+            Tool::Glab => &["ci", "status"],
+        }
+    }
+
+    /// The branch the current branch's PR merges into, asked of the forge.
+    pub fn base_branch(self) -> Result<String> {
+        let args: &[&str] = match self {
+            Tool::Gh => &["pr", "view", "--json", "baseRefName"],
+            Tool::Glab => &["mr", "view", "--output", "json"],
+        };
+        let output = crate::exec::run_output(Command::new(self.program()).args(args))
+            .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", self.program(), e))?;
+        if !output.status.success() {
+            return Err(Error::NotFound(format!(
+                "No pull request for this branch ({})",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+            .into());
+        }
+        self.parse_base_branch(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    fn parse_base_branch(self, output: &str) -> Result<String> {
+        let key = match self {
+            Tool::Gh => "baseRefName",
+            Tool::Glab => "target_branch",
+        };
+        json::parse(output)?
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                anyhow::anyhow!("{} didn't say which branch the PR targets", self.program())
+            })
+    }
+}
+
+/// A title and description for a PR of `commits` (subject and body,
+/// newest first as git logs them). One commit speaks for itself; for
+/// several, the first commit names the PR and the rest are listed.
+pub fn prefill(commits: &[(String, String)]) -> (String, String) {
+    match commits {
+        [] => (String::new(), String::new()),
+        [(subject, body)] => (subject.clone(), body.trim().to_string()),
+        _ => {
+            let title = commits.last().map(|(s, _)| s.clone()).unwrap_or_default();
+            let body = commits
+                .iter()
+                .rev()
+                .map(|(subject, _)| format!("- {}", subject))
+                .collect::<Vec<_>>()
+                .join("\n");
+            (title, body)
+        }
+    }
+}
+
+/// Subjects and bodies of the commits in `range`, newest first.
+pub fn messages(range: &[&str]) -> Result<Vec<(String, String)>> {
+    let output = crate::exec::run_output(
+        Command::new("git")
+            .args(["log", "--no-color", "--format=%s%x1f%b%x1e"])
+            .args(range)
+            .arg("--"),
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(parse_messages(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_messages(output: &str) -> Vec<(String, String)> {
+    output
+        .split('\x1e')
+        .filter_map(|record| {
+            let (subject, body) = record.trim_start_matches('\n').split_once('\x1f')?;
+            Some((subject.to_string(), body.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(subject: &str, body: &str) -> (String, String) {
+        (subject.to_string(), body.to_string())
+    }
+
+    #[test]
+    fn test_prefill() {
+        assert_eq!(
+            prefill(&[commit("Fix the parser", "It dropped quotes.\n")]),
+            (
+                "Fix the parser".to_string(),
+                "It dropped quotes.".to_string()
+            )
+        );
+        let (title, body) = prefill(&[
+            commit("Document the option", ""),
+            commit("Add --force", "Details"),
+        ]);
+        assert_eq!(title, "Add --force");
+        assert_eq!(body, "- Add --force\n- Document the option");
+    }
+
+    #[test]
+    fn test_parse_messages() {
+        let output = "Second\x1f\x1e\nFirst\x1fLine one\n\nLine two\n\x1e\n";
+        assert_eq!(
+            parse_messages(output),
+            [
+                commit("Second", ""),
+                commit("First", "Line one\n\nLine two")
+            ]
+        );
+    }
+
+    #[test]
+    fn create_args_per_tool() {
+        assert_eq!(
+            Tool::Gh.create_args("T", "B", true, Some("main")),
+            [
+                "pr", "create", "--title", "T", "--body", "B", "--draft", "--base", "main"
+            ]
+        );
+        assert_eq!(
+            Tool::Glab.create_args("T", "B", false, None),
+            ["mr", "create", "--title", "T", "--description", "B"]
+        );
+        assert!(Tool::for_forge(Forge::Bitbucket).is_err());
+    }
+
+    #[test]
+    fn test_parse_base_branch() {
+        assert_eq!(
+            Tool::Gh
+                .parse_base_branch("{\"baseRefName\":\"main\"}\n")
+                .unwrap(),
+            "main"
+        );
+        assert_eq!(
+            Tool::Glab
+                .parse_base_branch("{\"iid\":3,\"target_branch\":\"develop\"}")
+                .unwrap(),
+            "develop"
+        );
+        assert!(Tool::Gh.parse_base_branch("{}").is_err());
+    }
+}
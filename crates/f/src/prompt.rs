@@ -0,0 +1,68 @@
+use crate::git_status::StatusSummary;
+use colored::Colorize;
+
+/// Renders a compact one-line summary such as `main ↑1 ✚3 ●2 …1`.
+///
+/// Staged, unstaged and untracked counts use `✚`, `●` and `…`; conflicts use
+/// `✖`. Empty sections are omitted, so a clean branch renders as just its name.
+pub fn format_prompt(summary: &StatusSummary) -> String {
+    let mut parts = Vec::new();
+
+    let branch = summary.branch.as_deref().unwrap_or("(detached)");
+    parts.push(branch.magenta().to_string());
+
+    let mut tracking = String::new();
+    if summary.ahead > 0 {
+        tracking.push_str(&format!("↑{}", summary.ahead));
+    }
+    if summary.behind > 0 {
+        tracking.push_str(&format!("↓{}", summary.behind));
+    }
+    if !tracking.is_empty() {
+        parts.push(tracking.blue().to_string());
+    }
+
+    let counts = [
+        (summary.conflicted, "✖", "red"),
+        (summary.staged, "✚", "cyan"),
+        (summary.unstaged, "●", "yellow"),
+        (summary.untracked, "…", "green"),
+    ];
+    for (count, symbol, color) in counts {
+        if count > 0 {
+            parts.push(format!("{}{}", symbol, count).color(color).to_string());
+        }
+    }
+
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_prompt_clean_branch() {
+        let _colors = crate::snapshot::colors(false);
+        let summary = StatusSummary {
+            branch: Some("main".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(format_prompt(&summary), "main");
+    }
+
+    #[test]
+    fn format_prompt_dirty_branch() {
+        let _colors = crate::snapshot::colors(false);
+        let summary = StatusSummary {
+            branch: Some("main".to_string()),
+            ahead: 1,
+            behind: 2,
+            staged: 3,
+            unstaged: 2,
+            untracked: 1,
+            ..Default::default()
+        };
+        assert_eq!(format_prompt(&summary), "main ↑1↓2 ✚3 ●2 …1");
+    }
+}
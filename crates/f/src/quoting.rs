@@ -0,0 +1,124 @@
+//! Git's C-style path quoting, as used by `core.quotePath` in line-oriented
+//! output: special bytes become `\t`, `\"`, `\\` or `\ooo` octal escapes
+//! and the whole path is wrapped in double quotes.
+
+/// Quotes a path using git's C-style quoting when it contains characters that
+/// would break line- or tab-oriented parsing.
+pub fn quote_path(path: &str) -> String {
+    let needs_quoting = path
+        .bytes()
+        .any(|b| b < 0x20 || b == 0x7f || b >= 0x80 || b == b'"' || b == b'\\');
+    if !needs_quoting {
+        return path.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    for b in path.bytes() {
+        match b {
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            b'\t' => quoted.push_str("\\t"),
+            b'\n' => quoted.push_str("\\n"),
+            b'\r' => quoted.push_str("\\r"),
+            0x20..=0x7e => quoted.push(b as char),
+            _ => quoted.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Reverses `quote_path` (and git's own quoting). Returns `None` if `quoted`
+/// isn't a well-formed quoted string.
+pub fn unquote_path(quoted: &str) -> Option<String> {
+    let inner = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut iter = inner.bytes().peekable();
+    while let Some(b) = iter.next() {
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        let escaped = match iter.next()? {
+            b'"' => b'"',
+            b'\\' => b'\\',
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'v' => 0x0b,
+            d @ b'0'..=b'3' => {
+                let mut value = (d - b'0') as u32;
+                for _ in 0..2 {
+                    let digit = iter.next().filter(|c| (b'0'..=b'7').contains(c))?;
+                    value = value * 8 + (digit - b'0') as u32;
+                }
+                value as u8
+            }
+            _ => return None,
+        };
+        bytes.push(escaped);
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// A path that may or may not be quoted, as found in git output or piped
+/// back from `f list --porcelain`.
+pub fn maybe_unquote(path: &str) -> String {
+    if path.starts_with('"') {
+        unquote_path(path).unwrap_or_else(|| path.to_string())
+    } else {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unquote_octal_utf8() {
+        assert_eq!(
+            unquote_path(r#""a\303\251.txt""#).as_deref(),
+            Some("aé.txt")
+        );
+    }
+
+    #[test]
+    fn test_unquote_escapes() {
+        assert_eq!(
+            unquote_path(r#""say \"hi\"\tnow\\then""#).as_deref(),
+            Some("say \"hi\"\tnow\\then")
+        );
+    }
+
+    #[test]
+    fn test_unquote_rejects_malformed() {
+        assert_eq!(unquote_path("plain.txt"), None);
+        assert_eq!(unquote_path(r#""bad\q""#), None);
+        assert_eq!(unquote_path(r#""short\30""#), None);
+        assert_eq!(unquote_path(r#""trailing\""#), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for path in [
+            "plain.txt",
+            "café.txt",
+            "quote\"d",
+            "back\\slash",
+            "new\nline",
+            "\u{7f}del",
+        ] {
+            assert_eq!(maybe_unquote(&quote_path(path)), path);
+        }
+    }
+
+    #[test]
+    fn test_maybe_unquote_leaves_plain_paths() {
+        assert_eq!(maybe_unquote("src/main.rs"), "src/main.rs");
+        assert_eq!(maybe_unquote("\"unterminated"), "\"unterminated");
+    }
+}
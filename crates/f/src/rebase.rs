@@ -0,0 +1,172 @@
+//! The todo list of `f rebase -i`, edited in f's own picker and handed to
+//! `git rebase -i` in place of the one git would open in the editor.
+
+use crate::commits::Commit;
+use crate::error::Error;
+use crate::exec::quote;
+use crate::git_status::get_git_dir;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+/// What git does with a commit of the todo list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verb {
+    Pick,
+    Reword,
+    Squash,
+    Fixup,
+    Drop,
+}
+
+impl Verb {
+    pub const ALL: [Verb; 5] = [
+        Verb::Pick,
+        Verb::Reword,
+        Verb::Squash,
+        Verb::Fixup,
+        Verb::Drop,
+    ];
+
+    /// The todo command, which is also how the picker shows it.
+    pub fn word(self) -> &'static str {
+        match self {
+            Verb::Pick => "pick",
+            Verb::Reword => "reword",
+            Verb::Squash => "squash",
+            Verb::Fixup => "fixup",
+            Verb::Drop => "drop",
+        }
+    }
+
+    /// The key that sets it, its first letter as in git's own abbreviations.
+    pub fn key(self) -> char {
+        self.word().chars().next().unwrap_or('p')
+    }
+
+    pub fn from_key(key: char) -> Option<Verb> {
+        Verb::ALL.into_iter().find(|verb| verb.key() == key)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub verb: Verb,
+    pub commit: Commit,
+}
+
+/// A step picking each of `commits`, which `git log` lists newest first,
+/// in the order the rebase applies them.
+pub fn steps(commits: Vec<Commit>) -> Vec<Step> {
+    commits
+        .into_iter()
+        .rev()
+        .map(|commit| Step {
+            verb: Verb::Pick,
+            commit,
+        })
+        .collect()
+}
+
+/// Squashing or fixing up needs something to fold into.
+pub fn check(steps: &[Step]) -> Result<()> {
+    let first = steps.iter().find(|step| step.verb != Verb::Drop);
+    match first {
+        Some(step) if matches!(step.verb, Verb::Squash | Verb::Fixup) => {
+            Err(Error::Usage(format!(
+                "Can't {} {}, there's no commit before it to fold into",
+                step.verb.word(),
+                step.commit.short
+            ))
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The todo list in git's format.
+pub fn render(steps: &[Step]) -> String {
+    steps
+        .iter()
+        .map(|step| {
+            format!(
+                "{} {} {}\n",
+                step.verb.word(),
+                step.commit.sha,
+                step.commit.subject
+            )
+        })
+        .collect()
+}
+
+/// Writes the todo list to `.git/f/rebase-todo` and returns the
+/// `GIT_SEQUENCE_EDITOR` that puts it in place of git's.
+pub fn sequence_editor(steps: &[Step]) -> Result<String> {
+    let path: PathBuf = get_git_dir()?.join("f").join("rebase-todo");
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, render(steps))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(format!("cp {}", quote(&path.to_string_lossy())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_status::StableId;
+
+    fn step(verb: Verb, short: &str, subject: &str) -> Step {
+        Step {
+            verb,
+            commit: Commit {
+                sha: short.repeat(5),
+                short: short.to_string(),
+                subject: subject.to_string(),
+                author: "Test".to_string(),
+                date: "2 days ago".to_string(),
+                id: StableId {
+                    display: "aa".to_string(),
+                    full_hash: "aaaa".to_string(),
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let steps = [
+            step(Verb::Pick, "a1b2c3d4", "Add the parser"),
+            step(Verb::Fixup, "e5f6a7b8", "fixup! Add the parser"),
+        ];
+        assert_eq!(
+            render(&steps),
+            format!(
+                "pick {} Add the parser\nfixup {} fixup! Add the parser\n",
+                "a1b2c3d4".repeat(5),
+                "e5f6a7b8".repeat(5)
+            )
+        );
+    }
+
+    #[test]
+    fn check_needs_a_commit_to_fold_into() {
+        let squash_first = [
+            step(Verb::Drop, "a1b2c3d4", "One"),
+            step(Verb::Squash, "e5f6a7b8", "Two"),
+        ];
+        assert!(check(&squash_first).is_err());
+        let fine = [
+            step(Verb::Reword, "a1b2c3d4", "One"),
+            step(Verb::Fixup, "e5f6a7b8", "Two"),
+        ];
+        assert!(check(&fine).is_ok());
+    }
+
+    #[test]
+    fn verbs_by_key() {
+        assert_eq!(Verb::from_key('s'), Some(Verb::Squash));
+        assert_eq!(Verb::from_key('d'), Some(Verb::Drop));
+        assert_eq!(Verb::from_key('x'), None);
+    }
+}
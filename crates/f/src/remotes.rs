@@ -0,0 +1,164 @@
+//! Remotes for `f remote`: where each points, what it tracks and when it
+//! was last fetched.
+
+use anyhow::{Result, bail};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote {
+    pub name: String,
+    pub fetch_url: String,
+    /// Only when pushes go somewhere else
+    pub push_url: Option<String>,
+    /// Remote-tracking branches, not counting `HEAD`
+    pub branches: usize,
+    /// Seconds since the epoch
+    pub fetched: Option<u64>,
+}
+
+pub fn list(git_dir: &Path) -> Result<Vec<Remote>> {
+    let urls = run(&["remote", "-v"])?;
+    let refs = run(&["for-each-ref", "--format=%(refname)", "refs/remotes"])?;
+    let fetch_head = fs::read_to_string(git_dir.join("FETCH_HEAD")).unwrap_or_default();
+    let fetch_head_time = mtime(&git_dir.join("FETCH_HEAD"));
+    Ok(parse_remotes(&urls)
+        .into_iter()
+        .map(|(name, fetch_url, push_url)| {
+            let prefix = format!("refs/remotes/{}/", name);
+            let branches = refs
+                .lines()
+                .filter(|r| r.strip_prefix(&prefix).is_some_and(|b| b != "HEAD"))
+                .count();
+            // FETCH_HEAD is from the last fetch of any remote, which it
+            // names by URL without the `.git`; the reflogs of tracking
+            // branches only move when a fetch changes them
+            let url = fetch_url.trim_end_matches('/').trim_end_matches(".git");
+            let from_fetch_head =
+                fetch_head_time.filter(|_| fetch_head.lines().any(|l| l.ends_with(url)));
+            let from_reflogs = newest_mtime(&git_dir.join("logs/refs/remotes").join(&name));
+            Remote {
+                fetched: from_fetch_head.max(from_reflogs),
+                name,
+                fetch_url,
+                push_url,
+                branches,
+            }
+        })
+        .collect())
+}
+
+fn run(args: &[&str]) -> Result<String> {
+    let output = crate::exec::run_output(Command::new("git").args(args))
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `git remote -v`: a fetch and a push line per remote.
+fn parse_remotes(output: &str) -> Vec<(String, String, Option<String>)> {
+    let mut remotes: Vec<(String, String, Option<String>)> = Vec::new();
+    for line in output.lines() {
+        let Some((name, rest)) = line.split_once('\t') else {
+            continue;
+        };
+        let Some((url, kind)) = rest.rsplit_once(' ') else {
+            continue;
+        };
+        if !remotes.iter().any(|(n, _, _)| n == name) {
+            remotes.push((name.to_string(), String::new(), None));
+        }
+        let Some(remote) = remotes.iter_mut().find(|(n, _, _)| n == name) else {
+            continue;
+        };
+        match kind {
+            "(fetch)" => remote.1 = url.to_string(),
+            "(push)" => remote.2 = Some(url.to_string()),
+            _ => {}
+        }
+    }
+    for remote in &mut remotes {
+        if remote.2.as_ref() == Some(&remote.1) {
+            remote.2 = None;
+        }
+    }
+    remotes
+}
+
+fn mtime(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+fn newest_mtime(dir: &Path) -> Option<u64> {
+    let mut newest = None;
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        let time = if path.is_dir() {
+            newest_mtime(&path)
+        } else {
+            mtime(&path)
+        };
+        newest = newest.max(time);
+    }
+    newest
+}
+
+/// Whether `fetched` is old enough to mention, over a day.
+pub fn is_stale(fetched: Option<u64>) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    fetched.is_none_or(|time| now.saturating_sub(time) > 86400)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_remotes() {
+        let output = "origin\tgit@github.com:acme/f.git (fetch)\n\
+                      origin\tgit@github.com:acme/f.git (push)\n\
+                      fork\thttps://example.com/f.git (fetch)\n\
+                      fork\tgit@example.com:me/f.git (push)\n";
+        assert_eq!(
+            parse_remotes(output),
+            [
+                (
+                    "origin".to_string(),
+                    "git@github.com:acme/f.git".to_string(),
+                    None
+                ),
+                (
+                    "fork".to_string(),
+                    "https://example.com/f.git".to_string(),
+                    Some("git@example.com:me/f.git".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_stale() {
+        assert!(is_stale(None));
+        assert!(is_stale(Some(0)));
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert!(!is_stale(Some(now - 60)));
+    }
+}
@@ -0,0 +1,286 @@
+use crate::cache::StatusCache;
+use crate::config::Config;
+use crate::git_status::{
+    DiffStats, FileType, GitFile, IdMatch, StableId, file_diff, find_file_by_id,
+};
+use crate::json::{self, Value};
+use anyhow::Result;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+const NOT_FOUND: i64 = -32001;
+const AMBIGUOUS: i64 = -32002;
+const GIT_FAILED: i64 = -32003;
+
+/// How long a status snapshot is reused between requests. Short enough that
+/// edits made outside f show up almost immediately.
+pub const CACHE_TTL: Duration = Duration::from_millis(500);
+
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+pub fn file_json(file: &GitFile) -> Value {
+    let stats = file.diff_stats.as_ref();
+    Value::object([
+        ("id", Value::from(file.stable_id.display.as_str())),
+        ("full_id", Value::from(file.stable_id.full_hash.as_str())),
+        ("type", Value::from(file.file_type.as_str())),
+        ("path", Value::from(file.rel_path.as_str())),
+        (
+            "abs_path",
+            Value::from(file.abs_path.to_string_lossy().into_owned()),
+        ),
+        ("added", Value::from(stats.map(|s| s.added))),
+        ("removed", Value::from(stats.map(|s| s.removed))),
+        ("mtime", Value::from(file.mtime)),
+        ("lfs_size", Value::from(file.lfs_size)),
+        (
+            "old_mode",
+            Value::from(file.mode_change.map(|(old, _)| old)),
+        ),
+        (
+            "new_mode",
+            Value::from(file.mode_change.map(|(_, new)| new)),
+        ),
+        ("status", Value::from(file.status.to_string())),
+    ])
+}
+
+/// Inverse of `file_json`, for clients reading files back from the daemon.
+pub fn file_from_json(value: &Value) -> Option<GitFile> {
+    let field = |key| value.get(key).and_then(Value::as_str);
+    let count = |key| value.get(key).and_then(Value::as_u64).map(|n| n as u32);
+    let file_type = FileType::from_name(field("type")?)?;
+    Some(GitFile {
+        mtime: value.get("mtime")?.as_u64()?,
+        rel_path: field("path")?.to_string(),
+        abs_path: PathBuf::from(field("abs_path")?),
+        file_type,
+        stable_id: StableId {
+            display: field("id")?.to_string(),
+            full_hash: field("full_id")?.to_string(),
+        },
+        diff_stats: match (count("added"), count("removed")) {
+            (Some(added), Some(removed)) => Some(DiffStats { added, removed }),
+            _ => None,
+        },
+        lfs_size: value.get("lfs_size").and_then(Value::as_u64),
+        mode_change: match (count("old_mode"), count("new_mode")) {
+            (Some(old), Some(new)) => Some((old, new)),
+            _ => None,
+        },
+        status: match field("status").and_then(|s| s.chars().next()) {
+            Some(status) => status,
+            None if file_type == FileType::Untracked => '?',
+            None => 'M',
+        },
+    })
+}
+
+/// Serves newline-delimited JSON-RPC 2.0 over stdin/stdout until stdin closes.
+///
+/// Methods: `list`, `resolve {id}`, `stage {id}`, `unstage {id}` and
+/// `diff {id, staged?}`. The file list stays cached between requests so
+/// editor plugins can query on every keystroke.
+pub fn run(config: &Config) -> Result<()> {
+    let mut cache = StatusCache::new(config.id_chars(), CACHE_TTL);
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_line(&line, &mut cache) {
+            writeln!(stdout, "{}", response)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Handles one request line, returning the response to send (if any).
+pub fn handle_line(line: &str, cache: &mut StatusCache) -> Option<Value> {
+    let request = match json::parse(line) {
+        Ok(r) => r,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, e.to_string()),
+            ));
+        }
+    };
+    // Requests without an id are notifications and get no response
+    let id = request.get("id").cloned()?;
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return Some(error_response(
+            id,
+            RpcError::new(INVALID_REQUEST, "Missing method"),
+        ));
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = dispatch(method, &params, cache);
+    Some(match result {
+        Ok(value) => Value::object([
+            ("jsonrpc", Value::from("2.0")),
+            ("id", id),
+            ("result", value),
+        ]),
+        Err(e) => error_response(id, e),
+    })
+}
+
+pub fn error_response(id: Value, error: RpcError) -> Value {
+    Value::object([
+        ("jsonrpc", Value::from("2.0")),
+        ("id", id),
+        (
+            "error",
+            Value::object([
+                ("code", Value::from(error.code)),
+                ("message", Value::from(error.message)),
+            ]),
+        ),
+    ])
+}
+
+pub fn dispatch(method: &str, params: &Value, cache: &mut StatusCache) -> Result<Value, RpcError> {
+    match method {
+        "list" => {
+            let files = cache_files(cache)?;
+            Ok(Value::Array(files.iter().map(file_json).collect()))
+        }
+        "resolve" => Ok(file_json(&resolve(params, cache)?)),
+        "stage" => {
+            let file = resolve(params, cache)?;
+            run_git(&["add", "--", &file.abs_path.to_string_lossy()])?;
+            cache.invalidate();
+            Ok(file_json(&file))
+        }
+        "unstage" => {
+            let file = resolve(params, cache)?;
+            run_git(&[
+                "restore",
+                "--staged",
+                "--",
+                &file.abs_path.to_string_lossy(),
+            ])?;
+            cache.invalidate();
+            Ok(file_json(&file))
+        }
+        "diff" => {
+            let file = resolve(params, cache)?;
+            let staged = params
+                .get("staged")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            let diff =
+                file_diff(&file, staged).map_err(|e| RpcError::new(GIT_FAILED, e.to_string()))?;
+            Ok(Value::object([
+                ("file", file_json(&file)),
+                ("diff", Value::from(diff)),
+            ]))
+        }
+        other => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method: {}", other),
+        )),
+    }
+}
+
+fn cache_files(cache: &mut StatusCache) -> Result<&[GitFile], RpcError> {
+    cache
+        .files()
+        .map_err(|e| RpcError::new(GIT_FAILED, e.to_string()))
+}
+
+fn resolve(params: &Value, cache: &mut StatusCache) -> Result<GitFile, RpcError> {
+    let Some(id) = params.get("id").and_then(Value::as_str) else {
+        return Err(RpcError::new(INVALID_PARAMS, "Missing string param 'id'"));
+    };
+    match find_file_by_id(cache_files(cache)?, id) {
+        IdMatch::Unique(f) => Ok(f),
+        IdMatch::Ambiguous(n) => Err(RpcError::new(
+            AMBIGUOUS,
+            format!("ID '{}' matches {} files - be more specific", id, n),
+        )),
+        IdMatch::NotFound => Err(RpcError::new(
+            NOT_FOUND,
+            format!("No file matches ID: {}", id),
+        )),
+    }
+}
+
+pub fn run_git(args: &[&str]) -> Result<String, RpcError> {
+    let output = crate::exec::run_output(Command::new("git").args(args))
+        .map_err(|e| RpcError::new(GIT_FAILED, format!("Failed to run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(RpcError::new(
+            GIT_FAILED,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_json_round_trips() {
+        let file = GitFile {
+            mtime: 1_700_000_000,
+            rel_path: "src/new\nline.rs".to_string(),
+            abs_path: PathBuf::from("/repo/src/new\nline.rs"),
+            file_type: FileType::Untracked,
+            stable_id: StableId {
+                display: "df".to_string(),
+                full_hash: "dfkjsl".to_string(),
+            },
+            diff_stats: Some(DiffStats {
+                added: 4,
+                removed: 0,
+            }),
+            lfs_size: Some(120_000_000),
+            mode_change: Some((0o100644, 0o100755)),
+            status: '?',
+        };
+        let parsed = json::parse(&file_json(&file).to_string()).unwrap();
+        let back = file_from_json(&parsed).unwrap();
+        assert_eq!(back.rel_path, file.rel_path);
+        assert_eq!(back.abs_path, file.abs_path);
+        assert_eq!(back.file_type, file.file_type);
+        assert_eq!(back.mtime, file.mtime);
+        assert_eq!(back.stable_id.full_hash, "dfkjsl");
+        assert_eq!(back.diff_stats.map(|s| s.added), Some(4));
+        assert_eq!(back.lfs_size, Some(120_000_000));
+        assert_eq!(back.mode_change, Some((0o100644, 0o100755)));
+        assert_eq!(back.status, '?');
+    }
+
+    #[test]
+    fn file_from_json_rejects_incomplete_objects() {
+        let value = json::parse(r#"{"id":"df","type":"staged"}"#).unwrap();
+        assert!(file_from_json(&value).is_none());
+    }
+}
@@ -0,0 +1,148 @@
+//! `f session`: records the f actions taken while a session is active as a
+//! `f batch` script in `.git/f/sessions/<name>`, by path rather than by ID
+//! since IDs change as files do, so the same chore can be replayed later.
+
+use crate::error::Error;
+use crate::exec::quote;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+pub struct Sessions {
+    /// `.git/f/sessions`, one script per session
+    dir: PathBuf,
+    /// `.git/f/session`, holding the name of the session being recorded
+    active: PathBuf,
+}
+
+impl Sessions {
+    pub fn open() -> Result<Sessions> {
+        let f_dir = crate::git_status::get_git_dir()?.join("f");
+        Ok(Sessions {
+            dir: f_dir.join("sessions"),
+            active: f_dir.join("session"),
+        })
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// The session being recorded, if any.
+    pub fn active(&self) -> Option<String> {
+        let name = fs::read_to_string(&self.active).ok()?;
+        let name = name.trim();
+        (!name.is_empty()).then(|| name.to_string())
+    }
+
+    /// Starts recording into `name`, replacing an earlier session of that
+    /// name.
+    pub fn start(&self, name: &str) -> Result<()> {
+        check_name(name)?;
+        if let Some(active) = self.active() {
+            return Err(Error::Usage(format!(
+                "Already recording session {}, run f session stop first",
+                active
+            ))
+            .into());
+        }
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(name);
+        fs::write(&path, format!("# f session {}\n", name))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        fs::write(&self.active, format!("{}\n", name))
+            .with_context(|| format!("Failed to write {}", self.active.display()))
+    }
+
+    /// Stops recording, returning the session's name.
+    pub fn stop(&self) -> Result<String> {
+        let name = self
+            .active()
+            .ok_or_else(|| Error::NotFound("No session is being recorded".into()))?;
+        fs::remove_file(&self.active)?;
+        Ok(name)
+    }
+
+    /// Appends `command` with `args` to the active session, if there is
+    /// one, quoted so `f batch` reads the arguments back as given.
+    pub fn record(&self, command: &str, args: &[&str]) -> Result<()> {
+        let Some(name) = self.active() else {
+            return Ok(());
+        };
+        let path = self.path(&name);
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        writeln!(file, "{}", line(command, args))?;
+        Ok(())
+    }
+
+    /// The recorded sessions' names, sorted.
+    pub fn list(&self) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    pub fn read(&self, name: &str) -> Result<String> {
+        check_name(name)?;
+        fs::read_to_string(self.path(name))
+            .map_err(|_| Error::NotFound(format!("No session named {}", name)).into())
+    }
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with('.') || name.contains(std::path::is_separator) {
+        return Err(Error::Usage(format!("Invalid session name: {}", name)).into());
+    }
+    Ok(())
+}
+
+fn line(command: &str, args: &[&str]) -> String {
+    let mut line = command.to_string();
+    for arg in args {
+        line.push(' ');
+        line.push_str(&quote(arg));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::{Step, parse};
+
+    #[test]
+    fn test_line_round_trips_through_batch() {
+        let script = [
+            line("edit", &["Cargo.toml", "docs/it's here.md"]),
+            line("add", &["Cargo.toml"]),
+            line("commit", &["Bump version to 1.2.0"]),
+        ]
+        .join("\n");
+        let lines = parse(&script).unwrap();
+        assert_eq!(
+            lines[0].step,
+            Step::Edit(vec!["Cargo.toml".into(), "docs/it's here.md".into()])
+        );
+        assert_eq!(lines[1].step, Step::Add(vec!["Cargo.toml".into()]));
+        assert_eq!(lines[2].step, Step::Commit("Bump version to 1.2.0".into()));
+    }
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("release").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("../x").is_err());
+        assert!(check_name(".hidden").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+//! `f config show`: what f uses for each setting it shares with git, and
+//! where that came from, so f's config and git's can be told apart.
+//! Where f.toml is silent, git's own configuration comes before f's
+//! defaults.
+
+use crate::config::Config;
+use crate::git_status::git_output;
+use colored::Colorize;
+use std::fmt::Write;
+
+/// The tool git's `diff.tool` names, for `git difftool`.
+pub fn diff_tool() -> Option<String> {
+    git_output(&["config", "diff.tool"])
+}
+
+/// The tool git's `merge.tool` names, for `git mergetool`.
+pub fn merge_tool() -> Option<String> {
+    git_output(&["config", "merge.tool"])
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    pub name: &'static str,
+    pub value: String,
+    pub source: String,
+}
+
+impl Setting {
+    fn new(name: &'static str, value: impl Into<String>, source: impl Into<String>) -> Self {
+        Setting {
+            name,
+            value: value.into(),
+            source: source.into(),
+        }
+    }
+}
+
+/// Every setting f resolves between its config, git's and the
+/// environment, in the order `f config show` lists them.
+pub fn effective(config: &Config) -> Vec<Setting> {
+    let mut settings = Vec::new();
+    let file = match Config::config_path() {
+        Some(path) if path.exists() => Setting::new("config", path.display().to_string(), ""),
+        Some(path) => Setting::new("config", path.display().to_string(), "not found"),
+        None => Setting::new("config", "none", "no config directory"),
+    };
+    settings.push(file);
+
+    let (editor, source) = config.editor_source();
+    settings.push(Setting::new("editor", editor, source));
+
+    let image_diff = match (&config.images.diff_tool, diff_tool()) {
+        (Some(tool), _) => Setting::new("image diff", tool, "f.toml [images] diff_tool"),
+        (None, Some(tool)) => Setting::new(
+            "image diff",
+            format!("git difftool -t {}", tool),
+            "git diff.tool",
+        ),
+        (None, None) => Setting::new("image diff", "summary and previews", "default"),
+    };
+    settings.push(image_diff);
+
+    let merge = match merge_tool() {
+        Some(tool) => Setting::new(
+            "mergetool",
+            format!("git mergetool -t {}", tool),
+            "git merge.tool",
+        ),
+        None => Setting::new("mergetool", "none", "default"),
+    };
+    settings.push(merge);
+
+    let gpgsign = git_output(&["config", "--type=bool", "commit.gpgsign"]);
+    let signing = if config.commit.sign {
+        Setting::new("signing", "on", "f.toml [commit] sign")
+    } else {
+        match gpgsign.as_deref() {
+            Some(on) => Setting::new(
+                "signing",
+                if on == "true" { "on" } else { "off" },
+                "git commit.gpgsign",
+            ),
+            None => Setting::new("signing", "off", "default"),
+        }
+    };
+    settings.push(signing);
+
+    let pager = match git_output(&["var", "GIT_PAGER"]) {
+        Some(pager) => Setting::new("pager", pager, "git var GIT_PAGER"),
+        None => Setting::new("pager", "none", "git"),
+    };
+    settings.push(pager);
+    settings
+}
+
+/// One line per setting, names and values in columns, each followed by
+/// its source.
+pub fn render(settings: &[Setting]) -> String {
+    let name_width = settings.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    // A value without a source, like the config's path, can run long
+    // without pushing out the others' sources
+    let value_width = settings
+        .iter()
+        .filter(|s| !s.source.is_empty())
+        .map(|s| crate::width::str_width(&s.value))
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for setting in settings {
+        let line = if setting.source.is_empty() {
+            format!("{:<nw$}  {}", setting.name, setting.value, nw = name_width)
+        } else {
+            format!(
+                "{:<nw$}  {}  {}",
+                setting.name,
+                crate::width::pad(&setting.value, value_width),
+                format!("({})", setting.source).dimmed(),
+                nw = name_width
+            )
+        };
+        let _ = writeln!(out, "{}", line);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let _colors = crate::snapshot::colors(false);
+        let settings = [
+            Setting::new("config", "/home/me/.config/f.toml", ""),
+            Setting::new("editor", "nvim", "$EDITOR"),
+            Setting::new("mergetool", "git mergetool -t meld", "git merge.tool"),
+        ];
+        assert_eq!(
+            render(&settings),
+            "config     /home/me/.config/f.toml\n\
+             editor     nvim                   ($EDITOR)\n\
+             mergetool  git mergetool -t meld  (git merge.tool)\n"
+        );
+    }
+}
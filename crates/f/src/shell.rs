@@ -0,0 +1,104 @@
+//! `f shell-init`: a wrapper function for things only the shell itself can
+//! do. The wrapper gives f a file to write to in `F_SHELL_STATE`, and once
+//! f exits, changes directory for `f cd` and exports `F_LAST`, the last
+//! file f resolved.
+
+use crate::completions::Shell;
+use std::io::Write;
+use std::path::Path;
+
+pub const STATE: &str = "F_SHELL_STATE";
+
+/// The wrapper for `shell`, to be `eval`ed from its rc file.
+pub fn init(shell: Shell, bin: &str) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh => posix(bin),
+        Shell::Fish => fish(bin),
+    }
+}
+
+fn posix(bin: &str) -> String {
+    format!(
+        r#"{bin}() {{
+    local state code line
+    state=$(mktemp) || return
+    {state}="$state" command {bin} "$@"
+    code=$?
+    while IFS= read -r line; do
+        case $line in
+            cd=*) cd -- "${{line#cd=}}" ;;
+            last=*) export F_LAST="${{line#last=}}" ;;
+        esac
+    done < "$state"
+    rm -f -- "$state"
+    return $code
+}}
+"#,
+        state = STATE
+    )
+}
+
+fn fish(bin: &str) -> String {
+    format!(
+        r#"function {bin} --wraps {bin}
+    set -l state (mktemp); or return
+    {state}=$state command {bin} $argv
+    set -l code $status
+    while read -l line
+        switch $line
+            case 'cd=*'
+                cd (string replace -r '^cd=' '' -- $line)
+            case 'last=*'
+                set -gx F_LAST (string replace -r '^last=' '' -- $line)
+        end
+    end < $state
+    rm -f -- $state
+    return $code
+end
+"#,
+        state = STATE
+    )
+}
+
+/// Tells the wrapper `key` is `path`, or returns false when f was run
+/// without one.
+fn send(key: &str, path: &Path) -> bool {
+    let Some(state) = std::env::var_os(STATE) else {
+        return false;
+    };
+    let written = std::fs::OpenOptions::new()
+        .append(true)
+        .open(state)
+        .and_then(|mut file| writeln!(file, "{}={}", key, path.display()));
+    if let Err(e) = &written {
+        log::debug!("can't write to ${}: {}", STATE, e);
+    }
+    written.is_ok()
+}
+
+/// Has the wrapper export `path` as `F_LAST`.
+pub fn record_last(path: &Path) {
+    send("last", path);
+}
+
+/// Has the wrapper change directory to `dir`, returning false when
+/// there's no wrapper to do it.
+pub fn change_dir(dir: &Path) -> bool {
+    send("cd", dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrappers_call_the_binary_not_themselves() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = init(shell, "f");
+            assert!(script.contains("command f "), "{}", script);
+            assert!(script.contains("F_SHELL_STATE="), "{}", script);
+            assert!(script.contains("F_LAST"), "{}", script);
+        }
+        assert!(init(Shell::Fish, "f").starts_with("function f --wraps f\n"));
+    }
+}
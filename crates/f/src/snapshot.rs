@@ -0,0 +1,74 @@
+//! Snapshot assertions for rendered output. Each snapshot is a file under
+//! `src/snapshots`, with escape codes written out as `\e[..m` so color and
+//! alignment changes both show up when reviewing a diff of it.
+//!
+//! A mismatch leaves the new output next to the snapshot as `.snap.new`.
+//! Run the tests with `UPDATE_SNAPSHOTS=1` to accept it.
+
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+/// Held while a test depends on colored's global override, so tests that
+/// want colors and tests that want plain text don't race.
+static COLORS: Mutex<()> = Mutex::new(());
+
+pub struct Colors {
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Drop for Colors {
+    fn drop(&mut self) {
+        colored::control::unset_override();
+    }
+}
+
+/// Turns colors on or off until the returned guard is dropped.
+pub fn colors(on: bool) -> Colors {
+    let lock = COLORS.lock().unwrap_or_else(|e| e.into_inner());
+    colored::control::set_override(on);
+    Colors { _lock: lock }
+}
+
+/// `text` with escape codes made visible.
+fn visible(text: &str) -> String {
+    text.replace('\x1b', "\\e")
+}
+
+/// Compares `actual` with the snapshot `name`, or records it when
+/// `UPDATE_SNAPSHOTS` is set.
+pub fn assert_snapshot(name: &str, actual: &str) {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/snapshots");
+    let path = dir.join(format!("{}.snap", name));
+    let pending = dir.join(format!("{}.snap.new", name));
+    let actual = visible(actual);
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, &actual).unwrap();
+        let _ = std::fs::remove_file(&pending);
+        return;
+    }
+    match std::fs::read_to_string(&path) {
+        Ok(expected) if expected == actual => {
+            let _ = std::fs::remove_file(&pending);
+        }
+        expected => {
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(&pending, &actual).unwrap();
+            panic!(
+                "Snapshot {} {}, new output written to {}\n\
+                 (rerun with UPDATE_SNAPSHOTS=1 to accept it)\n\
+                 --- expected\n{}--- actual\n{}",
+                name,
+                if expected.is_ok() {
+                    "changed"
+                } else {
+                    "is missing"
+                },
+                pending.display(),
+                expected.unwrap_or_default(),
+                actual
+            );
+        }
+    }
+}
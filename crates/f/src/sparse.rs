@@ -0,0 +1,96 @@
+//! Sparse checkouts in cone mode, where only some directories are checked
+//! out. Files f lists outside them are usually left over from an earlier,
+//! wider checkout, so they're marked instead of mixed in with real work.
+
+use crate::git_status::git_output;
+use std::path::Path;
+
+/// The directories of a cone-mode sparse checkout, as `git sparse-checkout
+/// list` prints them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cone {
+    dirs: Vec<String>,
+}
+
+impl Cone {
+    /// The current cone, or `None` when the work tree isn't sparse or uses
+    /// non-cone patterns, which f doesn't try to interpret.
+    pub fn load() -> Option<Cone> {
+        if git_output(&["config", "--bool", "core.sparseCheckout"]).as_deref() != Some("true")
+            || git_output(&["config", "--bool", "core.sparseCheckoutCone"]).as_deref()
+                == Some("false")
+        {
+            return None;
+        }
+        let list = git_output(&["sparse-checkout", "list"])?;
+        Some(Cone::new(list.lines().map(str::to_string).collect()))
+    }
+
+    fn new(dirs: Vec<String>) -> Cone {
+        let dirs = dirs
+            .into_iter()
+            .map(|d| d.trim_matches('/').to_string())
+            .filter(|d| !d.is_empty())
+            .collect();
+        Cone { dirs }
+    }
+
+    pub fn dirs(&self) -> &[String] {
+        &self.dirs
+    }
+
+    /// Whether the repo-relative `path` is checked out. Like git, cone mode
+    /// includes everything under a cone directory plus the files directly
+    /// inside each of its parents, the root included.
+    pub fn contains(&self, path: &str) -> bool {
+        let parent = match path.rsplit_once('/') {
+            Some((parent, _)) => parent,
+            None => return true,
+        };
+        self.dirs.iter().any(|dir| {
+            path.starts_with(&format!("{}/", dir))
+                || dir == parent
+                || dir.starts_with(&format!("{}/", parent))
+        })
+    }
+}
+
+/// The directory to add to the cone so the repo-relative `path` gets
+/// checked out: its parent when it's a file, else `path` itself. `None`
+/// for files at the root, which are always checked out.
+pub fn dir_to_add(path: &str, is_file: bool) -> Option<String> {
+    let path = path.trim_matches('/');
+    if !is_file {
+        return (!path.is_empty()).then(|| path.to_string());
+    }
+    Path::new(path)
+        .parent()
+        .map(|p| p.to_string_lossy().into_owned())
+        .filter(|p| !p.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cone_contains() {
+        let cone = Cone::new(vec!["a".into(), "b/deep/".into()]);
+        assert!(cone.contains("README.md"));
+        assert!(cone.contains("a/x.rs"));
+        assert!(cone.contains("a/nested/y.rs"));
+        assert!(cone.contains("b/deep/z"));
+        // Files directly in a cone directory's parent come along with it
+        assert!(cone.contains("b/y"));
+        assert!(!cone.contains("b/other/w"));
+        assert!(!cone.contains("c/x"));
+        assert!(!cone.contains("ab/x"));
+    }
+
+    #[test]
+    fn test_dir_to_add() {
+        assert_eq!(dir_to_add("b/deep/z", true).as_deref(), Some("b/deep"));
+        assert_eq!(dir_to_add("b/deep/", false).as_deref(), Some("b/deep"));
+        assert_eq!(dir_to_add("top", true), None);
+    }
+}
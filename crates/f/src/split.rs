@@ -0,0 +1,174 @@
+//! `f diff --split`: a file's diff laid out side by side, the old version
+//! on the left and the new on the right, from the hunks review mode uses.
+
+use crate::interactive::Hunk;
+use crate::width::{pad, truncate_end};
+use colored::Colorize;
+use std::fmt::Write;
+
+/// A row of the layout: the line number and text on each side, either of
+/// which can be blank where the other side added or removed lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Row {
+    old: Option<(u32, String)>,
+    new: Option<(u32, String)>,
+    changed: bool,
+}
+
+/// The old and new start lines of a `@@ -a,b +c,d @@` header.
+fn starts(header: &str) -> (u32, u32) {
+    let start = |sign: char| {
+        header
+            .split(' ')
+            .find_map(|part| part.strip_prefix(sign))
+            .and_then(|range| range.split(',').next()?.parse().ok())
+            .unwrap_or(1)
+    };
+    (start('-'), start('+'))
+}
+
+/// The rows of `hunk`: context on both sides, and each run of removed
+/// lines beside the added lines that follow it.
+fn rows(hunk: &Hunk) -> Vec<Row> {
+    let (mut old_line, mut new_line) = starts(&hunk.header);
+    let mut rows = Vec::new();
+    let mut removed: Vec<(u32, String)> = Vec::new();
+    let mut added: Vec<(u32, String)> = Vec::new();
+    let flush = |rows: &mut Vec<Row>, removed: &mut Vec<_>, added: &mut Vec<_>| {
+        let count = removed.len().max(added.len());
+        let mut removed = std::mem::take(removed).into_iter();
+        let mut added = std::mem::take(added).into_iter();
+        for _ in 0..count {
+            rows.push(Row {
+                old: removed.next(),
+                new: added.next(),
+                changed: true,
+            });
+        }
+    };
+    for line in &hunk.lines {
+        if let Some(text) = line.strip_prefix('-') {
+            if !added.is_empty() {
+                flush(&mut rows, &mut removed, &mut added);
+            }
+            removed.push((old_line, text.to_string()));
+            old_line += 1;
+        } else if let Some(text) = line.strip_prefix('+') {
+            added.push((new_line, text.to_string()));
+            new_line += 1;
+        } else if line.starts_with('\\') {
+            // "\ No newline at end of file"
+            continue;
+        } else {
+            flush(&mut rows, &mut removed, &mut added);
+            let text = line.strip_prefix(' ').unwrap_or(line).to_string();
+            rows.push(Row {
+                old: Some((old_line, text.clone())),
+                new: Some((new_line, text)),
+                changed: false,
+            });
+            old_line += 1;
+            new_line += 1;
+        }
+    }
+    flush(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+/// `path`'s hunks side by side in `width` columns: each half has the line
+/// number and as much of the line as fits, removed lines red on the left
+/// and added ones green on the right.
+pub fn render(path: &str, hunks: &[Hunk], width: usize) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{}", path.bold());
+    if hunks.is_empty() {
+        let _ = writeln!(out, "{}", "No text changes to show".dimmed());
+        return out;
+    }
+    let all: Vec<Vec<Row>> = hunks.iter().map(rows).collect();
+    let widest = all
+        .iter()
+        .flatten()
+        .flat_map(|row| [&row.old, &row.new])
+        .flatten()
+        .map(|(number, _)| *number)
+        .max()
+        .unwrap_or(1);
+    let number_width = widest.to_string().len();
+    // Each half is the number, a space and the text; " │ " parts them
+    let text_width = width.saturating_sub(3 + 2 * (number_width + 1)) / 2;
+    let half = |side: &Option<(u32, String)>, changed: bool, color: fn(&str) -> String| {
+        let Some((number, text)) = side else {
+            return " ".repeat(number_width + 1 + text_width);
+        };
+        let text = pad(
+            &truncate_end(&text.replace('\t', "    "), text_width),
+            text_width,
+        );
+        let text = if changed { color(&text) } else { text };
+        format!(
+            "{} {}",
+            format!("{:>width$}", number, width = number_width).dimmed(),
+            text
+        )
+    };
+    for (hunk, rows) in hunks.iter().zip(&all) {
+        let _ = writeln!(out, "{}", truncate_end(&hunk.header, width).cyan());
+        for row in rows {
+            let _ = writeln!(
+                out,
+                "{} {} {}",
+                half(&row.old, row.changed, |t| t.red().to_string()),
+                "│".dimmed(),
+                half(&row.new, row.changed, |t| t.green().to_string())
+            );
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interactive::parse_hunks;
+    use crate::snapshot::{assert_snapshot, colors};
+
+    const DIFF: &str = "diff --git a/src/lib.rs b/src/lib.rs\n\
+                        --- a/src/lib.rs\n\
+                        +++ b/src/lib.rs\n\
+                        @@ -8,4 +8,5 @@ fn main() {\n \
+                        let a = 1;\n\
+                        -let b = 2;\n\
+                        +let b = 3;\n\
+                        +let c = b * 2;\n \
+                        print(a);\n\
+                        -done();\n";
+
+    #[test]
+    fn test_rows() {
+        let hunks = parse_hunks("src/lib.rs", DIFF);
+        let rows = rows(&hunks[0]);
+        let numbers: Vec<_> = rows
+            .iter()
+            .map(|row| (row.old.as_ref().map(|o| o.0), row.new.as_ref().map(|n| n.0)))
+            .collect();
+        assert_eq!(
+            numbers,
+            [
+                (Some(8), Some(8)),
+                (Some(9), Some(9)),
+                (None, Some(10)),
+                (Some(10), Some(11)),
+                (Some(11), None),
+            ]
+        );
+        assert!(rows[1].changed && !rows[3].changed);
+    }
+
+    #[test]
+    fn test_render() {
+        let _colors = colors(true);
+        let hunks = parse_hunks("src/lib.rs", DIFF);
+        assert_snapshot("split__render", &render("src/lib.rs", &hunks, 50));
+    }
+}
@@ -0,0 +1,133 @@
+//! `f state`: the branch, its upstream, section counts and any operation in
+//! progress as one JSON or TOML document, for scripts that would otherwise
+//! parse `git status` themselves.
+
+use crate::json::Value;
+use f_core::git_status::StatusSummary;
+use f_core::repo_state::RepoState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+/// The document `f state` prints. Fields that don't apply, like the
+/// upstream of a branch without one, are `null`.
+pub fn state(summary: &StatusSummary, operation: Option<&RepoState>) -> Value {
+    let clean = summary.staged + summary.unstaged + summary.untracked + summary.conflicted == 0;
+    Value::object([
+        ("branch", Value::from(summary.branch.clone())),
+        ("commit", Value::from(summary.commit.clone())),
+        (
+            "detached",
+            Value::from(summary.branch.is_none() && summary.commit.is_some()),
+        ),
+        ("upstream", Value::from(summary.upstream.clone())),
+        ("ahead", Value::from(summary.ahead)),
+        ("behind", Value::from(summary.behind)),
+        ("clean", Value::from(clean)),
+        (
+            "counts",
+            Value::object([
+                ("staged", Value::from(summary.staged)),
+                ("unstaged", Value::from(summary.unstaged)),
+                ("untracked", Value::from(summary.untracked)),
+                ("conflicted", Value::from(summary.conflicted)),
+            ]),
+        ),
+        ("operation", operation.map_or(Value::Null, operation_json)),
+    ])
+}
+
+fn operation_json(operation: &RepoState) -> Value {
+    let (progress, branch) = match operation {
+        RepoState::Rebase { progress, branch } => (*progress, branch.clone()),
+        _ => (None, None),
+    };
+    Value::object([
+        ("name", Value::from(operation.name())),
+        ("step", Value::from(progress.map(|(step, _)| step))),
+        ("total", Value::from(progress.map(|(_, total)| total))),
+        ("branch", Value::from(branch)),
+    ])
+}
+
+pub fn render(state: &Value, format: Format) -> String {
+    match format {
+        Format::Json => format!("{}\n", state),
+        Format::Toml => match to_toml(state) {
+            Some(toml::Value::Table(table)) => toml::to_string(&table).unwrap_or_default(),
+            _ => String::new(),
+        },
+    }
+}
+
+/// TOML has no null, so those fields are left out.
+fn to_toml(value: &Value) -> Option<toml::Value> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => toml::Value::Boolean(*b),
+        Value::Number(n) if n.fract() == 0.0 => toml::Value::Integer(*n as i64),
+        Value::Number(n) => toml::Value::Float(*n),
+        Value::String(s) => toml::Value::String(s.clone()),
+        Value::Array(items) => toml::Value::Array(items.iter().filter_map(to_toml).collect()),
+        Value::Object(fields) => toml::Value::Table(
+            fields
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), to_toml(value)?)))
+                .collect(),
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary() -> StatusSummary {
+        StatusSummary {
+            branch: Some("main".into()),
+            commit: Some("abc123".into()),
+            upstream: Some("origin/main".into()),
+            ahead: 2,
+            staged: 1,
+            untracked: 3,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn json_state() {
+        let rebase = RepoState::Rebase {
+            progress: Some((2, 5)),
+            branch: Some("feature".into()),
+        };
+        assert_eq!(
+            render(&state(&summary(), Some(&rebase)), Format::Json),
+            "{\"branch\":\"main\",\"commit\":\"abc123\",\"detached\":false,\
+             \"upstream\":\"origin/main\",\"ahead\":2,\"behind\":0,\"clean\":false,\
+             \"counts\":{\"staged\":1,\"unstaged\":0,\"untracked\":3,\"conflicted\":0},\
+             \"operation\":{\"name\":\"rebase\",\"step\":2,\"total\":5,\"branch\":\"feature\"}}\n"
+        );
+        let clean = state(&StatusSummary::default(), None);
+        assert_eq!(clean.get("clean"), Some(&Value::Bool(true)));
+        assert_eq!(clean.get("operation"), Some(&Value::Null));
+    }
+
+    #[test]
+    fn toml_state_leaves_out_nulls() {
+        let detached = StatusSummary {
+            branch: None,
+            upstream: None,
+            ..summary()
+        };
+        let text = render(&state(&detached, Some(&RepoState::Bisect)), Format::Toml);
+        let parsed: toml::Table = text.parse().unwrap();
+        assert_eq!(parsed["detached"].as_bool(), Some(true));
+        assert_eq!(parsed["counts"]["untracked"].as_integer(), Some(3));
+        assert_eq!(parsed["operation"]["name"].as_str(), Some("bisect"));
+        assert!(!parsed.contains_key("branch") && !parsed.contains_key("upstream"));
+        assert!(!parsed["operation"].as_table().unwrap().contains_key("step"));
+    }
+}
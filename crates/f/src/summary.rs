@@ -0,0 +1,151 @@
+//! `f diff --summary`: the outline of a long diff, one row per hunk with
+//! where it starts, what it adds and removes and the function git names
+//! in its header, and any hunks asked for in full.
+
+use crate::interactive::Hunk;
+use colored::Colorize;
+use std::fmt::Write;
+
+/// The lines `hunk` adds and removes.
+fn counts(hunk: &Hunk) -> (usize, usize) {
+    let added = hunk.lines.iter().filter(|l| l.starts_with('+')).count();
+    let removed = hunk.lines.iter().filter(|l| l.starts_with('-')).count();
+    (added, removed)
+}
+
+/// The function or section git puts after the `@@ -a,b +c,d @@` range,
+/// empty when the hunk is above the first one.
+fn context(hunk: &Hunk) -> &str {
+    hunk.header
+        .strip_prefix("@@ ")
+        .and_then(|rest| rest.split_once(" @@"))
+        .map_or("", |(_, context)| context.trim())
+}
+
+/// The distinct functions `hunks` touch, in order.
+pub fn functions(hunks: &[Hunk]) -> Vec<&str> {
+    let mut functions: Vec<&str> = Vec::new();
+    for context in hunks.iter().map(context) {
+        if !context.is_empty() && !functions.contains(&context) {
+            functions.push(context);
+        }
+    }
+    functions
+}
+
+/// `path`'s outline: a line of totals, then each hunk numbered from 1,
+/// with the hunks in `expand` followed by their lines.
+pub fn render(path: &str, hunks: &[Hunk], expand: &[usize]) -> String {
+    let mut out = String::new();
+    let (added, removed) = hunks
+        .iter()
+        .map(counts)
+        .fold((0, 0), |(a, r), (ha, hr)| (a + ha, r + hr));
+    let plural = |n: usize, one: &str| format!("{} {}{}", n, one, if n == 1 { "" } else { "s" });
+    let functions = functions(hunks).len();
+    let _ = writeln!(
+        out,
+        "{} {}{}, {}{}",
+        path.bold(),
+        plural(hunks.len(), "hunk"),
+        if functions > 0 {
+            format!(" in {}", plural(functions, "function"))
+        } else {
+            String::new()
+        },
+        format!("+{}", added).green(),
+        format!("/-{}", removed).red()
+    );
+    let number_width = hunks.len().to_string().len();
+    let line_width = hunks
+        .iter()
+        .map(|h| h.line.to_string().len())
+        .max()
+        .unwrap_or(1);
+    let counts_width = hunks
+        .iter()
+        .map(|h| {
+            let (a, r) = counts(h);
+            format!("+{}/-{}", a, r).len()
+        })
+        .max()
+        .unwrap_or(0);
+    for (i, hunk) in hunks.iter().enumerate() {
+        let (added, removed) = counts(hunk);
+        let changes = format!("+{}/-{}", added, removed);
+        let padding = " ".repeat(counts_width - changes.len());
+        let row = format!(
+            "{:>nw$}  {}  {}{}{}  {}",
+            i + 1,
+            format!("line {:<lw$}", hunk.line, lw = line_width).dimmed(),
+            format!("+{}", added).green(),
+            format!("/-{}", removed).red(),
+            padding,
+            context(hunk),
+            nw = number_width
+        );
+        let _ = writeln!(out, "{}", row.trim_end());
+        if expand.contains(&(i + 1)) {
+            let _ = writeln!(out, "{}", hunk.header.cyan());
+            for line in &hunk.lines {
+                let line = if line.starts_with('+') {
+                    line.green()
+                } else if line.starts_with('-') {
+                    line.red()
+                } else {
+                    line.normal()
+                };
+                let _ = writeln!(out, "{}", line);
+            }
+        }
+    }
+    if (1..=hunks.len()).any(|n| !expand.contains(&n)) {
+        let _ = writeln!(
+            out,
+            "{}",
+            "--hunk N shows a hunk in full, --full the whole diff".dimmed()
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interactive::parse_hunks;
+    use crate::snapshot::{assert_snapshot, colors};
+
+    const DIFF: &str = "diff --git a/src/lib.rs b/src/lib.rs\n\
+                        --- a/src/lib.rs\n\
+                        +++ b/src/lib.rs\n\
+                        @@ -1,2 +1,3 @@\n \
+                        use std::fs;\n\
+                        +use std::io;\n \
+                        \n\
+                        @@ -8,4 +9,5 @@ fn main() {\n \
+                        let a = 1;\n\
+                        -let b = 2;\n\
+                        +let b = 3;\n\
+                        +let c = b * 2;\n \
+                        print(a);\n\
+                        @@ -30,3 +32,2 @@ fn main() {\n \
+                        done();\n\
+                        -exit();\n \
+                        }\n";
+
+    #[test]
+    fn test_functions() {
+        let hunks = parse_hunks("src/lib.rs", DIFF);
+        assert_eq!(context(&hunks[0]), "");
+        assert_eq!(context(&hunks[1]), "fn main() {");
+        assert_eq!(functions(&hunks), ["fn main() {"]);
+        assert_eq!(counts(&hunks[1]), (2, 1));
+    }
+
+    #[test]
+    fn test_render() {
+        let _colors = colors(true);
+        let hunks = parse_hunks("src/lib.rs", DIFF);
+        assert_snapshot("summary__render", &render("src/lib.rs", &hunks, &[2]));
+    }
+}
@@ -0,0 +1,178 @@
+//! Tags for `f tag`, each with an ID hashed from its name so it can be
+//! picked or passed like a file's.
+
+use crate::error::Error;
+use crate::git_status::{StableId, generate_ids};
+use crate::interactive::Pickable;
+use anyhow::{Result, bail};
+use colored::Colorize;
+use std::process::Command;
+
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub name: String,
+    /// The commit tagged, abbreviated
+    pub short: String,
+    /// The tag message's subject, or the commit's for a lightweight tag
+    pub subject: String,
+    pub date: String,
+    pub annotated: bool,
+    pub id: StableId,
+}
+
+impl Pickable for Tag {
+    fn key(&self) -> &str {
+        &self.id.display
+    }
+
+    fn describe(&self) -> String {
+        let kind = if self.annotated { "" } else { ", lightweight" };
+        format!(
+            "{} {} {} {}",
+            self.name.bold(),
+            self.short.yellow(),
+            self.subject,
+            format!("({}{})", self.date, kind).dimmed()
+        )
+    }
+}
+
+const FORMAT: &str = "--format=%(refname:short)%1f%(objecttype)%1f%(objectname:short)%1f\
+                      %(*objectname:short)%1f%(contents:subject)%1f%(creatordate:relative)";
+
+/// Every tag, newest first.
+pub fn list(id_chars: &[char]) -> Result<Vec<Tag>> {
+    let output = crate::exec::run_output(Command::new("git").args([
+        "for-each-ref",
+        "--sort=-creatordate",
+        FORMAT,
+        "refs/tags",
+    ]))
+    .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        bail!(
+            "git for-each-ref failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(parse(&String::from_utf8_lossy(&output.stdout), id_chars))
+}
+
+fn parse(output: &str, id_chars: &[char]) -> Vec<Tag> {
+    let rows: Vec<Vec<&str>> = output
+        .lines()
+        .map(|line| line.split('\x1f').collect::<Vec<_>>())
+        .filter(|fields| fields.len() == 6)
+        .collect();
+    let names: Vec<String> = rows.iter().map(|r| r[0].to_string()).collect();
+    rows.iter()
+        .zip(generate_ids(&names, id_chars))
+        .map(|(r, (display, full_hash))| {
+            let annotated = r[1] == "tag";
+            Tag {
+                name: r[0].to_string(),
+                // An annotated tag's own object isn't the commit
+                short: if annotated { r[3] } else { r[2] }.to_string(),
+                subject: r[4].to_string(),
+                date: r[5].to_string(),
+                annotated,
+                id: StableId { display, full_hash },
+            }
+        })
+        .collect()
+}
+
+/// The tag named `arg`, or else the one with that ID.
+pub fn find<'a>(tags: &'a [Tag], arg: &str) -> Result<&'a Tag> {
+    if let Some(tag) = tags.iter().find(|t| t.name == arg) {
+        return Ok(tag);
+    }
+    let matches: Vec<_> = tags.iter().filter(|t| t.id.matches(arg)).collect();
+    match matches.as_slice() {
+        [tag] => Ok(tag),
+        [] => Err(Error::NotFound(format!("No tag named '{}' or with that ID", arg)).into()),
+        _ => Err(Error::AmbiguousId {
+            id: arg.to_string(),
+            matches: matches.len(),
+            what: "tags",
+        }
+        .into()),
+    }
+}
+
+/// The `git tag` arguments creating `name` at `rev`: annotated unless
+/// `lightweight`, signed with `sign`. Without a message git opens the
+/// editor for one.
+pub fn create_args(
+    name: &str,
+    rev: Option<&str>,
+    message: Option<&str>,
+    sign: bool,
+    lightweight: bool,
+) -> Vec<String> {
+    let mut args = vec!["tag".to_string()];
+    if !lightweight {
+        args.push(if sign { "-s" } else { "-a" }.to_string());
+        if let Some(message) = message {
+            args.extend(["-m".to_string(), message.to_string()]);
+        }
+    }
+    args.extend(["--".to_string(), name.to_string()]);
+    args.extend(rev.map(str::to_string));
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "v1.1\x1ftag\x1f9f8e7d6\x1fa1b2c3d\x1fRelease 1.1\x1f2 days ago\n\
+                          v1.0\x1fcommit\x1fe4f5a6b\x1f\x1fAdd the parser\x1f3 weeks ago\n";
+
+    #[test]
+    fn test_parse() {
+        let tags = parse(OUTPUT, &['a', 's', 'd', 'f']);
+        assert_eq!(tags.len(), 2);
+        assert_eq!(
+            (
+                tags[0].name.as_str(),
+                tags[0].short.as_str(),
+                tags[0].annotated
+            ),
+            ("v1.1", "a1b2c3d", true)
+        );
+        assert_eq!(
+            (
+                tags[1].short.as_str(),
+                tags[1].subject.as_str(),
+                tags[1].annotated
+            ),
+            ("e4f5a6b", "Add the parser", false)
+        );
+    }
+
+    #[test]
+    fn find_by_name_or_id() {
+        let tags = parse(OUTPUT, &['a', 's', 'd', 'f']);
+        assert_eq!(find(&tags, "v1.0").unwrap().name, "v1.0");
+        let id = tags[0].id.display.clone();
+        assert_eq!(find(&tags, &id).unwrap().name, "v1.1");
+        assert!(find(&tags, "v2.0").is_err());
+    }
+
+    #[test]
+    fn test_create_args() {
+        assert_eq!(
+            create_args("v1.2", None, Some("Release 1.2"), false, false),
+            ["tag", "-a", "-m", "Release 1.2", "--", "v1.2"]
+        );
+        assert_eq!(
+            create_args("v1.2", Some("HEAD~1"), None, true, false),
+            ["tag", "-s", "--", "v1.2", "HEAD~1"]
+        );
+        assert_eq!(
+            create_args("tmp", None, None, false, true),
+            ["tag", "--", "tmp"]
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//! The colors of the file list, the picker and `--help`: a preset, light
+//! or high-contrast for terminals where the default is hard to read, with
+//! the `[theme]` table's colors on top.
+
+use crate::config::ThemeConfig;
+use colored::{Color, ColoredString, Colorize};
+use std::sync::OnceLock;
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// A color with any of bold and dimmed, written like `bold bright red`,
+/// `dimmed` or `#268bd2`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    color: Option<Color>,
+    bold: bool,
+    dimmed: bool,
+}
+
+impl Style {
+    pub fn parse(text: &str) -> Option<Style> {
+        let mut style = Style::default();
+        let mut words = text.split_whitespace();
+        while let Some(word) = words.next() {
+            match word.to_ascii_lowercase().as_str() {
+                "bold" => style.bold = true,
+                "dimmed" | "dim" => style.dimmed = true,
+                "bright" => style.color = Some(format!("bright {}", words.next()?).parse().ok()?),
+                hex if hex.starts_with('#') && hex.len() == 7 => {
+                    let channel = |at: usize| u8::from_str_radix(&hex[at..at + 2], 16).ok();
+                    style.color = Some(Color::TrueColor {
+                        r: channel(1)?,
+                        g: channel(3)?,
+                        b: channel(5)?,
+                    });
+                }
+                name => style.color = Some(name.parse().ok()?),
+            }
+        }
+        Some(style)
+    }
+
+    pub fn paint(&self, text: &str) -> ColoredString {
+        let mut painted = text.normal();
+        if let Some(color) = self.color {
+            painted = painted.color(color);
+        }
+        if self.bold {
+            painted = painted.bold();
+        }
+        if self.dimmed {
+            painted = painted.dimmed();
+        }
+        painted
+    }
+
+    /// The style for clap's help, which has its own style type.
+    pub fn anstyle(&self) -> anstyle::Style {
+        use anstyle::{AnsiColor, RgbColor};
+        let color = self.color.map(|color| match color {
+            Color::Black => AnsiColor::Black.into(),
+            Color::Red => AnsiColor::Red.into(),
+            Color::Green => AnsiColor::Green.into(),
+            Color::Yellow => AnsiColor::Yellow.into(),
+            Color::Blue => AnsiColor::Blue.into(),
+            Color::Magenta => AnsiColor::Magenta.into(),
+            Color::Cyan => AnsiColor::Cyan.into(),
+            Color::White => AnsiColor::White.into(),
+            Color::BrightBlack => AnsiColor::BrightBlack.into(),
+            Color::BrightRed => AnsiColor::BrightRed.into(),
+            Color::BrightGreen => AnsiColor::BrightGreen.into(),
+            Color::BrightYellow => AnsiColor::BrightYellow.into(),
+            Color::BrightBlue => AnsiColor::BrightBlue.into(),
+            Color::BrightMagenta => AnsiColor::BrightMagenta.into(),
+            Color::BrightCyan => AnsiColor::BrightCyan.into(),
+            Color::BrightWhite => AnsiColor::BrightWhite.into(),
+            Color::TrueColor { r, g, b } => RgbColor(r, g, b).into(),
+        });
+        let mut style = anstyle::Style::new().fg_color(color);
+        if self.bold {
+            style = style.bold();
+        }
+        if self.dimmed {
+            style = style.dimmed();
+        }
+        style
+    }
+}
+
+/// A style for each thing f colors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub unstaged: Style,
+    pub untracked: Style,
+    pub staged: Style,
+    /// File IDs and picker keys, and the commands in `--help`
+    pub id: Style,
+    pub added: Style,
+    pub removed: Style,
+    /// Notes and hints that stay out of the way
+    pub muted: Style,
+    /// The picker's titles, and commit hashes
+    pub heading: Style,
+    /// Lines above the file list, like an operation in progress
+    pub warning: Style,
+    /// `--help`'s section titles
+    pub help: Style,
+}
+
+/// The roles as the `[theme]` table names them.
+const ROLES: [&str; 10] = [
+    "unstaged",
+    "untracked",
+    "staged",
+    "id",
+    "added",
+    "removed",
+    "muted",
+    "heading",
+    "warning",
+    "help",
+];
+
+pub const PRESETS: [&str; 4] = ["default", "light", "high-contrast", "solarized"];
+
+impl Theme {
+    /// The preset called `name`. Light backgrounds wash out yellow, cyan
+    /// and dimmed text, so `light` stays with darker colors;
+    /// `high-contrast` is bold and bright with nothing dimmed.
+    pub fn preset(name: &str) -> Option<Theme> {
+        let styles: [&str; 10] = match name {
+            "default" => [
+                "yellow",
+                "green",
+                "cyan",
+                "cyan",
+                "green",
+                "red",
+                "dimmed",
+                "yellow",
+                "yellow",
+                "bold green",
+            ],
+            "light" => [
+                "magenta",
+                "green",
+                "blue",
+                "blue",
+                "green",
+                "red",
+                "bright black",
+                "magenta",
+                "red",
+                "bold blue",
+            ],
+            "high-contrast" => [
+                "bold bright yellow",
+                "bold bright green",
+                "bold bright cyan",
+                "bold bright white",
+                "bold bright green",
+                "bold bright red",
+                "white",
+                "bold bright white",
+                "bold bright yellow",
+                "bold bright white",
+            ],
+            "solarized" => [
+                "#b58900",
+                "#859900",
+                "#2aa198",
+                "#268bd2",
+                "#859900",
+                "#dc322f",
+                "#839496",
+                "#cb4b16",
+                "#cb4b16",
+                "bold #268bd2",
+            ],
+            _ => return None,
+        };
+        let mut theme = Theme::plain();
+        for (role, style) in ROLES.iter().zip(styles) {
+            theme.set(role, Style::parse(style)?);
+        }
+        Some(theme)
+    }
+
+    fn plain() -> Theme {
+        let plain = Style::default();
+        Theme {
+            unstaged: plain,
+            untracked: plain,
+            staged: plain,
+            id: plain,
+            added: plain,
+            removed: plain,
+            muted: plain,
+            heading: plain,
+            warning: plain,
+            help: plain,
+        }
+    }
+
+    fn set(&mut self, role: &str, style: Style) -> bool {
+        let slot = match role {
+            "unstaged" => &mut self.unstaged,
+            "untracked" => &mut self.untracked,
+            "staged" => &mut self.staged,
+            "id" => &mut self.id,
+            "added" => &mut self.added,
+            "removed" => &mut self.removed,
+            "muted" => &mut self.muted,
+            "heading" => &mut self.heading,
+            "warning" => &mut self.warning,
+            "help" => &mut self.help,
+            _ => return false,
+        };
+        *slot = style;
+        true
+    }
+
+    /// The theme `config` describes, warning about an unknown preset,
+    /// role or color and leaving the preset's own in their place.
+    pub fn from_config(config: &ThemeConfig) -> Theme {
+        let name = config.preset.as_deref().unwrap_or("default");
+        let mut theme = Theme::preset(name).unwrap_or_else(|| {
+            eprintln!(
+                "Warning: Unknown theme '{}', expected one of {}",
+                name,
+                PRESETS.join(", ")
+            );
+            Theme::default()
+        });
+        for (role, text) in &config.colors {
+            match Style::parse(text) {
+                Some(style) => {
+                    if !theme.set(role, style) {
+                        eprintln!(
+                            "Warning: Unknown [theme] color '{}', expected one of {}",
+                            role,
+                            ROLES.join(", ")
+                        );
+                    }
+                }
+                None => eprintln!("Warning: Invalid [theme] {} color '{}'", role, text),
+            }
+        }
+        theme
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::preset("default").unwrap_or_else(Theme::plain)
+    }
+}
+
+/// Sets the theme for the rest of the run. Until then, and in tests, it's
+/// the default.
+pub fn init(config: &ThemeConfig) {
+    let _ = THEME.set(Theme::from_config(config));
+}
+
+pub fn get() -> &'static Theme {
+    THEME.get_or_init(Theme::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_style() {
+        assert_eq!(
+            Style::parse("bold bright red"),
+            Some(Style {
+                color: Some(Color::BrightRed),
+                bold: true,
+                dimmed: false,
+            })
+        );
+        assert_eq!(
+            Style::parse("#268bd2").and_then(|s| s.color),
+            Some(Color::TrueColor {
+                r: 0x26,
+                g: 0x8b,
+                b: 0xd2
+            })
+        );
+        assert_eq!(Style::parse("dimmed").map(|s| s.dimmed), Some(true));
+        assert_eq!(Style::parse("bright"), None);
+        assert_eq!(Style::parse("mauve"), None);
+        assert_eq!(Style::parse("#12345"), None);
+    }
+
+    #[test]
+    fn default_preset_matches_the_built_in_colors() {
+        let _colors = crate::snapshot::colors(true);
+        let theme = Theme::default();
+        assert_eq!(theme.unstaged.paint("x"), "x".yellow());
+        assert_eq!(theme.muted.paint("x"), "x".dimmed());
+        assert_eq!(theme.help.paint("x"), "x".green().bold());
+    }
+
+    #[test]
+    fn every_preset_parses() {
+        for name in PRESETS {
+            assert!(Theme::preset(name).is_some(), "{}", name);
+        }
+        assert_eq!(Theme::preset("neon"), None);
+    }
+
+    #[test]
+    fn table_colors_override_the_preset() {
+        let config = ThemeConfig {
+            preset: Some("light".into()),
+            colors: [("staged".to_string(), "bold yellow".to_string())].into(),
+        };
+        let theme = Theme::from_config(&config);
+        assert_eq!(theme.staged, Style::parse("bold yellow").unwrap());
+        assert_eq!(theme.unstaged, Theme::preset("light").unwrap().unstaged);
+    }
+}
@@ -0,0 +1,367 @@
+//! `f fmt` and `f test`: the formatter or test command configured for a
+//! file's glob in `[[tools]]`, with placeholders filled in from the file
+//! and the package it belongs to. `f check` runs every command configured
+//! for each changed file, linters included.
+//!
+//! Placeholders, each shell-quoted: `{file}` (absolute path), `{path}`
+//! (repo-relative), `{root}`, `{package}` and `{package_dir}`. The package
+//! is the nearest `Cargo.toml` with a `[package]` or `package.json` with a
+//! `name` above the file, within the repository.
+
+use crate::config::ToolConfig;
+use crate::error::Error;
+use crate::exec::quote;
+use crate::glob::Glob;
+use anyhow::Result;
+use colored::Colorize;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Lint,
+    Fmt,
+    Test,
+}
+
+impl Tool {
+    /// What `f check` runs, in order. A check mustn't change files, so
+    /// `fmt`, which formats in place, isn't one; a formatter's check mode
+    /// belongs under `lint`.
+    pub const CHECKS: [Tool; 2] = [Tool::Lint, Tool::Test];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Lint => "lint",
+            Tool::Fmt => "fmt",
+            Tool::Test => "test",
+        }
+    }
+}
+
+/// A Cargo package or npm workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+/// The command template for `tool` on the repo-relative `path`.
+pub fn template<'a>(tools: &'a [ToolConfig], tool: Tool, path: &str) -> Result<Option<&'a str>> {
+    for entry in tools {
+        let command = match tool {
+            Tool::Lint => entry.lint.as_deref(),
+            Tool::Fmt => entry.fmt.as_deref(),
+            Tool::Test => entry.test.as_deref(),
+        };
+        if let Some(command) = command
+            && Glob::new(&entry.glob)
+                .map_err(|e| Error::Config(format!("{:#}", e)))?
+                .matches(path)
+        {
+            return Ok(Some(command));
+        }
+    }
+    Ok(None)
+}
+
+/// The package `file` belongs to, looking no higher than `root`.
+pub fn find_package(file: &Path, root: &Path) -> Option<Package> {
+    for dir in file.ancestors().skip(1) {
+        if let Some(name) = cargo_package(&dir.join("Cargo.toml"))
+            .or_else(|| npm_package(&dir.join("package.json")))
+        {
+            return Some(Package {
+                name,
+                dir: dir.to_path_buf(),
+            });
+        }
+        if dir == root {
+            break;
+        }
+    }
+    None
+}
+
+/// A workspace's root manifest has no `[package]`, so the search goes on
+/// past it to nothing, rather than naming the workspace.
+fn cargo_package(manifest: &Path) -> Option<String> {
+    let content = fs::read_to_string(manifest).ok()?;
+    let table: toml::Table = content.parse().ok()?;
+    table
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(str::to_string)
+}
+
+fn npm_package(manifest: &Path) -> Option<String> {
+    let content = fs::read_to_string(manifest).ok()?;
+    crate::json::parse(&content)
+        .ok()?
+        .get("name")?
+        .as_str()
+        .map(str::to_string)
+}
+
+pub struct Target<'a> {
+    pub file: &'a Path,
+    pub path: &'a str,
+    pub root: &'a Path,
+    pub package: Option<&'a Package>,
+}
+
+/// `template` with its placeholders replaced. `{package}` without a
+/// package to fill it in is an error, so a test command never silently
+/// runs the whole suite.
+pub fn expand(template: &str, target: &Target) -> Result<String> {
+    let lossy = |p: &Path| quote(&p.to_string_lossy());
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            // A stray `{` is left as it is, with what follows
+            rest = &rest[start..];
+            break;
+        };
+        let name = &rest[start + 1..start + len];
+        let package = || {
+            target.package.ok_or_else(|| {
+                Error::NotFound(format!(
+                    "{} isn't in a Cargo package or npm workspace, needed for {{{}}}",
+                    target.path, name
+                ))
+            })
+        };
+        match name {
+            "file" => out.push_str(&lossy(target.file)),
+            "path" => out.push_str(&quote(target.path)),
+            "root" => out.push_str(&lossy(target.root)),
+            "package" => out.push_str(&quote(&package()?.name)),
+            "package_dir" => out.push_str(&lossy(&package()?.dir)),
+            _ => out.push_str(&rest[start..=start + len]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// A command `f check` runs, and the files it covers. Files that expand
+/// to the same command, like the tests of one package, share it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub tool: Tool,
+    pub command: String,
+    pub paths: Vec<String>,
+}
+
+/// Every configured lint and test command for `targets`, tool by tool.
+pub fn checks(tools: &[ToolConfig], targets: &[Target]) -> Result<Vec<Check>> {
+    let mut checks: Vec<Check> = Vec::new();
+    for tool in Tool::CHECKS {
+        for target in targets {
+            let Some(template) = template(tools, tool, target.path)? else {
+                continue;
+            };
+            let command = expand(template, target)?;
+            match checks
+                .iter_mut()
+                .find(|c| c.tool == tool && c.command == command)
+            {
+                Some(check) => check.paths.push(target.path.to_string()),
+                None => checks.push(Check {
+                    tool,
+                    command,
+                    paths: vec![target.path.to_string()],
+                }),
+            }
+        }
+    }
+    Ok(checks)
+}
+
+/// One row per file, by ID, with a tick or cross per tool that ran on it.
+/// `passed` goes with `checks`.
+pub fn report(files: &[(&str, &str)], checks: &[Check], passed: &[bool]) -> String {
+    let id_width = files.iter().map(|(id, _)| id.len()).max().unwrap_or(0);
+    let path_width = files
+        .iter()
+        .map(|(_, path)| crate::width::str_width(path))
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for (id, path) in files {
+        let results: Vec<String> = checks
+            .iter()
+            .zip(passed)
+            .filter(|(check, _)| check.paths.iter().any(|p| p == path))
+            .map(|(check, &ok)| {
+                if ok {
+                    format!("{} {}", "✓".green(), check.tool.name())
+                } else {
+                    format!("{} {}", "✗".red(), check.tool.name())
+                        .bold()
+                        .to_string()
+                }
+            })
+            .collect();
+        let results = if results.is_empty() {
+            "nothing configured".dimmed().to_string()
+        } else {
+            results.join("  ")
+        };
+        let padding = path_width - crate::width::str_width(path);
+        let _ = writeln!(
+            out,
+            "  {}  {}{}  {}",
+            format!("{:<id_width$}", id).cyan(),
+            path,
+            " ".repeat(padding),
+            results
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(glob: &str, fmt: Option<&str>, test: Option<&str>) -> ToolConfig {
+        ToolConfig {
+            glob: glob.into(),
+            fmt: fmt.map(Into::into),
+            lint: None,
+            test: test.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn test_template_first_match_with_command() {
+        let tools = [
+            tool("*.rs", Some("rustfmt {file}"), None),
+            tool("crates/**", None, Some("cargo test -p {package}")),
+        ];
+        assert_eq!(
+            template(&tools, Tool::Fmt, "crates/f/src/a.rs").unwrap(),
+            Some("rustfmt {file}")
+        );
+        assert_eq!(
+            template(&tools, Tool::Test, "crates/f/src/a.rs").unwrap(),
+            Some("cargo test -p {package}")
+        );
+        assert_eq!(template(&tools, Tool::Fmt, "web/a.ts").unwrap(), None);
+    }
+
+    #[test]
+    fn test_expand() {
+        let package = Package {
+            name: "my-crate".into(),
+            dir: PathBuf::from("/repo/crates/my crate"),
+        };
+        let target = Target {
+            file: Path::new("/repo/crates/my crate/src/lib.rs"),
+            path: "crates/my crate/src/lib.rs",
+            root: Path::new("/repo"),
+            package: Some(&package),
+        };
+        assert_eq!(
+            expand("rustfmt {file}", &target).unwrap(),
+            "rustfmt '/repo/crates/my crate/src/lib.rs'"
+        );
+        assert_eq!(
+            expand(
+                "cd {package_dir} && cargo test -p {package} {unknown}",
+                &target
+            )
+            .unwrap(),
+            "cd '/repo/crates/my crate' && cargo test -p my-crate {unknown}"
+        );
+        assert_eq!(expand("echo a{b", &target).unwrap(), "echo a{b");
+        assert_eq!(
+            expand("echo {path} {", &target).unwrap(),
+            "echo 'crates/my crate/src/lib.rs' {"
+        );
+        let target = Target {
+            package: None,
+            ..target
+        };
+        let err = expand("cargo test -p {package}", &target).unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 4);
+    }
+
+    #[test]
+    fn test_find_package() {
+        let root = std::env::temp_dir().join(format!("f-tools-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("crates/a/src")).unwrap();
+        fs::create_dir_all(root.join("web/app/src")).unwrap();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("crates/a/Cargo.toml"),
+            "[package]\nname = \"alpha\"\n",
+        )
+        .unwrap();
+        fs::write(root.join("web/app/package.json"), r#"{"name": "@x/app"}"#).unwrap();
+
+        let found = find_package(&root.join("crates/a/src/lib.rs"), &root).unwrap();
+        assert_eq!(found.name, "alpha");
+        assert_eq!(found.dir, root.join("crates/a"));
+        let found = find_package(&root.join("web/app/src/index.ts"), &root).unwrap();
+        assert_eq!(found.name, "@x/app");
+        assert_eq!(find_package(&root.join("README.md"), &root), None);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn checks_share_commands_and_report_per_file() {
+        let tools = [
+            ToolConfig {
+                lint: Some("clippy-driver {path}".into()),
+                ..tool("*.rs", None, Some("cargo test -p {package}"))
+            },
+            tool("*.sh", Some("shfmt -w {path}"), None),
+        ];
+        let package = Package {
+            name: "alpha".into(),
+            dir: PathBuf::from("/repo/alpha"),
+        };
+        let target = |path: &'static str| Target {
+            file: Path::new(path),
+            path,
+            root: Path::new("/repo"),
+            package: Some(&package),
+        };
+        let targets = [target("alpha/a.rs"), target("alpha/b.rs"), target("run.sh")];
+        let checks = checks(&tools, &targets).unwrap();
+        let commands: Vec<(&str, &str, usize)> = checks
+            .iter()
+            .map(|c| (c.tool.name(), c.command.as_str(), c.paths.len()))
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                ("lint", "clippy-driver alpha/a.rs", 1),
+                ("lint", "clippy-driver alpha/b.rs", 1),
+                // Formatting in place isn't a check
+                ("test", "cargo test -p alpha", 2),
+            ]
+        );
+
+        let _colors = crate::snapshot::colors(false);
+        let files = [("d", "alpha/a.rs"), ("fs", "run.sh"), ("g", "notes.txt")];
+        assert_eq!(
+            report(&files, &checks, &[true, true, false]),
+            "  d   alpha/a.rs  ✓ lint  ✗ test\n\
+             \x20 fs  run.sh      nothing configured\n\
+             \x20 g   notes.txt   nothing configured\n"
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::error::Error;
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Entries older than this are removed when a new one is saved.
+const KEEP_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Copies of files f was about to destroy, under `.git/f/trash`. Each entry
+/// is a directory named after its timestamp, holding an `entry` file (the
+/// command, then one path per line) and the files under `files/`.
+pub struct Trash {
+    dir: PathBuf,
+    root: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrashEntry {
+    pub name: String,
+    pub time: u64,
+    pub command: String,
+    pub paths: Vec<String>,
+}
+
+impl Trash {
+    pub fn open() -> Result<Trash> {
+        let git_dir = crate::git_status::get_git_dir()?;
+        let root = crate::git_status::get_git_root()?;
+        Ok(Trash {
+            dir: git_dir.join("f").join("trash"),
+            root,
+        })
+    }
+
+    /// Copies the given repo-relative paths into a new entry. Paths that
+    /// don't exist are skipped; returns `None` if none did.
+    pub fn save(&self, command: &str, paths: &[&str]) -> Result<Option<String>> {
+        let existing: Vec<&str> = paths
+            .iter()
+            .copied()
+            .filter(|p| self.root.join(p).is_file())
+            .collect();
+        if existing.is_empty() {
+            return Ok(None);
+        }
+        self.prune();
+
+        let now = now();
+        let mut name = now.to_string();
+        let mut n = 1;
+        while self.dir.join(&name).exists() {
+            n += 1;
+            name = format!("{}-{}", now, n);
+        }
+        let entry_dir = self.dir.join(&name);
+        for path in &existing {
+            let target = entry_dir.join("files").join(path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(self.root.join(path), &target)
+                .with_context(|| format!("Failed to back up {}", path))?;
+        }
+        let mut meta = format!("{}\n", command);
+        for path in &existing {
+            meta.push_str(path);
+            meta.push('\n');
+        }
+        fs::write(entry_dir.join("entry"), meta)?;
+        Ok(Some(name))
+    }
+
+    /// All entries, newest first.
+    pub fn entries(&self) -> Vec<TrashEntry> {
+        let Ok(dirs) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut entries: Vec<TrashEntry> = dirs
+            .flatten()
+            .filter_map(|d| {
+                let name = d.file_name().to_string_lossy().into_owned();
+                let meta = fs::read_to_string(d.path().join("entry")).ok()?;
+                parse_entry(&name, &meta)
+            })
+            .collect();
+        entries.sort_by(|a, b| b.time.cmp(&a.time).then(b.name.cmp(&a.name)));
+        entries
+    }
+
+    pub fn find(&self, name: Option<&str>) -> Result<TrashEntry> {
+        let entries = self.entries();
+        let entry = match name {
+            Some(name) => entries.into_iter().find(|e| e.name == name),
+            None => entries.into_iter().next(),
+        };
+        match (entry, name) {
+            (Some(entry), _) => Ok(entry),
+            (None, Some(name)) => {
+                Err(Error::NotFound(format!("No trash entry named {}", name)).into())
+            }
+            (None, None) => bail!("Trash is empty"),
+        }
+    }
+
+    /// Copies an entry's files (or just `only`) back into the work tree.
+    /// Files that exist with other contents are left alone unless `force`.
+    pub fn restore(
+        &self,
+        entry: &TrashEntry,
+        only: Option<&str>,
+        force: bool,
+    ) -> Result<Vec<String>> {
+        let paths: Vec<&String> = entry
+            .paths
+            .iter()
+            .filter(|p| only.is_none_or(|o| o == p.as_str()))
+            .collect();
+        if paths.is_empty() {
+            bail!("{} isn't in trash entry {}", only.unwrap_or(""), entry.name);
+        }
+        let files = self.dir.join(&entry.name).join("files");
+        let mut restored = Vec::new();
+        for path in paths {
+            let source = files.join(path);
+            let target = self.root.join(path);
+            if !force && target.exists() && !same_contents(&source, &target) {
+                eprintln!(
+                    "Skipping {}: it exists with other changes (--force overwrites)",
+                    path
+                );
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(&source, &target).with_context(|| format!("Failed to restore {}", path))?;
+            restored.push(path.clone());
+        }
+        Ok(restored)
+    }
+
+    pub fn remove(&self, entry: &TrashEntry) -> Result<()> {
+        fs::remove_dir_all(self.dir.join(&entry.name))
+            .with_context(|| format!("Failed to remove trash entry {}", entry.name))
+    }
+
+    fn prune(&self) {
+        let cutoff = now().saturating_sub(KEEP_SECS);
+        for entry in self.entries().iter().filter(|e| e.time < cutoff) {
+            let _ = self.remove(entry);
+        }
+    }
+}
+
+fn parse_entry(name: &str, meta: &str) -> Option<TrashEntry> {
+    let time = name.split('-').next()?.parse().ok()?;
+    let mut lines = meta.lines();
+    let command = lines.next()?.to_string();
+    Some(TrashEntry {
+        name: name.to_string(),
+        time,
+        command,
+        paths: lines
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+fn same_contents(a: &Path, b: &Path) -> bool {
+    matches!((fs::read(a), fs::read(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// A rough "how long ago" for `time`, e.g. "5 minutes ago".
+pub fn ago(time: u64) -> String {
+    let secs = now().saturating_sub(time);
+    let (n, unit) = match secs {
+        0..60 => return "just now".to_string(),
+        60..3600 => (secs / 60, "minute"),
+        3600..86400 => (secs / 3600, "hour"),
+        _ => (secs / 86400, "day"),
+    };
+    format!("{} {}{} ago", n, unit, if n == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entry() {
+        let entry = parse_entry("1700000000-2", "discard\nsrc/a.rs\nb.txt\n").unwrap();
+        assert_eq!(entry.time, 1700000000);
+        assert_eq!(entry.command, "discard");
+        assert_eq!(entry.paths, vec!["src/a.rs", "b.txt"]);
+        assert!(parse_entry("junk", "discard\n").is_none());
+    }
+
+    #[test]
+    fn test_save_and_restore() {
+        let dir = std::env::temp_dir().join(format!("f-trash-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let root = dir.join("work");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/a.rs"), "contents").unwrap();
+        let trash = Trash {
+            dir: dir.join("trash"),
+            root: root.clone(),
+        };
+
+        let name = trash
+            .save("discard", &["src/a.rs", "missing.txt"])
+            .unwrap()
+            .unwrap();
+        fs::remove_file(root.join("src/a.rs")).unwrap();
+        let entry = trash.find(None).unwrap();
+        assert_eq!(entry.name, name);
+        assert_eq!(entry.paths, vec!["src/a.rs"]);
+
+        assert_eq!(
+            trash.restore(&entry, None, false).unwrap(),
+            vec!["src/a.rs"]
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("src/a.rs")).unwrap(),
+            "contents"
+        );
+
+        fs::write(root.join("src/a.rs"), "edited").unwrap();
+        assert!(trash.restore(&entry, None, false).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_ago() {
+        assert_eq!(ago(now()), "just now");
+        assert_eq!(ago(now() - 120), "2 minutes ago");
+        assert_eq!(ago(now() - 3600), "1 hour ago");
+    }
+}
@@ -0,0 +1,443 @@
+use crate::json::{self, Value};
+use crate::trash::Trash;
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Oldest entries are dropped beyond this many.
+const MAX_ENTRIES: usize = 50;
+
+/// A mutating f command, recorded with what's needed to reverse it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// `f add` / `f unstage` / `f chmod`: each path's index entry
+    /// beforehand (`"<mode> <sha>"`, or `None` if the path wasn't in the
+    /// index), and for `f chmod` whether each was executable in the work
+    /// tree
+    Index {
+        command: String,
+        entries: Vec<(String, Option<String>)>,
+        executable: Vec<(String, bool)>,
+    },
+    /// `f discard` / `f clean`: the trash entry holding the discarded files
+    Discard { command: String, trash: String },
+    /// `f <id> ours` / `theirs`: the conflicted path it resolved, and the
+    /// trash entry holding the file as it was, markers and all
+    Resolve {
+        side: String,
+        path: String,
+        trash: Option<String>,
+    },
+    /// `f commit`: HEAD beforehand, `None` on an unborn branch
+    Commit { head: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub time: u64,
+    pub action: Action,
+}
+
+impl Action {
+    pub fn describe(&self) -> String {
+        match self {
+            Action::Index {
+                command, entries, ..
+            } => {
+                format!("{} {}", command, paths(entries.iter().map(|(p, _)| p)))
+            }
+            Action::Discard { command, trash } => format!("{} (trash {})", command, trash),
+            Action::Resolve { side, path, .. } => format!("{} {}", side, path),
+            Action::Commit { .. } => "commit".to_string(),
+        }
+    }
+}
+
+fn paths<'a>(paths: impl Iterator<Item = &'a String>) -> String {
+    paths.map(String::as_str).collect::<Vec<_>>().join(", ")
+}
+
+impl Entry {
+    fn to_json(&self) -> Value {
+        let mut fields = vec![("time", Value::from(self.time))];
+        match &self.action {
+            Action::Index {
+                command,
+                entries,
+                executable,
+            } => {
+                fields.push(("action", command.as_str().into()));
+                let entries = entries
+                    .iter()
+                    .map(|(path, index)| {
+                        Value::object([
+                            ("path", path.as_str().into()),
+                            ("index", index.clone().into()),
+                        ])
+                    })
+                    .collect();
+                fields.push(("entries", Value::Array(entries)));
+                if !executable.is_empty() {
+                    let executable = executable
+                        .iter()
+                        .map(|(path, on)| {
+                            Value::object([
+                                ("path", path.as_str().into()),
+                                ("executable", Value::Bool(*on)),
+                            ])
+                        })
+                        .collect();
+                    fields.push(("executable", Value::Array(executable)));
+                }
+            }
+            Action::Discard { command, trash } => {
+                fields.push(("action", command.as_str().into()));
+                fields.push(("trash", trash.as_str().into()));
+            }
+            Action::Resolve { side, path, trash } => {
+                fields.push(("action", side.as_str().into()));
+                fields.push(("resolved", path.as_str().into()));
+                fields.push(("trash", trash.clone().into()));
+            }
+            Action::Commit { head } => {
+                fields.push(("action", "commit".into()));
+                fields.push(("head", head.clone().into()));
+            }
+        }
+        Value::object(fields)
+    }
+
+    fn from_json(value: &Value) -> Option<Entry> {
+        let items = |key: &str| match value.get(key) {
+            Some(Value::Array(items)) => Some(items.clone()),
+            _ => None,
+        };
+        let string = |v: &Value, key: &str| v.get(key)?.as_str().map(str::to_string);
+        // Index and discard entries are told apart by what they hold, not
+        // by the command that made them, so every command recording one
+        // can be undone
+        let action = match value.get("action")?.as_str()? {
+            "commit" => Action::Commit {
+                head: string(value, "head"),
+            },
+            side if value.get("resolved").is_some() => Action::Resolve {
+                side: side.to_string(),
+                path: string(value, "resolved")?,
+                trash: string(value, "trash"),
+            },
+            command if value.get("trash").is_some() => Action::Discard {
+                command: command.to_string(),
+                trash: string(value, "trash")?,
+            },
+            command => Action::Index {
+                command: command.to_string(),
+                entries: items("entries")?
+                    .iter()
+                    .map(|e| Some((string(e, "path")?, string(e, "index"))))
+                    .collect::<Option<_>>()?,
+                executable: items("executable")
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|e| Some((string(e, "path")?, e.get("executable")?.as_bool()?)))
+                    .collect::<Option<_>>()?,
+            },
+        };
+        Some(Entry {
+            time: value.get("time")?.as_u64()?,
+            action,
+        })
+    }
+}
+
+/// The action journal at `.git/f/journal`, one JSON entry per line.
+pub struct Journal {
+    path: PathBuf,
+    /// Work tree root; recorded paths are relative to it
+    root: PathBuf,
+}
+
+impl Journal {
+    pub fn open() -> Result<Journal> {
+        let git_dir = crate::git_status::get_git_dir()?;
+        let root = crate::git_status::get_git_root()?;
+        Ok(Journal {
+            path: git_dir.join("f").join("journal"),
+            root,
+        })
+    }
+
+    pub fn entries(&self) -> Vec<Entry> {
+        let Ok(content) = fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        content
+            .lines()
+            .filter_map(|line| json::parse(line).ok())
+            .filter_map(|value| Entry::from_json(&value))
+            .collect()
+    }
+
+    fn save(&self, entries: &[Entry]) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let start = entries.len().saturating_sub(MAX_ENTRIES);
+        let mut content = String::new();
+        for entry in &entries[start..] {
+            content.push_str(&entry.to_json().to_string());
+            content.push('\n');
+        }
+        fs::write(&self.path, content)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    fn push(&self, action: Action) -> Result<()> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        // A commit still pointing at the current HEAD never happened, or
+        // was already reset, so there's nothing left for it to undo
+        let head = self.rev_parse("HEAD");
+        let mut entries = self.entries();
+        entries.retain(|e| !matches!(&e.action, Action::Commit { head: h } if *h == head));
+        entries.push(Entry { time, action });
+        self.save(&entries)
+    }
+
+    /// Saves the index entries of `paths` before `command` changes them.
+    pub fn record_index(&self, command: &str, paths: &[&str]) -> Result<()> {
+        self.push(Action::Index {
+            command: command.to_string(),
+            entries: self.index_entries(paths)?,
+            executable: Vec::new(),
+        })
+    }
+
+    /// Saves the index entries of `paths` and their executable bits in the
+    /// work tree before `f chmod` changes both.
+    pub fn record_chmod(&self, paths: &[&str]) -> Result<()> {
+        let executable = paths
+            .iter()
+            .filter_map(|path| {
+                Some((
+                    path.to_string(),
+                    crate::is_executable(&self.root.join(path))?,
+                ))
+            })
+            .collect();
+        self.push(Action::Index {
+            command: "chmod".to_string(),
+            entries: self.index_entries(paths)?,
+            executable,
+        })
+    }
+
+    fn index_entries(&self, paths: &[&str]) -> Result<Vec<(String, Option<String>)>> {
+        let listing = self.git(&[&["ls-files", "-s", "-z", "--"], paths].concat(), None)?;
+        Ok(paths
+            .iter()
+            .map(|path| (path.to_string(), index_entry(&listing, path)))
+            .collect())
+    }
+
+    /// Records a discard whose files were saved to the trash first.
+    pub fn record_discard(&self, command: &str, trash: &str) -> Result<()> {
+        self.push(Action::Discard {
+            command: command.to_string(),
+            trash: trash.to_string(),
+        })
+    }
+
+    /// Records taking `side` of the conflicted `path`, whose file was
+    /// saved to the trash first, if it was there to save.
+    pub fn record_resolve(&self, side: &str, path: &str, trash: Option<&str>) -> Result<()> {
+        self.push(Action::Resolve {
+            side: side.to_string(),
+            path: path.to_string(),
+            trash: trash.map(str::to_string),
+        })
+    }
+
+    pub fn record_commit(&self) -> Result<()> {
+        let head = self.rev_parse("HEAD");
+        self.push(Action::Commit { head })
+    }
+
+    /// Reverses the most recent action and drops it from the journal.
+    /// Commits that never happened (say, a hook rejected them) are skipped.
+    pub fn undo(&self) -> Result<Entry> {
+        let mut entries = self.entries();
+        loop {
+            let Some(entry) = entries.pop() else {
+                bail!("Nothing to undo");
+            };
+            if let Action::Commit { head } = &entry.action
+                && self.rev_parse("HEAD") == *head
+            {
+                continue;
+            }
+            self.reverse(&entry.action)?;
+            self.save(&entries)?;
+            return Ok(entry);
+        }
+    }
+
+    fn reverse(&self, action: &Action) -> Result<()> {
+        match action {
+            Action::Index {
+                entries,
+                executable,
+                ..
+            } => {
+                // Mode 0 removes the path from the index
+                let info: String = entries
+                    .iter()
+                    .map(|(path, entry)| {
+                        let entry = entry.as_deref().unwrap_or(NO_ENTRY);
+                        format!("{}\t{}\n", entry, path)
+                    })
+                    .collect();
+                self.git(&["update-index", "--index-info"], Some(&info))?;
+                for (path, on) in executable {
+                    crate::set_exec_bit(&self.root.join(path), *on)
+                        .with_context(|| format!("Cannot chmod {}", path))?;
+                }
+            }
+            Action::Discard { trash, .. } => {
+                // Overwrite what discard left behind, i.e. the index version
+                let trash_store = Trash::open()?;
+                let entry = trash_store.find(Some(trash))?;
+                trash_store.restore(&entry, None, true)?;
+            }
+            Action::Resolve { path, trash, .. } => {
+                // Puts the base, ours and theirs back in the index from what
+                // git kept when the resolution was staged
+                self.git(&["checkout", "-m", "--", path], None)
+                    .context("Cannot bring the conflict back")?;
+                // Then any of it already resolved by hand
+                if let Some(trash) = trash {
+                    let trash_store = Trash::open()?;
+                    let entry = trash_store.find(Some(trash))?;
+                    trash_store.restore(&entry, None, true)?;
+                }
+            }
+            Action::Commit { head } => {
+                let parent = self.rev_parse("HEAD^");
+                if parent != *head {
+                    bail!("HEAD has moved since that commit, not undoing it");
+                }
+                match head {
+                    Some(head) => self.git(&["reset", "--soft", head], None)?,
+                    None => self.git(&["update-ref", "-d", "HEAD"], None)?,
+                };
+            }
+        }
+        Ok(())
+    }
+
+    fn rev_parse(&self, rev: &str) -> Option<String> {
+        self.git(&["rev-parse", "--verify", "-q", rev], None)
+            .ok()
+            .map(|s| s.trim().to_string())
+    }
+
+    fn git(&self, args: &[&str], input: Option<&str>) -> Result<String> {
+        let bytes = self.git_run(args, input)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn git_run(&self, args: &[&str], input: Option<&str>) -> Result<Vec<u8>> {
+        crate::exec::output_with_input(
+            Command::new("git").args(args).current_dir(&self.root),
+            input.map(str::as_bytes),
+        )
+    }
+}
+
+const NO_ENTRY: &str = "0 0000000000000000000000000000000000000000";
+
+/// The stage-0 `"<mode> <sha>"` for `path` in `git ls-files -s -z` output.
+fn index_entry(listing: &str, path: &str) -> Option<String> {
+    listing.split('\0').find_map(|record| {
+        let (info, name) = record.split_once('\t')?;
+        let mut fields = info.split(' ');
+        let (mode, sha, stage) = (fields.next()?, fields.next()?, fields.next()?);
+        (name == path && stage == "0").then(|| format!("{} {}", mode, sha))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_round_trip() {
+        let actions = [
+            Action::Index {
+                command: "add".to_string(),
+                entries: vec![
+                    ("src/a.rs".to_string(), Some("100644 abc".to_string())),
+                    ("new.txt".to_string(), None),
+                ],
+                executable: Vec::new(),
+            },
+            Action::Index {
+                command: "chmod".to_string(),
+                entries: vec![("run.sh".to_string(), Some("100644 abc".to_string()))],
+                executable: vec![("run.sh".to_string(), false)],
+            },
+            Action::Resolve {
+                side: "theirs".to_string(),
+                path: "c.txt".to_string(),
+                trash: Some("1700000001".to_string()),
+            },
+            Action::Resolve {
+                side: "ours".to_string(),
+                path: "c.txt".to_string(),
+                trash: None,
+            },
+            Action::Discard {
+                command: "clean".to_string(),
+                trash: "1700000000".to_string(),
+            },
+            Action::Commit { head: None },
+            Action::Commit {
+                head: Some("123".to_string()),
+            },
+        ];
+        for action in actions {
+            let entry = Entry { time: 42, action };
+            let parsed = json::parse(&entry.to_json().to_string()).unwrap();
+            assert_eq!(Entry::from_json(&parsed), Some(entry));
+        }
+    }
+
+    #[test]
+    fn test_index_entry() {
+        let listing = [
+            "100644 aaa 0\tsrc/a.rs",
+            "100755 bbb 0\trun.sh",
+            "100644 ccc 2\tboth.rs",
+        ]
+        .join("\0");
+        assert_eq!(
+            index_entry(&listing, "run.sh").as_deref(),
+            Some("100755 bbb")
+        );
+        assert_eq!(index_entry(&listing, "missing"), None);
+        assert_eq!(index_entry(&listing, "both.rs"), None);
+    }
+
+    #[test]
+    fn test_describe() {
+        let action = Action::Index {
+            command: "unstage".to_string(),
+            entries: vec![("a".into(), None), ("b".into(), None)],
+            executable: Vec::new(),
+        };
+        assert_eq!(action.describe(), "unstage a, b");
+    }
+}
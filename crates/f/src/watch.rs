@@ -0,0 +1,343 @@
+use crate::config::{Config, OnClean};
+use crate::display;
+use crate::filter::FileFilter;
+use crate::git_status::{FileType, GitFile, get_all_files, get_git_dir, get_git_root};
+use crate::glob::{self, Glob};
+use crate::notify;
+use crate::watcher;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use std::collections::HashMap;
+use std::io::{Write, stdout};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+
+/// In event mode, how long the tree must be quiet before redrawing, and the
+/// longest a burst of changes can postpone a redraw.
+const DEBOUNCE_QUIET: Duration = Duration::from_millis(100);
+const DEBOUNCE_MAX: Duration = Duration::from_secs(1);
+
+/// In event mode the frame is still refreshed this often, to catch changes
+/// the watcher can't see (e.g. edits to .gitignore'd directories becoming
+/// tracked).
+const EVENT_FALLBACK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often key presses are checked while waiting on filesystem events.
+const KEY_POLL: Duration = Duration::from_millis(50);
+
+/// Number of refreshes a changed row stays highlighted.
+const HIGHLIGHT_TICKS: u32 = 2;
+
+type RowKey = (String, FileType);
+
+/// Remembers each row's diff stats between refreshes so rows that appeared
+/// or changed can be highlighted while a formatter or codegen is running.
+#[derive(Default)]
+struct ChangeTracker {
+    previous: Option<HashMap<RowKey, (u32, u32)>>,
+    recent: HashMap<RowKey, u32>,
+}
+
+impl ChangeTracker {
+    fn update(&mut self, files: &[GitFile]) {
+        let current: HashMap<RowKey, (u32, u32)> = files
+            .iter()
+            .map(|f| {
+                let stats = f
+                    .diff_stats
+                    .as_ref()
+                    .map_or((0, 0), |s| (s.added, s.removed));
+                ((f.rel_path.clone(), f.file_type), stats)
+            })
+            .collect();
+
+        self.recent.retain(|_, ticks| {
+            *ticks -= 1;
+            *ticks > 0
+        });
+        // Nothing is "new" on the very first frame
+        if let Some(previous) = &self.previous {
+            for (key, stats) in &current {
+                if previous.get(key) != Some(stats) {
+                    self.recent.insert(key.clone(), HIGHLIGHT_TICKS);
+                }
+            }
+        }
+        self.previous = Some(current);
+    }
+
+    fn is_highlighted(&self, file: &GitFile) -> bool {
+        self.recent
+            .contains_key(&(file.rel_path.clone(), file.file_type))
+    }
+}
+
+pub enum Wake {
+    Refresh,
+    Quit,
+}
+
+/// Redraws the file list on the alternate screen until `q`, Esc or Ctrl-C is
+/// pressed: every `interval`, or with `events` whenever the work tree or
+/// index changes. Only files passing `filter` are shown, and "clean" means
+/// nothing left that matches it.
+pub fn run(config: &Config, interval: Duration, events: bool, filter: &FileFilter) -> Result<()> {
+    let changes = if events {
+        let root = get_git_root()?;
+        let git_dir = get_git_dir()?;
+        Some(watcher::spawn(&root, &git_dir)?)
+    } else {
+        None
+    };
+
+    fullscreen(|| watch_loop(config, interval, changes.as_ref(), filter))
+}
+
+/// Runs `body` on the alternate screen in raw mode, restoring the terminal
+/// afterwards even if it fails.
+pub fn fullscreen(body: impl FnOnce() -> Result<()>) -> Result<()> {
+    if !crate::exec::interactive() {
+        return Err(crate::error::Error::Usage(crate::interactive::NEEDS_TERMINAL.into()).into());
+    }
+    let mut out = stdout();
+    terminal::enable_raw_mode().context("Terminal error")?;
+    execute!(out, EnterAlternateScreen, cursor::Hide).context("Terminal error")?;
+
+    let result = body();
+
+    let _ = execute!(out, cursor::Show, LeaveAlternateScreen);
+    terminal::disable_raw_mode().context("Terminal error")?;
+    result
+}
+
+fn watch_loop(
+    config: &Config,
+    interval: Duration,
+    changes: Option<&Receiver<()>>,
+    filter: &FileFilter,
+) -> Result<()> {
+    let id_chars = config.id_chars();
+    let auto_stage = glob::compile_all(&config.watch.auto_stage);
+    let on_clean = config.watch_on_clean();
+    let mut was_dirty: Option<bool> = None;
+    let mut tracker = ChangeTracker::default();
+    let mut status_line = String::new();
+
+    loop {
+        let frame = match refresh(&id_chars, &auto_stage) {
+            Ok((files, staged)) => {
+                if !staged.is_empty() {
+                    status_line = format!("Auto-staged: {}", staged.join(", "));
+                }
+                let files = filter.apply(files);
+                let dirty = !files.is_empty();
+                if was_dirty == Some(true) && !dirty {
+                    run_on_clean(on_clean);
+                }
+                was_dirty = Some(dirty);
+                tracker.update(&files);
+                display::render_files_highlighted(&files, |f| tracker.is_highlighted(f))
+            }
+            Err(e) => format!("{} {}\n", "Error:".red(), e),
+        };
+        let mut top = header(interval, changes.is_some(), filter);
+        if !status_line.is_empty() {
+            top = format!("{}\n{}", top, status_line.green());
+        }
+        draw(&top, &frame)?;
+
+        let wake = match changes {
+            Some(rx) => wait_for_change(rx)?,
+            None => wait_for_tick(interval)?,
+        };
+        if let Wake::Quit = wake {
+            return Ok(());
+        }
+    }
+}
+
+/// Collects the file list, first staging anything matching `auto_stage`.
+/// Returns the files plus the paths that were just staged.
+fn refresh(id_chars: &[char], auto_stage: &[Glob]) -> Result<(Vec<GitFile>, Vec<String>)> {
+    let files = get_all_files(id_chars)?;
+    let to_stage: Vec<&GitFile> = files
+        .iter()
+        .filter(|f| f.file_type != FileType::Staged && glob::any_match(auto_stage, &f.rel_path))
+        .collect();
+    if to_stage.is_empty() {
+        return Ok((files, vec![]));
+    }
+
+    let status = std::process::Command::new("git")
+        .arg("add")
+        .arg("--")
+        .args(to_stage.iter().map(|f| &f.abs_path))
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()?;
+    if !status.success() {
+        return Ok((files, vec![]));
+    }
+    let staged = to_stage.iter().map(|f| f.rel_path.clone()).collect();
+    Ok((get_all_files(id_chars)?, staged))
+}
+
+fn run_on_clean(action: OnClean) {
+    match action {
+        OnClean::Nothing => {}
+        OnClean::Notify => notify::send("f", "Working tree is clean"),
+        OnClean::Bell => {
+            let mut out = stdout();
+            let _ = write!(out, "\x07");
+            let _ = out.flush();
+        }
+    }
+}
+
+fn header(interval: Duration, events: bool, filter: &FileFilter) -> String {
+    let mut mode = if events {
+        "On change".to_string()
+    } else {
+        format!("Every {:.1}s", interval.as_secs_f64())
+    };
+    if !filter.is_empty() {
+        mode = format!("{} · {}", mode, filter.describe());
+    }
+    format!("{}", format!("{} · q to quit", mode).dimmed())
+}
+
+/// Overwrites the previous frame in place rather than clearing the screen
+/// first, which avoids flicker on every refresh.
+pub fn draw(header: &str, frame: &str) -> Result<()> {
+    let mut out = stdout();
+    queue!(out, cursor::MoveTo(0, 0)).context("Terminal error")?;
+    for line in std::iter::once(header).chain([""]).chain(frame.lines()) {
+        // Raw mode disables the implicit carriage return on newline
+        write!(out, "{}", line)?;
+        queue!(out, terminal::Clear(ClearType::UntilNewLine)).context("Terminal error")?;
+        write!(out, "\r\n")?;
+    }
+    queue!(out, terminal::Clear(ClearType::FromCursorDown)).context("Terminal error")?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Sleeps until the next refresh while watching for quit keys.
+fn wait_for_tick(interval: Duration) -> Result<Wake> {
+    let deadline = Instant::now() + interval;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(Wake::Refresh);
+        }
+        if let Some(wake) = poll_keys(remaining)? {
+            return Ok(wake);
+        }
+    }
+}
+
+/// Waits for a (debounced) filesystem change while watching for quit keys.
+fn wait_for_change(changes: &Receiver<()>) -> Result<Wake> {
+    let deadline = Instant::now() + EVENT_FALLBACK_INTERVAL;
+    loop {
+        match changes.try_recv() {
+            Ok(()) => {
+                watcher::debounce(changes, DEBOUNCE_QUIET, DEBOUNCE_MAX);
+                return Ok(Wake::Refresh);
+            }
+            Err(TryRecvError::Disconnected) => return wait_for_tick(EVENT_FALLBACK_INTERVAL),
+            Err(TryRecvError::Empty) => {}
+        }
+        if Instant::now() >= deadline {
+            return Ok(Wake::Refresh);
+        }
+        if let Some(wake) = poll_keys(KEY_POLL)? {
+            return Ok(wake);
+        }
+    }
+}
+
+/// Handles at most one terminal event within `timeout`. A resize ends the
+/// wait early so the frame is redrawn at the new size.
+pub fn poll_keys(timeout: Duration) -> Result<Option<Wake>> {
+    if !event::poll(timeout).context("Event error")? {
+        return Ok(None);
+    }
+    match event::read().context("Read error")? {
+        Event::Key(key) => {
+            let ctrl_c =
+                key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c');
+            if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                return Ok(Some(Wake::Quit));
+            }
+            Ok(None)
+        }
+        Event::Resize(..) => Ok(Some(Wake::Refresh)),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_status::{DiffStats, StableId};
+    use std::path::PathBuf;
+
+    fn file(path: &str, file_type: FileType, added: u32) -> GitFile {
+        GitFile {
+            mtime: 0,
+            rel_path: path.to_string(),
+            abs_path: PathBuf::from(path),
+            file_type,
+            stable_id: StableId {
+                display: "d".to_string(),
+                full_hash: "dddddddddddd".to_string(),
+            },
+            diff_stats: Some(DiffStats { added, removed: 0 }),
+            lfs_size: None,
+            mode_change: None,
+            status: 'M',
+        }
+    }
+
+    #[test]
+    fn first_frame_has_no_highlights() {
+        let mut tracker = ChangeTracker::default();
+        let files = vec![file("a.rs", FileType::Unstaged, 1)];
+        tracker.update(&files);
+        assert!(!tracker.is_highlighted(&files[0]));
+    }
+
+    #[test]
+    fn changed_rows_stay_highlighted_for_two_ticks() {
+        let mut tracker = ChangeTracker::default();
+        tracker.update(&[file("a.rs", FileType::Unstaged, 1)]);
+
+        let changed = vec![
+            file("a.rs", FileType::Unstaged, 2),
+            file("b.rs", FileType::Untracked, 1),
+        ];
+        tracker.update(&changed);
+        assert!(tracker.is_highlighted(&changed[0]));
+        assert!(tracker.is_highlighted(&changed[1]));
+
+        tracker.update(&changed);
+        assert!(tracker.is_highlighted(&changed[0]));
+
+        tracker.update(&changed);
+        assert!(!tracker.is_highlighted(&changed[0]));
+        assert!(!tracker.is_highlighted(&changed[1]));
+    }
+
+    #[test]
+    fn staging_a_file_highlights_its_staged_row() {
+        let mut tracker = ChangeTracker::default();
+        tracker.update(&[file("a.rs", FileType::Unstaged, 1)]);
+        let staged = vec![file("a.rs", FileType::Staged, 1)];
+        tracker.update(&staged);
+        assert!(tracker.is_highlighted(&staged[0]));
+    }
+}
@@ -0,0 +1,402 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Files under the git directory whose changes affect status output.
+pub const GIT_FILES: &[&str] = &["index", "HEAD"];
+
+/// Watches the work tree and git index on a background thread, sending a
+/// message whenever something relevant changes. The thread exits once the
+/// receiver is dropped.
+pub fn spawn(root: &Path, git_dir: &Path) -> Result<Receiver<()>> {
+    let (tx, rx) = mpsc::channel();
+    let dirs = watched_dirs(root);
+    backend::spawn(root.to_path_buf(), git_dir.to_path_buf(), dirs, tx)?;
+    Ok(rx)
+}
+
+/// Drains a burst of changes, such as an editor writing a swap file, a
+/// backup and the file itself. Returns once no change has arrived for
+/// `quiet`, or after `max_wait` so a continuous stream still refreshes.
+pub fn debounce(rx: &Receiver<()>, quiet: Duration, max_wait: Duration) {
+    let deadline = Instant::now() + max_wait;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return;
+        }
+        match rx.recv_timeout(quiet.min(remaining)) {
+            Ok(()) => continue,
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// Directories containing tracked or untracked-but-not-ignored files. Build
+/// output and other ignored trees are left alone so builds don't trigger
+/// refreshes.
+pub fn watched_dirs(root: &Path) -> Vec<PathBuf> {
+    let mut dirs = HashSet::new();
+    dirs.insert(root.to_path_buf());
+    let output = crate::exec::run_output(
+        std::process::Command::new("git")
+            .args([
+                "ls-files",
+                "-z",
+                "--cached",
+                "--others",
+                "--exclude-standard",
+            ])
+            .current_dir(root),
+    );
+    if let Ok(output) = output {
+        for path in String::from_utf8_lossy(&output.stdout).split('\0') {
+            let mut dir = Path::new(path).parent();
+            while let Some(d) = dir {
+                if d.as_os_str().is_empty() || !dirs.insert(root.join(d)) {
+                    break;
+                }
+                dir = d.parent();
+            }
+        }
+    }
+    dirs.into_iter().collect()
+}
+
+/// Whether git ignores `path`, a directory just created under `root`, so
+/// a build's output tree isn't watched from then on.
+fn is_ignored(root: &Path, path: &Path) -> bool {
+    crate::exec::run_output(
+        std::process::Command::new("git")
+            .args(["check-ignore", "-q", "--"])
+            .arg(path)
+            .current_dir(root),
+    )
+    .is_ok_and(|output| output.status.success())
+}
+
+fn is_git_internal(path: &Path, git_dir: &Path) -> bool {
+    path.starts_with(git_dir) || path.components().any(|c| c.as_os_str() == ".git")
+}
+
+#[cfg(target_os = "linux")]
+mod backend {
+    use super::{GIT_FILES, is_git_internal, is_ignored, poll};
+    use anyhow::{Result, bail};
+    use std::collections::HashMap;
+    use std::ffi::CString;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc::Sender;
+
+    const DIR_MASK: u32 = libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_MODIFY
+        | libc::IN_CLOSE_WRITE
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO
+        | libc::IN_ATTRIB;
+
+    struct Inotify {
+        fd: OwnedFd,
+        watches: HashMap<i32, PathBuf>,
+    }
+
+    impl Inotify {
+        fn new() -> Result<Self> {
+            // SAFETY: plain syscall; the returned fd is owned by OwnedFd below
+            let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+            if fd < 0 {
+                bail!("inotify_init1 failed: {}", std::io::Error::last_os_error());
+            }
+            Ok(Self {
+                // SAFETY: fd is a freshly created, valid descriptor
+                fd: unsafe { OwnedFd::from_raw_fd(fd) },
+                watches: HashMap::new(),
+            })
+        }
+
+        /// Fails when the watch can't be added, most often because
+        /// `fs.inotify.max_user_watches` has been reached (ENOSPC). A
+        /// directory already gone again isn't a failure.
+        fn add(&mut self, path: &Path, mask: u32) -> Result<()> {
+            let cpath = CString::new(path.as_os_str().as_bytes())?;
+            // SAFETY: cpath is a valid NUL-terminated string for the call
+            let wd = unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), cpath.as_ptr(), mask) };
+            if wd < 0 {
+                let e = std::io::Error::last_os_error();
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    return Ok(());
+                }
+                bail!("Cannot watch {}: {}", path.display(), e);
+            }
+            self.watches.insert(wd, path.to_path_buf());
+            Ok(())
+        }
+
+        fn add_dirs(&mut self, dirs: &[PathBuf]) -> Result<()> {
+            dirs.iter().try_for_each(|dir| self.add(dir, DIR_MASK))
+        }
+
+        /// Blocks until at least one event is available and returns the
+        /// affected paths, along with whether each one is a directory.
+        fn read(&self) -> Result<Events> {
+            let mut buf = [0u8; 8192];
+            // SAFETY: buf is valid for writes of buf.len() bytes
+            let n = unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                )
+            };
+            if n < 0 {
+                bail!("inotify read failed: {}", std::io::Error::last_os_error());
+            }
+            Ok(parse_events(&buf[..n as usize], &self.watches))
+        }
+    }
+
+    pub struct Events {
+        pub paths: Vec<(PathBuf, bool)>,
+        /// The kernel's queue filled up and events were dropped, so
+        /// anything may have changed, new directories included.
+        pub overflowed: bool,
+    }
+
+    /// Decodes the packed `struct inotify_event` records in `buf`.
+    pub fn parse_events(buf: &[u8], watches: &HashMap<i32, PathBuf>) -> Events {
+        const HEADER: usize = 16;
+        let mut events = Events {
+            paths: Vec::new(),
+            overflowed: false,
+        };
+        let mut offset = 0;
+        while offset + HEADER <= buf.len() {
+            let field = |i: usize| {
+                let start = offset + i * 4;
+                [buf[start], buf[start + 1], buf[start + 2], buf[start + 3]]
+            };
+            let wd = i32::from_ne_bytes(field(0));
+            let mask = u32::from_ne_bytes(field(1));
+            let len = u32::from_ne_bytes(field(3)) as usize;
+            let name_bytes = &buf[offset + HEADER..(offset + HEADER + len).min(buf.len())];
+            let name_end = name_bytes.iter().position(|&b| b == 0).unwrap_or(len);
+            let name = std::ffi::OsStr::from_bytes(&name_bytes[..name_end]);
+            if mask & libc::IN_Q_OVERFLOW != 0 {
+                events.overflowed = true;
+            } else if let Some(dir) = watches.get(&wd) {
+                events
+                    .paths
+                    .push((dir.join(name), mask & libc::IN_ISDIR != 0));
+            }
+            offset += HEADER + len;
+        }
+        events
+    }
+
+    pub fn spawn(
+        root: PathBuf,
+        git_dir: PathBuf,
+        dirs: Vec<PathBuf>,
+        tx: Sender<()>,
+    ) -> Result<()> {
+        let mut inotify = Inotify::new()?;
+        let added = inotify.add_dirs(&dirs).and_then(|()| {
+            inotify.add(
+                &git_dir,
+                libc::IN_MOVED_TO | libc::IN_CLOSE_WRITE | libc::IN_DELETE,
+            )
+        });
+        if let Err(e) = added {
+            // A change under a directory left unwatched would never show
+            log::debug!("{:#}, polling instead", e);
+            return poll::spawn(root, git_dir, dirs, tx);
+        }
+
+        std::thread::spawn(move || {
+            while let Ok(events) = inotify.read() {
+                let mut relevant = events.overflowed;
+                let mut added = Ok(());
+                if events.overflowed {
+                    // Directories created meanwhile need watching too;
+                    // re-adding a watched one is harmless
+                    added = inotify.add_dirs(&super::watched_dirs(&root));
+                }
+                for (path, is_dir) in events.paths {
+                    if path.starts_with(&git_dir) {
+                        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                        relevant |= path.parent() == Some(&git_dir) && GIT_FILES.contains(&name);
+                    } else if !is_git_internal(&path, &git_dir) && path.starts_with(&root) {
+                        if is_dir {
+                            // Created like the rest of a build's output
+                            if is_ignored(&root, &path) {
+                                continue;
+                            }
+                            added = added.and_then(|()| inotify.add(&path, DIR_MASK));
+                        }
+                        relevant = true;
+                    }
+                }
+                if relevant && tx.send(()).is_err() {
+                    return;
+                }
+                if let Err(e) = added {
+                    log::debug!("{:#}, polling instead", e);
+                    drop(inotify);
+                    poll::run(&root, &git_dir, super::watched_dirs(&root), &tx);
+                    return;
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+use poll as backend;
+
+/// Polls for changes, where there's no native notification API or it
+/// couldn't watch everything.
+mod poll {
+    use super::{GIT_FILES, is_git_internal};
+    use anyhow::Result;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc::Sender;
+    use std::time::{Duration, SystemTime};
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Without a native notification API, stat the watched directories (whose
+    /// mtimes change when entries are added or removed), their files and the
+    /// index. This is far cheaper than re-running `git status`.
+    fn fingerprint(dirs: &[PathBuf], git_dir: &Path) -> Vec<Option<SystemTime>> {
+        let mtime = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+        let mut stamps: Vec<_> = GIT_FILES.iter().map(|f| mtime(&git_dir.join(f))).collect();
+        for dir in dirs {
+            stamps.push(mtime(dir));
+            if let Ok(entries) = std::fs::read_dir(dir) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if !is_git_internal(&path, git_dir) && path.is_file() {
+                        stamps.push(mtime(&path));
+                    }
+                }
+            }
+        }
+        stamps
+    }
+
+    pub fn spawn(
+        root: PathBuf,
+        git_dir: PathBuf,
+        dirs: Vec<PathBuf>,
+        tx: Sender<()>,
+    ) -> Result<()> {
+        std::thread::spawn(move || run(&root, &git_dir, dirs, &tx));
+        Ok(())
+    }
+
+    /// Polls until the receiver is dropped.
+    pub fn run(root: &Path, git_dir: &Path, dirs: Vec<PathBuf>, tx: &Sender<()>) {
+        let mut dirs = dirs;
+        let mut last = fingerprint(&dirs, git_dir);
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let current = fingerprint(&dirs, git_dir);
+            if current != last {
+                if tx.send(()).is_err() {
+                    return;
+                }
+                dirs = super::watched_dirs(root);
+                last = fingerprint(&dirs, git_dir);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debounce_returns_after_quiet_period() {
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..5 {
+            tx.send(()).unwrap();
+        }
+        let start = Instant::now();
+        debounce(&rx, Duration::from_millis(20), Duration::from_secs(5));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn debounce_caps_continuous_streams() {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            while tx.send(()).is_ok() {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        });
+        let start = Instant::now();
+        debounce(&rx, Duration::from_millis(50), Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn ignored_directories_are_detected() {
+        let root = std::env::temp_dir().join(format!("f-watcher-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        let init = std::process::Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(&root)
+            .status()
+            .unwrap();
+        assert!(init.success());
+        assert!(is_ignored(&root, &root.join("target")));
+        assert!(is_ignored(&root, &root.join("target/debug")));
+        assert!(!is_ignored(&root, &root.join("src")));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn queue_overflow_is_reported_as_a_change() {
+        let event = |wd: i32, mask: u32, name: &[u8]| {
+            let mut bytes = Vec::new();
+            for field in [wd.to_ne_bytes(), mask.to_ne_bytes(), [0; 4]] {
+                bytes.extend(field);
+            }
+            bytes.extend((name.len() as u32).to_ne_bytes());
+            bytes.extend(name);
+            bytes
+        };
+        let watches = std::collections::HashMap::from([(1, PathBuf::from("/repo/src"))]);
+
+        let mut buf = event(1, libc::IN_MODIFY, b"lib.rs\0\0");
+        let events = backend::parse_events(&buf, &watches);
+        assert_eq!(events.paths, [(PathBuf::from("/repo/src/lib.rs"), false)]);
+        assert!(!events.overflowed);
+
+        // The kernel reports it with a wd of -1, matching no watch
+        buf.extend(event(-1, libc::IN_Q_OVERFLOW, b""));
+        let events = backend::parse_events(&buf, &watches);
+        assert_eq!(events.paths.len(), 1);
+        assert!(events.overflowed);
+    }
+
+    #[test]
+    fn git_internal_paths_are_detected() {
+        let git_dir = Path::new("/repo/.git");
+        assert!(is_git_internal(Path::new("/repo/.git/index"), git_dir));
+        assert!(is_git_internal(Path::new("/repo/sub/.git/HEAD"), git_dir));
+        assert!(!is_git_internal(Path::new("/repo/src/main.rs"), git_dir));
+    }
+}
@@ -0,0 +1,1441 @@
+//! End-to-end tests: the `f` binary run against fixture repositories.
+
+mod support;
+
+use support::{Repo, png, stderr, stdout};
+
+fn listed(repo: &Repo) -> Vec<(String, String)> {
+    let mut files: Vec<(String, String)> =
+        repo.list().into_iter().map(|(_, t, p)| (t, p)).collect();
+    files.sort();
+    files
+}
+
+fn pair(file_type: &str, path: &str) -> (String, String) {
+    (file_type.to_string(), path.to_string())
+}
+
+#[test]
+fn list_covers_every_kind_of_change() {
+    let repo = Repo::new();
+    repo.write("old.txt", "a\n");
+    repo.write("gone.txt", "d\n");
+    repo.write("keep.txt", "k\n");
+    repo.commit_all("Add files");
+
+    repo.git(&["mv", "old.txt", "new.txt"]);
+    repo.git(&["rm", "-q", "gone.txt"]);
+    repo.write("keep.txt", "k\nmore\n");
+    repo.write("dir/untracked.txt", "u\n");
+
+    assert_eq!(
+        listed(&repo),
+        [
+            pair("staged", "gone.txt"),
+            pair("staged", "new.txt"),
+            pair("unstaged", "keep.txt"),
+            pair("untracked", "dir/untracked.txt"),
+        ]
+    );
+
+    let human = stdout(&repo.f(&[]));
+    for header in ["── Unstaged ──", "── Untracked ──", "── Staged ──"] {
+        assert!(
+            human.contains(header),
+            "{} missing from:\n{}",
+            header,
+            human
+        );
+    }
+}
+
+#[test]
+fn unstaged_deletion_is_listed() {
+    let repo = Repo::new();
+    repo.remove("README.md");
+    assert_eq!(listed(&repo), [pair("unstaged", "README.md")]);
+}
+
+#[test]
+fn old_ids_keep_resolving_as_other_files_change() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a\n");
+    let before = repo.id("untracked", "a.txt");
+    for name in ["b.txt", "c.txt", "d/e.txt", "f.txt", "g.txt", "h.txt"] {
+        repo.write(name, "x\n");
+    }
+    repo.write("README.md", "changed\n");
+    // The shown ID may grow to stay unique, but it extends the old one, and
+    // the old one never names a different file: it's either still a.txt or
+    // now ambiguous
+    let after = repo.id("untracked", "a.txt");
+    assert!(after.starts_with(&before), "{} -> {}", before, after);
+    let out = repo.f(&["path", "--relative", &before]);
+    match out.status.code() {
+        Some(0) => assert_eq!(stdout(&out), "a.txt\n"),
+        code => assert_eq!(code, Some(5), "{}", stderr(&out)),
+    }
+    let out = repo.f(&["path", "--relative", &after]);
+    assert_eq!(stdout(&out), "a.txt\n", "{}", stderr(&out));
+}
+
+#[test]
+fn ids_and_paths_resolve_to_the_file() {
+    let repo = Repo::new();
+    repo.write("src/lib.rs", "fn main() {}\n");
+    let id = repo.id("untracked", "src/lib.rs");
+    let expected = format!("{}\n", repo.path().join("src/lib.rs").display());
+
+    assert_eq!(stdout(&repo.f(&["path", &id])), expected);
+    // Paths are taken relative to the current directory, like f shows them
+    let out = repo.f_in(&repo.path().join("src"), &["path", "lib.rs"]);
+    assert_eq!(stdout(&out), expected);
+    let out = repo.f_in(&repo.path().join("src"), &["path", "--relative", &id]);
+    assert_eq!(stdout(&out), "lib.rs\n");
+}
+
+#[test]
+fn unknown_ids_and_missing_repos_have_their_own_exit_codes() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a\n");
+    let out = repo.f(&["path", "no/such/file"]);
+    assert_eq!(out.status.code(), Some(4), "{}", stderr(&out));
+    assert!(stderr(&out).contains("No file matches ID or path"));
+
+    let outside = repo.path().join("..").join("home");
+    let out = repo.f_in(&outside, &["list"]);
+    assert_eq!(out.status.code(), Some(3), "{}", stderr(&out));
+}
+
+#[test]
+fn mistyped_ids_suggest_the_near_miss() {
+    let repo = Repo::new();
+    repo.write("src/lib.rs", "fn lib() {}\n");
+    let id = repo.id("untracked", "src/lib.rs");
+    let typo = format!("{}x", id);
+
+    let out = repo.f(&["path", &typo]);
+    assert_eq!(out.status.code(), Some(4), "{}", stderr(&out));
+    assert_eq!(
+        stderr(&out),
+        format!(
+            "Error: No file matches ID or path: {}, did you mean {} (src/lib.rs)?\n",
+            typo, id
+        )
+    );
+
+    // Every ID is a change away from a single character
+    let first = id.chars().next().unwrap();
+    let other = "dfghklsa".chars().find(|&c| c != first).unwrap();
+    let out = repo.f(&[&other.to_string(), "d"]);
+    assert_eq!(out.status.code(), Some(4), "{}", stderr(&out));
+    assert_eq!(
+        stderr(&out),
+        format!("Error: No file matches ID or path: {}\n", other)
+    );
+}
+
+#[test]
+fn add_and_unstage_move_files_between_sections() {
+    let repo = Repo::new();
+    repo.write("README.md", "changed\n");
+    repo.write("new.txt", "n\n");
+
+    let id = repo.id("unstaged", "README.md");
+    let out = repo.f(&["add", &id]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let new_id = repo.id("untracked", "new.txt");
+    let out = repo.f(&[&new_id, "a"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        listed(&repo),
+        [pair("staged", "README.md"), pair("staged", "new.txt")]
+    );
+
+    let out = repo.f(&["unstage", &id]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        listed(&repo),
+        [pair("staged", "new.txt"), pair("unstaged", "README.md")]
+    );
+}
+
+#[test]
+fn discard_refuses_changes_that_are_only_staged() {
+    let repo = Repo::new();
+    repo.write("README.md", "staged\n");
+    repo.git(&["add", "README.md"]);
+
+    let out = repo.f_with_input(&["discard", "-"], "README.md\n");
+    assert_eq!(out.status.code(), Some(2), "{}", stderr(&out));
+    assert!(
+        stderr(&out).contains("unstage it first"),
+        "{}",
+        stderr(&out)
+    );
+    assert_eq!(listed(&repo), [pair("staged", "README.md")]);
+
+    // Once the work tree differs too, that's what goes
+    repo.write("README.md", "staged and more\n");
+    let out = repo.f_with_input(&["discard", "-"], "README.md\n");
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(repo.read("README.md"), "staged\n");
+    assert_eq!(listed(&repo), [pair("staged", "README.md")]);
+}
+
+#[test]
+fn diff_shows_tracked_and_untracked_changes() {
+    let repo = Repo::new();
+    repo.write("README.md", "# fixture\nmore\n");
+    repo.write("new.txt", "brand new\n");
+
+    let out = repo.f(&["diff", &repo.id("unstaged", "README.md")]);
+    assert!(stdout(&out).contains("+more"), "{}", stdout(&out));
+    let out = repo.f(&["diff", &repo.id("untracked", "new.txt")]);
+    assert!(stdout(&out).contains("+brand new"), "{}", stdout(&out));
+}
+
+#[test]
+fn commit_records_the_staged_changes() {
+    let repo = Repo::new();
+    repo.write("README.md", "changed\n");
+    repo.f(&["add", &repo.id("unstaged", "README.md")]);
+
+    let out = repo.f(&["c", "Update", "the", "readme"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        repo.git(&["log", "-1", "--format=%s"]),
+        "Update the readme\n"
+    );
+    assert!(repo.list().is_empty());
+    assert_eq!(repo.read("README.md"), "changed\n");
+}
+
+#[test]
+fn conflicted_files_are_listed_and_block_partial_commits() {
+    let repo = Repo::new();
+    repo.conflict("c.txt");
+
+    assert_eq!(
+        listed(&repo),
+        [pair("staged", "c.txt"), pair("unstaged", "c.txt")]
+    );
+    let human = stdout(&repo.f(&[]));
+    assert!(human.contains("Merge in progress"), "{}", human);
+
+    let id = repo.id("unstaged", "c.txt");
+    let out = repo.f(&["c", "-m", "Resolve", &id]);
+    assert_eq!(out.status.code(), Some(2), "{}", stderr(&out));
+    assert!(stderr(&out).contains("during a merge"), "{}", stderr(&out));
+}
+
+#[test]
+fn chmod_sets_the_executable_bit_in_index_and_work_tree() {
+    let repo = Repo::new();
+    repo.write("build.sh", "#!/bin/sh\n");
+    repo.commit_all("Add script");
+
+    // Unchanged files are named by path
+    let out = repo.f(&["chmod", "build.sh", "+x"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        repo.git(&["ls-files", "-s", "build.sh"])
+            .starts_with("100755")
+    );
+    assert_eq!(listed(&repo), [pair("staged", "build.sh")]);
+    assert!(stdout(&repo.f(&[])).contains("mode +x"));
+
+    let out = repo.f(&["chmod", &repo.id("staged", "build.sh"), "-x"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(repo.list().is_empty(), "{:?}", repo.list());
+}
+
+#[cfg(unix)]
+#[test]
+fn undo_reverses_a_chmod_in_index_and_work_tree() {
+    use std::os::unix::fs::PermissionsExt;
+    let repo = Repo::new();
+    repo.write("build.sh", "#!/bin/sh\n");
+    repo.commit_all("Add script");
+    repo.write("a.txt", "a\n");
+    let mode = || {
+        std::fs::metadata(repo.path().join("build.sh"))
+            .unwrap()
+            .permissions()
+            .mode()
+            & 0o111
+    };
+
+    repo.f(&["add", "a.txt"]);
+    let out = repo.f(&["chmod", "build.sh", "+x"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_ne!(mode(), 0);
+
+    let out = repo.f(&["undo"]);
+    assert_eq!(stdout(&out), "Undid: chmod build.sh\n", "{}", stderr(&out));
+    assert_eq!(mode(), 0);
+    assert_eq!(listed(&repo), [pair("staged", "a.txt")]);
+    // The chmod was the journal's newest entry, not the end of it
+    let out = repo.f(&["undo"]);
+    assert_eq!(stdout(&out), "Undid: add a.txt\n", "{}", stderr(&out));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_show_their_target_and_are_not_edited() {
+    let repo = Repo::new();
+    repo.write("config.toml", "a = 1\n");
+    repo.commit_all("Add config");
+    repo.remove("config.toml");
+    std::os::unix::fs::symlink("README.md", repo.path().join("config.toml")).unwrap();
+    std::os::unix::fs::symlink("..", repo.path().join("parent")).unwrap();
+
+    let human = stdout(&repo.f(&[]));
+    assert!(
+        human.contains("config.toml +1/-1 file → symlink"),
+        "{}",
+        human
+    );
+    assert!(human.contains("→ README.md"), "{}", human);
+
+    // A link to a directory, which git diff --no-index can't show
+    let out = repo.f(&["diff", &repo.id("untracked", "parent")]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        stdout(&out).contains("new file mode 120000\n"),
+        "{}",
+        stdout(&out)
+    );
+    assert!(stdout(&out).contains("\n+..\n"), "{}", stdout(&out));
+
+    let out = repo.f(&["edit", &repo.id("unstaged", "config.toml")]);
+    assert_eq!(out.status.code(), Some(2), "{}", stderr(&out));
+    assert!(stderr(&out).contains("is a symlink to README.md"));
+}
+
+#[test]
+fn staged_deletions_show_their_status_and_removal_diff() {
+    let repo = Repo::new();
+    repo.git(&["rm", "-q", "README.md"]);
+    repo.write("new.txt", "n\n");
+    repo.git(&["add", "new.txt"]);
+
+    let human = stdout(&repo.f(&[]));
+    assert!(human.contains(" D README.md"), "{}", human);
+    assert!(human.contains(" A new.txt"), "{}", human);
+
+    let out = repo.f(&["sd", &repo.id("staged", "README.md")]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        stdout(&out).contains("deleted file mode"),
+        "{}",
+        stdout(&out)
+    );
+    assert!(stdout(&out).contains("-# fixture"), "{}", stdout(&out));
+}
+
+#[test]
+fn push_shows_outgoing_commits_and_asks_when_configured() {
+    let repo = Repo::new();
+    let remote = repo.path().join("../remote.git");
+    repo.git(&["init", "-q", "--bare", &remote.to_string_lossy()]);
+    repo.git(&["remote", "add", "origin", &remote.to_string_lossy()]);
+    repo.git(&["push", "-q", "-u", "origin", "main"]);
+    repo.write("README.md", "# fixture\nmore\n");
+    repo.commit_all("Extend the readme");
+
+    // Scripts that push aren't asked anything
+    let out = repo.f(&["push"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        stderr(&out).contains("1 commit ahead of origin/main"),
+        "{}",
+        stderr(&out)
+    );
+    assert_eq!(
+        repo.git(&["rev-list", "--count", "origin/main..main"]),
+        "0\n"
+    );
+
+    repo.write_config("[push]\nconfirm = true\n");
+    repo.write("README.md", "# fixture\nmore\nagain\n");
+    repo.commit_all("Extend the readme again");
+    // With no terminal to answer on, the question counts as a no
+    let out = repo.f(&["push"]);
+    assert_eq!(out.status.code(), Some(1), "{}", stderr(&out));
+    assert!(
+        stderr(&out).contains("1 commit ahead of origin/main (+1/−0)"),
+        "{}",
+        stderr(&out)
+    );
+    assert_eq!(
+        repo.git(&["rev-list", "--count", "origin/main..main"]),
+        "1\n"
+    );
+
+    let out = repo.f(&["push", "-y"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        repo.git(&["rev-list", "--count", "origin/main..main"]),
+        "0\n"
+    );
+}
+
+#[test]
+fn commits_lists_ids_that_show_or_diff() {
+    let repo = Repo::new();
+    repo.write("README.md", "# fixture\nsecond\n");
+    repo.commit_all("Second");
+    repo.write("README.md", "# fixture\nsecond\nthird\n");
+    repo.commit_all("Third");
+
+    let listing = stdout(&repo.f(&["commits"]));
+    let id = |subject: &str| {
+        listing
+            .lines()
+            .find(|line| line.contains(subject))
+            .and_then(|line| line.split_whitespace().next())
+            .unwrap_or_else(|| panic!("{} not in:\n{}", subject, listing))
+            .to_string()
+    };
+
+    let out = repo.f(&["commits", &id("Second")]);
+    assert!(stdout(&out).contains("+second"), "{}", stdout(&out));
+    assert!(!stdout(&out).contains("+third"), "{}", stdout(&out));
+
+    // Either order diffs from the older commit to the newer
+    let out = repo.f(&["commits", &id("Third"), &id("Initial commit")]);
+    let diff = stdout(&out);
+    assert!(diff.contains("+second\n+third"), "{}", diff);
+}
+
+#[test]
+fn diff_range_shows_one_files_history() {
+    let repo = Repo::new();
+    repo.write("other.txt", "o\n");
+    repo.commit_all("Add other");
+    repo.git(&["tag", "base"]);
+    repo.write("README.md", "# fixture\ncommitted\n");
+    repo.write("other.txt", "o\nchanged\n");
+    repo.commit_all("Change both");
+    repo.write("README.md", "# fixture\ncommitted\nuncommitted\n");
+
+    // An unchanged file, by path, between two refs
+    let out = repo.f(&["diff", "other.txt", "--range", "base..HEAD"]);
+    assert!(stdout(&out).contains("+changed"), "{}", stderr(&out));
+    assert!(!stdout(&out).contains("README"), "{}", stdout(&out));
+
+    // Without the second ref, up to the work tree
+    let id = repo.id("unstaged", "README.md");
+    let diff = stdout(&repo.f(&["diff", &id, "--range", "base"]));
+    assert!(diff.contains("+committed\n+uncommitted"), "{}", diff);
+}
+
+#[test]
+fn pr_needs_github_or_gitlab() {
+    let repo = Repo::new();
+    let out = repo.f(&["pr", "status"]);
+    assert_eq!(out.status.code(), Some(4), "{}", stderr(&out));
+    assert!(stderr(&out).contains("No remote named 'origin'"));
+
+    repo.git(&["remote", "add", "origin", "git@bitbucket.org:team/repo.git"]);
+    let out = repo.f(&["pr", "checks"]);
+    assert_eq!(out.status.code(), Some(2), "{}", stderr(&out));
+    assert!(stderr(&out).contains("GitHub (gh) and GitLab (glab)"));
+}
+
+#[test]
+fn pr_files_lists_paths_git_would_quote() {
+    use std::os::unix::fs::PermissionsExt;
+    let repo = Repo::new();
+    let bin = repo.path().join("../bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    std::fs::write(
+        bin.join("gh"),
+        "#!/bin/sh\necho '{\"baseRefName\": \"main\"}'\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(bin.join("gh"), PermissionsExt::from_mode(0o755)).unwrap();
+    repo.git(&["remote", "add", "origin", "git@github.com:team/repo.git"]);
+    repo.git(&["update-ref", "refs/remotes/origin/main", "HEAD"]);
+    repo.write("naïve \"notes\".txt", "n\n");
+    repo.commit_all("Add notes");
+
+    let out = repo.bash(&format!("PATH={}:$PATH f pr files", bin.display()));
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        stdout(&out).ends_with("  naïve \"notes\".txt\n"),
+        "{}",
+        stdout(&out)
+    );
+}
+
+#[test]
+fn notes_list_export_and_clear() {
+    let repo = Repo::new();
+    assert!(stdout(&repo.f(&["notes"])).contains("No notes"));
+
+    // As f review saves them
+    std::fs::create_dir_all(repo.path().join(".git/f")).unwrap();
+    repo.write(
+        ".git/f/notes",
+        r#"[{"path":"src/a.rs","line":3,"text":"Rename this"},{"path":"README.md","line":null,"text":"Typo"}]"#,
+    );
+    let out = stdout(&repo.f(&["notes"]));
+    assert!(out.contains("src/a.rs:3  Rename this"), "{}", out);
+    let out = stdout(&repo.f(&["notes", "export"]));
+    assert!(
+        out.contains("## `src/a.rs`\n\n- Line 3: Rename this\n"),
+        "{}",
+        out
+    );
+    let out = stdout(&repo.f(&["notes", "export", "--gh"]));
+    assert!(
+        out.starts_with("{\"event\":\"COMMENT\",\"body\":\"`README.md`: Typo\""),
+        "{}",
+        out
+    );
+
+    assert!(stdout(&repo.f(&["notes", "clear"])).contains("Deleted 2 notes"));
+    assert!(stdout(&repo.f(&["notes"])).contains("No notes"));
+}
+
+#[test]
+fn check_runs_tools_for_changed_files_only() {
+    let repo = Repo::new();
+    repo.write_config("[[tools]]\nglob = \"*.txt\"\nlint = \"! grep -q TODO {file}\"\n");
+    repo.write("clean.txt", "done\n");
+    repo.write("messy.txt", "TODO\n");
+    repo.write("README.md", "# fixture\nchanged\n");
+
+    let out = repo.f(&["check"]);
+    assert_eq!(out.status.code(), Some(1), "{}", stderr(&out));
+    let report = stdout(&out);
+    let row = |path: &str| {
+        report
+            .lines()
+            .find(|l| l.contains(path))
+            .unwrap_or_else(|| panic!("{} not in {}", path, report))
+            .to_string()
+    };
+    assert!(row("clean.txt").ends_with("✓ lint"), "{}", report);
+    assert!(row("messy.txt").ends_with("✗ lint"), "{}", report);
+    assert!(
+        row("README.md").ends_with("nothing configured"),
+        "{}",
+        report
+    );
+    assert!(report.contains("── ✗ ! grep -q TODO"), "{}", report);
+
+    repo.write("messy.txt", "fixed\n");
+    assert!(repo.f(&["check"]).status.success());
+}
+
+#[test]
+fn timeout_fails_a_hung_check_and_non_interactive_refuses_the_picker() {
+    let repo = Repo::new();
+    repo.write_config("[[tools]]\nglob = \"*.txt\"\nlint = \"sleep 30\"\n");
+    repo.write("slow.txt", "x\n");
+
+    let started = std::time::Instant::now();
+    let out = repo.f(&["--timeout", "1", "check"]);
+    assert!(started.elapsed() < std::time::Duration::from_secs(20));
+    assert_eq!(out.status.code(), Some(1), "{}", stderr(&out));
+    let report = stdout(&out);
+    assert!(report.contains("✗ lint"), "{}", report);
+    assert!(report.contains("sleep 30 timed out after 1s"), "{}", report);
+
+    let out = repo.f(&["--non-interactive", "i"]);
+    assert_eq!(out.status.code(), Some(2));
+    assert!(
+        stderr(&out).contains("non-interactively"),
+        "{}",
+        stderr(&out)
+    );
+}
+
+#[test]
+fn shell_init_wrapper_changes_directory_and_exports_the_last_file() {
+    let repo = Repo::new();
+    repo.write("src/deep/lib.rs", "fn main() {}\n");
+    let id = repo.id("untracked", "src/deep/lib.rs");
+    let root = repo.path().canonicalize().unwrap();
+
+    let out = repo.bash(&format!(
+        "eval \"$(f shell-init bash)\"\nf cd {id} && pwd && echo \"$F_LAST\""
+    ));
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        stdout(&out),
+        format!(
+            "{}\n{}\n",
+            root.join("src/deep").display(),
+            root.join("src/deep/lib.rs").display()
+        )
+    );
+
+    // Without the wrapper it prints the directory for cd "$(f cd ...)"
+    let out = repo.f(&["cd", &id]);
+    assert_eq!(
+        stdout(&out),
+        format!("{}\n", root.join("src/deep").display())
+    );
+    assert!(stderr(&out).contains("shell-init"), "{}", stderr(&out));
+}
+
+#[test]
+fn sessions_replay_from_the_root_wherever_f_runs() {
+    let repo = Repo::new();
+    repo.write("version.txt", "1\n");
+    repo.write("src/version.txt", "1\n");
+    repo.commit_all("Add versions");
+
+    repo.f(&["session", "record", "bump"]);
+    repo.write("version.txt", "2\n");
+    let out = repo.f(&["add", "version.txt"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    repo.f(&["session", "stop"]);
+    let script = stdout(&repo.f(&["session", "show", "bump"]));
+    assert!(script.contains("add version.txt"), "{}", script);
+
+    // From src, version.txt would be src/version.txt
+    repo.git(&["reset", "-q"]);
+    repo.write("src/version.txt", "2\n");
+    let out = repo.f_in(&repo.path().join("src"), &["session", "replay", "bump"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        listed(&repo),
+        [
+            pair("staged", "version.txt"),
+            pair("unstaged", "src/version.txt")
+        ]
+    );
+}
+
+#[test]
+fn dash_repeats_the_previous_file_and_history_lists_recent_ones() {
+    let repo = Repo::new();
+    let out = repo.f(&["-", "a"]);
+    assert_eq!(out.status.code(), Some(4));
+    assert!(
+        stderr(&out).contains("No previous file"),
+        "{}",
+        stderr(&out)
+    );
+
+    repo.write("README.md", "# fixture\nchanged\n");
+    repo.write("notes.txt", "new\n");
+    let id = repo.id("unstaged", "README.md");
+    assert!(
+        repo.f(&["path", &repo.id("untracked", "notes.txt")])
+            .status
+            .success()
+    );
+    assert!(repo.f(&[&id, "d"]).status.success());
+
+    let out = repo.f(&["-", "a"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        repo.git(&["diff", "--cached", "--name-only"]),
+        "README.md\n"
+    );
+
+    let staged = repo.id("staged", "README.md");
+    let notes = repo.id("untracked", "notes.txt");
+    let out = stdout(&repo.f(&["history"]));
+    let lines: Vec<&str> = out.lines().map(str::trim).collect();
+    assert_eq!(
+        lines,
+        [
+            "── History ──".to_string(),
+            format!("{} README.md staged", staged),
+            format!("{} notes.txt untracked", notes)
+        ]
+    );
+}
+
+#[test]
+fn plugins_get_the_file_their_argument_names_and_nothing_else() {
+    use std::os::unix::fs::PermissionsExt;
+    let repo = Repo::new();
+    repo.write("README.md", "# fixture\nchanged\n");
+    let plugins = repo.path().join("../plugins");
+    std::fs::create_dir_all(&plugins).unwrap();
+    // Named in ID characters, so it has to be told apart from an ID
+    let plugin = plugins.join("f-hall");
+    std::fs::write(&plugin, "#!/bin/sh\necho \"$1 file=$F_FILE\"\n").unwrap();
+    std::fs::set_permissions(&plugin, PermissionsExt::from_mode(0o755)).unwrap();
+    let history = repo.path().join(".git/f/history");
+    let run = |arg: &str| repo.bash(&format!("PATH={}:$PATH f hall {}", plugins.display(), arg));
+
+    let out = run("notes");
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(stdout(&out), "notes file=\n");
+    assert!(!history.exists());
+
+    let out = run("README.md");
+    assert_eq!(
+        stdout(&out),
+        format!(
+            "README.md file={}\n",
+            repo.path()
+                .canonicalize()
+                .unwrap()
+                .join("README.md")
+                .display()
+        )
+    );
+    assert_eq!(std::fs::read_to_string(&history).unwrap(), "README.md\n");
+}
+
+#[test]
+fn default_target_picks_the_file_actions_take_without_an_id() {
+    let repo = Repo::new();
+    repo.write("small.txt", "one\n");
+    repo.write("big.txt", "one\ntwo\nthree\n");
+    let root = repo.path().canonicalize().unwrap();
+    let default = |repo: &Repo| stdout(&repo.f(&["path"]));
+    let first = default(&repo);
+
+    repo.write_config("default_target = \"largest-diff\"\n");
+    assert_eq!(
+        default(&repo),
+        format!("{}\n", root.join("big.txt").display())
+    );
+
+    repo.write_config("default_target = \"last-used\"\n");
+    let other = if first.contains("small.txt") {
+        "big.txt"
+    } else {
+        "small.txt"
+    };
+    repo.f(&["path", other]);
+    assert_eq!(default(&repo), format!("{}\n", root.join(other).display()));
+
+    repo.write_config("default_target = \"prompt\"\n");
+    let out = repo.f(&["path"]);
+    assert_eq!(out.status.code(), Some(2));
+    assert!(stderr(&out).contains("give an ID"), "{}", stderr(&out));
+}
+
+#[test]
+fn list_ignored_and_unignore() {
+    let repo = Repo::new();
+    repo.write(".gitignore", "/build/\n*.log\n");
+    repo.commit_all("Ignore build output");
+    repo.write("build/out.o", "");
+    repo.write("debug.log", "");
+
+    let out = stdout(&repo.f(&["list", "--porcelain", "--ignored"]));
+    let ignored: Vec<(&str, &str)> = out
+        .lines()
+        .filter_map(|l| {
+            let mut fields = l.split('\t');
+            let id = fields.next()?;
+            (fields.next()? == "ignored").then_some((id, fields.next()?))
+        })
+        .collect();
+    assert_eq!(
+        ignored.iter().map(|(_, p)| *p).collect::<Vec<_>>(),
+        ["build/", "debug.log"],
+        "{}",
+        out
+    );
+    assert!(stdout(&repo.f(&["list", "--ignored"])).contains("── Ignored ──"));
+
+    // A rule for just that directory goes, a wider one is overridden
+    let out = repo.f(&["unignore", ignored[0].0]);
+    assert!(
+        stdout(&out).contains("Removed /build/ from .gitignore"),
+        "{}",
+        stderr(&out)
+    );
+    let out = repo.f(&["unignore", "debug.log"]);
+    assert!(
+        stdout(&out).contains("Added !/debug.log to .gitignore"),
+        "{}",
+        stderr(&out)
+    );
+    assert_eq!(repo.read(".gitignore"), "*.log\n!/debug.log\n");
+    // Listed now, as an untracked file
+    repo.id("untracked", "debug.log");
+
+    let out = repo.f(&["unignore", "README.md"]);
+    assert_eq!(out.status.code(), Some(4), "{}", stderr(&out));
+}
+
+#[test]
+fn focus_narrows_the_list_until_cleared() {
+    let repo = Repo::new();
+    repo.write("crates/f/src/a.rs", "a\n");
+    repo.write("crates/g/b.rs", "b\n");
+    repo.write("README.md", "# fixture\nchanged\n");
+
+    let out = repo.f_in(&repo.path().join("crates"), &["focus", "f/"]);
+    assert!(
+        stdout(&out).contains("Focused on crates/f/**"),
+        "{}",
+        stderr(&out)
+    );
+    let listed = stdout(&repo.f(&[]));
+    assert!(listed.contains("crates/f/src/a.rs"), "{}", listed);
+    assert!(
+        !listed.contains("b.rs") && !listed.contains("README"),
+        "{}",
+        listed
+    );
+    assert!(listed.contains("2 more outside the focus"), "{}", listed);
+    assert_eq!(repo.list().len(), 1);
+    assert!(stdout(&repo.f(&["list", "--no-focus"])).contains("crates/g/b.rs"));
+
+    assert!(stdout(&repo.f(&["focus"])).contains("Focused on crates/f/**"));
+    repo.f(&["focus", "--clear"]);
+    assert_eq!(repo.list().len(), 3);
+}
+
+#[test]
+fn codeowners_annotate_and_mine_filters() {
+    let repo = Repo::new();
+    repo.write(".github/CODEOWNERS", "* @acme/core\n/docs/ @alice\n");
+    repo.commit_all("Add owners");
+    repo.write("docs/guide.md", "guide\n");
+    repo.write("src/lib.rs", "lib\n");
+
+    let out = repo.f(&["list", "--mine"]);
+    assert_eq!(out.status.code(), Some(6), "{}", stderr(&out));
+
+    let listed = stdout(&repo.f(&[]));
+    assert!(listed.contains("docs/guide.md +1/-0 @alice"), "{}", listed);
+    assert!(listed.contains("src/lib.rs +1/-0 @acme/core"), "{}", listed);
+
+    repo.write_config("[owners]\nme = [\"@alice\"]\n");
+    let mine = stdout(&repo.f(&["list", "--mine"]));
+    assert!(mine.contains("docs/guide.md"), "{}", mine);
+    assert!(!mine.contains("lib.rs"), "{}", mine);
+}
+
+#[test]
+fn commit_by_dir_makes_one_commit_per_directory() {
+    let repo = Repo::new();
+    repo.write("docs/old.md", "old\n");
+    repo.commit_all("Add docs");
+    repo.write("src/a.rs", "staged\n");
+    repo.write("docs/guide.md", "guide\n");
+    repo.git(&["add", "-A"]);
+    repo.git(&["mv", "docs/old.md", "src/old.md"]);
+    repo.write("README.md", "# fixture\nnot staged\n");
+    repo.write("src/a.rs", "staged\nthen edited\n");
+
+    let out = repo.f(&["commit", "--by-dir", "Tidy {group}"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        repo.git(&["log", "--format=%s", "-2"]),
+        "Tidy src/\nTidy docs/\n"
+    );
+    assert_eq!(
+        repo.git(&["show", "--name-status", "--format=", "HEAD"]),
+        "A\tsrc/a.rs\nA\tsrc/old.md\n"
+    );
+    assert_eq!(repo.git(&["show", "HEAD:src/a.rs"]), "staged\n");
+    // What wasn't staged is left as it was
+    assert_eq!(
+        listed(&repo),
+        [
+            ("unstaged".to_string(), "README.md".to_string()),
+            ("unstaged".to_string(), "src/a.rs".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn rebase_interactive_needs_commits_and_a_terminal() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a\n");
+    repo.commit_all("Add a");
+
+    let out = repo.f(&["rebase", "-i", "HEAD"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(stdout(&out).contains("Nothing to rebase"));
+    let out = repo.f(&["rebase", "-i", "HEAD~1"]);
+    assert_eq!(out.status.code(), Some(2), "{}", stderr(&out));
+    assert!(stderr(&out).contains("needs a terminal"));
+}
+
+#[test]
+fn tag_new_list_and_push() {
+    let repo = Repo::new();
+    assert!(stdout(&repo.f(&["tag"])).contains("No tags"));
+
+    let out = repo.f(&["tag", "new", "v1.0", "-m", "First release"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    repo.f(&["tag", "new", "scratch", "--lightweight"]);
+    assert_eq!(
+        repo.git(&["cat-file", "-t", "v1.0"]),
+        "tag\n",
+        "annotated by default"
+    );
+    let listed = stdout(&repo.f(&["tag"]));
+    assert!(
+        listed.contains("v1.0") && listed.contains("First release"),
+        "{}",
+        listed
+    );
+    assert!(listed.contains("lightweight"), "{}", listed);
+
+    let remote = repo.path().join("../remote.git");
+    repo.git(&["init", "-q", "--bare", remote.to_str().unwrap()]);
+    repo.git(&["remote", "add", "origin", remote.to_str().unwrap()]);
+    let out = repo.f(&["tag", "push", "v1.0"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        repo.git(&["ls-remote", "--tags", "origin"])
+            .contains("refs/tags/v1.0")
+    );
+    assert_eq!(repo.f(&["tag", "push", "v9"]).status.code(), Some(4));
+}
+
+#[test]
+fn remote_lists_urls_and_fetch_status() {
+    let repo = Repo::new();
+    assert!(stdout(&repo.f(&["remote"])).contains("No remotes"));
+
+    let remote = repo.path().join("../remote.git");
+    let url = remote.to_str().unwrap();
+    repo.git(&["init", "-q", "--bare", url]);
+    let out = repo.f(&["remote", "add", "upstream", url]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(stdout(&repo.f(&["remote"])).contains("never fetched"));
+
+    repo.git(&["push", "-q", "upstream", "HEAD:refs/heads/main"]);
+    repo.git(&["fetch", "-q", "upstream"]);
+    let listed = stdout(&repo.f(&["remote"]));
+    assert!(listed.contains(&format!("upstream  {}", url)), "{}", listed);
+    assert!(listed.contains("1 branch, fetched just now"), "{}", listed);
+
+    repo.f(&["remote", "rename", "upstream", "origin"]);
+    assert!(stdout(&repo.f(&["remote"])).starts_with("origin"));
+    assert!(repo.f(&["remote", "prune"]).status.success());
+}
+
+#[test]
+fn archive_packs_changed_or_staged_files() {
+    let repo = Repo::new();
+    repo.write("src/a.rs", "committed\n");
+    repo.write("gone.txt", "g\n");
+    repo.commit_all("Add files");
+    repo.write("src/a.rs", "staged\n");
+    repo.git(&["add", "src/a.rs"]);
+    repo.write("src/a.rs", "edited\n");
+    repo.write("new.txt", "new\n");
+    repo.remove("gone.txt");
+
+    let tar = |args: &[&str]| {
+        let out = std::process::Command::new("tar")
+            .args(args)
+            .current_dir(repo.path())
+            .output()
+            .unwrap();
+        stdout(&out)
+    };
+    let sub = repo.path().join("src");
+    let out = repo.f_in(&sub, &["archive", "../wip.tar"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(stdout(&out).contains("Packed 2 files into ../wip.tar"));
+    assert!(stdout(&out).contains("left out: gone.txt"));
+    assert_eq!(tar(&["-tf", "wip.tar"]), "new.txt\nsrc/\nsrc/a.rs\n");
+    assert_eq!(tar(&["-xOf", "wip.tar", "src/a.rs"]), "edited\n");
+    // The real index is untouched
+    assert_eq!(repo.git(&["show", ":src/a.rs"]), "staged\n");
+
+    repo.f(&["archive", "--staged", "staged.tar"]);
+    assert_eq!(tar(&["-xOf", "staged.tar", "src/a.rs"]), "staged\n");
+    assert_eq!(repo.f(&["archive", "wip.rar"]).status.code(), Some(2));
+}
+
+#[test]
+fn paste_apply_previews_then_applies_or_lists_conflicts() {
+    let repo = Repo::new();
+    repo.write("a.txt", "one\ntwo\n");
+    repo.commit_all("Add a");
+    repo.write("a.txt", "one\n2\n");
+    repo.write("b.txt", "new\n");
+    repo.git(&["add", "-N", "b.txt"]);
+    let diff = repo.git(&["diff"]);
+    repo.git(&["checkout", "--", "a.txt"]);
+    repo.remove("b.txt");
+    repo.git(&["reset", "-q"]);
+
+    let out = repo.f_with_input(&["paste-apply", "--dry-run"], &diff);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(stdout(&out).contains("a.txt +1/-1"), "{}", stdout(&out));
+    assert_eq!(repo.read("a.txt"), "one\ntwo\n");
+
+    let out = repo.f_with_input(&["paste-apply"], &diff);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        (repo.read("a.txt"), repo.read("b.txt")),
+        ("one\n2\n".into(), "new\n".into())
+    );
+    assert!(
+        stdout(&out).contains("Applied and staged"),
+        "{}",
+        stdout(&out)
+    );
+    assert_eq!(
+        listed(&repo),
+        [pair("staged", "a.txt"), pair("staged", "b.txt")]
+    );
+
+    // Applied again over a conflicting change, the three-way merge stops
+    repo.commit_all("Take the diff");
+    repo.git(&["checkout", "-q", "HEAD~1", "--", "a.txt"]);
+    repo.write("a.txt", "one\nzwei\n");
+    repo.commit_all("Diverge");
+    let out = repo.f_with_input(&["paste-apply"], &diff.replace("b.txt", "c.txt"));
+    assert!(!out.status.success());
+    assert!(stdout(&out).contains("Conflicts:"), "{}", stdout(&out));
+    assert_eq!(
+        repo.f_with_input(&["paste-apply"], "").status.code(),
+        Some(2)
+    );
+}
+
+#[test]
+fn conflicts_resolve_by_side_or_open_at_the_first_marker() {
+    let repo = Repo::new();
+    repo.conflict("c.txt");
+    let id = repo.id("unstaged", "c.txt");
+
+    let out = repo.f(&["--print", &id, "both"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(stdout(&out).contains("sh +1 "), "{}", stdout(&out));
+
+    let out = repo.f(&[&id, "theirs"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(repo.read("c.txt"), "side\n");
+    assert_eq!(listed(&repo), [pair("staged", "c.txt")]);
+
+    let out = repo.f(&[&repo.id("staged", "c.txt"), "ours"]);
+    assert_eq!(out.status.code(), Some(2), "{}", stderr(&out));
+}
+
+#[test]
+fn undo_brings_back_a_conflict_resolved_by_side() {
+    let repo = Repo::new();
+    repo.conflict("c.txt");
+    let conflicted = repo.read("c.txt");
+
+    let out = repo.f(&[&repo.id("unstaged", "c.txt"), "theirs"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(repo.read("c.txt"), "side\n");
+
+    let out = repo.f(&["undo"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(stdout(&out), "Undid: theirs c.txt\n");
+    assert_eq!(repo.read("c.txt"), conflicted);
+    let stages = repo.git(&["ls-files", "-s", "--", "c.txt"]);
+    let stages: Vec<_> = stages
+        .lines()
+        .map(|l| l.split_whitespace().nth(2))
+        .collect();
+    assert_eq!(stages, [Some("1"), Some("2"), Some("3")]);
+
+    // Still a conflict, so it can be resolved the other way
+    let out = repo.f(&[&repo.id("unstaged", "c.txt"), "ours"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(repo.read("c.txt"), "main\n");
+}
+
+#[test]
+fn conflict_shows_the_base_and_both_sides() {
+    let repo = Repo::new();
+    repo.conflict("c.txt");
+    // Partly resolved in the work tree, the view still comes from the index
+    repo.write("c.txt", "main\n");
+
+    let out = repo.f(&["conflict"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        stdout(&out),
+        "c.txt (1 conflict)\n\n<<<<<<< ours\nmain\n||||||| base\nbase\n=======\nside\n>>>>>>> theirs\n"
+    );
+
+    let out = repo.f(&["conflict", &repo.id("unstaged", "c.txt"), "--panes"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let row = stdout(&out).lines().nth(5).unwrap_or_default().to_string();
+    assert!(
+        row.starts_with(" base ") && row.contains(" │ main ") && row.contains(" │ side"),
+        "{}",
+        stdout(&out)
+    );
+}
+
+#[test]
+fn first_changed_line_goes_to_locations_and_the_editor() {
+    let repo = Repo::new();
+    repo.write("a.txt", "one\ntwo\nthree\nfour\n");
+    repo.commit_all("Add a");
+    repo.write("a.txt", "one\ntwo\n3\nfour\n");
+
+    // Only worked out for what's opened, not for the list
+    assert!(stdout(&repo.f(&[])).contains("a.txt +1/-1"));
+    let quickfix = stdout(&repo.f(&["list", "--format", "vim-quickfix"]));
+    assert!(quickfix.starts_with("a.txt:3: "), "{}", quickfix);
+
+    repo.write_config("editor = \"code -g {file}:{line}\"\n");
+    let out = repo.f(&["--print", &repo.id("unstaged", "a.txt"), "e"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        stdout(&out).contains("code -g \"$f\":3"),
+        "{}",
+        stdout(&out)
+    );
+
+    repo.git(&["add", "a.txt"]);
+    let out = repo.f(&["--print", &repo.id("staged", "a.txt"), "e"]);
+    assert!(
+        stdout(&out).contains("code -g \"$f\":3"),
+        "{}",
+        stdout(&out)
+    );
+}
+
+#[test]
+fn split_diff_puts_old_and_new_side_by_side() {
+    let repo = Repo::new();
+    repo.write("a.txt", "one\ntwo\nthree\n");
+    repo.commit_all("Add a");
+    repo.write("a.txt", "one\n2\nthree\nfour\n");
+
+    let out = repo.f(&["diff", &repo.id("unstaged", "a.txt"), "--split"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let text = stdout(&out);
+    let rows: Vec<Vec<&str>> = text
+        .lines()
+        .skip(2)
+        .map(|l| l.split(" │ ").map(str::trim).collect())
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            vec!["1 one", "1 one"],
+            vec!["2 two", "2 2"],
+            vec!["3 three", "3 three"],
+            vec!["", "4 four"],
+        ],
+        "{}",
+        text
+    );
+    assert_eq!(
+        repo.f(&["diff", "--split", "--range", "HEAD"])
+            .status
+            .code(),
+        Some(2)
+    );
+}
+
+#[test]
+fn image_diff_describes_both_versions_or_runs_the_tool() {
+    let repo = Repo::new();
+    std::fs::write(repo.path().join("logo.png"), png(1, 1)).unwrap();
+    repo.commit_all("Add logo");
+    std::fs::write(repo.path().join("logo.png"), png(2, 2)).unwrap();
+
+    let id = repo.id("unstaged", "logo.png");
+    let out = repo.f(&["d", &id]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        stdout(&out).contains("logo.png PNG 1×1, 29 B → PNG 2×2, 29 B (+0 B)"),
+        "{}",
+        stdout(&out)
+    );
+
+    repo.write_config("[images]\ndiff_tool = \"compare {old} {new}\"\n");
+    let out = repo.f(&["--print", "d", &id]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        stdout(&out).contains("image-diff/old.png") && stdout(&out).contains("image-diff/new.png"),
+        "{}",
+        stdout(&out)
+    );
+
+    // The copies the tool compared go once it's done
+    repo.write_config("[images]\ndiff_tool = \"cmp -s {old} {new} || echo differ\"\n");
+    let out = repo.f(&["d", &id]);
+    assert_eq!(stdout(&out), "differ\n", "{}", stderr(&out));
+    let scratch = repo.path().join(".git/f/image-diff");
+    assert_eq!(std::fs::read_dir(&scratch).unwrap().count(), 0);
+
+    // Staged, it's HEAD against the index
+    repo.write_config("");
+    repo.git(&["add", "logo.png"]);
+    std::fs::write(repo.path().join("logo.png"), png(3, 3)).unwrap();
+    let out = repo.f(&["sd", &repo.id("staged", "logo.png")]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        stdout(&out).contains("logo.png PNG 1×1, 29 B → PNG 2×2, 29 B (+0 B)"),
+        "{}",
+        stdout(&out)
+    );
+}
+
+#[test]
+fn summary_outlines_hunks_and_expands_those_asked_for() {
+    let repo = Repo::new();
+    let lines: Vec<String> = (1..=40).map(|n| format!("  {}", n)).collect();
+    repo.write("a.txt", &(lines.join("\n") + "\n"));
+    repo.commit_all("Add a");
+    let mut changed = lines.clone();
+    changed[1] = "  two".to_string();
+    changed[29] = "  thirty".to_string();
+    repo.write("a.txt", &(changed.join("\n") + "\n"));
+    let id = repo.id("unstaged", "a.txt");
+
+    let out = repo.f(&["d", &id, "--summary"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let text = stdout(&out);
+    assert!(text.starts_with("a.txt 2 hunks, +2/-2\n"), "{}", text);
+    assert!(
+        text.contains("1  line 2   +1/-1\n2  line 30  +1/-1"),
+        "{}",
+        text
+    );
+    assert!(!text.contains("+  thirty"), "{}", text);
+
+    let text = stdout(&repo.f(&["d", &id, "--hunk", "2"]));
+    assert!(
+        text.contains("-  30\n+  thirty") && !text.contains("+  two"),
+        "{}",
+        text
+    );
+    assert_eq!(repo.f(&["d", &id, "--hunk", "3"]).status.code(), Some(2));
+
+    repo.write_config("[diff]\nsummary_lines = 10\n");
+    assert!(stdout(&repo.f(&[&id, "d"])).starts_with("a.txt 2 hunks"));
+    assert!(stdout(&repo.f(&["d", &id, "--full"])).contains("+  thirty"));
+}
+
+#[test]
+fn limit_cuts_each_section_short_with_a_count_of_the_rest() {
+    let repo = Repo::new();
+    for name in ["a", "b", "c", "d", "e"] {
+        repo.write(&format!("{}.txt", name), "x\n");
+    }
+
+    let text = stdout(&repo.f(&["l", "--limit", "2"]));
+    assert!(
+        text.contains("b.txt") && !text.contains("c.txt"),
+        "{}",
+        text
+    );
+    assert!(text.contains("  … and 3 more (f l --all)\n"), "{}", text);
+
+    repo.write_config("[list]\nlimit = 4\n");
+    assert!(stdout(&repo.f(&[])).contains("… and 1 more"));
+    let text = stdout(&repo.f(&["l", "--all"]));
+    assert!(text.contains("e.txt") && !text.contains("more"), "{}", text);
+}
+
+#[test]
+fn collapse_rolls_matching_files_into_a_row_per_pattern() {
+    let repo = Repo::new();
+    for name in ["a.js", "b.js", "c/d.js"] {
+        repo.write(&format!("node_modules/{}", name), "x\n");
+    }
+    repo.write("app.js", "x\n");
+    repo.write_config("collapse = [\"node_modules/**\", \"*.lock\"]\n");
+
+    let text = stdout(&repo.f(&[]));
+    assert!(
+        text.contains("app.js") && text.contains("\n        node_modules/** · 3 files\n"),
+        "{}",
+        text
+    );
+    assert!(!text.contains("a.js") && !text.contains("lock"), "{}", text);
+    let text = stdout(&repo.f(&["l", "--all"]));
+    assert!(
+        text.contains("node_modules/c/d.js") && !text.contains("·"),
+        "{}",
+        text
+    );
+}
+
+#[test]
+fn listing_warns_when_the_upstream_is_gone() {
+    let repo = Repo::new();
+    let remote = repo.path().join("../remote.git");
+    repo.git(&["init", "-q", "--bare", &remote.to_string_lossy()]);
+    repo.git(&["remote", "add", "origin", &remote.to_string_lossy()]);
+    repo.git(&["push", "-q", "-u", "origin", "main"]);
+    repo.git(&["switch", "-q", "-c", "feature"]);
+    repo.git(&["push", "-q", "-u", "origin", "feature"]);
+    assert!(!stdout(&repo.f(&[])).contains("gone"));
+
+    repo.git(&["push", "-q", "origin", "--delete", "feature"]);
+    let text = stdout(&repo.f(&[]));
+    assert!(
+        text.starts_with(
+            "Upstream origin/feature is gone · git switch main && git branch -d feature\n\n"
+        ),
+        "{}",
+        text
+    );
+}
+
+#[test]
+fn loose_objects_show_in_doctor_and_the_optional_hint() {
+    let repo = Repo::new();
+    let out = repo.f(&["doctor"]);
+    assert!(
+        stdout(&out).contains("✓ maintenance not scheduled, 3 loose objects"),
+        "{}",
+        stdout(&out)
+    );
+    assert!(!stdout(&repo.f(&[])).contains("loose"));
+
+    repo.git(&["config", "gc.auto", "2"]);
+    let out = repo.f(&["doctor"]);
+    assert!(
+        stdout(&out).contains("more than gc.auto's 2"),
+        "{}",
+        stdout(&out)
+    );
+    repo.write_config("[maintenance]\nhint = true\n");
+    assert!(
+        stdout(&repo.f(&[]))
+            .starts_with("3 loose objects are slowing git down · f maintenance run\n")
+    );
+
+    let out = repo.f(&["--print", "maintenance"]);
+    assert_eq!(stdout(&out), "git maintenance run\n");
+}
+
+#[test]
+fn unknown_theme_warns_and_falls_back_to_the_default() {
+    let repo = Repo::new();
+    repo.write_config("theme = \"light\"\n");
+    let out = repo.f(&[]);
+    assert!(out.status.success());
+    assert_eq!(stderr(&out), "");
+
+    repo.write_config("[theme]\npreset = \"neon\"\nstaged = \"mauve\"\n");
+    let out = repo.f(&[]);
+    assert!(out.status.success());
+    assert!(
+        stderr(&out).contains("Unknown theme 'neon', expected one of default, light"),
+        "{}",
+        stderr(&out)
+    );
+    assert!(stderr(&out).contains("Invalid [theme] staged color 'mauve'"));
+}
+
+#[test]
+fn locale_translates_messages_but_not_machine_output() {
+    let repo = Repo::new();
+    repo.write("notes.txt", "draft\n");
+    repo.write_config("locale = \"es\"\n");
+    let out = stdout(&repo.f(&[]));
+    assert!(out.starts_with("── Sin seguimiento ──\n"), "{}", out);
+    assert!(stdout(&repo.f(&["list", "--porcelain"])).contains("\tuntracked\tnotes.txt"));
+
+    let id = repo.id("untracked", "notes.txt");
+    repo.f(&["add", &id]);
+    repo.git(&["commit", "-qm", "notes"]);
+    assert_eq!(stdout(&repo.f(&[])), "No hay archivos modificados\n");
+}
+
+#[test]
+fn init_hooks_applies_the_commit_rules_to_raw_git_commits() {
+    let repo = Repo::new();
+    repo.write_config("[commit]\nimperative = true\n\n[[tools]]\nglob = \"*.txt\"\nlint = \"grep -q ok {file}\"\nfmt = \"echo formatted > {file}\"\n");
+    let out = repo.f(&["init-hooks"]);
+    assert_eq!(stdout(&out), "✓ pre-commit\n✓ commit-msg\n");
+    assert_eq!(
+        stdout(&repo.f(&["init-hooks"])),
+        "✓ pre-commit (updated)\n✓ commit-msg (updated)\n"
+    );
+
+    repo.write("a.txt", "ok\n");
+    repo.git(&["add", "a.txt"]);
+    let out = repo.git_output(&["commit", "-q", "-m", "Added a"]);
+    assert!(!out.status.success());
+    assert!(
+        stderr(&out).contains("Commit message rejected:"),
+        "{}",
+        stderr(&out)
+    );
+    let out = repo.f(&["commit", "--no-verify-msg", "Added a"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+
+    repo.write("b.txt", "not yet\n");
+    repo.git(&["add", "b.txt"]);
+    let out = repo.git_output(&["commit", "-q", "-m", "Add b"]);
+    assert!(!out.status.success());
+    assert!(stderr(&out).contains("git commit --no-verify commits anyway"));
+    repo.write("b.txt", "ok now\n");
+    repo.git(&["add", "b.txt"]);
+    // What's staged is checked, not the work tree
+    repo.write("b.txt", "bad again\n");
+    repo.git(&["commit", "-q", "-m", "Add b"]);
+    // Formatters don't run in the hook
+    assert_eq!(repo.read("b.txt"), "bad again\n");
+
+    // commit -a commits from an index of its own
+    let out = repo.git_output(&["commit", "-q", "-a", "-m", "Break b"]);
+    assert!(!out.status.success(), "{}", stderr(&out));
+    repo.write("b.txt", "ok at last\n");
+    repo.git(&["add", "b.txt"]);
+    repo.write("b.txt", "bad in the work tree\n");
+    let out = repo.git_output(&["commit", "-q", "-m", "Fix b"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(!repo.path().join(".git/f").read_dir().unwrap().any(|e| {
+        e.unwrap()
+            .file_name()
+            .to_string_lossy()
+            .starts_with("pre-commit")
+    }));
+
+    std::fs::write(repo.path().join(".git/hooks/pre-commit"), "#!/bin/sh\n").unwrap();
+    let out = repo.f(&["init-hooks"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stdout(&out).contains("✗ pre-commit left alone, it isn't f's (--force replaces it)"));
+}
+
+#[test]
+fn git_config_fills_in_for_unset_f_settings_and_config_show_says_which() {
+    let repo = Repo::new();
+    // The row for `name`, with the column padding squeezed out
+    let show = |repo: &Repo, name: &str| {
+        let out = stdout(&repo.f(&["config", "show"]));
+        let line = out
+            .lines()
+            .find(|l| l.starts_with(name))
+            .unwrap_or_default();
+        line.split_whitespace().collect::<Vec<_>>().join(" ")
+    };
+    assert!(show(&repo, "config").ends_with("f.toml (not found)"));
+    assert_eq!(show(&repo, "editor"), "editor vim (default)");
+
+    repo.git(&["config", "core.editor", "nano"]);
+    repo.git(&["config", "merge.tool", "meld"]);
+    assert_eq!(show(&repo, "editor"), "editor nano (git core.editor)");
+    assert_eq!(
+        show(&repo, "mergetool"),
+        "mergetool git mergetool -t meld (git merge.tool)"
+    );
+
+    repo.write_config("editor = \"hx\"\n");
+    assert_eq!(show(&repo, "editor"), "editor hx (f.toml)");
+
+    repo.conflict("c.txt");
+    let id = repo.id("unstaged", "c.txt");
+    let out = repo.f(&["--print", &id, "mergetool"]);
+    assert!(
+        stdout(&out).starts_with("git mergetool --no-prompt -- /"),
+        "{}",
+        stdout(&out)
+    );
+    // Editing by hand stays in the editor
+    let out = repo.f(&["--print", &id, "both"]);
+    assert!(stdout(&out).ends_with("c.txt:1\n"), "{}", stdout(&out));
+}
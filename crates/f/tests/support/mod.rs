@@ -0,0 +1,220 @@
+//! Throwaway git repositories for the CLI tests, and a way to run the `f`
+//! binary inside one with the user's own git and f config kept out.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod png;
+pub use png::png;
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// A git repository in a temporary directory, deleted on drop. It starts
+/// with one commit so HEAD exists.
+pub struct Repo {
+    dir: PathBuf,
+}
+
+impl Repo {
+    pub fn new() -> Repo {
+        let dir = std::env::temp_dir().join(format!(
+            "f-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("work")).unwrap();
+        fs::create_dir_all(dir.join("home")).unwrap();
+        let repo = Repo { dir };
+        repo.git(&["init", "-q", "-b", "main"]);
+        repo.write("README.md", "# fixture\n");
+        repo.commit_all("Initial commit");
+        repo
+    }
+
+    /// The work tree.
+    pub fn path(&self) -> PathBuf {
+        self.dir.join("work")
+    }
+
+    pub fn write(&self, path: &str, content: &str) {
+        let path = self.path().join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, content).unwrap();
+    }
+
+    pub fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.path().join(path)).unwrap()
+    }
+
+    /// Writes f's config file, where f run in the repo reads it from.
+    pub fn write_config(&self, content: &str) {
+        let path = self.dir.join("home/.config/f.toml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    pub fn remove(&self, path: &str) {
+        fs::remove_file(self.path().join(path)).unwrap();
+    }
+
+    pub fn commit_all(&self, message: &str) {
+        self.git(&["add", "-A"]);
+        self.git(&["commit", "-q", "-m", message]);
+    }
+
+    /// Runs git in the work tree, for when it's expected to fail.
+    pub fn git_output(&self, args: &[&str]) -> Output {
+        self.command("git").args(args).output().unwrap()
+    }
+
+    /// Runs git in the work tree, panicking if it fails.
+    pub fn git(&self, args: &[&str]) -> String {
+        let output = self.command("git").args(args).output().unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    /// Leaves `path` conflicted: `main` and a side branch both change it,
+    /// and merging the side branch stops.
+    pub fn conflict(&self, path: &str) {
+        self.write(path, "base\n");
+        self.commit_all("Add conflicting file");
+        self.git(&["checkout", "-q", "-b", "side"]);
+        self.write(path, "side\n");
+        self.commit_all("Change on side");
+        self.git(&["checkout", "-q", "main"]);
+        self.write(path, "main\n");
+        self.commit_all("Change on main");
+        let status = self
+            .command("git")
+            .args(["merge", "-q", "side"])
+            .output()
+            .unwrap()
+            .status;
+        assert!(!status.success(), "merge should have conflicted");
+    }
+
+    /// Runs `f` in the work tree.
+    pub fn f(&self, args: &[&str]) -> Output {
+        self.f_in(&self.path(), args)
+    }
+
+    /// Runs `f` in `dir`, with stdin empty.
+    pub fn f_in(&self, dir: &Path, args: &[&str]) -> Output {
+        self.command(env!("CARGO_BIN_EXE_f"))
+            .args(args)
+            .current_dir(dir)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .unwrap()
+    }
+
+    /// Runs `f` in the work tree with `input` on stdin.
+    pub fn f_with_input(&self, args: &[&str], input: &str) -> Output {
+        use std::io::Write;
+        let mut child = self
+            .command(env!("CARGO_BIN_EXE_f"))
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    /// Runs `script` with bash in the work tree, with f first on the PATH.
+    pub fn bash(&self, script: &str) -> Output {
+        let bin = Path::new(env!("CARGO_BIN_EXE_f")).parent().unwrap();
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let mut dirs = vec![bin.to_path_buf()];
+        dirs.extend(std::env::split_paths(&path));
+        self.command("bash")
+            .args(["-c", script])
+            .env("PATH", std::env::join_paths(dirs).unwrap())
+            .stdin(std::process::Stdio::null())
+            .output()
+            .unwrap()
+    }
+
+    /// `f list --porcelain` as `(id, type, path)` records.
+    pub fn list(&self) -> Vec<(String, String, String)> {
+        stdout(&self.f(&["list", "--porcelain"]))
+            .lines()
+            .map(|line| {
+                let mut fields = line.splitn(3, '\t').map(str::to_string);
+                let mut next = || fields.next().unwrap_or_default();
+                (next(), next(), next())
+            })
+            .collect()
+    }
+
+    /// The ID `f list` gives `path` as `file_type`.
+    pub fn id(&self, file_type: &str, path: &str) -> String {
+        self.list()
+            .into_iter()
+            .find(|(_, t, p)| t == file_type && p == path)
+            .map(|(id, ..)| id)
+            .unwrap_or_else(|| panic!("{} {} not listed in {:?}", file_type, path, self.list()))
+    }
+
+    /// `program` run in the work tree with a clean environment: an empty
+    /// home, so neither the user's git config nor their f.toml applies,
+    /// and the C locale, so messages are in English.
+    fn command(&self, program: &str) -> Command {
+        let home = self.dir.join("home");
+        let mut cmd = Command::new(program);
+        cmd.current_dir(self.path())
+            .env("HOME", &home)
+            .env("XDG_CONFIG_HOME", home.join(".config"))
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_CONFIG_GLOBAL", home.join(".gitconfig"))
+            .env("GIT_AUTHOR_NAME", "Test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "Test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .env("GIT_PAGER", "cat")
+            .env("PAGER", "cat")
+            .env("GIT_EDITOR", "true")
+            .env("NO_COLOR", "1")
+            .env("LANG", "C")
+            .env_remove("LC_ALL")
+            .env_remove("LC_MESSAGES")
+            .env("F_NO_DAEMON", "1")
+            .env_remove("EDITOR")
+            .env_remove("VISUAL")
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE");
+        cmd
+    }
+}
+
+impl Drop for Repo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
@@ -0,0 +1,9 @@
+/// The start of a PNG, as far as the header with its size, which is all
+/// f reads of one. Shared by the image unit tests and the CLI tests.
+pub fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    bytes.extend(width.to_be_bytes());
+    bytes.extend(height.to_be_bytes());
+    bytes.extend([8, 6, 0, 0, 0]);
+    bytes
+}